@@ -11,6 +11,7 @@
     enable-secrets ? true,
     enable-smb ? false,
     enable-ftp ? true,
+    enable-s3 ? true,
 }:
 
 let
@@ -50,6 +51,7 @@ let
 
     features = []   ++ pkgs.lib.optional enable-secrets "secrets"
                     ++ pkgs.lib.optional enable-ftp "ftp"
+                    ++ pkgs.lib.optional enable-s3 "s3"
                     ++ pkgs.lib.optional enable-smb "smb";
 
     cargoArtifacts = craneLib.buildDepsOnly commonArgs;
@@ -0,0 +1,444 @@
+use anyhow::anyhow;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::config::RemoteTimeouts;
+use crate::error::MkubeError;
+use crate::library::LibraryId;
+use crate::multifs::{ConnectionStatus, MultiFs};
+use crate::{AppMessage, AppState, ConnectionPool, Connections, LibraryHandle};
+
+const RECONNECT_ATTEMPTS: u32 = 4;
+
+static TIMEOUTS: RwLock<Option<RemoteTimeouts>> = RwLock::new(None);
+
+/// Set the timeouts of the calls, again each time the configuration is reloaded.
+pub fn configure(timeouts: &RemoteTimeouts) {
+    *TIMEOUTS.write().unwrap() = Some(timeouts.clone());
+}
+
+/// Kind of a blocking call, each kind with its own timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Connect,
+    List,
+    /// Opening, reading or writing a small file like an NFO.
+    Open,
+    Rename,
+    /// Reading a whole media.
+    Read,
+}
+
+impl Operation {
+    /// The configured timeout, None when it is disabled.
+    pub fn timeout(self) -> Option<Duration> {
+        let timeouts = TIMEOUTS.read().unwrap().clone().unwrap_or_default();
+        let secs = match self {
+            Operation::Connect => timeouts.connect,
+            Operation::List => timeouts.list,
+            Operation::Open => timeouts.open,
+            Operation::Rename => timeouts.rename,
+            Operation::Read => timeouts.read,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    }
+}
+
+/// Connection of a library running its calls on the blocking threads of tokio.
+///
+/// Every [`MultiFs`] call blocks, which freezes the UI ticks of the tasks sharing the thread
+/// during slow remote calls. The pool stays locked during a call, like with [`get_connection`].
+///
+/// [`get_connection`]: crate::get_connection
+#[derive(Clone, Copy)]
+pub struct AsyncMultiFs<'a> {
+    conns: &'a ConnectionPool,
+    fs_id: LibraryId,
+    handle: Option<LibraryHandle>,
+    operation: Operation,
+    timeout: Option<Duration>,
+}
+
+impl<'a> AsyncMultiFs<'a> {
+    pub fn new(conns: &'a ConnectionPool, fs_id: LibraryId) -> Self {
+        Self {
+            conns,
+            fs_id,
+            handle: None,
+            operation: Operation::Open,
+            timeout: None,
+        }
+    }
+
+    /// The library as seen by a long running task, the calls fail with ScanCancelled once it
+    /// is edited or removed.
+    pub fn with_handle(conns: &'a ConnectionPool, handle: LibraryHandle) -> Self {
+        Self {
+            handle: Some(handle),
+            ..Self::new(conns, handle.fs_id)
+        }
+    }
+
+    pub fn fs_id(&self) -> LibraryId {
+        self.fs_id
+    }
+
+    /// The kind of the calls, Open by default, which gives their timeout.
+    pub fn for_operation(mut self, operation: Operation) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    /// Replace the configured timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run `op` with the connection on a blocking thread, reconnecting it first if needed.
+    ///
+    /// A call that times out goes on in the background with its connection, see
+    /// [`Connections::abandon`].
+    pub async fn run<T, E, F>(&self, op: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut MultiFs) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<MkubeError> + Send + 'static,
+    {
+        self.check_handle(&*self.conns.lock().await)?;
+        let mut conns = crate::get_connection(self.conns, self.fs_id).await?;
+        self.check_handle(&conns)?;
+        let fs = conns
+            .take(self.fs_id)
+            .ok_or(MkubeError::UnknownLibrary(self.fs_id))?;
+        let (taken, receiver) = spawn(fs, op);
+        let ret = wait(
+            &mut conns,
+            vec![(self.fs_id, taken)],
+            receiver,
+            self.timeout(),
+        )
+        .await?;
+        if ret.is_err() {
+            conns.mark_failed(self.fs_id);
+        }
+        ret
+    }
+
+    /// Run `op` with the connections of this library and of `other` at once, like
+    /// [`AsyncMultiFs::run`]. A timeout leaves both connections to the call.
+    pub async fn run_with<T, E, F>(&self, other: LibraryId, op: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut MultiFs, &mut MultiFs) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<MkubeError> + Send + 'static,
+    {
+        if other == self.fs_id {
+            return Err(
+                MkubeError::Other(anyhow!("Both connections are of library {}.", other)).into(),
+            );
+        }
+        self.check_handle(&*self.conns.lock().await)?;
+        drop(crate::get_connection(self.conns, other).await?);
+        let mut conns = crate::get_connection(self.conns, self.fs_id).await?;
+        self.check_handle(&conns)?;
+        conns.reclaim(other)?;
+        let mut other_fs = conns.take(other).ok_or(MkubeError::UnknownLibrary(other))?;
+        let mut fs = conns
+            .take(self.fs_id)
+            .ok_or(MkubeError::UnknownLibrary(self.fs_id))?;
+        let (sender, receiver) = oneshot::channel();
+        let (fs_sender, fs_taken) = oneshot::channel();
+        let (other_sender, other_taken) = oneshot::channel();
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(op(&mut fs, &mut other_fs));
+            let _ = fs_sender.send(fs);
+            let _ = other_sender.send(other_fs);
+        });
+        let taken = vec![(self.fs_id, fs_taken), (other, other_taken)];
+        let ret = wait(&mut conns, taken, receiver, self.timeout()).await?;
+        if ret.is_err() {
+            conns.mark_failed(self.fs_id);
+            conns.mark_failed(other);
+        }
+        ret
+    }
+
+    fn check_handle(&self, conns: &Connections) -> Result<(), MkubeError> {
+        match self.handle {
+            Some(handle) if !conns.is_current(handle) => Err(MkubeError::ScanCancelled(self.fs_id)),
+            _ => Ok(()),
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout.or_else(|| self.operation.timeout())
+    }
+}
+
+/// Check the connection of a library and reconnect it with an exponential backoff if needed.
+///
+/// `probe` checks it with a call to the server, otherwise that is only done after a failed
+/// call, see [`Connections::mark_failed`]. Returns Degraded if a reconnection was needed,
+/// Offline if all the attempts failed. The pool is unlocked during the backoff.
+pub(crate) async fn connect(
+    pool: &ConnectionPool,
+    fs_id: LibraryId,
+    probe: bool,
+) -> Result<ConnectionStatus, MkubeError> {
+    let mut delay = Duration::from_millis(250);
+    for attempt in 0..=RECONNECT_ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        let mut conns = pool.lock().await;
+        conns.reclaim(fs_id)?;
+        let probe = attempt == 0 && (conns.take_failed(fs_id) || probe);
+        let fs = conns.take(fs_id).ok_or(MkubeError::UnknownLibrary(fs_id))?;
+        let (taken, receiver) = spawn(fs, move |fs| {
+            if attempt == 0 {
+                return fs.is_alive(probe);
+            }
+            fs.reconnect()
+                .map_err(|err| {
+                    log::warn!(
+                        "Reconnection attempt {}/{} of library {} failed, causes:\n{:?}",
+                        attempt,
+                        RECONNECT_ATTEMPTS,
+                        fs_id,
+                        err
+                    )
+                })
+                .is_ok()
+        });
+        let timeout = Operation::Connect.timeout();
+        if wait(&mut conns, vec![(fs_id, taken)], receiver, timeout).await? {
+            return Ok(match attempt {
+                0 => ConnectionStatus::Connected,
+                _ => ConnectionStatus::Degraded,
+            });
+        }
+    }
+    Ok(ConnectionStatus::Offline)
+}
+
+/// First connection of a library just added to the pool, with a single attempt.
+pub async fn open(conns: &mut Connections, fs_id: LibraryId) -> ConnectionStatus {
+    let Some(fs) = conns.take(fs_id) else {
+        return ConnectionStatus::Offline;
+    };
+    let (taken, receiver) = spawn(fs, |fs| {
+        let rfs = fs.as_mut_rfs();
+        if !rfs.is_connected() {
+            let _ = rfs.connect();
+        }
+        rfs.is_connected()
+    });
+    match wait(
+        conns,
+        vec![(fs_id, taken)],
+        receiver,
+        Operation::Connect.timeout(),
+    )
+    .await
+    {
+        Ok(true) => ConnectionStatus::Connected,
+        _ => ConnectionStatus::Offline,
+    }
+}
+
+/// Run `op` on a blocking thread, for a connection outside of the pool.
+///
+/// None if `op` panicked or timed out, the call then goes on in the background.
+pub async fn run_detached<T, F>(operation: Operation, op: F) -> Option<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let call = tokio::task::spawn_blocking(op);
+    match operation.timeout() {
+        Some(timeout) => match tokio::time::timeout(timeout, call).await {
+            Ok(ret) => ret.ok(),
+            Err(_) => {
+                log::warn!("A {:?} call timed out after {:?}.", operation, timeout);
+                None
+            }
+        },
+        None => call.await.ok(),
+    }
+}
+
+/// Connection taken from the pool by a call on a blocking thread, sent back once it returns.
+pub(crate) type Taken = oneshot::Receiver<MultiFs>;
+
+fn spawn<T, F>(mut fs: MultiFs, op: F) -> (Taken, oneshot::Receiver<T>)
+where
+    F: FnOnce(&mut MultiFs) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    let (fs_sender, taken) = oneshot::channel();
+    tokio::task::spawn_blocking(move || {
+        let _ = sender.send(op(&mut fs));
+        let _ = fs_sender.send(fs);
+    });
+    (taken, receiver)
+}
+
+/// Wait for the answer of a call holding connections taken from the pool, putting them back.
+async fn wait<T>(
+    conns: &mut Connections,
+    taken: Vec<(LibraryId, Taken)>,
+    receiver: oneshot::Receiver<T>,
+    timeout: Option<Duration>,
+) -> Result<T, MkubeError> {
+    let fs_id = taken[0].0;
+    let answer = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, receiver).await,
+        None => Ok(receiver.await),
+    };
+    match answer {
+        Ok(Ok(ret)) => {
+            for (fs_id, fs) in taken {
+                match fs.await {
+                    Ok(fs) => conns.put_back(fs_id, fs),
+                    Err(_) => {
+                        log::error!("Connection of library {} lost.", fs_id);
+                        conns.lose(fs_id);
+                    }
+                }
+            }
+            Ok(ret)
+        }
+        // The call panicked, the connections with it.
+        Ok(Err(_)) => {
+            for (fs_id, _) in taken {
+                log::error!("Connection of library {} lost by a call.", fs_id);
+                conns.lose(fs_id);
+            }
+            Err(MkubeError::Connection(fs_id))
+        }
+        Err(_) => {
+            for (fs_id, fs) in taken {
+                log::warn!(
+                    "A call to library {} timed out after {:?}, it goes on in the background.",
+                    fs_id,
+                    timeout.unwrap_or_default()
+                );
+                conns.abandon(fs_id, fs);
+                if let Some(sender) = crate::MESSAGE_SENDER.get() {
+                    let _ = sender.send(AppMessage::Closure(Box::new(
+                        move |app_state: &mut AppState| {
+                            app_state.set_library_status(fs_id, ConnectionStatus::Degraded)
+                        },
+                    )));
+                }
+            }
+            Err(MkubeError::Timeout(fs_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockfs::MockFs;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn async_multifs() {
+        let mock = MockFs::new().with_file("/movies/alien/alien.mkv", "");
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        conns
+            .lock()
+            .await
+            .insert(fs_id, MultiFs::Mock(mock.clone()));
+        let handle = conns.lock().await.handle(fs_id).unwrap();
+        let fs = AsyncMultiFs::with_handle(&conns, handle);
+
+        // The other tasks keep running during a slow call.
+        let slow = fs.run(|fs| {
+            std::thread::sleep(Duration::from_millis(200));
+            let exists = fs.as_mut_rfs().exists("/movies/alien/alien.mkv".as_ref());
+            Ok::<_, MkubeError>((exists.unwrap(), Instant::now()))
+        });
+        let tick = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Instant::now()
+        };
+        let (slow, ticked) = tokio::join!(slow, tick);
+        let (exists, returned) = slow.unwrap();
+        assert!(exists);
+        assert!(ticked < returned);
+
+        // A timed out call keeps the connection until it returns.
+        let short = fs.with_timeout(Duration::from_millis(20));
+        let stuck = short.run(|_| {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok::<_, MkubeError>(())
+        });
+        assert!(matches!(stuck.await, Err(MkubeError::Timeout(_))));
+        let busy = crate::get_connection(&conns, fs_id).await.map(|_| ());
+        assert!(matches!(busy, Err(MkubeError::Timeout(_))));
+        assert_eq!(conns.lock().await.handle(fs_id), Some(handle));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(fs.run(|_| Ok::<_, MkubeError>(())).await.is_ok());
+
+        // Unless a new connection replaces the one held by the call.
+        conns
+            .lock()
+            .await
+            .set_reopen(fs_id, move || Some(MultiFs::Mock(mock.clone())));
+        let short = fs.with_timeout(Duration::from_millis(20));
+        let stuck = short.run(|_| {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok::<_, MkubeError>(())
+        });
+        assert!(matches!(stuck.await, Err(MkubeError::Timeout(_))));
+        assert!(fs.run(|_| Ok::<_, MkubeError>(())).await.is_ok());
+
+        conns.lock().await.close(fs_id);
+        let stale = fs.run(|_| Ok::<_, MkubeError>(())).await;
+        assert!(matches!(stale, Err(MkubeError::ScanCancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn async_multifs_two_libraries() {
+        let conns: ConnectionPool = Default::default();
+        let (from_id, to_id) = (LibraryId::new(), LibraryId::new());
+        let from = MockFs::new().with_file("/movies/alien.mkv", "");
+        let to = MockFs::new().with_file("/films/.keep", "");
+        conns.lock().await.insert(from_id, MultiFs::Mock(from));
+        conns.lock().await.insert(to_id, MultiFs::Mock(to));
+        let fs = AsyncMultiFs::new(&conns, from_id);
+
+        let both = fs.run_with(to_id, |from, to| {
+            let from = from.as_mut_rfs().exists("/movies/alien.mkv".as_ref());
+            let to = to.as_mut_rfs().exists("/movies/alien.mkv".as_ref());
+            Ok::<_, MkubeError>((from.unwrap(), to.unwrap()))
+        });
+        assert_eq!(both.await.unwrap(), (true, false));
+        let same = fs.run_with(from_id, |_, _| Ok::<_, MkubeError>(()));
+        assert!(same.await.is_err());
+
+        // A timed out call keeps both connections until it returns.
+        let short = fs.with_timeout(Duration::from_millis(20));
+        let stuck = short.run_with(to_id, |_, _| {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok::<_, MkubeError>(())
+        });
+        assert!(matches!(stuck.await, Err(MkubeError::Timeout(_))));
+        let busy = AsyncMultiFs::new(&conns, to_id)
+            .run(|_| Ok::<_, MkubeError>(()))
+            .await;
+        assert!(matches!(busy, Err(MkubeError::Timeout(_))));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(fs
+            .run_with(to_id, |_, _| Ok::<_, MkubeError>(()))
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::config::{BackupMode, NfoBackups};
+use crate::multifs::MultiFs;
+
+static SETTINGS: RwLock<Option<NfoBackups>> = RwLock::new(None);
+
+/// Set the backup settings, again each time the configuration is reloaded.
+pub fn configure(settings: &NfoBackups) {
+    *SETTINGS.write().unwrap() = Some(settings.clone());
+}
+
+fn settings() -> NfoBackups {
+    SETTINGS.read().unwrap().clone().unwrap_or_default()
+}
+
+/// Copy the NFO at `nfo_path` before it gets overwritten, as the configured settings say.
+pub fn before_overwrite(fs: &mut MultiFs, nfo_path: &Path) -> Result<Option<PathBuf>> {
+    backup(fs, nfo_path, &settings())
+}
+
+/// Copy the NFO at `nfo_path` and prune the old copies.
+///
+/// Returns the copy, None when backups are off or there is no NFO yet.
+pub fn backup(fs: &mut MultiFs, nfo_path: &Path, settings: &NfoBackups) -> Result<Option<PathBuf>> {
+    if settings.mode == BackupMode::Off || fs.as_mut_rfs().stat(nfo_path).is_err() {
+        return Ok(None);
+    }
+    let backup = match settings.mode {
+        BackupMode::Timestamped => {
+            let stamp: String = crate::nfo::format_datetime(SystemTime::now())
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect();
+            // 20230401213000 as 20230401-213000.
+            let (date, time) = stamp.split_at(8);
+            with_suffix(nfo_path, &format!(".{}-{}.bak", date, time))
+        }
+        _ => with_suffix(nfo_path, ".bak"),
+    };
+    let bytes = read_all(fs, nfo_path)?;
+    write_all(fs, &backup, &bytes)?;
+    if settings.mode == BackupMode::Timestamped {
+        let single = with_suffix(nfo_path, ".bak");
+        for old in list(fs, nfo_path)?
+            .into_iter()
+            .filter(|path| *path != single)
+            .skip(settings.keep.max(1))
+        {
+            if let Err(err) = fs.as_mut_rfs().remove_file(&old) {
+                log::warn!(
+                    "Failed to remove the old backup {}:\n{:?}",
+                    old.display(),
+                    err
+                );
+            }
+        }
+    }
+    Ok(Some(backup))
+}
+
+/// The backups of the NFO at `nfo_path`, the timestamped ones newest first then the single one.
+pub fn list(fs: &mut MultiFs, nfo_path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = nfo_path.parent().unwrap_or(Path::new(""));
+    let name = nfo_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let entries = fs
+        .as_mut_rfs()
+        .list_dir(dir)
+        .context(format!("Failed to list {}", dir.display()))?;
+    let single = format!("{}.bak", name);
+    let mut single_path = None;
+    let mut stamped = Vec::new();
+    for entry in entries.into_iter().filter(|entry| entry.is_file()) {
+        let file_name = entry.name();
+        if file_name == single {
+            single_path = Some(entry.path().to_owned());
+        } else if file_name
+            .strip_prefix(&name)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .is_some_and(is_stamp)
+        {
+            stamped.push(entry.path().to_owned());
+        }
+    }
+    // The stamps sort like the dates.
+    stamped.sort();
+    stamped.reverse();
+    stamped.extend(single_path);
+    Ok(stamped)
+}
+
+/// `20230401-213000`, as written by [`before_overwrite`].
+fn is_stamp(stamp: &str) -> bool {
+    stamp.len() == 15
+        && stamp
+            .char_indices()
+            .all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+}
+
+/// Put the content of `backup` back in the NFO at `nfo_path`, the current NFO is backed up first.
+pub fn restore(fs: &mut MultiFs, backup: &Path, nfo_path: &Path) -> Result<()> {
+    let bytes = read_all(fs, backup)?;
+    before_overwrite(fs, nfo_path)?;
+    write_all(fs, nfo_path, &bytes)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn read_all(fs: &mut MultiFs, path: &Path) -> Result<Vec<u8>> {
+    let mut reader = fs.open_reader(path)?;
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .context(format!("Failed to read {}", path.display()))?;
+    reader.finish()?;
+    Ok(bytes)
+}
+
+fn write_all(fs: &mut MultiFs, path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut writer = fs.create_writer(path)?;
+    writer
+        .write_all(bytes)
+        .context(format!("Failed to write {}", path.display()))?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockfs::MockFs;
+    use crate::nfo::Movie;
+    use crate::views::movie_manager::write_nfo;
+
+    #[tokio::test]
+    async fn nfo_backups() {
+        let mock = MockFs::new()
+            .with_file("/movies/alien/alien.mkv", "")
+            .with_file("/movies/alien/alien.nfo.20230401-213000.bak", "oldest")
+            .with_file("/movies/alien/alien.nfo.20230402-213000.bak", "older")
+            .with_file("/movies/alien/alien.nfo.bak", "single")
+            .with_file("/movies/alien/alien.nfo.notes.bak", "");
+        let mut fs = MultiFs::Mock(mock.clone());
+        let path = PathBuf::from("/movies/alien/alien.mkv");
+        let nfo_path = path.with_extension("nfo");
+        let stamped = NfoBackups {
+            mode: BackupMode::Timestamped,
+            keep: 2,
+        };
+        // Nothing to back up before the first write.
+        assert_eq!(backup(&mut fs, &nfo_path, &stamped).unwrap(), None);
+        let nfo = Movie {
+            title: "Alien".into(),
+            ..Default::default()
+        };
+        write_nfo(&mut fs, &nfo, &path).await.unwrap();
+        let written = mock.read(&nfo_path).unwrap();
+        let copy = backup(&mut fs, &nfo_path, &stamped).unwrap().unwrap();
+        assert_eq!(mock.read(&copy), Some(written.clone()));
+        // The newest copies are kept, the single one and the unrelated files are left alone.
+        let listed = list(&mut fs, &nfo_path).unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                copy.clone(),
+                PathBuf::from("/movies/alien/alien.nfo.20230402-213000.bak"),
+                PathBuf::from("/movies/alien/alien.nfo.bak"),
+            ]
+        );
+        assert!(mock
+            .read("/movies/alien/alien.nfo.20230401-213000.bak")
+            .is_none());
+        assert!(mock.read("/movies/alien/alien.nfo.notes.bak").is_some());
+
+        let single = NfoBackups {
+            mode: BackupMode::Single,
+            ..Default::default()
+        };
+        assert_eq!(
+            backup(&mut fs, &nfo_path, &single).unwrap(),
+            Some(PathBuf::from("/movies/alien/alien.nfo.bak"))
+        );
+        assert_eq!(mock.read("/movies/alien/alien.nfo.bak"), Some(written));
+        let off = NfoBackups::default();
+        assert_eq!(backup(&mut fs, &nfo_path, &off).unwrap(), None);
+
+        restore(&mut fs, &listed[1], &nfo_path).unwrap();
+        assert_eq!(mock.read(&nfo_path).as_deref(), Some(&b"older"[..]));
+    }
+}
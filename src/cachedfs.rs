@@ -0,0 +1,270 @@
+use remotefs::fs::{File, Metadata, ReadStream, UnixPex, Welcome, WriteStream};
+use remotefs::{RemoteFs, RemoteResult};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::multifs::MultiFs;
+
+/// Keeps the directory listings of a slow filesystem for a short time.
+///
+/// Repeated scans and navigations skip the round-trips to the server. Any write through
+/// this filesystem drops the listings it may change, changes made by other clients are only
+/// seen once the listing expires.
+pub struct CachedFs {
+    pub inner: MultiFs,
+    ttl: Duration,
+    listings: HashMap<PathBuf, (Instant, Vec<File>)>,
+    /// Files opened for writing, their listings are dropped again once the streams are closed.
+    writing: Vec<PathBuf>,
+}
+
+impl CachedFs {
+    pub fn new(inner: MultiFs, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            listings: HashMap::new(),
+            writing: Vec::new(),
+        }
+    }
+
+    /// Drop the listings of `path`, of its parent and of the directories below it.
+    fn invalidate(&mut self, path: &Path) {
+        let parent = path.parent();
+        self.listings
+            .retain(|dir, _| Some(dir.as_path()) != parent && !dir.starts_with(path));
+    }
+}
+
+impl RemoteFs for CachedFs {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.listings.clear();
+        self.inner.as_mut_rfs().connect()
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        self.listings.clear();
+        self.inner.as_mut_rfs().disconnect()
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.inner.as_mut_rfs().is_connected()
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.inner.as_mut_rfs().pwd()
+    }
+
+    fn change_dir(&mut self, dir: &Path) -> RemoteResult<PathBuf> {
+        self.inner.as_mut_rfs().change_dir(dir)
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        if let Some((listed_at, files)) = self.listings.get(path) {
+            if listed_at.elapsed() < self.ttl {
+                return Ok(files.clone());
+            }
+        }
+        let files = self.inner.as_mut_rfs().list_dir(path)?;
+        self.listings
+            .insert(path.to_path_buf(), (Instant::now(), files.clone()));
+        Ok(files)
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.inner.as_mut_rfs().stat(path)
+    }
+
+    fn setstat(&mut self, path: &Path, metadata: Metadata) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().setstat(path, metadata)
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        self.inner.as_mut_rfs().exists(path)
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().remove_file(path)
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().remove_dir(path)
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().remove_dir_all(path)
+    }
+
+    fn create_dir(&mut self, path: &Path, mode: UnixPex) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().create_dir(path, mode)
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> RemoteResult<()> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().symlink(path, target)
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.invalidate(dest);
+        self.inner.as_mut_rfs().copy(src, dest)
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        self.invalidate(src);
+        self.invalidate(dest);
+        self.inner.as_mut_rfs().mov(src, dest)
+    }
+
+    fn exec(&mut self, cmd: &str) -> RemoteResult<(u32, String)> {
+        // A command can change anything.
+        self.listings.clear();
+        self.inner.as_mut_rfs().exec(cmd)
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read>,
+    ) -> RemoteResult<u64> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().append_file(path, metadata, reader)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        reader: Box<dyn Read>,
+    ) -> RemoteResult<u64> {
+        self.invalidate(path);
+        self.inner.as_mut_rfs().create_file(path, metadata, reader)
+    }
+
+    fn open_file(&mut self, path: &Path, dest: Box<dyn Write + Send>) -> RemoteResult<u64> {
+        self.inner.as_mut_rfs().open_file(path, dest)
+    }
+
+    fn append(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.invalidate(path);
+        let stream = self.inner.as_mut_rfs().append(path, metadata)?;
+        self.writing.push(path.to_path_buf());
+        Ok(stream)
+    }
+
+    fn create(&mut self, path: &Path, metadata: &Metadata) -> RemoteResult<WriteStream> {
+        self.invalidate(path);
+        let stream = self.inner.as_mut_rfs().create(path, metadata)?;
+        self.writing.push(path.to_path_buf());
+        Ok(stream)
+    }
+
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        self.inner.as_mut_rfs().open(path)
+    }
+
+    fn on_read(&mut self, readable: ReadStream) -> RemoteResult<()> {
+        self.inner.as_mut_rfs().on_read(readable)
+    }
+
+    fn on_written(&mut self, writable: WriteStream) -> RemoteResult<()> {
+        // The size of the file is only known once the stream is closed. The stream does not
+        // tell its path, a connection only writes one file at a time.
+        for path in std::mem::take(&mut self.writing) {
+            self.invalidate(&path);
+        }
+        self.inner.as_mut_rfs().on_written(writable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ListingCache;
+    #[cfg(feature = "ftp")]
+    use crate::library::{Library, LibraryType};
+    use crate::localfs::LocalFs;
+    use crate::mockfs::MockFs;
+    use crate::test_util::library;
+
+    #[test]
+    fn written_file_drops_its_directory() {
+        let root = std::env::temp_dir().join(format!("mkube-cachedfs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("alpha")).unwrap();
+        std::fs::create_dir_all(root.join("beta")).unwrap();
+        let mut fs =
+            MultiFs::Local(LocalFs::new(root.clone())).cached(Some(Duration::from_secs(60)));
+        let names = |fs: &mut MultiFs, dir: &str| -> Vec<String> {
+            let mut names: Vec<String> = fs
+                .as_mut_rfs()
+                .list_dir(&root.join(dir))
+                .unwrap()
+                .iter()
+                .map(|file| file.name())
+                .collect();
+            names.sort();
+            names
+        };
+        assert!(names(&mut fs, "alpha").is_empty());
+        assert!(names(&mut fs, "beta").is_empty());
+        std::fs::write(root.join("beta/beta.mkv"), "").unwrap();
+        let mut writer = fs.create_writer(&root.join("alpha/alpha.nfo")).unwrap();
+        writer.write_all(b"<movie/>").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(names(&mut fs, "alpha"), vec!["alpha.nfo"]);
+        // The other listings are kept.
+        assert!(names(&mut fs, "beta").is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn listing_cache() {
+        let cache = ListingCache::default();
+        assert_eq!(cache.for_library(&library()), None);
+        #[cfg(feature = "ftp")]
+        {
+            let ftp = Library {
+                fs_type: LibraryType::Ftp,
+                ..library()
+            };
+            assert_eq!(cache.for_library(&ftp), Some(Duration::from_secs(30)));
+            assert_eq!(ListingCache { ttl: 0 }.for_library(&ftp), None);
+        }
+
+        let mock = MockFs::new().with_file("/movies/Alpha/alpha.mkv", "");
+        let mut fs = MultiFs::Mock(mock.clone()).cached(Some(Duration::from_secs(60)));
+        let names = |fs: &mut MultiFs, dir: &str| -> Vec<String> {
+            let mut names: Vec<String> = fs
+                .as_mut_rfs()
+                .list_dir(Path::new(dir))
+                .unwrap()
+                .iter()
+                .map(|file| file.name())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(&mut fs, "/movies"), vec!["Alpha"]);
+        // Changes of other clients are not seen until the listing expires.
+        let _ = mock.clone().with_dir("/movies/Beta");
+        assert_eq!(names(&mut fs, "/movies"), vec!["Alpha"]);
+        // Writes through the cache drop the listings they change.
+        fs.as_mut_rfs()
+            .create_dir(Path::new("/movies/Gamma"), UnixPex::from(0o755))
+            .unwrap();
+        assert_eq!(names(&mut fs, "/movies"), vec!["Alpha", "Beta", "Gamma"]);
+        fs.as_mut_rfs()
+            .mov(Path::new("/movies/Alpha"), Path::new("/movies/Beta/Alpha"))
+            .unwrap();
+        assert_eq!(names(&mut fs, "/movies"), vec!["Beta", "Gamma"]);
+        assert_eq!(names(&mut fs, "/movies/Beta"), vec!["Alpha"]);
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::library::LibraryId;
+use crate::nfo::Movie;
+
+/// Artworks and NFO found next to a movie file during the scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MovieAssets {
+    /// An NFO was found next to the movie, or written since.
+    pub nfo: bool,
+    pub poster: bool,
+    pub fanart: bool,
+    pub actors: bool,
+    /// Files of a multi-part movie, 1 for a single file.
+    pub parts: usize,
+    /// Bonus videos (trailers, featurettes...) attached to the movie.
+    pub extras: usize,
+    /// Size in bytes of the movie file, all its parts included.
+    pub size: u64,
+}
+
+impl MovieAssets {
+    /// Short summary for the movie table, e.g. `PF-` for a poster and a fanart.
+    pub fn flags(&self) -> String {
+        [(self.poster, 'P'), (self.fanart, 'F'), (self.actors, 'A')]
+            .iter()
+            .map(|(present, flag)| if *present { *flag } else { '-' })
+            .collect()
+    }
+}
+
+/// Counts of a library, kept up to date as the movies come and go.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LibraryStats {
+    pub movies: usize,
+    pub nfos: usize,
+    pub posters: usize,
+    pub fanarts: usize,
+    /// Size in bytes of the movie files.
+    pub size: u64,
+}
+
+impl LibraryStats {
+    fn add(&mut self, assets: &MovieAssets) {
+        self.nfos += assets.nfo as usize;
+        self.posters += assets.poster as usize;
+        self.fanarts += assets.fanart as usize;
+        self.size += assets.size;
+    }
+
+    fn sub(&mut self, assets: &MovieAssets) {
+        self.nfos -= assets.nfo as usize;
+        self.posters -= assets.poster as usize;
+        self.fanarts -= assets.fanart as usize;
+        self.size -= assets.size;
+    }
+}
+
+/// In-memory index of the scanned movies, sorted by title and keyed by (fs_id, path).
+///
+/// The views query it instead of walking the movies, the stats of each library and the movies
+/// sharing a TMDB id are updated along the changes.
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    entries: Vec<(Movie, LibraryId, PathBuf)>,
+    index: HashMap<(LibraryId, PathBuf), usize>,
+    assets: HashMap<(LibraryId, PathBuf), MovieAssets>,
+    stats: HashMap<LibraryId, LibraryStats>,
+    by_tmdb_id: HashMap<u64, BTreeSet<(LibraryId, PathBuf)>>,
+}
+
+impl Catalog {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&(Movie, LibraryId, PathBuf)> {
+        self.entries.get(index)
+    }
+
+    pub fn get(&self, fs_id: LibraryId, path: &Path) -> Option<&Movie> {
+        self.position(fs_id, path).map(|i| &self.entries[i].0)
+    }
+
+    pub fn position(&self, fs_id: LibraryId, path: &Path) -> Option<usize> {
+        self.index.get(&(fs_id, path.to_path_buf())).copied()
+    }
+
+    pub fn assets(&self, fs_id: LibraryId, path: &Path) -> Option<&MovieAssets> {
+        self.assets.get(&(fs_id, path.to_path_buf()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Movie, LibraryId, PathBuf)> {
+        self.entries.iter()
+    }
+
+    pub fn iter_library(
+        &self,
+        fs_id: LibraryId,
+    ) -> impl Iterator<Item = &(Movie, LibraryId, PathBuf)> {
+        self.entries.iter().filter(move |(_, id, _)| *id == fs_id)
+    }
+
+    pub fn stats(&self, fs_id: LibraryId) -> LibraryStats {
+        self.stats.get(&fs_id).copied().unwrap_or_default()
+    }
+
+    /// The movies sharing a TMDB id, each group sorted by library and path.
+    pub fn duplicates(&self) -> impl Iterator<Item = (u64, Vec<(LibraryId, &Path)>)> {
+        self.by_tmdb_id
+            .iter()
+            .filter(|(_, movies)| movies.len() > 1)
+            .map(|(tmdb_id, movies)| {
+                let movies = movies
+                    .iter()
+                    .map(|(fs_id, path)| (*fs_id, path.as_path()))
+                    .collect();
+                (*tmdb_id, movies)
+            })
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+        self.assets.clear();
+        self.stats.clear();
+        self.by_tmdb_id.clear();
+    }
+
+    /// Forget the movies of a library and their assets.
+    pub fn clear_library(&mut self, fs_id: LibraryId) {
+        self.entries.retain(|(_, id, _)| *id != fs_id);
+        self.assets.retain(|(id, _), _| *id != fs_id);
+        self.stats.remove(&fs_id);
+        self.by_tmdb_id.retain(|_, movies| {
+            movies.retain(|(id, _)| *id != fs_id);
+            !movies.is_empty()
+        });
+        self.reindex();
+    }
+
+    pub fn insert(&mut self, movie: Movie, fs_id: LibraryId, path: PathBuf) {
+        if let Some(ind) = self.position(fs_id, &path) {
+            let (old, _, _) = self.entries.remove(ind);
+            self.shift(ind.., -1);
+            self.forget_tmdb_id(&old, fs_id, &path);
+        } else {
+            self.stats.entry(fs_id).or_default().movies += 1;
+        }
+        if let Some(tmdb_id) = movie.tmdb_id() {
+            let movies = self.by_tmdb_id.entry(tmdb_id).or_default();
+            movies.insert((fs_id, path.clone()));
+        }
+        let ind = match self
+            .entries
+            .binary_search_by(|m| m.0.title.as_str().cmp(&movie.title))
+        {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+        self.entries.insert(ind, (movie, fs_id, path.clone()));
+        self.shift(ind + 1.., 1);
+        self.index.insert((fs_id, path), ind);
+    }
+
+    /// Replace the assets found next to a movie.
+    pub fn set_assets(&mut self, fs_id: LibraryId, path: PathBuf, assets: MovieAssets) {
+        let stats = self.stats.entry(fs_id).or_default();
+        stats.add(&assets);
+        if let Some(old) = self.assets.insert((fs_id, path), assets) {
+            stats.sub(&old);
+        }
+    }
+
+    /// An NFO was written next to a movie.
+    pub fn set_nfo(&mut self, fs_id: LibraryId, path: &Path) {
+        if let Some(assets) = self.assets.get_mut(&(fs_id, path.to_path_buf())) {
+            if !assets.nfo {
+                assets.nfo = true;
+                self.stats.entry(fs_id).or_default().nfos += 1;
+            }
+        }
+    }
+
+    /// Change the path of a movie, returns false if the movie is unknown.
+    pub fn relocate(&mut self, fs_id: LibraryId, path: &Path, new_path: PathBuf) -> bool {
+        let Some(ind) = self.index.remove(&(fs_id, path.to_path_buf())) else {
+            return false;
+        };
+        if let Some(assets) = self.assets.remove(&(fs_id, path.to_path_buf())) {
+            self.assets.insert((fs_id, new_path.clone()), assets);
+        }
+        let movie = &self.entries[ind].0;
+        if let Some(movies) = movie.tmdb_id().and_then(|id| self.by_tmdb_id.get_mut(&id)) {
+            movies.remove(&(fs_id, path.to_path_buf()));
+            movies.insert((fs_id, new_path.clone()));
+        }
+        self.entries[ind].2 = new_path.clone();
+        self.index.insert((fs_id, new_path), ind);
+        true
+    }
+
+    /// Forget a movie and its assets, returns false if the movie is unknown.
+    pub fn remove(&mut self, fs_id: LibraryId, path: &Path) -> bool {
+        let Some(ind) = self.index.remove(&(fs_id, path.to_path_buf())) else {
+            return false;
+        };
+        let (movie, _, _) = self.entries.remove(ind);
+        self.shift(ind.., -1);
+        self.forget_tmdb_id(&movie, fs_id, path);
+        let stats = self.stats.entry(fs_id).or_default();
+        stats.movies -= 1;
+        if let Some(assets) = self.assets.remove(&(fs_id, path.to_path_buf())) {
+            stats.sub(&assets);
+        }
+        true
+    }
+
+    fn forget_tmdb_id(&mut self, movie: &Movie, fs_id: LibraryId, path: &Path) {
+        let Some(tmdb_id) = movie.tmdb_id() else {
+            return;
+        };
+        if let Some(movies) = self.by_tmdb_id.get_mut(&tmdb_id) {
+            movies.remove(&(fs_id, path.to_path_buf()));
+            if movies.is_empty() {
+                self.by_tmdb_id.remove(&tmdb_id);
+            }
+        }
+    }
+
+    /// Move by `delta` the index of the entries in `range`, after an insertion or a removal.
+    fn shift(&mut self, range: std::ops::RangeFrom<usize>, delta: isize) {
+        for (fs_id, path) in self.entries[range].iter().map(|(_, id, path)| (*id, path)) {
+            if let Some(ind) = self.index.get_mut(&(fs_id, path.clone())) {
+                *ind = ind.wrapping_add_signed(delta);
+            }
+        }
+    }
+
+    fn reindex(&mut self) {
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, (_, fs_id, path))| ((*fs_id, path.clone()), i))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfo::UniqueId;
+
+    fn movie(title: &str, tmdb_id: Option<u64>) -> Movie {
+        Movie {
+            title: title.into(),
+            uniqueid: tmdb_id
+                .map(|id| UniqueId {
+                    id_type: "tmdb".into(),
+                    default: true,
+                    value: id.to_string(),
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn titles(catalog: &Catalog) -> Vec<&str> {
+        catalog.iter().map(|(m, _, _)| m.title.as_str()).collect()
+    }
+
+    /// Every movie is found at its position.
+    fn assert_indexed(catalog: &Catalog) {
+        for (i, (_, fs_id, path)) in catalog.iter().enumerate() {
+            assert_eq!(catalog.position(*fs_id, path), Some(i));
+        }
+        assert_eq!(catalog.index.len(), catalog.len());
+    }
+
+    #[test]
+    fn incremental_index() {
+        let (a, b) = (LibraryId::new(), LibraryId::new());
+        let mut catalog = Catalog::default();
+        for (title, fs_id) in [("Delta", a), ("Alpha", a), ("Charlie", b), ("Bravo", a)] {
+            catalog.insert(movie(title, None), fs_id, format!("/{}.mkv", title).into());
+            assert_indexed(&catalog);
+        }
+        assert_eq!(titles(&catalog), ["Alpha", "Bravo", "Charlie", "Delta"]);
+
+        // Updated with a new title, it moves.
+        catalog.insert(movie("Echo", None), a, "/Alpha.mkv".into());
+        assert_indexed(&catalog);
+        assert_eq!(titles(&catalog), ["Bravo", "Charlie", "Delta", "Echo"]);
+        assert!(catalog.relocate(a, Path::new("/Bravo.mkv"), "/b/Bravo.mkv".into()));
+        assert!(catalog.remove(b, Path::new("/Charlie.mkv")));
+        assert!(!catalog.remove(b, Path::new("/Charlie.mkv")));
+        assert_indexed(&catalog);
+        assert_eq!(catalog.position(a, Path::new("/b/Bravo.mkv")), Some(0));
+        catalog.clear_library(a);
+        assert!(catalog.is_empty());
+        assert_indexed(&catalog);
+    }
+
+    #[test]
+    fn library_stats() {
+        let fs_id = LibraryId::new();
+        let mut catalog = Catalog::default();
+        catalog.insert(movie("Alpha", None), fs_id, "/alpha.mkv".into());
+        catalog.insert(movie("Bravo", None), fs_id, "/bravo.mkv".into());
+        let assets = MovieAssets {
+            poster: true,
+            size: 100,
+            ..Default::default()
+        };
+        catalog.set_assets(fs_id, "/alpha.mkv".into(), assets);
+        catalog.set_assets(fs_id, "/bravo.mkv".into(), assets);
+        // Detected again, not counted twice.
+        catalog.set_assets(fs_id, "/bravo.mkv".into(), assets);
+        catalog.set_nfo(fs_id, Path::new("/alpha.mkv"));
+        catalog.set_nfo(fs_id, Path::new("/alpha.mkv"));
+        let expected = LibraryStats {
+            movies: 2,
+            nfos: 1,
+            posters: 2,
+            fanarts: 0,
+            size: 200,
+        };
+        assert_eq!(catalog.stats(fs_id), expected);
+        catalog.remove(fs_id, Path::new("/alpha.mkv"));
+        let expected = LibraryStats {
+            movies: 1,
+            nfos: 0,
+            posters: 1,
+            fanarts: 0,
+            size: 100,
+        };
+        assert_eq!(catalog.stats(fs_id), expected);
+        catalog.clear_library(fs_id);
+        assert_eq!(catalog.stats(fs_id), LibraryStats::default());
+    }
+
+    #[test]
+    fn tmdb_duplicates() {
+        let (a, b) = (LibraryId::new(), LibraryId::new());
+        let mut catalog = Catalog::default();
+        catalog.insert(movie("Alien", Some(348)), a, "/alien.mkv".into());
+        catalog.insert(movie("Alien", Some(348)), b, "/films/alien.mkv".into());
+        catalog.insert(movie("Aliens", Some(679)), a, "/aliens.mkv".into());
+        let duplicates: Vec<_> = catalog.duplicates().collect();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].0, 348);
+        assert_eq!(duplicates[0].1.len(), 2);
+
+        // Matched again with another movie, or moved.
+        catalog.insert(movie("Alien 3", Some(8077)), b, "/films/alien.mkv".into());
+        assert_eq!(catalog.duplicates().count(), 0);
+        catalog.insert(movie("Aliens", Some(679)), b, "/films/aliens.mkv".into());
+        catalog.relocate(
+            b,
+            Path::new("/films/aliens.mkv"),
+            "/films/2/aliens.mkv".into(),
+        );
+        let duplicates: Vec<_> = catalog.duplicates().collect();
+        let mut expected = vec![
+            (a, Path::new("/aliens.mkv")),
+            (b, Path::new("/films/2/aliens.mkv")),
+        ];
+        expected.sort();
+        assert_eq!(duplicates[0].1, expected);
+        catalog.remove(a, Path::new("/aliens.mkv"));
+        assert_eq!(catalog.duplicates().count(), 0);
+    }
+}
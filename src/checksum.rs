@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::path::Path;
+
+use crate::config::HashAlgorithm;
+use crate::multifs::MultiFs;
+use crate::nfo::Checksum;
+
+/// Bytes hashed at both ends of the file by the OpenSubtitles hash.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Checksum of the file at `path`, read through the library.
+///
+/// The file is read entirely as most backends can not seek, even for the OpenSubtitles hash.
+pub fn compute(fs: &mut MultiFs, path: &Path, algorithm: HashAlgorithm) -> Result<Checksum> {
+    let size = fs
+        .as_mut_rfs()
+        .stat(path)
+        .map_err(|err| anyhow!("Failed to stat {}, causes:\n{:?}", path.display(), err))?
+        .metadata
+        .size;
+    let mut reader = fs.open_reader(path)?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut sha1 = Sha1::new();
+    let mut head = Vec::with_capacity(CHUNK_SIZE);
+    let mut tail = VecDeque::with_capacity(CHUNK_SIZE);
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let data = &buffer[..read];
+        match algorithm {
+            HashAlgorithm::Sha1 => sha1.update(data),
+            HashAlgorithm::OpenSubtitles => {
+                let missing = CHUNK_SIZE.saturating_sub(head.len()).min(data.len());
+                head.extend_from_slice(&data[..missing]);
+                tail.extend(data);
+                let extra = tail.len().saturating_sub(CHUNK_SIZE);
+                tail.drain(..extra);
+            }
+        }
+    }
+    reader.finish()?;
+    let value = match algorithm {
+        HashAlgorithm::Sha1 => format!("{:x}", sha1.finalize()),
+        HashAlgorithm::OpenSubtitles => {
+            let tail: Vec<u8> = tail.into();
+            format!("{:016x}", opensubtitles_hash(size, &head, &tail))
+        }
+    };
+    Ok(Checksum { algorithm, value })
+}
+
+/// File size plus the sum of the little-endian 64-bit words of the first and last 64KB.
+fn opensubtitles_hash(size: u64, head: &[u8], tail: &[u8]) -> u64 {
+    let sum = |data: &[u8]| {
+        data.chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+            .fold(0u64, u64::wrapping_add)
+    };
+    size.wrapping_add(sum(head)).wrapping_add(sum(tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::localfs::LocalFs;
+    use crate::nfo::Movie;
+
+    #[test]
+    fn file_checksums() {
+        let root = std::env::temp_dir().join(format!("mkube-checksums-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("abc.mkv"), b"abc").unwrap();
+        let mut words = [0u8; 16];
+        words[0] = 1;
+        words[8] = 2;
+        std::fs::write(root.join("words.mkv"), words).unwrap();
+        let mut fs = MultiFs::Local(LocalFs::new(root.clone()));
+        let sha1 = compute(&mut fs, &root.join("abc.mkv"), HashAlgorithm::Sha1);
+        let osdb = compute(
+            &mut fs,
+            &root.join("words.mkv"),
+            HashAlgorithm::OpenSubtitles,
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+        let sha1 = sha1.unwrap();
+        assert_eq!(sha1.value, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // Size (16) plus both words, counted in the head and the tail.
+        assert_eq!(osdb.unwrap().value, "0000000000000016");
+
+        let movie = Movie {
+            title: "Movie".into(),
+            checksum: Some(sha1.clone()),
+            ..Default::default()
+        };
+        let xml = quick_xml::se::to_string(&movie).unwrap();
+        assert!(
+            xml.contains(r#"<checksum algorithm="sha1">a9993e"#),
+            "{}",
+            xml
+        );
+        let parsed: Movie = quick_xml::de::from_str(&xml).unwrap();
+        assert_eq!(parsed.checksum, Some(sha1));
+    }
+}
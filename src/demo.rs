@@ -0,0 +1,82 @@
+use std::path::{Path, PathBuf};
+
+use crate::library::{Library, LibraryFlavor, LibraryType};
+use crate::mockfs::MockFs;
+use crate::multifs::MultiFs;
+use crate::provider::MetadataProvider;
+
+const DEMO_ROOT: &str = "/Movies";
+
+/// Movies of the demo library with their TMDB id, the last one has no NFO to try the search.
+const DEMO_MOVIES: &[(&str, Option<u64>)] = &[
+    ("The Matrix (1999)/The.Matrix.1999.1080p.BluRay.mkv", Some(603)),
+    ("Amelie (2001)/Amelie.2001.DVDRip.mkv", Some(194)),
+    ("Spirited.Away.2001.720p.WEB.mkv", None),
+];
+
+/// The in-memory library of the `--demo` mode, its NFOs built from the `provider` answers.
+pub async fn library(provider: &dyn MetadataProvider) -> (Library, MultiFs) {
+    let mut mock = MockFs::new().with_dir(DEMO_ROOT);
+    for (path, _) in DEMO_MOVIES {
+        mock = mock.with_file(Path::new(DEMO_ROOT).join(path), vec![0; 1024]);
+    }
+    let mut fs = MultiFs::Mock(mock);
+    for (path, tmdb_id) in DEMO_MOVIES {
+        let Some(tmdb_id) = tmdb_id else {
+            continue;
+        };
+        let path = Path::new(DEMO_ROOT).join(path);
+        let nfo = match crate::transform_as_nfo(
+            provider,
+            *tmdb_id,
+            &["en".into()],
+            "US",
+            &Default::default(),
+        )
+        .await
+        {
+            Ok(mut nfo) => {
+                let parsed = crate::parser::parse_path(&path);
+                nfo.source = parsed.source;
+                nfo.edition = parsed.edition;
+                nfo
+            }
+            Err(err) => {
+                log::warn!("Failed to build the demo NFO of {}:\n{:?}", tmdb_id, err);
+                continue;
+            }
+        };
+        if let Err(err) = crate::views::movie_manager::write_nfo(&mut fs, &nfo, &path).await {
+            log::warn!("Failed to write the demo NFO of {}:\n{:?}", tmdb_id, err);
+        }
+    }
+    let library = Library {
+        id: Default::default(),
+        fs_type: LibraryType::Local,
+        flavor: LibraryFlavor::Movie,
+        name: "Demo".into(),
+        host: None,
+        username: None,
+        password: None,
+        path: PathBuf::from(DEMO_ROOT),
+        prefered_lang: None,
+        prefered_country: None,
+        port: None,
+        secure: false,
+        passive_mode: true,
+        smb_domain: None,
+        smb_share: None,
+        s3_bucket: None,
+        s3_region: None,
+        // ffmpeg can not open the files of the mock.
+        probe_media: false,
+        max_depth: 4,
+        follow_symlinks: false,
+        asset_naming: Default::default(),
+        fs_profile: Default::default(),
+        keep_unicode: false,
+        read_only: false,
+        ffmpeg_path_mapping: None,
+    };
+    (library, fs)
+}
@@ -0,0 +1,653 @@
+use crate::asyncfs::AsyncMultiFs;
+use crate::config::Downloads;
+use crate::error::MkubeError;
+use crate::multifs::{MultiFs, Transfer};
+use crate::views::modal::{self, Modal};
+use crate::{AppMessage, AppState, ConnectionPool, LibraryId, MESSAGE_SENDER};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, ETAG, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+static MANAGER: RwLock<Option<Arc<DownloadManager>>> = RwLock::new(None);
+
+/// Set up the download queue, again when another profile is opened.
+///
+/// The downloads already running finish in the previous queue.
+pub fn configure(settings: &Downloads) {
+    *MANAGER.write().unwrap() = Some(Arc::new(DownloadManager::new(settings)));
+}
+
+/// The download queue shared by all the tasks.
+pub fn manager() -> Arc<DownloadManager> {
+    MANAGER
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(DownloadManager::new(&Downloads::default())))
+        .clone()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Download {
+    pub url: String,
+    pub output: PathBuf,
+    /// Describes the file in the logs.
+    pub label: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DownloadProgress {
+    pub total: usize,
+    pub done: usize,
+    pub failed: usize,
+}
+
+impl DownloadProgress {
+    pub fn is_finished(&self) -> bool {
+        self.done + self.failed >= self.total
+    }
+}
+
+/// An existing file, not empty.
+#[derive(Clone, Copy, Debug)]
+struct LocalFile {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// ETag of a downloaded file, with the size written.
+#[derive(Clone, Debug)]
+struct Validator {
+    etag: String,
+    size: u64,
+}
+
+/// Whether an existing file matches the remote one, from the headers of a HEAD request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Freshness {
+    UpToDate,
+    Stale,
+    /// The server sent neither the size, a known ETag nor a usable Last-Modified date.
+    Unknown,
+}
+
+enum Fetched {
+    Data {
+        data: Vec<u8>,
+        etag: Option<String>,
+    },
+    UpToDate,
+    /// The existing file is kept, it could not be compared with the remote one.
+    Unverified,
+}
+
+/// Downloads files with a bounded concurrency and a global rate limit.
+///
+/// Files are fetched concurrently but written one after the other, as a library connection
+/// can only do one thing at a time. The connection is only locked to stat and write the files.
+pub struct DownloadManager {
+    permits: Semaphore,
+    next_slot: Mutex<Instant>,
+    interval: Duration,
+    retries: u32,
+    progress: Mutex<DownloadProgress>,
+    /// ETags of the files written since the profile was opened.
+    validators: Mutex<HashMap<(LibraryId, PathBuf), Validator>>,
+}
+
+impl DownloadManager {
+    pub fn new(settings: &Downloads) -> Self {
+        let interval = if settings.requests_per_second > 0f64 {
+            Duration::from_secs_f64(1f64 / settings.requests_per_second)
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            permits: Semaphore::new(settings.max_concurrent.max(1)),
+            next_slot: Mutex::new(Instant::now()),
+            interval,
+            retries: settings.retries,
+            progress: Mutex::new(DownloadProgress::default()),
+            validators: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Download `jobs` to `fs`, files already up to date are skipped unless `force` is set.
+    ///
+    /// Existing files that cannot be compared with the remote ones are kept, the user is then
+    /// asked whether to download them again.
+    ///
+    /// Returns the number of failed downloads.
+    pub async fn run(
+        &self,
+        fs: AsyncMultiFs<'_>,
+        client: &reqwest::Client,
+        jobs: Vec<Download>,
+        force: bool,
+    ) -> usize {
+        let fs_id = fs.fs_id();
+        self.update_progress(|p| p.total += jobs.len());
+        let mut pending: FuturesUnordered<_> = jobs
+            .into_iter()
+            .map(|job| async move {
+                let local = if force {
+                    Ok(None)
+                } else {
+                    let output = job.output.clone();
+                    fs.run(move |fs| Ok::<_, MkubeError>(local_file(fs, &output)))
+                        .await
+                };
+                let known = self
+                    .validators
+                    .lock()
+                    .unwrap()
+                    .get(&(fs_id, job.output.clone()))
+                    .cloned();
+                let res = match local {
+                    Ok(local) => self.fetch(client, &job, local, known).await,
+                    Err(err) => Err(err),
+                };
+                (job, res)
+            })
+            .collect();
+        let mut failed = 0;
+        let mut downloaded = 0;
+        let mut unverified = Vec::new();
+        while let Some((job, res)) = pending.next().await {
+            let kept = matches!(res, Ok(Fetched::Unverified));
+            let res = match res {
+                Ok(Fetched::Data { data, etag }) => {
+                    let output = job.output.clone();
+                    let size = data.len() as u64;
+                    let res = fs.run(move |fs| write_file(fs, &output, &data)).await;
+                    if let (Ok(_), Some(etag)) = (&res, etag) {
+                        self.validators
+                            .lock()
+                            .unwrap()
+                            .insert((fs_id, job.output.clone()), Validator { etag, size });
+                    }
+                    res.map(|_| Some(size))
+                }
+                Ok(Fetched::UpToDate | Fetched::Unverified) => Ok(None),
+                Err(err) => Err(err),
+            };
+            let succeeded = res.is_ok();
+            match res {
+                Ok(Some(len)) => {
+                    log::info!("Sucessfully downloaded file {}.", job.output.display());
+                    downloaded += len;
+                }
+                Ok(None) if kept => {
+                    log::info!(
+                        "Keeping {}, it could not be compared with the remote file.",
+                        job.output.display()
+                    );
+                    unverified.push(job);
+                }
+                Ok(None) => log::info!("Skipping {}, already up to date.", job.output.display()),
+                Err(err) => {
+                    log::error!(
+                        "Failed to download {} ({}). Cause:\n{:?}",
+                        &job.label,
+                        &job.url,
+                        err
+                    );
+                    failed += 1;
+                }
+            }
+            self.update_progress(|p| {
+                if succeeded {
+                    p.done += 1
+                } else {
+                    p.failed += 1
+                }
+            });
+        }
+        if let Some(sender) = MESSAGE_SENDER.get() {
+            let _ = sender.send(AppMessage::Closure(Box::new(
+                move |app_state: &mut AppState| {
+                    app_state.stats.downloaded_bytes += downloaded;
+                    app_state.stats.errors += failed;
+                    vec![]
+                },
+            )));
+        }
+        if !unverified.is_empty() {
+            modal::open_modal(overwrite_modal(fs_id, unverified));
+        }
+        failed
+    }
+
+    /// Fetch the file, unless the local file matches the remote one.
+    ///
+    /// The cached images are not requested again, see [`crate::imagecache`].
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        job: &Download,
+        local: Option<LocalFile>,
+        known: Option<Validator>,
+    ) -> Result<Fetched, MkubeError> {
+        let cache = crate::imagecache::cache();
+        if let Some(cache) = cache.clone() {
+            let url = job.url.clone();
+            if let Ok(Some(data)) = tokio::task::spawn_blocking(move || cache.get(&url)).await {
+                log::debug!("{} found in the image cache.", &job.url);
+                return Ok(match local {
+                    Some(local) if local.size == data.len() as u64 => Fetched::UpToDate,
+                    _ => Fetched::Data { data, etag: None },
+                });
+            }
+        }
+        let fetched = self.download(client, job, local, known).await?;
+        if let (Some(cache), Fetched::Data { data, .. }) = (cache, &fetched) {
+            let (url, data) = (job.url.clone(), data.clone());
+            let _ = tokio::task::spawn_blocking(move || cache.put(&url, &data)).await;
+        }
+        Ok(fetched)
+    }
+
+    async fn download(
+        &self,
+        client: &reqwest::Client,
+        job: &Download,
+        local: Option<LocalFile>,
+        known: Option<Validator>,
+    ) -> Result<Fetched, MkubeError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|err| MkubeError::Other(err.into()))?;
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            self.throttle().await;
+            match try_fetch(client, &job.url, local, known.as_ref()).await {
+                Ok(data) => return Ok(data),
+                Err(err) if err.is_transient() && attempt <= self.retries => {
+                    log::warn!(
+                        "Download of {} failed (attempt {}/{}), retrying in {:?}. Cause:\n{:?}",
+                        &job.url,
+                        attempt,
+                        self.retries + 1,
+                        delay,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Wait for the next request slot of the rate limit.
+    async fn throttle(&self) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let slot = {
+            let mut next = self.next_slot.lock().unwrap();
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    fn update_progress(&self, update: impl FnOnce(&mut DownloadProgress)) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            update(&mut progress);
+            let current = *progress;
+            if current.is_finished() {
+                *progress = DownloadProgress::default();
+            }
+            current
+        };
+        if let Some(sender) = MESSAGE_SENDER.get() {
+            let _ = sender.send(AppMessage::Closure(Box::new(
+                move |app_state: &mut AppState| app_state.set_download_progress(progress),
+            )));
+        }
+    }
+}
+
+/// Ask before downloading again the files that could not be compared with the remote ones.
+fn overwrite_modal(fs_id: LibraryId, jobs: Vec<Download>) -> Modal {
+    Modal::confirm(
+        "Overwrite artworks",
+        format!(
+            "{} artworks already exist but the server does not tell whether they changed. \
+            Download them again?",
+            jobs.len()
+        ),
+        move |_: &mut AppState| {
+            let sender = MESSAGE_SENDER.get().unwrap();
+            sender
+                .send(AppMessage::IOFuture(Box::new(
+                    move |_, client: &reqwest::Client, _, conns: &ConnectionPool| {
+                        Box::pin(async move {
+                            let fs = AsyncMultiFs::new(conns, fs_id);
+                            manager().run(fs, client, jobs, true).await;
+                            vec![]
+                        })
+                    },
+                )))
+                .unwrap();
+            vec![]
+        },
+    )
+}
+
+/// Compare the local file with the headers of a HEAD request.
+///
+/// A different size or ETag means the file changed, the same ETag or an older Last-Modified
+/// date that it did not. The size alone is trusted when nothing else is known.
+fn freshness(headers: &HeaderMap, local: &LocalFile, known: Option<&Validator>) -> Freshness {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    // content_length() reflects the (empty) body of a HEAD response, read the header instead.
+    let size = header(CONTENT_LENGTH).and_then(|len| len.parse::<u64>().ok());
+    if size.is_some_and(|size| size != local.size) {
+        return Freshness::Stale;
+    }
+    // The ETag is only known for the files written by this manager, still as they were written.
+    if let (Some(etag), Some(known)) = (header(ETAG), known) {
+        if known.size == local.size {
+            return if etag == known.etag {
+                Freshness::UpToDate
+            } else {
+                Freshness::Stale
+            };
+        }
+    }
+    let last_modified = header(LAST_MODIFIED).and_then(|date| httpdate::parse_http_date(date).ok());
+    if let (Some(remote), Some(local)) = (last_modified, local.modified) {
+        return if remote <= local {
+            Freshness::UpToDate
+        } else {
+            Freshness::Stale
+        };
+    }
+    match size {
+        Some(_) => Freshness::UpToDate,
+        None => Freshness::Unknown,
+    }
+}
+
+async fn try_fetch(
+    client: &reqwest::Client,
+    url: &str,
+    local: Option<LocalFile>,
+    known: Option<&Validator>,
+) -> Result<Fetched, MkubeError> {
+    if let Some(local) = local {
+        match client.head(url).send().await {
+            Ok(rsp) => match freshness(rsp.headers(), &local, known) {
+                Freshness::UpToDate => return Ok(Fetched::UpToDate),
+                Freshness::Stale => (),
+                Freshness::Unknown => return Ok(Fetched::Unverified),
+            },
+            // The remote file cannot be checked, download it again.
+            Err(err) => {
+                log::warn!(
+                    "Failed to check the remote file {}, causes:\n{:?}",
+                    url,
+                    err
+                );
+            }
+        }
+    }
+    let failed = |reason: String, transient: bool| MkubeError::Provider {
+        request: format!("Download of {}", url),
+        reason,
+        transient,
+    };
+    let rsp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| failed(format!("{:?}", err), true))?;
+    let status = rsp.status();
+    if !status.is_success() {
+        let transient = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+        return Err(failed(
+            format!("Answered with status {}.", status),
+            transient,
+        ));
+    }
+    let etag = rsp
+        .headers()
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_owned);
+    match rsp.bytes().await {
+        Ok(data) => Ok(Fetched::Data {
+            data: data.to_vec(),
+            etag,
+        }),
+        Err(err) => Err(failed(
+            format!("Failed to read incoming data, causes:\n{:?}", err),
+            true,
+        )),
+    }
+}
+
+/// The existing file, if not empty.
+fn local_file(fs: &mut MultiFs, output: &Path) -> Option<LocalFile> {
+    match fs.as_mut_rfs().stat(output) {
+        Ok(file) if file.metadata.size > 0 => Some(LocalFile {
+            size: file.metadata.size,
+            modified: file.metadata.modified,
+        }),
+        _ => None,
+    }
+}
+
+fn write_file(fs: &mut MultiFs, output: &Path, data: &[u8]) -> Result<(), MkubeError> {
+    let io = |reason: String| MkubeError::Io {
+        path: output.to_owned(),
+        reason,
+    };
+    let label = output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut writer = fs
+        .create_writer(output)
+        .map_err(|err| io(format!("{:?}", err)))?;
+    Transfer::new(label, data.len() as u64)
+        .copy(&mut &data[..], &mut writer)
+        .map_err(|err| io(format!("{:?}", err)))?;
+    writer.finish().map_err(|err| io(format!("{:?}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockfs::MockFs;
+    use remotefs::RemoteErrorType;
+    use reqwest::header::HeaderValue;
+    use std::io::{Read, Write};
+
+    fn headers(entries: &[(reqwest::header::HeaderName, &str)]) -> HeaderMap {
+        entries
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_str(value).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn freshness_from_headers() {
+        let written = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let local = LocalFile {
+            size: 4,
+            modified: Some(written),
+        };
+        let known = Validator {
+            etag: "\"v1\"".into(),
+            size: 4,
+        };
+        let before = httpdate::fmt_http_date(written - Duration::from_secs(60));
+        let after = httpdate::fmt_http_date(written + Duration::from_secs(60));
+        let cases = [
+            (headers(&[(CONTENT_LENGTH, "4")]), None, Freshness::UpToDate),
+            (
+                headers(&[(CONTENT_LENGTH, "5")]),
+                Some(&known),
+                Freshness::Stale,
+            ),
+            (headers(&[]), None, Freshness::Unknown),
+            (headers(&[(ETAG, "\"v1\"")]), None, Freshness::Unknown),
+            (
+                headers(&[(ETAG, "\"v1\"")]),
+                Some(&known),
+                Freshness::UpToDate,
+            ),
+            (
+                headers(&[(CONTENT_LENGTH, "4"), (ETAG, "\"v2\"")]),
+                Some(&known),
+                Freshness::Stale,
+            ),
+            (
+                headers(&[(LAST_MODIFIED, &before)]),
+                None,
+                Freshness::UpToDate,
+            ),
+            (headers(&[(LAST_MODIFIED, &after)]), None, Freshness::Stale),
+            (
+                headers(&[(LAST_MODIFIED, "yesterday")]),
+                None,
+                Freshness::Unknown,
+            ),
+        ];
+        for (headers, known, expected) in cases {
+            assert_eq!(
+                freshness(&headers, &local, known),
+                expected,
+                "{:?}",
+                headers
+            );
+        }
+        // Without the local date, Last-Modified tells nothing.
+        let undated = LocalFile {
+            modified: None,
+            ..local
+        };
+        let dated = headers(&[(LAST_MODIFIED, &before)]);
+        assert_eq!(freshness(&dated, &undated, None), Freshness::Unknown);
+        // The file changed since it was written, its ETag is no longer trusted.
+        let edited = LocalFile { size: 3, ..undated };
+        let tagged = headers(&[(ETAG, "\"v1\"")]);
+        assert_eq!(
+            freshness(&tagged, &edited, Some(&known)),
+            Freshness::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_artwork_download() {
+        // Serves `/poster.jpg`, `/clearart.png` without its size on HEAD, everything else is missing.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let get = request.starts_with("GET");
+                let rsp = if request.contains(" /poster.jpg ") {
+                    let body = if get { "JPEG" } else { "" };
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\n{}",
+                        body
+                    )
+                } else if request.contains(" /clearart.png ") && get {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nETag: \"v1\"\r\nConnection: close\r\n\r\nPNG!".into()
+                } else if request.contains(" /clearart.png ") {
+                    "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".into()
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .into()
+                };
+                let _ = stream.write_all(rsp.as_bytes());
+            }
+        });
+        let mock = MockFs::new()
+            .with_dir("/movies/Alpha")
+            .with_dir("/movies/Locked");
+        mock.fail("/movies/Locked", RemoteErrorType::FileCreateDenied);
+        let conns = ConnectionPool::default();
+        let fs_id = LibraryId::new();
+        conns
+            .lock()
+            .await
+            .insert(fs_id, MultiFs::Mock(mock.clone()));
+        let fs = AsyncMultiFs::new(&conns, fs_id);
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        let manager = DownloadManager::new(&Downloads {
+            max_concurrent: 2,
+            requests_per_second: 0.0,
+            retries: 0,
+        });
+        let job = |url: &str, output: &str| Download {
+            url: format!("{}{}", base, url),
+            output: output.into(),
+            label: output.into(),
+        };
+        let jobs = vec![
+            job("/poster.jpg", "/movies/Alpha/poster.jpg"),
+            job("/fanart.jpg", "/movies/Alpha/fanart.jpg"),
+            job("/poster.jpg", "/movies/Locked/poster.jpg"),
+        ];
+        assert_eq!(manager.run(fs, &client, jobs, false).await, 2);
+        assert_eq!(
+            mock.read("/movies/Alpha/poster.jpg").as_deref(),
+            Some(&b"JPEG"[..])
+        );
+        assert!(mock.read("/movies/Alpha/fanart.jpg").is_none());
+        // Same size as the remote file, it is not downloaded again.
+        let mock = mock.with_file("/movies/Alpha/poster.jpg", "OLD!");
+        let again = vec![job("/poster.jpg", "/movies/Alpha/poster.jpg")];
+        assert_eq!(manager.run(fs, &client, again, false).await, 0);
+        assert_eq!(
+            mock.read("/movies/Alpha/poster.jpg").as_deref(),
+            Some(&b"OLD!"[..])
+        );
+        // Without its size nor a known ETag, the existing file is kept until forced.
+        let mock = mock.with_file("/movies/Alpha/clearart.png", "OLD!");
+        let clearart = || vec![job("/clearart.png", "/movies/Alpha/clearart.png")];
+        assert_eq!(manager.run(fs, &client, clearart(), false).await, 0);
+        assert_eq!(
+            mock.read("/movies/Alpha/clearart.png").as_deref(),
+            Some(&b"OLD!"[..])
+        );
+        assert_eq!(manager.run(fs, &client, clearart(), true).await, 0);
+        assert_eq!(
+            mock.read("/movies/Alpha/clearart.png").as_deref(),
+            Some(&b"PNG!"[..])
+        );
+        // Same ETag as the written file, it is not downloaded again.
+        let mock = mock.with_file("/movies/Alpha/clearart.png", "OLD!");
+        assert_eq!(manager.run(fs, &client, clearart(), false).await, 0);
+        assert_eq!(
+            mock.read("/movies/Alpha/clearart.png").as_deref(),
+            Some(&b"OLD!"[..])
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::library::LibraryId;
+
+/// Errors of the library API, telling apart what can be retried from what needs the user.
+#[derive(Debug, Error)]
+pub enum MkubeError {
+    /// The library is offline and reconnecting failed.
+    #[error("Library {0} is offline, reconnection failed.")]
+    Connection(LibraryId),
+    /// A call to the library took too long, it goes on in the background.
+    #[error("Library {0} is not answering, a call timed out.")]
+    Timeout(LibraryId),
+    /// The library was removed, or edited and not saved again.
+    #[error("Library {0} does not exist anymore.")]
+    UnknownLibrary(LibraryId),
+    /// The library was edited or removed while it was scanned.
+    #[error("Library {0} changed during the scan.")]
+    ScanCancelled(LibraryId),
+    #[error("No {what} found at {}.", path.display())]
+    NotFound { what: &'static str, path: PathBuf },
+    #[error("Failed to parse {}, causes:\n{reason}", path.display())]
+    Parse { path: PathBuf, reason: String },
+    /// TMDB or an artwork server failed, `transient` if asking again later might work.
+    #[error("{request} failed, causes:\n{reason}")]
+    Provider {
+        request: String,
+        reason: String,
+        transient: bool,
+    },
+    #[error("Failed to access {}, causes:\n{reason}", path.display())]
+    Io { path: PathBuf, reason: String },
+    /// The destination of a rename or a move is already used.
+    #[error("{} already exists.", .0.display())]
+    Collision(PathBuf),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl MkubeError {
+    /// Worth retrying later without any change from the user.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            MkubeError::Connection(_) | MkubeError::Timeout(_) => true,
+            MkubeError::Provider { transient, .. } => *transient,
+            _ => false,
+        }
+    }
+
+    /// Short message for the status bar, the details go to the logs.
+    pub fn notice(&self) -> String {
+        match self {
+            MkubeError::Connection(_) => "the library is offline, check its connection".into(),
+            MkubeError::Timeout(_) => "the library is too slow to answer, try again later".into(),
+            MkubeError::UnknownLibrary(_) | MkubeError::ScanCancelled(_) => {
+                "the library was edited or removed".into()
+            }
+            MkubeError::NotFound { what, path } => format!("no {} at {}", what, path.display()),
+            MkubeError::Parse { path, .. } => format!("{} is invalid", path.display()),
+            MkubeError::Provider { transient, .. } if *transient => {
+                "the server is unavailable, try again later".into()
+            }
+            MkubeError::Provider { request, .. } => format!("{} was refused", request),
+            MkubeError::Io { path, .. } => format!("can't access {}", path.display()),
+            MkubeError::Collision(path) => format!("{} already exists", path.display()),
+            MkubeError::Other(_) => "see the logs".into(),
+        }
+    }
+
+    /// The first MkubeError in the causes of `err`, anyhow contexts included.
+    pub fn find(err: &anyhow::Error) -> Option<&MkubeError> {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<MkubeError>())
+    }
+}
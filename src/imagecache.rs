@@ -0,0 +1,143 @@
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::ImageCache;
+
+static SETTINGS: RwLock<Option<ImageCache>> = RwLock::new(None);
+
+/// Set the cache settings, again each time the configuration is reloaded.
+///
+/// Nothing is cached before the first call.
+pub fn configure(settings: &ImageCache) {
+    *SETTINGS.write().unwrap() = Some(settings.clone());
+}
+
+/// The cache of the configured settings, None when it is disabled.
+pub fn cache() -> Option<DiskCache> {
+    let settings = SETTINGS.read().unwrap().clone()?;
+    if settings.max_size == 0 {
+        return None;
+    }
+    let dir = settings.dir.or_else(|| {
+        directories::ProjectDirs::from("", "", "mkube").map(|dirs| dirs.cache_dir().join("images"))
+    })?;
+    Some(DiskCache::new(dir, settings.max_size))
+}
+
+/// Images downloaded from TMDB, one file per URL, the least recently used dropped past
+/// `max_size` bytes.
+///
+/// The URLs of TMDB images never change content, a cached image is never out of date.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiskCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, max_size: u64) -> Self {
+        Self { dir, max_size }
+    }
+
+    /// The image downloaded from `url`, if cached. It becomes the most recently used.
+    pub fn get(&self, url: &str) -> Option<Vec<u8>> {
+        let data = std::fs::read(self.path(url)).ok()?;
+        // The modification time tells the last use, written again to update it.
+        let _ = self.store(url, &data);
+        Some(data)
+    }
+
+    /// Keep the image downloaded from `url`, dropping the least recently used ones past the
+    /// size limit. Failures are only logged, the image is downloaded again next time.
+    pub fn put(&self, url: &str, data: &[u8]) {
+        if data.len() as u64 > self.max_size {
+            return;
+        }
+        if let Err(err) = self.store(url, data).and_then(|()| self.evict()) {
+            log::warn!("Failed to cache the image {}, causes:\n{:?}", url, err);
+        }
+    }
+
+    /// Size of the cached images, in bytes.
+    pub fn size(&self) -> u64 {
+        entries(&self.dir).iter().map(|(_, len, _)| len).sum()
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:x}", Sha1::digest(url.as_bytes())))
+    }
+
+    fn store(&self, url: &str, data: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(url);
+        // Written aside then renamed, a reader never gets half an image.
+        let part = path.with_extension("part");
+        std::fs::write(&part, data)?;
+        std::fs::rename(&part, &path)
+    }
+
+    fn evict(&self) -> std::io::Result<()> {
+        let mut entries = entries(&self.dir);
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        // The least recently used first.
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            std::fs::remove_file(&path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+/// Last use, size and path of the files in `dir`.
+fn entries(dir: &Path) -> Vec<(SystemTime, u64, PathBuf)> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| {
+                let used = metadata.modified().unwrap_or(UNIX_EPOCH);
+                (used, metadata.len(), entry.path())
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn image_cache_eviction() {
+        let dir = std::env::temp_dir().join(format!("mkube-images-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let url = |name: &str| format!("https://image.tmdb.org/t/p/original/{}.jpg", name);
+        let pause = || std::thread::sleep(Duration::from_millis(20));
+        let cache = DiskCache::new(dir.clone(), 10);
+        cache.put(&url("a"), b"AAAA");
+        pause();
+        cache.put(&url("b"), b"BBBB");
+        pause();
+        // Used again, `a` is kept over `b`.
+        assert_eq!(cache.get(&url("a")).as_deref(), Some(&b"AAAA"[..]));
+        pause();
+        cache.put(&url("c"), b"CCCC");
+        assert!(cache.get(&url("b")).is_none());
+        assert!(cache.get(&url("a")).is_some());
+        assert!(cache.get(&url("c")).is_some());
+        assert_eq!(cache.size(), 8);
+        // Larger than the whole cache, it is not kept.
+        cache.put(&url("d"), b"DDDDDDDDDDDD");
+        assert!(cache.get(&url("d")).is_none());
+        assert_eq!(cache.size(), 8);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,524 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    OpenHome,
+    OpenSettings,
+    Help,
+    NextField,
+    PreviousField,
+    Reload,
+    Search,
+    Edit,
+    EditSource,
+    DownloadArtworks,
+    ForceDownloadArtworks,
+    RefreshMetadata,
+    Rename,
+    DeleteMovie,
+    MoveMovie,
+    SourceTv,
+    SourceBluray,
+    SourceDvd,
+    SourceUhdBluray,
+    SourceWeb,
+    Sort,
+    AutoIdentify,
+    Review,
+    ProbeMedia,
+    GrabThumb,
+    VerifyChecksum,
+    Tasks,
+    ToggleOffline,
+    KodiScan,
+    KodiClean,
+    SwitchProfile,
+    QuickOpen,
+    Columns,
+    GroupByLibrary,
+    ReloadLibrary,
+    Recent,
+    Pin,
+    ToggleWatched,
+    Normalize,
+    HealthReport,
+    RestoreNfo,
+    SaveSource,
+    CloseSource,
+}
+
+impl Action {
+    pub const ALL: [Action; 43] = [
+        Action::OpenHome,
+        Action::OpenSettings,
+        Action::Help,
+        Action::NextField,
+        Action::PreviousField,
+        Action::Reload,
+        Action::Search,
+        Action::Edit,
+        Action::EditSource,
+        Action::DownloadArtworks,
+        Action::ForceDownloadArtworks,
+        Action::RefreshMetadata,
+        Action::Rename,
+        Action::DeleteMovie,
+        Action::MoveMovie,
+        Action::SourceTv,
+        Action::SourceBluray,
+        Action::SourceDvd,
+        Action::SourceUhdBluray,
+        Action::SourceWeb,
+        Action::Sort,
+        Action::AutoIdentify,
+        Action::Review,
+        Action::ProbeMedia,
+        Action::GrabThumb,
+        Action::VerifyChecksum,
+        Action::Tasks,
+        Action::ToggleOffline,
+        Action::KodiScan,
+        Action::KodiClean,
+        Action::SwitchProfile,
+        Action::QuickOpen,
+        Action::Columns,
+        Action::GroupByLibrary,
+        Action::ReloadLibrary,
+        Action::Recent,
+        Action::Pin,
+        Action::ToggleWatched,
+        Action::Normalize,
+        Action::HealthReport,
+        Action::RestoreNfo,
+        Action::SaveSource,
+        Action::CloseSource,
+    ];
+
+    /// Name of the action in the configuration file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::OpenHome => "open_home",
+            Action::OpenSettings => "open_settings",
+            Action::Help => "help",
+            Action::NextField => "next_field",
+            Action::PreviousField => "previous_field",
+            Action::Reload => "reload",
+            Action::Search => "search",
+            Action::Edit => "edit",
+            Action::EditSource => "edit_source",
+            Action::DownloadArtworks => "download_artworks",
+            Action::ForceDownloadArtworks => "force_download_artworks",
+            Action::RefreshMetadata => "refresh_metadata",
+            Action::Rename => "rename",
+            Action::DeleteMovie => "delete_movie",
+            Action::MoveMovie => "move_movie",
+            Action::SourceTv => "source_tv",
+            Action::SourceBluray => "source_bluray",
+            Action::SourceDvd => "source_dvd",
+            Action::SourceUhdBluray => "source_uhd_bluray",
+            Action::SourceWeb => "source_web",
+            Action::Sort => "sort",
+            Action::AutoIdentify => "auto_identify",
+            Action::Review => "review",
+            Action::ProbeMedia => "probe_media",
+            Action::GrabThumb => "grab_thumb",
+            Action::VerifyChecksum => "verify_checksum",
+            Action::Tasks => "tasks",
+            Action::ToggleOffline => "toggle_offline",
+            Action::KodiScan => "kodi_scan",
+            Action::KodiClean => "kodi_clean",
+            Action::SwitchProfile => "switch_profile",
+            Action::QuickOpen => "quick_open",
+            Action::Columns => "columns",
+            Action::GroupByLibrary => "group_by_library",
+            Action::ReloadLibrary => "reload_library",
+            Action::Recent => "recent",
+            Action::Pin => "pin",
+            Action::ToggleWatched => "toggle_watched",
+            Action::Normalize => "normalize",
+            Action::HealthReport => "health_report",
+            Action::RestoreNfo => "restore_nfo",
+            Action::SaveSource => "save_source",
+            Action::CloseSource => "close_source",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::OpenHome => "Go to the movie table",
+            Action::OpenSettings => "Go to the settings",
+            Action::Help => "Show this help",
+            Action::NextField => "Focus next input",
+            Action::PreviousField => "Focus previous input",
+            Action::Reload => "Reload libraries",
+            Action::Search => "Search movie (on TMDB)",
+            Action::Edit => "Edit movie NFO",
+            Action::EditSource => "Edit raw NFO (XML)",
+            Action::DownloadArtworks => "Download missing artworks",
+            Action::ForceDownloadArtworks => "Download all artworks",
+            Action::RefreshMetadata => "Refresh metadata from TMDB",
+            Action::Rename => "Rename movie files",
+            Action::DeleteMovie => "Delete movie files",
+            Action::MoveMovie => "Move movie to another library",
+            Action::SourceTv => "Set source as TV",
+            Action::SourceBluray => "Set source as Bluray",
+            Action::SourceDvd => "Set source as DVD",
+            Action::SourceUhdBluray => "Set source as 4K Bluray",
+            Action::SourceWeb => "Set source as WEB",
+            Action::Sort => "Sort by title/year/rating",
+            Action::AutoIdentify => "Auto-identify all",
+            Action::Review => "Review next unmatched movie",
+            Action::ProbeMedia => "Probe stream details (ffmpeg)",
+            Action::GrabThumb => "Grab a frame as thumbnail (ffmpeg)",
+            Action::VerifyChecksum => "Verify the file checksum",
+            Action::Tasks => "Show the running tasks",
+            Action::ToggleOffline => "Toggle offline mode",
+            Action::KodiScan => "Scan the Kodi library",
+            Action::KodiClean => "Clean the Kodi library",
+            Action::SwitchProfile => "Switch configuration profile",
+            Action::QuickOpen => "Jump to a movie (fuzzy find)",
+            Action::Columns => "Choose the table columns",
+            Action::GroupByLibrary => "Group the movies by library",
+            Action::ReloadLibrary => "Reload the selected library",
+            Action::Recent => "Recently edited and pinned movies",
+            Action::Pin => "Pin/unpin the selected movie",
+            Action::ToggleWatched => "Mark as watched/unwatched",
+            Action::Normalize => "Normalize countries/studios of all NFOs",
+            Action::HealthReport => "Check all NFOs (health report)",
+            Action::RestoreNfo => "Restore the NFO from a backup",
+            Action::SaveSource => "Save raw NFO (XML editor)",
+            Action::CloseSource => "Close raw NFO (XML editor)",
+        }
+    }
+
+    /// Views where the action is handled, None for the actions available everywhere.
+    pub fn context(&self) -> Option<KeyContext> {
+        match self {
+            Action::OpenHome
+            | Action::OpenSettings
+            | Action::Tasks
+            | Action::ToggleOffline
+            | Action::KodiScan
+            | Action::KodiClean
+            | Action::SwitchProfile => None,
+            Action::NextField | Action::PreviousField => Some(KeyContext::SettingsForm),
+            Action::SaveSource | Action::CloseSource => Some(KeyContext::Editor),
+            _ => Some(KeyContext::Table),
+        }
+    }
+
+    /// Whether the action is handled in `context`.
+    pub fn applies_to(&self, context: KeyContext) -> bool {
+        match self {
+            // Typed in the views with text inputs, they only have the `HELP_KEY`.
+            Action::Help => matches!(context, KeyContext::Table | KeyContext::Settings),
+            _ => self.context().map_or(true, |c| c == context),
+        }
+    }
+
+    /// Actions calling TMDB or downloading images, disabled in offline mode.
+    pub fn needs_network(&self) -> bool {
+        matches!(
+            self,
+            Action::Search
+                | Action::DownloadArtworks
+                | Action::ForceDownloadArtworks
+                | Action::RefreshMetadata
+                | Action::AutoIdentify
+                | Action::Review
+        )
+    }
+
+    /// Actions writing to the library of the selected movie, disabled for read-only libraries.
+    pub fn writes_library(&self) -> bool {
+        matches!(
+            self,
+            Action::Search
+                | Action::DownloadArtworks
+                | Action::ForceDownloadArtworks
+                | Action::RefreshMetadata
+                | Action::Rename
+                | Action::DeleteMovie
+                | Action::MoveMovie
+                | Action::SourceTv
+                | Action::SourceBluray
+                | Action::SourceDvd
+                | Action::SourceUhdBluray
+                | Action::SourceWeb
+                | Action::ProbeMedia
+                | Action::GrabThumb
+                | Action::ToggleWatched
+                | Action::RestoreNfo
+        )
+    }
+
+    fn default_key(&self) -> &'static str {
+        match self {
+            Action::OpenHome => "alt+h",
+            Action::OpenSettings => "alt+s",
+            Action::Help => "?",
+            Action::NextField => "tab",
+            Action::PreviousField => "backtab",
+            Action::Reload => "r",
+            Action::Search => "s",
+            Action::Edit => "e",
+            Action::EditSource => "E",
+            Action::DownloadArtworks => "a",
+            Action::ForceDownloadArtworks => "A",
+            Action::RefreshMetadata => "f",
+            Action::Rename => "n",
+            Action::DeleteMovie => "delete",
+            Action::MoveMovie => "m",
+            Action::SourceTv => "t",
+            Action::SourceBluray => "b",
+            Action::SourceDvd => "d",
+            Action::SourceUhdBluray => "u",
+            Action::SourceWeb => "w",
+            Action::Sort => "o",
+            Action::AutoIdentify => "i",
+            Action::Review => "v",
+            Action::ProbeMedia => "p",
+            Action::GrabThumb => "g",
+            Action::VerifyChecksum => "V",
+            Action::Tasks => "alt+t",
+            Action::ToggleOffline => "alt+o",
+            Action::KodiScan => "alt+k",
+            Action::KodiClean => "alt+c",
+            Action::SwitchProfile => "alt+p",
+            Action::QuickOpen => "ctrl+p",
+            Action::Columns => "c",
+            Action::GroupByLibrary => "l",
+            Action::ReloadLibrary => "R",
+            Action::Recent => "ctrl+r",
+            Action::Pin => "P",
+            Action::ToggleWatched => "W",
+            Action::Normalize => "N",
+            Action::HealthReport => "H",
+            Action::RestoreNfo => "B",
+            Action::SaveSource => "ctrl+s",
+            Action::CloseSource => "esc",
+        }
+    }
+}
+
+/// Key to open the help from any view, the help binding being typed in the text inputs.
+pub const HELP_KEY: KeyBinding = KeyBinding {
+    code: KeyCode::F(1),
+    modifiers: KeyModifiers::NONE,
+};
+
+/// Views with their own keys, to list them in the help popup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyContext {
+    Table,
+    Search,
+    Editor,
+    Settings,
+    SettingsForm,
+}
+
+impl KeyContext {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyContext::Table => "Movie table",
+            KeyContext::Search => "TMDB search",
+            KeyContext::Editor => "NFO editor",
+            KeyContext::Settings => "Settings",
+            KeyContext::SettingsForm => "Settings form",
+        }
+    }
+
+    /// Keys handled by the view itself, they can not be rebound.
+    pub fn fixed_keys(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            KeyContext::Table => &[
+                ("up/down", "Select movie"),
+                ("enter", "Fold/unfold a library group"),
+            ],
+            KeyContext::Search => &[
+                ("tab", "Focus next input"),
+                ("backtab", "Focus previous input"),
+                ("enter", "Search, or pick the selected result"),
+                ("up/down", "Select result"),
+                ("pgup/pgdn", "Previous/next result page"),
+            ],
+            KeyContext::Editor => &[
+                ("tab", "Focus next field"),
+                ("backtab", "Focus previous field"),
+                ("enter", "Open tab, next column or tag picker"),
+                ("ctrl+d", "Delete cast/crew row"),
+                ("ctrl+f", "Search person on TMDB"),
+                ("alt+up/dn", "Move cast/crew row"),
+                ("esc", "Close picker or editor"),
+            ],
+            KeyContext::Settings => &[("up/down", "Select entry"), ("enter", "Open entry")],
+            KeyContext::SettingsForm => &[
+                ("enter", "Press button or toggle checkbox"),
+                ("ctrl+r", "Show/hide a password"),
+                ("ctrl+o", "Browse from the path field"),
+            ],
+        }
+    }
+}
+
+/// A key with its modifiers, written like `r`, `A`, `alt+s` or `ctrl+f5` in the configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, kev: &KeyEvent) -> bool {
+        // Shift is already part of the char (or of BackTab), some terminals do not report it.
+        let ignored = match self.code {
+            KeyCode::Char(_) | KeyCode::BackTab => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        self.code == kev.code && (self.modifiers - ignored) == (kev.modifiers - ignored)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+        while let Some((modifier, rest)) = key.split_once('+').filter(|(_, r)| !r.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{}` in `{}`", modifier, s)),
+            };
+            key = rest;
+        }
+        let code = match key.to_lowercase().as_str() {
+            _ if key.chars().count() == 1 => KeyCode::Char(key.chars().next().unwrap_or(' ')),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers -= KeyModifiers::SHIFT;
+                KeyCode::BackTab
+            }
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            k => match k.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) => KeyCode::F(n),
+                None => return Err(format!("unknown key `{}`", s)),
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                text.push_str(name);
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => text.push_str("Space"),
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::F(n) => text.push_str(&format!("F{}", n)),
+            KeyCode::BackTab => text.push_str("Shift+Tab"),
+            code => text.push_str(&format!("{:?}", code)),
+        }
+        // Padded, the help popup aligns the keys.
+        f.pad(&text)
+    }
+}
+
+/// Keys bound to each action, missing actions use their default key.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(from = "BTreeMap<String, String>")]
+#[serde(into = "BTreeMap<String, String>")]
+pub struct Keybindings {
+    bindings: HashMap<Action, KeyBinding>,
+}
+
+impl Keybindings {
+    pub fn key(&self, action: Action) -> KeyBinding {
+        self.bindings
+            .get(&action)
+            .copied()
+            .unwrap_or_else(|| default_binding(action))
+    }
+
+    pub fn is(&self, action: Action, kev: &KeyEvent) -> bool {
+        self.key(action).matches(kev)
+    }
+
+    /// First action bound to this key event, among `actions`.
+    pub fn action(&self, actions: &[Action], kev: &KeyEvent) -> Option<Action> {
+        actions.iter().copied().find(|a| self.is(*a, kev))
+    }
+}
+
+fn default_binding(action: Action) -> KeyBinding {
+    action
+        .default_key()
+        .parse()
+        .expect("default key bindings are valid")
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .iter()
+                .map(|a| (*a, default_binding(*a)))
+                .collect(),
+        }
+    }
+}
+
+impl From<BTreeMap<String, String>> for Keybindings {
+    fn from(value: BTreeMap<String, String>) -> Self {
+        let mut keys = Keybindings::default();
+        for (name, key) in value {
+            let action = Action::ALL.iter().find(|a| a.name() == name);
+            match (action, key.parse::<KeyBinding>()) {
+                (Some(action), Ok(binding)) => {
+                    keys.bindings.insert(*action, binding);
+                }
+                (None, _) => log::error!("Unknown key binding action `{}`, ignored.", name),
+                (_, Err(err)) => log::error!("Invalid key binding for `{}`: {}", name, err),
+            }
+        }
+        keys
+    }
+}
+
+impl From<Keybindings> for BTreeMap<String, String> {
+    fn from(value: Keybindings) -> Self {
+        Action::ALL
+            .iter()
+            .map(|a| (a.name().to_owned(), value.key(*a).to_string()))
+            .collect()
+    }
+}
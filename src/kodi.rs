@@ -0,0 +1,87 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+
+use crate::config::Kodi;
+use crate::{AppEvent, AppMessage, AppState};
+
+pub const SCAN: &str = "VideoLibrary.Scan";
+pub const CLEAN: &str = "VideoLibrary.Clean";
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+/// Call a JSON-RPC method without parameters on the Kodi server.
+pub async fn call(client: &reqwest::Client, kodi: &Kodi, method: &str) -> Result<()> {
+    if !kodi.is_enabled() {
+        bail!("No Kodi host is configured.");
+    }
+    let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+    let mut request = client
+        .post(kodi.endpoint())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string());
+    if let Some(username) = kodi.username.as_deref() {
+        request = request.basic_auth(username, kodi.password.as_deref());
+    }
+    let response = request
+        .send()
+        .await
+        .context(format!("Kodi is not reachable at {}", kodi.endpoint()))?
+        .error_for_status()?;
+    let text = response.text().await?;
+    let rpc: RpcResponse = serde_json::from_str(&text)
+        .map_err(|err| anyhow!("Invalid Kodi response `{}`, causes:\n{:?}", text, err))?;
+    match rpc.error {
+        Some(err) => bail!("Kodi refused {}: {} ({})", method, err.message, err.code),
+        None => Ok(()),
+    }
+}
+
+/// Call `method` on the Kodi server, the outcome is reported as a notice.
+pub fn message(method: &'static str) -> AppMessage {
+    AppMessage::HttpFuture(Box::new(move |app_state: &mut AppState, client, _| {
+        let kodi = app_state.config.kodi.clone();
+        Box::pin(async move {
+            let notice = match call(client, &kodi, method).await {
+                Ok(()) => format!("Kodi: {} requested.", method),
+                Err(err) => {
+                    log::error!("Kodi {} failed, causes:\n{:?}", method, err);
+                    format!("Kodi: {} failed, see the logs.", method)
+                }
+            };
+            vec![AppEvent::ContinuationFuture(Box::new(
+                move |app_state: &mut AppState| {
+                    app_state.notice = Some(notice);
+                    Box::pin(async { vec![] })
+                },
+            ))]
+        })
+    }))
+}
+
+/// Post-save hook, scan the Kodi library when `scan_on_save` is enabled.
+pub fn after_save(kodi: &Kodi) -> Option<AppEvent> {
+    if !kodi.is_enabled() || !kodi.scan_on_save {
+        return None;
+    }
+    let kodi = kodi.clone();
+    Some(AppEvent::ContinuationHttpFuture(Box::new(
+        move |_, client, _| {
+            Box::pin(async move {
+                if let Err(err) = call(client, &kodi, SCAN).await {
+                    log::error!("Kodi library scan failed, causes:\n{:?}", err);
+                }
+                vec![]
+            })
+        },
+    )))
+}
@@ -1,30 +1,60 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Result};
 use core::convert::AsRef;
 use futures_core::stream::Stream;
-use remotefs::fs::Metadata;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
-use std::path::PathBuf;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::task::{Context, Poll};
-use std::{io::Cursor, io::Seek};
-use tmdb_api::client::Client as TmdbClient;
 use tmdb_api::{
-    movie::credits::MovieCredits, movie::details::MovieDetails, movie::images::MovieImages,
-    prelude::*,
+    common::image::Image, movie::credits::MovieCredits, movie::details::MovieDetails,
+    movie::images::MovieImages, movie::release_dates::MovieReleaseDates, prelude::*,
 };
 use tokio::sync::mpsc::UnboundedSender;
 use url::Url;
 
+pub mod asyncfs;
+pub mod backups;
+pub mod cachedfs;
+pub mod catalog;
+pub mod checksum;
 pub mod config;
+#[cfg(any(test, feature = "demo"))]
+pub mod demo;
+pub mod downloads;
+pub mod error;
+pub mod imagecache;
+pub mod keys;
+pub mod kodi;
 pub mod library;
+pub mod lint;
 pub mod localfs;
+pub mod logging;
+#[cfg(any(test, feature = "demo"))]
+pub mod mockfs;
 pub mod multifs;
 pub mod nfo;
+pub mod parser;
+pub mod probing;
+pub mod provider;
+pub mod recent;
+#[cfg(feature = "s3")]
+pub mod s3fs;
+pub mod stats;
+#[cfg(test)]
+mod test_util;
 pub mod util;
 pub mod views;
 
-use multifs::{MultiFs, OwnedCursor};
+use error::MkubeError;
+use library::{Library, LibraryId};
+use multifs::{ConnectionStatus, MultiFs};
+use provider::MetadataProvider;
 pub use views::{AppEvent, AppMessage, AppState};
 
 const VIDEO_EXTENSIONS: &'static [&'static str] = &[
@@ -32,96 +62,494 @@ const VIDEO_EXTENSIONS: &'static [&'static str] = &[
 ];
 pub static MESSAGE_SENDER: OnceLock<UnboundedSender<AppMessage>> = OnceLock::new();
 
-pub type ConnectionPool = tokio::sync::Mutex<Vec<Option<crate::multifs::MultiFs>>>;
-
-pub async fn download_file<'a, U>(
-    lfs: &mut MultiFs,
-    client: &reqwest::Client,
-    output: PathBuf,
-    url: U,
-) -> Result<()>
-where
-    U: Into<&'a str> + Clone,
-{
-    let rsp = client
-        .get(url.clone().into())
-        .send()
-        .await
-        .map_err(|err| anyhow!("Failed to request {}, causes:\n{:?}", url.into(), err))?;
+pub type ConnectionPool = tokio::sync::Mutex<Connections>;
+
+/// Connections of the open libraries, each library counting how many times it was closed.
+#[derive(Default)]
+pub struct Connections {
+    conns: HashMap<LibraryId, MultiFs>,
+    generations: HashMap<LibraryId, u64>,
+    /// Connections held by a blocking call that timed out, back once the call returns.
+    busy: HashMap<LibraryId, asyncfs::Taken>,
+    /// Libraries whose last call failed, their connection is checked before the next one.
+    failed: HashSet<LibraryId>,
+    /// Open a new connection of a library, in place of one held by a call that timed out.
+    reopen: HashMap<LibraryId, Box<dyn Fn() -> Option<MultiFs> + Send>>,
+    /// Libraries being checked by a heartbeat, the next heartbeats skip them meanwhile.
+    heartbeats: HashSet<LibraryId>,
+}
+
+/// A library as seen by a long running task, stale once the library is edited or removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LibraryHandle {
+    pub fs_id: LibraryId,
+    pub generation: u64,
+}
+
+impl Connections {
+    /// Open a library, replacing its previous connection if any.
+    pub fn insert(&mut self, fs_id: LibraryId, conn: MultiFs) {
+        self.close(fs_id);
+        self.conns.insert(fs_id, conn);
+    }
+
+    /// Drop the connection of a library, the handles taken before become stale.
+    pub fn close(&mut self, fs_id: LibraryId) {
+        self.reopen.remove(&fs_id);
+        self.failed.remove(&fs_id);
+        let busy = self.busy.remove(&fs_id).is_some();
+        if self.conns.remove(&fs_id).is_some() || busy {
+            *self.generations.entry(fs_id).or_default() += 1;
+        }
+    }
+
+    /// How to open a new connection of an open library, for when a call hangs.
+    pub fn set_reopen(
+        &mut self,
+        fs_id: LibraryId,
+        reopen: impl Fn() -> Option<MultiFs> + Send + 'static,
+    ) {
+        self.reopen.insert(fs_id, Box::new(reopen));
+    }
+
+    /// Take a connection out of the pool, for a call running on another thread.
+    pub(crate) fn take(&mut self, fs_id: LibraryId) -> Option<MultiFs> {
+        self.conns.remove(&fs_id)
+    }
+
+    /// Put back a connection taken by [`Connections::take`].
+    pub(crate) fn put_back(&mut self, fs_id: LibraryId, conn: MultiFs) {
+        self.conns.insert(fs_id, conn);
+    }
+
+    /// Leave a timed out call with the connection it took, a new connection replaces it when
+    /// the library can be reopened. Otherwise the connection is put back once the call returns.
+    pub(crate) fn abandon(&mut self, fs_id: LibraryId, call: asyncfs::Taken) {
+        match self.reopen.get(&fs_id).and_then(|reopen| reopen()) {
+            Some(conn) => {
+                log::info!("Library {} reopened with a new connection.", fs_id);
+                self.conns.insert(fs_id, conn);
+            }
+            None => {
+                self.busy.insert(fs_id, call);
+            }
+        }
+    }
+
+    /// A taken connection that is not coming back, the handles taken before become stale.
+    pub(crate) fn lose(&mut self, fs_id: LibraryId) {
+        *self.generations.entry(fs_id).or_default() += 1;
+    }
+
+    /// A call on the connection failed, which may be a dead connection.
+    pub(crate) fn mark_failed(&mut self, fs_id: LibraryId) {
+        self.failed.insert(fs_id);
+    }
+
+    fn take_failed(&mut self, fs_id: LibraryId) -> bool {
+        self.failed.remove(&fs_id)
+    }
+
+    /// Put back the connection of a timed out call, Timeout while the call is still running.
+    fn reclaim(&mut self, fs_id: LibraryId) -> Result<(), MkubeError> {
+        let Some(mut call) = self.busy.remove(&fs_id) else {
+            return Ok(());
+        };
+        match call.try_recv() {
+            Ok(conn) => {
+                self.conns.insert(fs_id, conn);
+                Ok(())
+            }
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty) => {
+                self.busy.insert(fs_id, call);
+                Err(MkubeError::Timeout(fs_id))
+            }
+            Err(tokio::sync::oneshot::error::TryRecvError::Closed) => {
+                log::error!("Connection of library {} lost by a call.", fs_id);
+                self.lose(fs_id);
+                Err(MkubeError::Connection(fs_id))
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, fs_id: LibraryId) -> Option<&mut MultiFs> {
+        self.conns.get_mut(&fs_id)
+    }
+
+    /// Two distinct connections at once, None if any is missing or both are the same.
+    pub fn get_pair_mut(
+        &mut self,
+        a: LibraryId,
+        b: LibraryId,
+    ) -> Option<(&mut MultiFs, &mut MultiFs)> {
+        let (mut first, mut second) = (None, None);
+        for (fs_id, fs) in self.conns.iter_mut() {
+            if *fs_id == a {
+                first = Some(fs);
+            } else if *fs_id == b {
+                second = Some(fs);
+            }
+        }
+        Some((first?, second?))
+    }
+
+    /// Handle of an open library.
+    pub fn handle(&self, fs_id: LibraryId) -> Option<LibraryHandle> {
+        let open = self.conns.contains_key(&fs_id) || self.busy.contains_key(&fs_id);
+        open.then(|| LibraryHandle {
+            fs_id,
+            generation: self.generations.get(&fs_id).copied().unwrap_or_default(),
+        })
+    }
+
+    pub fn is_current(&self, handle: LibraryHandle) -> bool {
+        self.handle(handle.fs_id) == Some(handle)
+    }
+
+    /// The open libraries.
+    pub fn libraries(&self) -> Vec<LibraryId> {
+        self.conns.keys().chain(self.busy.keys()).copied().collect()
+    }
+}
+
+/// Reconnect a library if needed, then lock the pool holding its connection.
+///
+/// The pool is unlocked during the reconnection, see [`asyncfs`].
+pub async fn get_connection(
+    conns: &ConnectionPool,
+    fs_id: LibraryId,
+) -> Result<tokio::sync::MutexGuard<'_, Connections>, MkubeError> {
+    if check_connection(conns, fs_id, false).await? == ConnectionStatus::Offline {
+        return Err(MkubeError::Connection(fs_id));
+    }
+    let mut conns = conns.lock().await;
+    conns.reclaim(fs_id)?;
+    match conns.get_mut(fs_id) {
+        Some(_) => Ok(conns),
+        None => Err(MkubeError::UnknownLibrary(fs_id)),
+    }
+}
+
+/// Check the connection of a library and reconnect it if needed, the status is sent to the app.
+///
+/// `probe` checks it with a call to the server, like the heartbeat does.
+pub async fn check_connection(
+    conns: &ConnectionPool,
+    fs_id: LibraryId,
+    probe: bool,
+) -> Result<ConnectionStatus, MkubeError> {
+    let status = asyncfs::connect(conns, fs_id, probe).await?;
+    if let Some(sender) = MESSAGE_SENDER.get() {
+        let _ = sender.send(AppMessage::Closure(Box::new(
+            move |app_state: &mut AppState| app_state.set_library_status(fs_id, status),
+        )));
+    }
+    Ok(status)
+}
+
+/// Add a library to the pool and connect it, None if its URL is invalid.
+///
+/// A new connection replaces the one of a call that hangs, see [`Connections::set_reopen`].
+pub async fn open_library(
+    conns: &mut Connections,
+    lib: &Library,
+    ttl: Option<std::time::Duration>,
+) -> Option<ConnectionStatus> {
+    let conn = MultiFs::try_from(lib).ok()?;
+    conns.insert(lib.id, conn.cached(ttl));
+    let reopen = lib.clone();
+    conns.set_reopen(lib.id, move || {
+        MultiFs::try_from(&reopen).ok().map(|conn| conn.cached(ttl))
+    });
+    Some(asyncfs::open(conns, lib.id).await)
+}
+
+/// Check the connection of each open library, reconnecting the offline ones.
+///
+/// A library still busy with a call that timed out is Degraded. The libraries still checked
+/// by a previous heartbeat are skipped, a slow server does not pile the checks up.
+pub async fn heartbeat(conns: &ConnectionPool) -> Vec<(LibraryId, ConnectionStatus)> {
+    let libraries: Vec<LibraryId> = {
+        let mut conns = conns.lock().await;
+        let libraries = conns.libraries();
+        libraries
+            .into_iter()
+            .filter(|fs_id| conns.heartbeats.insert(*fs_id))
+            .collect()
+    };
+    let mut statuses = Vec::with_capacity(libraries.len());
+    for fs_id in libraries {
+        // Locked once per attempt, the other tasks go on between the checks.
+        let checked = check_connection(conns, fs_id, true).await;
+        conns.lock().await.heartbeats.remove(&fs_id);
+        let status = match checked {
+            Ok(status) => status,
+            Err(MkubeError::Timeout(_)) => ConnectionStatus::Degraded,
+            Err(MkubeError::UnknownLibrary(_)) => continue,
+            Err(err) => {
+                log::debug!("Heartbeat of library {} failed:\n{:?}", fs_id, err);
+                ConnectionStatus::Offline
+            }
+        };
+        statuses.push((fs_id, status));
+    }
+    statuses
+}
 
-    let data = rsp.bytes().await.map_err(|err| {
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()))
+}
+
+/// Look for the artworks stored next to a movie (`poster.jpg`, `<name>-fanart.jpg`, `.actors`, ...)
+/// and its extras (`Extras/`, `<name>-trailer.mkv`, ...).
+pub fn detect_assets(lfs: &mut MultiFs, path: &Path) -> catalog::MovieAssets {
+    let mut assets = catalog::MovieAssets::default();
+    let parent = path.parent().unwrap_or(Path::new(""));
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let files = match lfs.as_mut_rfs().list_dir(parent) {
+        Ok(files) => files,
+        Err(err) => {
+            log::warn!(
+                "Failed to list artworks in {}, causes:\n{:?}",
+                parent.display(),
+                err
+            );
+            return assets;
+        }
+    };
+    let paths: Vec<PathBuf> = files.iter().map(|f| f.path.clone()).collect();
+    assets.parts = parser::count_parts(path, &paths);
+    let base = parser::stack_stem(path, &paths)
+        .unwrap_or_default()
+        .to_lowercase();
+    let stack = parser::stack_key(path).filter(|_| assets.parts > 1);
+    for file in files {
+        let name = file.name().to_lowercase();
+        if file.is_dir() {
+            assets.actors |= name == ".actors";
+            if parser::is_extras_dir(&name) {
+                match lfs.as_mut_rfs().list_dir(&file.path) {
+                    Ok(extras) => {
+                        assets.extras += extras
+                            .iter()
+                            .filter(|e| e.is_file() && is_video_file(&e.path))
+                            .count()
+                    }
+                    Err(err) => log::warn!(
+                        "Failed to list extras in {}, causes:\n{:?}",
+                        file.path.display(),
+                        err
+                    ),
+                }
+            }
+            continue;
+        }
+        if is_video_file(&file.path) {
+            if file.path == path || (stack.is_some() && parser::stack_key(&file.path) == stack) {
+                assets.size += file.metadata.size;
+            }
+            if parser::is_extra_file(&name) && name.starts_with(&base) {
+                assets.extras += 1;
+            }
+            continue;
+        }
+        let kind = match name.rsplit_once('.') {
+            Some((kind, "nfo")) => {
+                assets.nfo |= kind == stem || kind == "movie";
+                continue;
+            }
+            Some((kind, "jpg" | "jpeg" | "png")) => kind,
+            _ => continue,
+        };
+        let kind = kind
+            .strip_prefix(stem.as_str())
+            .and_then(|k| k.strip_prefix('-'))
+            .unwrap_or(kind);
+        match kind {
+            // Plex names the poster after the movie when its folder is shared.
+            kind if kind == stem => assets.poster = true,
+            "poster" | "folder" | "cover" | "thumb" => assets.poster = true,
+            "fanart" | "backdrop" | "landscape" => assets.fanart = true,
+            _ => {}
+        }
+    }
+    assets
+}
+
+/// The NFO of the movie at `path`, NotFound if there is none and Parse if it is invalid.
+pub async fn try_open_nfo(lfs: &mut MultiFs, path: PathBuf) -> Result<nfo::Movie, MkubeError> {
+    open_nfo(lfs, &path)
+}
+
+/// [`try_open_nfo`] for the blocking calls of an [`asyncfs::AsyncMultiFs`].
+pub fn open_nfo(lfs: &mut MultiFs, path: &Path) -> Result<nfo::Movie, MkubeError> {
+    let candidates = [path.with_extension("nfo"), path.join("movie.nfo")];
+    for nfo_path in candidates {
+        if lfs.as_mut_rfs().stat(&nfo_path).is_ok() {
+            return read_nfo(lfs, &nfo_path);
+        }
+    }
+    Err(MkubeError::NotFound {
+        what: "nfo",
+        path: path.with_extension("nfo"),
+    })
+}
+
+/// Raw content of the movie NFO, looked up like in try_open_nfo, and its path.
+pub fn read_nfo_source(lfs: &mut MultiFs, path: &Path) -> Result<Option<(PathBuf, String)>> {
+    let candidates = [path.with_extension("nfo"), path.join("movie.nfo")];
+    for nfo_path in candidates {
+        if lfs.as_mut_rfs().stat(&nfo_path).is_err() {
+            continue;
+        }
+        let source = read_source(lfs, &nfo_path)?;
+        return Ok(Some((nfo_path, source)));
+    }
+    Ok(None)
+}
+
+/// The whole text of the NFO at `nfo_path`.
+fn read_source(lfs: &mut MultiFs, nfo_path: &Path) -> Result<String> {
+    let mut reader = lfs.open_reader(nfo_path)?;
+    let mut source = String::new();
+    reader.read_to_string(&mut source).map_err(|err| {
         anyhow!(
-            "Failed to read incoming data for {}, causes:\n{:?}",
-            output.display(),
+            "Failed to read nfo at {}, causes:\n{:?}",
+            nfo_path.display(),
             err
         )
     })?;
+    reader.finish()?;
+    Ok(source)
+}
 
-    let buf = Cursor::new(Vec::from(data.as_ref()));
-
-    let _ = lfs
-        .as_mut_rfs()
-        .create_file(&output, &Metadata::default(), Box::new(buf))
-        .map_err(|err| {
-            anyhow!(
-                "Failed to create(or open) file {}, causes:\n{:?}",
-                output.display(),
-                err
-            )
-        })?;
-
-    log::info!("Sucessfully downloaded file {}.", output.display());
-    Ok(())
-}
-
-pub async fn try_open_nfo(lfs: &mut MultiFs, mut path: PathBuf) -> Result<nfo::Movie> {
-    let mut oc = OwnedCursor::new();
-    let cursor = Box::new(oc.clone());
-    if path.set_extension("nfo") {
-        if let Ok(_) = lfs.as_mut_rfs().open_file(&path, cursor.clone()) {
-            let buf_cursor = Box::new(std::io::BufReader::new(oc.clone()));
-            let _ = oc.rewind();
-            let movie: nfo::Movie = quick_xml::de::from_reader(buf_cursor).map_err(|err| {
-                anyhow!(
-                    "Failed to read nfo at {}, causes:\n{:?}",
-                    path.display(),
-                    err
-                )
-            })?;
-            return Ok(movie);
-        }
+/// The `tvshow.nfo` of a show directory, if any.
+pub fn read_show_nfo(lfs: &mut MultiFs, show_dir: &Path) -> Option<nfo::TvShow> {
+    let path = show_dir.join("tvshow.nfo");
+    if lfs.as_mut_rfs().stat(&path).is_err() {
+        return None;
     }
-    path.push("movie.nfo");
-    if let Ok(_) = lfs.as_mut_rfs().open_file(&path, cursor) {
-        let buf_cursor = Box::new(std::io::BufReader::new(oc.clone()));
-        let _ = oc.rewind();
-        let movie: nfo::Movie = quick_xml::de::from_reader(buf_cursor).map_err(|err| {
-            anyhow!(
-                "Failed to read nfo at {}, causes:\n{:?}",
-                path.display(),
-                err
-            )
-        })?;
-        return Ok(movie);
+    match read_nfo(lfs, &path) {
+        Ok(show) => Some(show),
+        Err(err) => {
+            log::warn!("{:?}", err);
+            None
+        }
     }
-    Err(anyhow!("No nfo available."))
 }
 
+fn read_nfo<T: DeserializeOwned>(lfs: &mut MultiFs, path: &Path) -> Result<T, MkubeError> {
+    let io = |err: anyhow::Error| MkubeError::Io {
+        path: path.to_owned(),
+        reason: format!("{:?}", err),
+    };
+    let mut reader = lfs.open_reader(path).map_err(io)?;
+    let nfo: T = quick_xml::de::from_reader(BufReader::new(&mut reader)).map_err(|err| {
+        MkubeError::Parse {
+            path: path.to_owned(),
+            reason: format!("{:?}", err),
+        }
+    })?;
+    reader.finish().map_err(io)?;
+    Ok(nfo)
+}
+
+/// Probe an episode and store its stream details in its NFO, created if missing.
+///
+/// Only the `fileinfo` of an existing NFO is replaced, see [`nfo::with_fileinfo`]. Returns the
+/// updated NFO and its path.
+pub async fn probe_episode(
+    conns: &ConnectionPool,
+    fs_id: LibraryId,
+    base_url: Url,
+    path: PathBuf,
+) -> Result<(nfo::Episode, PathBuf)> {
+    let fs = asyncfs::AsyncMultiFs::new(conns, fs_id);
+    let nfo_path = path.with_extension("nfo");
+    let source_path = nfo_path.clone();
+    let existing = fs
+        .run(move |lfs| {
+            if lfs.as_mut_rfs().stat(&source_path).is_err() {
+                return Ok(None);
+            }
+            read_source(lfs, &source_path).map(Some)
+        })
+        .await?;
+    let fileinfo = get_metadata(conns, fs_id, base_url, path.clone()).await?;
+    let Some(source) = existing else {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parsed = parser::parse_filename(&name);
+        let numbers = parser::parse_episode(&name);
+        let episode = nfo::Episode {
+            title: parsed.title,
+            season: numbers.map(|(season, _)| season),
+            episode: numbers.map(|(_, episode)| episode),
+            source: parsed.source,
+            fileinfo: Some(fileinfo),
+            ..Default::default()
+        };
+        return fs
+            .run(move |lfs| {
+                views::movie_manager::store_nfo(lfs, &episode, &path).map(|path| (episode, path))
+            })
+            .await;
+    };
+    let parse_error = |err: quick_xml::DeError| MkubeError::Parse {
+        path: nfo_path.clone(),
+        reason: format!("{:?}", err),
+    };
+    let mut episode: nfo::Episode = quick_xml::de::from_str(&source).map_err(parse_error)?;
+    let source = nfo::with_fileinfo(&source, &fileinfo).map_err(parse_error)?;
+    episode.fileinfo = Some(fileinfo);
+    fs.run(move |lfs| {
+        views::movie_manager::store_nfo_source(lfs, &nfo_path, &source)
+            .map(|()| (episode, nfo_path))
+    })
+    .await
+}
+
+/// Kodi `hdrtype` of a video track, from its transfer characteristic and side data.
+fn hdr_type(color_trc: Option<&str>, side_data: multifs::HdrSideData) -> Option<String> {
+    let hdr_type = if side_data.dolby_vision {
+        "dolbyvision"
+    } else if side_data.hdr10_plus {
+        "hdr10plus"
+    } else {
+        match color_trc? {
+            "smpte2084" => "hdr10",
+            "arib-std-b67" => "hlg",
+            _ => return None,
+        }
+    };
+    Some(hdr_type.into())
+}
+
+/// Stream details of a media, probed by the [`probing::pool`].
+///
+/// The connections must not be locked by the caller, the pool locks them when needed.
 pub async fn get_metadata(
-    lfs: &mut MultiFs,
+    conns: &ConnectionPool,
+    fs_id: LibraryId,
     base_url: Url,
     path: PathBuf,
-) -> Result<nfo::FileInfo> {
+) -> Result<nfo::FileInfo, MkubeError> {
+    probing::pool().probe(conns, fs_id, base_url, path).await
+}
+
+/// Stream details of a media probed by ffmpeg.
+pub fn file_info(
+    meta: metadata::MediaFileMetadata,
+    hdr_side_data: HashMap<usize, multifs::HdrSideData>,
+) -> nfo::FileInfo {
     use metadata::stream::StreamMetadata;
 
-    let meta =
-        multifs::open_multifs_media(lfs.as_mut_rfs(), base_url, path.clone()).map_err(|err| {
-            anyhow!(
-                "Unable to get metadata for file {}, causes:\n{:?}",
-                path.display(),
-                err
-            )
-        })?;
     let mut vtracks = Vec::new();
     let mut atracks = Vec::new();
     let mut stracks = Vec::new();
@@ -136,7 +564,10 @@ pub async fn get_metadata(
                     height: Some(vt.height.into()),
                     duration_in_seconds: meta._duration.map(|dur| dur as u64),
                     language: None,
-                    hdr_type: None,
+                    hdr_type: hdr_type(
+                        vt.color_trc.as_deref(),
+                        hdr_side_data.get(&vt.index).copied().unwrap_or_default(),
+                    ),
                 };
                 vtracks.push(vi);
             }
@@ -163,46 +594,217 @@ pub async fn get_metadata(
         audio: atracks,
         subtitle: stracks,
     };
-    Ok(nfo::FileInfo { streamdetails: sd })
+    nfo::FileInfo { streamdetails: sd }
 }
 
+/// A person found by the TMDB person search, tmdb-api does not cover it.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PersonResult {
+    pub id: u64,
+    pub name: String,
+    pub profile_path: Option<String>,
+    pub known_for_department: Option<String>,
+}
+
+pub async fn search_person(
+    client: &dyn MetadataProvider,
+    name: &str,
+    lang: Option<String>,
+) -> Result<Vec<PersonResult>> {
+    let mut params = vec![("query", Cow::Borrowed(name))];
+    if let Some(lang) = lang {
+        params.push(("language", Cow::Owned(lang)));
+    }
+    let page: tmdb_api::common::PaginatedResult<PersonResult> = client
+        .fetch("/search/person", params)
+        .await
+        .map_err(|err| anyhow!("Failed to search person `{}`, causes:\n{:?}", name, err))?;
+    Ok(page.results)
+}
+
+/// The TMDB genre and country names, offered by the editor pickers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Taxonomy {
+    pub genres: Vec<String>,
+    pub countries: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TmdbCountry {
+    english_name: String,
+}
+
+pub async fn fetch_taxonomy(
+    client: &dyn MetadataProvider,
+    lang: Option<String>,
+) -> Result<Taxonomy> {
+    use tmdb_api::genre::list::GenreList;
+    let mut genre_list = GenreList::movie();
+    genre_list.language = lang;
+    let genres = client
+        .execute(&genre_list)
+        .await
+        .map_err(|err| anyhow!("Failed to get the TMDB genres, causes:\n{:?}", err))?;
+    let countries: Vec<TmdbCountry> = client
+        .fetch("/configuration/countries", vec![])
+        .await
+        .map_err(|err| anyhow!("Failed to get the TMDB countries, causes:\n{:?}", err))?;
+    let mut countries: Vec<String> = countries.into_iter().map(|c| c.english_name).collect();
+    countries.sort();
+    Ok(Taxonomy {
+        genres: genres.into_iter().map(|g| g.name).collect(),
+        countries,
+    })
+}
+
+/// The logos of a movie, tmdb-api only lists its posters and backdrops.
+struct MovieLogos {
+    movie_id: u64,
+    languages: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MovieLogosResult {
+    #[serde(default)]
+    logos: Vec<Image>,
+}
+
+impl MovieLogosResult {
+    /// The logo in the first available language, then in English, then any.
+    fn best(&self, languages: &[String]) -> Option<&Image> {
+        let in_language = |lang: &str| {
+            self.logos
+                .iter()
+                .find(|logo| logo.iso_639_1.as_deref() == Some(lang))
+        };
+        languages
+            .iter()
+            .find_map(|lang| in_language(lang))
+            .or_else(|| in_language("en"))
+            .or(self.logos.first())
+    }
+}
+
+impl Command for MovieLogos {
+    type Output = MovieLogosResult;
+
+    fn path(&self) -> Cow<'static, str> {
+        Cow::Owned(format!("/movie/{}/images", self.movie_id))
+    }
+
+    fn params(&self) -> Vec<(&'static str, Cow<'_, str>)> {
+        let mut languages = self.languages.clone();
+        for lang in ["en", "null"] {
+            if !languages.iter().any(|l| l == lang) {
+                languages.push(lang.into());
+            }
+        }
+        vec![("include_image_language", Cow::Owned(languages.join(",")))]
+    }
+}
+
+/// First image in the languages order, then without language (no text), then any.
+fn pick_image<'a>(images: &'a [Image], langs: &[String]) -> Option<&'a Image> {
+    langs
+        .iter()
+        .find_map(|lang| {
+            images
+                .iter()
+                .find(|image| image.iso_639_1.as_deref() == Some(lang))
+        })
+        .or_else(|| images.iter().find(|image| image.iso_639_1.is_none()))
+        .or(images.first())
+}
+
+const TMDB_IMAGES: &str = "https://image.tmdb.org/t/p/";
+
+/// URL of a TMDB image at `size`, `path` being its TMDB path (`/abc.jpg`) or an
+/// image.tmdb.org URL of any size. The other URLs are left as they are.
+pub fn tmdb_image_url(path: &str, size: config::ImageSize) -> String {
+    if let Some(sized) = path.strip_prefix(TMDB_IMAGES) {
+        let file = sized.split_once('/').map_or(sized, |(_, file)| file);
+        format!("{}{}/{}", TMDB_IMAGES, size.as_str(), file)
+    } else if path.starts_with("http") {
+        path.to_owned()
+    } else {
+        format!("{}{}{}", TMDB_IMAGES, size.as_str(), path)
+    }
+}
+
+/// Build the NFO of a TMDB movie, `langs` being tried in order for the plot and the artworks.
+///
+/// The artworks get the sizes of `artworks`.
 pub async fn transform_as_nfo(
-    client: &TmdbClient,
+    client: &dyn MetadataProvider,
     tmdb_id: u64,
-    lang: Option<String>,
+    langs: &[String],
+    certification_country: &str,
+    artworks: &config::ArtworkTypes,
 ) -> Result<nfo::Movie> {
-    let mdr = MovieDetails::new(tmdb_id).with_language(lang.clone());
-    let md = mdr.execute(&client).await.map_err(|err| {
+    let mdr = MovieDetails::new(tmdb_id).with_language(langs.first().cloned());
+    let mut md = client.execute(&mdr).await.map_err(|err| {
         anyhow!(
             "Failed to get movie details (id: {}), causes:\n{:?}",
             tmdb_id,
             err
         )
     })?;
+    // TMDB leaves the overview empty when it is not translated.
+    for lang in langs.iter().skip(1) {
+        if !md.inner.overview.is_empty() {
+            break;
+        }
+        match client
+            .execute(&MovieDetails::new(tmdb_id).with_language(Some(lang.clone())))
+            .await
+        {
+            Ok(translated) => {
+                md.inner.overview = translated.inner.overview;
+                if md.tagline.as_deref().map_or(true, str::is_empty) {
+                    md.tagline = translated.tagline;
+                }
+            }
+            Err(err) => log::warn!(
+                "Failed to get movie details (id: {}, lang: {}), causes:\n{:?}",
+                tmdb_id,
+                lang,
+                err
+            ),
+        }
+    }
     let mcr = MovieCredits::new(tmdb_id);
-    let mc = mcr.execute(&client).await.map_err(|err| {
+    let mc = client.execute(&mcr).await.map_err(|err| {
         anyhow!(
             "Failed to get movie credits (id: {}), causes:\n{:?}",
             tmdb_id,
             err
         )
     })?;
-    let mir = MovieImages::new(tmdb_id).with_language(lang);
-    let mi = mir.execute(&client).await.map_err(|err| {
+    let mira = MovieImages::new(tmdb_id);
+    let mia = client.execute(&mira).await.map_err(|err| {
         anyhow!(
             "Failed to get movie image (id: {}), causes:\n{:?}",
             tmdb_id,
             err
         )
     })?;
-    let mira = MovieImages::new(tmdb_id);
-    let mia = mira.execute(&client).await.map_err(|err| {
+    let mrdr = MovieReleaseDates::new(tmdb_id);
+    let mrd = client.execute(&mrdr).await.map_err(|err| {
         anyhow!(
-            "Failed to get movie image (id: {}), causes:\n{:?}",
+            "Failed to get movie release dates (id: {}), causes:\n{:?}",
             tmdb_id,
             err
         )
     })?;
+    let mpaa = mrd
+        .results
+        .into_iter()
+        .find(|lrd| lrd.iso_3166_1.eq_ignore_ascii_case(certification_country))
+        .and_then(|lrd| {
+            lrd.release_dates
+                .into_iter()
+                .find_map(|rd| rd.certification)
+        });
 
     let mut actors = Vec::new();
     let mut directors = Vec::new();
@@ -211,7 +813,7 @@ pub async fn transform_as_nfo(
         let thumb = if let Some(path) = p.person.profile_path {
             Some(nfo::Thumb {
                 aspect: None,
-                path: format!("https://image.tmdb.org/t/p/original{}", path),
+                path: tmdb_image_url(&path, config::ImageSize::Original),
             })
         } else {
             None
@@ -232,7 +834,7 @@ pub async fn transform_as_nfo(
             tmdbid: Some(p.person.id),
             thumb: p.person.profile_path.map(|url| nfo::Thumb {
                 aspect: None,
-                path: format!("https://image.tmdb.org/t/p/original{}", url),
+                path: tmdb_image_url(&url, config::ImageSize::Original),
             }),
         };
         if &p.job == "Director" {
@@ -242,21 +844,41 @@ pub async fn transform_as_nfo(
         }
     }
 
+    let art = |kind: nfo::ArtworkKind, image: &Image| nfo::Thumb {
+        aspect: Some(kind.aspect().into()),
+        path: tmdb_image_url(&image.file_path, artworks.size(kind)),
+    };
     let mut thumb = Vec::new();
-    if let Some(bd) = mi.backdrops.first().or(mia.backdrops.first()) {
-        let art = nfo::Thumb {
-            aspect: Some("landscape".into()),
-            path: format!("https://image.tmdb.org/t/p/original{}", &bd.file_path),
-        };
-        thumb.push(art);
+    if let Some(poster) = pick_image(&mia.posters, langs) {
+        thumb.push(art(nfo::ArtworkKind::Poster, poster));
     }
-
-    if let Some(poster) = mi.posters.first().or(mia.posters.first()) {
-        let art = nfo::Thumb {
-            aspect: Some("poster".into()),
-            path: format!("https://image.tmdb.org/t/p/original{}", &poster.file_path),
-        };
-        thumb.push(art);
+    // The backdrops without a language have no text, the others are the landscapes.
+    let fanart = pick_image(&mia.backdrops, &[]).map(|bd| nfo::Fanart {
+        thumb: vec![art(nfo::ArtworkKind::Fanart, bd)],
+    });
+    let landscape = langs.iter().find_map(|lang| {
+        mia.backdrops
+            .iter()
+            .find(|bd| bd.iso_639_1.as_deref() == Some(lang))
+    });
+    if let Some(bd) = landscape {
+        thumb.push(art(nfo::ArtworkKind::Landscape, bd));
+    }
+    let mlr = MovieLogos {
+        movie_id: tmdb_id,
+        languages: langs.to_vec(),
+    };
+    match client.execute(&mlr).await {
+        Ok(ml) => {
+            if let Some(logo) = ml.best(langs) {
+                thumb.push(art(nfo::ArtworkKind::Logo, logo));
+            }
+        }
+        Err(err) => log::warn!(
+            "Failed to get movie logos (id: {}), causes:\n{:?}",
+            tmdb_id,
+            err
+        ),
     }
 
     let tmdb_uid = nfo::UniqueId {
@@ -265,9 +887,25 @@ pub async fn transform_as_nfo(
         value: tmdb_id.to_string(),
     };
 
+    let ratings = if md.inner.vote_count > 0 {
+        Some(nfo::Ratings {
+            rating: vec![nfo::Rating {
+                name: "themoviedb".into(),
+                max: Some(10),
+                default: true,
+                value: md.inner.vote_average,
+                votes: Some(md.inner.vote_count),
+            }],
+        })
+    } else {
+        None
+    };
+
     let movie = nfo::Movie {
         title: md.inner.title.clone(),
         original_title: Some(md.inner.original_title.clone()),
+        ratings,
+        outline: None,
         plot: Some(md.inner.overview),
         uniqueid: vec![tmdb_uid],
         genre: md.genres.into_iter().map(|g| g.name.clone()).collect(),
@@ -291,93 +929,127 @@ pub async fn transform_as_nfo(
             .collect(),
         actor: actors,
         thumb,
+        fanart,
         runtime: md.runtime,
         tagline: md.tagline.clone(),
+        mpaa,
         source: None,
+        edition: None,
         fileinfo: None,
+        tmdbid: None,
+        dateadded: None,
+        lockdata: None,
+        playcount: None,
+        lastplayed: None,
+        watched: None,
+        checksum: None,
     };
 
     Ok(movie)
 }
 
 pub fn analyze_library<'a>(
-    conn: (&'a ConnectionPool, usize),
+    conn: (&'a ConnectionPool, LibraryHandle),
     path: PathBuf,
     depth: usize,
 ) -> LibraryStream<'a> {
     LibraryStream::new(conn, path, depth)
 }
 
+/// Scan of a library directory, yielding the videos found down to `depth` levels.
+///
+/// On filesystems following the symlinks, every directory is visited once by its canonical
+/// path, so a link to a parent directory does not loop.
 pub struct LibraryStream<'a> {
-    conn: (&'a ConnectionPool, usize),
+    conn: (&'a ConnectionPool, LibraryHandle),
     depth: usize,
+    visited: Arc<Mutex<HashSet<PathBuf>>>,
     sub_streams: Vec<Pin<Box<LibraryStream<'a>>>>,
     found_path: Vec<PathBuf>,
-    search_future: Option<Pin<Box<dyn Future<Output = Result<Vec<(PathBuf, bool)>>> + 'a>>>,
+    search_future:
+        Option<Pin<Box<dyn Future<Output = Result<Vec<(PathBuf, bool)>, MkubeError>> + 'a>>>,
 }
 
 impl<'a> LibraryStream<'a> {
     pub fn new(
-        conn: (&'a ConnectionPool, usize),
+        conn: (&'a ConnectionPool, LibraryHandle),
         path: PathBuf,
         depth: usize,
+    ) -> LibraryStream<'a> {
+        LibraryStream::with_visited(conn, path, depth, Default::default())
+    }
+
+    fn with_visited(
+        conn: (&'a ConnectionPool, LibraryHandle),
+        path: PathBuf,
+        depth: usize,
+        visited: Arc<Mutex<HashSet<PathBuf>>>,
     ) -> LibraryStream<'a> {
         LibraryStream {
             conn,
             depth,
-            search_future: Some(Box::pin(LibraryStream::search(conn.clone(), path, depth))),
+            search_future: Some(Box::pin(LibraryStream::search(
+                conn,
+                path,
+                depth,
+                visited.clone(),
+            ))),
+            visited,
             sub_streams: Vec::new(),
             found_path: Vec::new(),
         }
     }
 
     async fn search(
-        conn: (&'a ConnectionPool, usize),
+        conn: (&'a ConnectionPool, LibraryHandle),
         path: PathBuf,
         depth: usize,
-    ) -> Result<Vec<(PathBuf, bool)>> {
-        let dir;
-        {
-            let mut conn_lock = conn.0.lock().await;
-            let lfs = (match conn_lock.get_mut(conn.1) {
-                Some(c) => match c {
-                    Some(ref mut lfs) => Ok::<&mut MultiFs, anyhow::Error>(lfs),
-                    None => bail!(
-                        "Searching a path on an unexistant library {} (editted or deleted).",
-                        conn.1
-                    ),
-                },
-                None => bail!(
-                    "Searching a path on an unexistant library {} (never existed).",
-                    conn.1
-                ),
-            })?;
-            let no_media = path.join("./.nomedia");
-            if lfs.as_mut_rfs().exists(&no_media).map_err(|err| {
-                anyhow!(
-                    "Failed to open directory {}, causes:\n{:?}",
-                    &no_media.display(),
-                    err
-                )
-            })? {
-                log::info!("Ignoring entry {} (.nomedia).", path.display());
-                return Ok(vec![]);
-            }
-            dir = lfs.as_mut_rfs().list_dir(&path).map_err(|err| {
-                anyhow!(
-                    "Failed to open directory {}, causes:\n{:?}",
-                    &path.display(),
-                    err
-                )
-            })?;
-        }
+        visited: Arc<Mutex<HashSet<PathBuf>>>,
+    ) -> Result<Vec<(PathBuf, bool)>, MkubeError> {
+        let dir_path = path.clone();
+        let listed = asyncfs::AsyncMultiFs::with_handle(conn.0, conn.1)
+            .for_operation(asyncfs::Operation::List)
+            .run(move |lfs| {
+                if let Some(real_path) = lfs.real_path(&dir_path) {
+                    if !visited.lock().unwrap().insert(real_path) {
+                        log::info!("Ignoring entry {} (already scanned).", dir_path.display());
+                        return Ok(None);
+                    }
+                }
+                let no_media = dir_path.join("./.nomedia");
+                if lfs
+                    .as_mut_rfs()
+                    .exists(&no_media)
+                    .map_err(|err| MkubeError::Io {
+                        path: no_media.clone(),
+                        reason: format!("{:?}", err),
+                    })?
+                {
+                    log::info!("Ignoring entry {} (.nomedia).", dir_path.display());
+                    return Ok(None);
+                }
+                lfs.as_mut_rfs()
+                    .list_dir(&dir_path)
+                    .map(Some)
+                    .map_err(|err| MkubeError::Io {
+                        path: dir_path.clone(),
+                        reason: format!("{:?}", err),
+                    })
+            })
+            .await?;
+        let Some(dir) = listed else {
+            return Ok(vec![]);
+        };
+        // Kodi extras are bonus content of the movie in this directory, not movies of their own.
+        let movie_dir = dir.iter().any(|entry| {
+            entry.is_file() && is_video_file(entry.path()) && !parser::is_extra_file(&entry.name())
+        });
         let mut video_paths = Vec::new();
         for entry in dir {
             if entry.metadata().file_type.is_file() {
-                if entry.path().extension().is_some()
-                    && VIDEO_EXTENSIONS
-                        .contains(&entry.path().extension().unwrap().to_string_lossy().as_ref())
-                {
+                if is_video_file(entry.path()) && parser::is_extra_file(&entry.name()) {
+                    log::debug!("Ignored {} (extra)!", entry.path().display());
+                } else if is_video_file(entry.path()) {
                     log::debug!("Found {}!", entry.path().display());
                     video_paths.push((entry.path().to_owned(), false));
                 } else {
@@ -390,6 +1062,10 @@ impl<'a> LibraryStream<'a> {
                 if entry.path().ends_with(".") || entry.path().ends_with("..") {
                     continue;
                 }
+                if movie_dir && parser::is_extras_dir(&entry.name()) {
+                    log::debug!("Ignoring entry {} (extras).", entry.path().display());
+                    continue;
+                }
                 if depth > 0 {
                     video_paths.push((entry.path().to_owned(), true));
                 }
@@ -402,7 +1078,7 @@ impl<'a> LibraryStream<'a> {
 }
 
 impl<'a> Stream for LibraryStream<'a> {
-    type Item = Result<PathBuf>;
+    type Item = Result<PathBuf, MkubeError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let ls = self.as_mut().get_mut();
@@ -417,17 +1093,25 @@ impl<'a> Stream for LibraryStream<'a> {
                             } else {
                                 let depth = ls.depth;
                                 let conn = ls.conn;
-                                ls.sub_streams.push(Box::pin(LibraryStream::new(
+                                ls.sub_streams.push(Box::pin(LibraryStream::with_visited(
                                     conn,
                                     path,
                                     depth - 1,
+                                    ls.visited.clone(),
                                 )));
                             }
                         }
                         ls.search_future = None;
                         self.poll_next(cx)
                     }
-                    Err(err) => Poll::Ready(Some(Err(err))),
+                    Err(err) => {
+                        ls.search_future = None;
+                        if matches!(err, MkubeError::ScanCancelled(_)) {
+                            // Nothing left to scan in a stale library.
+                            ls.found_path.clear();
+                        }
+                        Poll::Ready(Some(Err(err)))
+                    }
                 },
             }
         } else {
@@ -448,14 +1132,21 @@ impl<'a> Stream for LibraryStream<'a> {
                             ls.sub_streams.swap_remove(i);
                         }
                         Poll::Ready(Some(Err(err))) => {
+                            if matches!(err, MkubeError::ScanCancelled(_)) {
+                                ls.sub_streams.clear();
+                                ls.found_path.clear();
+                            }
                             return Poll::Ready(Some(Err(err)));
                         }
                     }
                 }
                 if let Some(path) = ls.found_path.pop() {
                     Poll::Ready(Some(Ok(path)))
-                } else {
+                } else if ls.sub_streams.is_empty() {
                     Poll::Ready(None)
+                } else {
+                    // The subdirectories are still listed on the blocking threads.
+                    Poll::Pending
                 }
             }
         }
@@ -479,3 +1170,295 @@ impl<'a> Stream for LibraryStream<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ArtworkTypes, BackdropSize, ImageSize, PosterSize};
+    use crate::localfs::LocalFs;
+    use crate::mockfs::MockFs;
+    use crate::provider::FixtureProvider;
+    use futures_util::stream::StreamExt;
+    use remotefs::RemoteErrorType;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn reconnect_backoff() {
+        let mock = MockFs::new();
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        conns
+            .lock()
+            .await
+            .insert(fs_id, MultiFs::Mock(mock.clone()));
+        let status = check_connection(&conns, fs_id, true).await;
+        assert_eq!(status.unwrap(), ConnectionStatus::Connected);
+
+        // The other tasks lock the pool between the attempts.
+        mock.set_online(false);
+        let check = check_connection(&conns, fs_id, false);
+        let during = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let unlocked = conns.try_lock().is_ok();
+            mock.set_online(true);
+            unlocked
+        };
+        let (status, unlocked) = tokio::join!(check, during);
+        assert_eq!(status.unwrap(), ConnectionStatus::Degraded);
+        assert!(unlocked);
+    }
+
+    #[tokio::test]
+    async fn heartbeats_do_not_pile_up() {
+        let mock = MockFs::new().with_latency(Duration::from_millis(300));
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        conns.lock().await.insert(fs_id, MultiFs::Mock(mock));
+        let slow = heartbeat(&conns);
+        let next = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            heartbeat(&conns).await
+        };
+        let (slow, next) = tokio::join!(slow, next);
+        assert_eq!(slow, vec![(fs_id, ConnectionStatus::Connected)]);
+        // Still checked by the first heartbeat, the library is skipped.
+        assert!(next.is_empty());
+        assert_eq!(
+            heartbeat(&conns).await,
+            vec![(fs_id, ConnectionStatus::Connected)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn scan_follows_symlinks_once() {
+        let root = std::env::temp_dir().join(format!("mkube-symlinks-{}", std::process::id()));
+        let downloads = root.join("downloads");
+        let library = root.join("library");
+        std::fs::create_dir_all(downloads.join("Movie (2020)")).unwrap();
+        std::fs::create_dir_all(&library).unwrap();
+        std::fs::write(downloads.join("Movie (2020)/movie.mkv"), b"").unwrap();
+        std::os::unix::fs::symlink(downloads.join("Movie (2020)"), library.join("Movie")).unwrap();
+        std::os::unix::fs::symlink(&library, library.join("loop")).unwrap();
+        let fs = LocalFs::new(library.clone()).follow_symlinks(true);
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        conns.lock().await.insert(fs_id, MultiFs::Local(fs));
+        let handle = conns.lock().await.handle(fs_id).unwrap();
+        let found: Vec<_> = analyze_library((&conns, handle), library.clone(), usize::MAX)
+            .collect()
+            .await;
+        // An edited library stops the scans still holding its old handle.
+        conns.lock().await.close(fs_id);
+        let stale: Vec<_> = analyze_library((&conns, handle), library.clone(), usize::MAX)
+            .collect()
+            .await;
+        std::fs::remove_dir_all(&root).unwrap();
+        let found: Vec<_> = found.into_iter().map(Result::unwrap).collect();
+        assert_eq!(found, vec![library.join("Movie/movie.mkv")]);
+        assert_eq!(stale.len(), 1);
+        assert!(matches!(stale[0], Err(MkubeError::ScanCancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn missing_nfo() {
+        let root = std::env::temp_dir().join(format!("mkube-nfo-missing-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let mut fs = MultiFs::Local(LocalFs::new(root.clone()));
+        let missing = try_open_nfo(&mut fs, root.join("missing.mkv")).await;
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(missing, Err(MkubeError::NotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn broken_nfo() {
+        let root = std::env::temp_dir().join(format!("mkube-nfo-broken-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("broken.nfo"), b"<movie><title>").unwrap();
+        let mut fs = MultiFs::Local(LocalFs::new(root.clone()));
+        let broken = try_open_nfo(&mut fs, root.join("broken.mkv")).await;
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(matches!(broken, Err(MkubeError::Parse { .. })));
+    }
+
+    #[tokio::test]
+    async fn unknown_library_error() {
+        let conns = ConnectionPool::default();
+        let Err(err) = get_connection(&conns, LibraryId::new()).await else {
+            panic!("unknown library connected");
+        };
+        assert!(matches!(err, MkubeError::UnknownLibrary(_)));
+        assert!(!err.is_transient());
+        let err = anyhow::Error::from(err).context("NFO save failed");
+        assert_eq!(
+            MkubeError::find(&err).map(MkubeError::notice).as_deref(),
+            Some("the library was edited or removed")
+        );
+    }
+
+    #[tokio::test]
+    async fn mock_library_scan() {
+        let mock = MockFs::new()
+            .with_file("/movies/Alpha (2001)/alpha.mkv", "")
+            .with_file("/movies/Alpha (2001)/alpha-trailer.mkv", "")
+            .with_file("/movies/Beta/beta.mkv", "")
+            .with_file("/movies/Hidden/.nomedia", "")
+            .with_file("/movies/Hidden/hidden.mkv", "")
+            .with_file("/movies/Broken/broken.mkv", "")
+            .with_latency(std::time::Duration::from_millis(1));
+        mock.fail("/movies/Broken", RemoteErrorType::CouldNotOpenFile);
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        conns.lock().await.insert(fs_id, MultiFs::Mock(mock));
+        let handle = conns.lock().await.handle(fs_id).unwrap();
+        let scan: Vec<_> = analyze_library((&conns, handle), "/movies".into(), 2)
+            .collect()
+            .await;
+        let (found, errors): (Vec<_>, Vec<_>) = scan.into_iter().partition(Result::is_ok);
+        let mut found: Vec<_> = found.into_iter().map(Result::unwrap).collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                PathBuf::from("/movies/Alpha (2001)/alpha.mkv"),
+                PathBuf::from("/movies/Beta/beta.mkv"),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0], Err(MkubeError::Io { path, .. }) if path.starts_with("/movies/Broken"))
+        );
+    }
+
+    #[tokio::test]
+    async fn nfo_from_fixtures() {
+        let provider = FixtureProvider::demo();
+        let artworks = ArtworkTypes::default();
+        let movie = transform_as_nfo(&provider, 603, &["en".into()], "US", &artworks)
+            .await
+            .unwrap();
+        assert_eq!(movie.title, "The Matrix");
+        assert_eq!(movie.tmdb_id(), Some(603));
+        assert_eq!(movie.premiered.as_deref(), Some("1999-03-31"));
+        assert_eq!(movie.mpaa.as_deref(), Some("R"));
+        assert_eq!(movie.director[0].name, "Lana Wachowski");
+        assert!(movie.actor.iter().any(|actor| actor.name == "Keanu Reeves"));
+        assert_eq!(
+            movie.fanart.unwrap().thumb[0].path,
+            "https://image.tmdb.org/t/p/original/demo-matrix-backdrop.jpg"
+        );
+    }
+
+    #[tokio::test]
+    async fn nfo_of_an_unknown_movie() {
+        let provider = FixtureProvider::demo();
+        let artworks = ArtworkTypes::default();
+        assert!(transform_as_nfo(&provider, 1, &[], "US", &artworks)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn nfo_artwork_sizes() {
+        let provider = FixtureProvider::demo();
+        let smaller = ArtworkTypes {
+            poster_size: PosterSize::W500,
+            fanart_size: BackdropSize::W1280,
+            ..Default::default()
+        };
+        let movie = transform_as_nfo(&provider, 603, &["en".into()], "US", &smaller)
+            .await
+            .unwrap();
+        assert_eq!(
+            movie.fanart.unwrap().thumb[0].path,
+            "https://image.tmdb.org/t/p/w1280/demo-matrix-backdrop.jpg"
+        );
+        assert!(movie.thumb[0]
+            .path
+            .starts_with("https://image.tmdb.org/t/p/w500/"));
+    }
+
+    #[test]
+    fn tmdb_image_urls() {
+        assert_eq!(
+            tmdb_image_url("https://image.tmdb.org/t/p/original/a.jpg", ImageSize::W780),
+            "https://image.tmdb.org/t/p/w780/a.jpg"
+        );
+        assert_eq!(
+            tmdb_image_url("https://assets.fanart.tv/a.png", ImageSize::W780),
+            "https://assets.fanart.tv/a.png"
+        );
+    }
+
+    #[tokio::test]
+    async fn demo_library_nfos() {
+        let provider = FixtureProvider::demo();
+        let (lib, mut fs) = crate::demo::library(&provider).await;
+        let saved = try_open_nfo(
+            &mut fs,
+            lib.path.join("Amelie (2001)/Amelie.2001.DVDRip.mkv"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved.tmdb_id(), Some(194));
+    }
+
+    #[tokio::test]
+    async fn artwork_language_fallback() {
+        let artworks = &ArtworkTypes::default();
+        let poster = |movie: nfo::Movie| {
+            movie
+                .thumb
+                .into_iter()
+                .find(|thumb| thumb.aspect.as_deref() == Some("poster"))
+                .map(|thumb| thumb.path)
+        };
+        let amelie = |provider: FixtureProvider, langs: Vec<String>| async move {
+            transform_as_nfo(&provider, 194, &langs, "FR", artworks)
+                .await
+                .unwrap()
+        };
+        let url = |file: &str| format!("https://image.tmdb.org/t/p/original/{}", file);
+
+        let provider = FixtureProvider::demo();
+        let movie = amelie(provider.clone(), vec!["fr".into(), "en".into()]).await;
+        assert_eq!(poster(movie), Some(url("demo-amelie-poster-fr.jpg")));
+        let movie = amelie(provider.clone(), vec!["de".into(), "en".into()]).await;
+        assert_eq!(poster(movie), Some(url("demo-amelie-poster.jpg")));
+
+        // Neither fr nor en: the poster without text, then the first one.
+        let image = |file: &str, lang: Option<&str>| {
+            serde_json::json!({
+                "aspect_ratio": 0.667,
+                "file_path": file,
+                "height": 1500,
+                "iso_639_1": lang,
+                "vote_average": 5.3,
+                "vote_count": 4,
+                "width": 1000
+            })
+        };
+        let images = |posters| {
+            serde_json::json!({
+                "id": 194,
+                "backdrops": [image("/demo-amelie-backdrop.jpg", None)],
+                "posters": posters,
+                "logos": []
+            })
+        };
+        let ja = image("/demo-amelie-poster-ja.jpg", Some("ja"));
+        let provider = FixtureProvider::demo().with_response(
+            "/movie/194/images",
+            images(serde_json::json!([
+                ja,
+                image("/demo-amelie-poster-textless.jpg", None),
+            ])),
+        );
+        let movie = amelie(provider, vec!["fr".into(), "en".into()]).await;
+        assert_eq!(poster(movie), Some(url("demo-amelie-poster-textless.jpg")));
+        let provider = FixtureProvider::demo()
+            .with_response("/movie/194/images", images(serde_json::json!([ja])));
+        let movie = amelie(provider, vec!["fr".into(), "en".into()]).await;
+        assert_eq!(poster(movie), Some(url("demo-amelie-poster-ja.jpg")));
+    }
+}
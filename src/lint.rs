@@ -0,0 +1,197 @@
+use quick_xml::events::Event;
+use std::collections::HashMap;
+
+use crate::nfo::Movie;
+
+/// Structural problems of a movie NFO that Kodi may silently ignore or fail on.
+///
+/// An NFO that is not valid XML only reports that, the other checks need it parsed.
+pub fn lint_nfo(source: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let text = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if let Some(problem) = lint_declaration(text) {
+        problems.push(problem);
+    }
+    match root_element(text) {
+        Some(root) if root == "movie" => {}
+        Some(root) => problems.push(format!("Root element is <{}>, not <movie>.", root)),
+        None => {
+            problems.push("No root element.".into());
+            return problems;
+        }
+    }
+    let movie: Movie = match quick_xml::de::from_str(text) {
+        Ok(movie) => movie,
+        Err(err) => {
+            problems.push(format!("Invalid XML: {}", err));
+            return problems;
+        }
+    };
+    if movie.title.trim().is_empty() {
+        problems.push("Empty <title>.".into());
+    }
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    for uid in &movie.uniqueid {
+        *ids.entry(uid.id_type.as_str()).or_default() += 1;
+    }
+    let mut duplicates: Vec<&str> = ids
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(id_type, _)| id_type)
+        .collect();
+    duplicates.sort();
+    for id_type in duplicates {
+        problems.push(format!("Duplicate <uniqueid type=\"{}\">.", id_type));
+    }
+    if movie.uniqueid.iter().filter(|uid| uid.default).count() > 1 {
+        problems.push("Several <uniqueid> are the default one.".into());
+    }
+    if let Some(premiered) = movie.premiered.as_deref() {
+        if !is_date(premiered) {
+            problems.push(format!(
+                "Invalid <premiered> `{}`, expected YYYY-MM-DD.",
+                premiered
+            ));
+        }
+    }
+    for (tag, value) in [
+        ("dateadded", &movie.dateadded),
+        ("lastplayed", &movie.lastplayed),
+    ] {
+        if let Some(value) = value.as_deref().filter(|value| !is_datetime(value)) {
+            problems.push(format!(
+                "Invalid <{}> `{}`, expected YYYY-MM-DD HH:MM:SS.",
+                tag, value
+            ));
+        }
+    }
+    problems
+}
+
+fn lint_declaration(text: &str) -> Option<String> {
+    if !text.starts_with("<?xml") {
+        return Some(if text.trim_start().starts_with("<?xml") {
+            "The XML declaration is not at the start of the file.".into()
+        } else {
+            "Missing XML declaration.".into()
+        });
+    }
+    let Some(end) = text.find("?>") else {
+        return Some("Unterminated XML declaration.".into());
+    };
+    let decl = text[..end].replace('\'', "\"");
+    if !decl.contains("version=\"1.0\"") {
+        return Some("The XML declaration has no version=\"1.0\".".into());
+    }
+    match decl.split("encoding=\"").nth(1) {
+        Some(encoding) if !encoding.to_ascii_lowercase().starts_with("utf-8\"") => Some(format!(
+            "The XML declaration has the encoding `{}`, not UTF-8.",
+            encoding.split('"').next().unwrap_or_default()
+        )),
+        _ => None,
+    }
+}
+
+/// Name of the first element, None if there is none or the XML breaks before it.
+fn root_element(text: &str) -> Option<String> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(elem)) | Ok(Event::Empty(elem)) => {
+                return Some(String::from_utf8_lossy(elem.name().as_ref()).into_owned());
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+/// `YYYY-MM-DD`, a day that exists.
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return false;
+    };
+    let digits =
+        |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+    if !(digits(year, 4) && digits(month, 2) && digits(day, 2)) {
+        return false;
+    }
+    let (year, month, day): (u32, u32, u32) = (
+        year.parse().unwrap_or_default(),
+        month.parse().unwrap_or_default(),
+        day.parse().unwrap_or_default(),
+    );
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// `YYYY-MM-DD HH:MM:SS`, as Kodi writes the dates of `<dateadded>` and `<lastplayed>`.
+fn is_datetime(value: &str) -> bool {
+    let Some((date, time)) = value.split_once(' ') else {
+        return false;
+    };
+    let fields: Vec<Option<u32>> = time
+        .split(':')
+        .map(|field| field.parse().ok().filter(|_| field.len() == 2))
+        .collect();
+    is_date(date)
+        && matches!(fields[..], [Some(hours), Some(minutes), Some(seconds)]
+            if hours < 24 && minutes < 60 && seconds < 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nfo_lint() {
+        let declaration = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+        let movie = Movie {
+            title: "Alien".into(),
+            premiered: Some("1979-05-25".into()),
+            lastplayed: Some("2023-04-01 21:30:00".into()),
+            ..Default::default()
+        };
+        let written = format!(
+            "{}\n{}",
+            declaration,
+            quick_xml::se::to_string(&movie).unwrap()
+        );
+        assert!(lint_nfo(&written).is_empty(), "{:?}", lint_nfo(&written));
+
+        let broken = "\n<movie><title> </title><premiered>1979-02-30</premiered>\
+            <uniqueid type=\"tmdb\" default=\"true\">348</uniqueid>\
+            <uniqueid type=\"tmdb\" default=\"true\">349</uniqueid>\
+            <dateadded>2023-04-01</dateadded></movie>";
+        assert_eq!(
+            lint_nfo(broken),
+            vec![
+                "Missing XML declaration.",
+                "Empty <title>.",
+                "Duplicate <uniqueid type=\"tmdb\">.",
+                "Several <uniqueid> are the default one.",
+                "Invalid <premiered> `1979-02-30`, expected YYYY-MM-DD.",
+                "Invalid <dateadded> `2023-04-01`, expected YYYY-MM-DD HH:MM:SS.",
+            ]
+        );
+        let latin1 =
+            r#"<?xml version="1.0" encoding="ISO-8859-1"?><tvshow><title>A</title></tvshow>"#;
+        assert_eq!(
+            lint_nfo(latin1),
+            vec![
+                "The XML declaration has the encoding `ISO-8859-1`, not UTF-8.",
+                "Root element is <tvshow>, not <movie>.",
+            ]
+        );
+        let unclosed = format!("{}\n<movie><title>Alien</movie>", declaration);
+        assert!(lint_nfo(&unclosed)[0].starts_with("Invalid XML"));
+    }
+}
@@ -6,11 +6,29 @@ use std::path::{Path, PathBuf};
 #[derive(Clone, Debug)]
 pub struct LocalFs {
     pub pwd: PathBuf,
+    /// Report the symlinks as their target, otherwise they are left out of the listings.
+    pub follow_symlinks: bool,
 }
 
 impl LocalFs {
     pub fn new(start: PathBuf) -> Self {
-        Self { pwd: start }
+        Self {
+            pwd: start,
+            follow_symlinks: false,
+        }
+    }
+
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Canonical path of a directory, to detect the symlink loops. None if symlinks are not followed.
+    pub fn real_path(&self, path: &Path) -> Option<PathBuf> {
+        if !self.follow_symlinks {
+            return None;
+        }
+        std::fs::canonicalize(self.pwd.join(path)).ok()
     }
 }
 
@@ -60,7 +78,7 @@ impl RemoteFs for LocalFs {
             .filter_map(Result::ok)
             .filter_map(|d| {
                 if let Ok(ft) = d.file_type() {
-                    if ft.is_dir() || ft.is_file() {
+                    if ft.is_dir() || ft.is_file() || (ft.is_symlink() && self.follow_symlinks) {
                         Some(self.stat(&d.path()))
                     } else {
                         None
@@ -76,8 +94,18 @@ impl RemoteFs for LocalFs {
     fn stat(&mut self, path: &Path) -> RemoteResult<File> {
         let path = self.pwd.join(path);
         //trace!("get stat for {}", path);
-        let metadata = std::fs::symlink_metadata(&path)
+        let mut metadata = std::fs::symlink_metadata(&path)
             .map_err(|e| RemoteError::new_ex(RemoteErrorType::StatFailed, e))?;
+        let mut symlink = None;
+        if metadata.file_type().is_symlink() && self.follow_symlinks {
+            // Broken links are kept as symlinks.
+            if let (Ok(target), Ok(target_metadata)) =
+                (std::fs::read_link(&path), std::fs::metadata(&path))
+            {
+                symlink = Some(target);
+                metadata = target_metadata;
+            }
+        }
 
         let file_type = if metadata.file_type().is_dir() {
             remotefs::fs::FileType::Directory
@@ -95,7 +123,7 @@ impl RemoteFs for LocalFs {
             mode: None,
             modified: metadata.modified().ok(),
             size: metadata.len(),
-            symlink: None,
+            symlink,
             file_type,
             uid: None,
         };
@@ -137,6 +165,19 @@ impl RemoteFs for LocalFs {
             .map_err(|e| RemoteError::new_ex(RemoteErrorType::CouldNotRemoveFile, e))
     }
 
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        let path = self.pwd.join(path);
+        // Only unlink the symlinks, even followed ones, never their target.
+        let metadata = std::fs::symlink_metadata(&path)
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::StatFailed, e))?;
+        if metadata.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        }
+        .map_err(|e| RemoteError::new_ex(RemoteErrorType::CouldNotRemoveFile, e))
+    }
+
     fn create_dir(&mut self, path: &Path, _mode: UnixPex) -> RemoteResult<()> {
         if self.exists(path)? {
             return Err(RemoteError::new(RemoteErrorType::DirectoryAlreadyExists));
@@ -223,15 +264,31 @@ impl RemoteFs for LocalFs {
             .map_err(|e| RemoteError::new_ex(RemoteErrorType::IoError, e))
     }
 
-    fn append(&mut self, _path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
-        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    fn append(&mut self, path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let path = self.pwd.join(path);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::FileCreateDenied, e))?;
+        Ok(WriteStream::from(Box::new(file) as Box<dyn Write>))
     }
 
-    fn create(&mut self, _path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
-        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    fn create(&mut self, path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
+        let path = self.pwd.join(path);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::FileCreateDenied, e))?;
+        Ok(WriteStream::from(Box::new(file) as Box<dyn Write>))
     }
 
-    fn open(&mut self, _path: &Path) -> RemoteResult<ReadStream> {
-        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    fn open(&mut self, path: &Path) -> RemoteResult<ReadStream> {
+        let path = self.pwd.join(path);
+        let file = std::fs::File::open(&path)
+            .map_err(|e| RemoteError::new_ex(RemoteErrorType::CouldNotOpenFile, e))?;
+        Ok(ReadStream::from(Box::new(file) as Box<dyn Read>))
     }
 }
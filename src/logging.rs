@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use log::kv::{Key, Value};
+use log::{Level, LevelFilter};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use structured_logger::{Builder, Writer};
+
+use crate::config::Logging;
+
+const LOG_NAME: &str = "mkube.log";
+
+/// Directory of the log files: the configured one, else the XDG state or cache directory.
+pub fn log_dir(cfg: &Logging) -> PathBuf {
+    if let Some(dir) = &cfg.dir {
+        return dir.clone();
+    }
+    match directories::ProjectDirs::from("", "", "mkube") {
+        Some(dirs) => dirs
+            .state_dir()
+            .unwrap_or_else(|| dirs.cache_dir())
+            .to_path_buf(),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Install the JSON logger writing to `mkube.log` in the [`log_dir`].
+pub fn init(cfg: &Logging) -> Result<()> {
+    let dir = log_dir(cfg);
+    fs::create_dir_all(&dir).context(format!("Failed to create {}", dir.display()))?;
+    let writer = RotatingWriter::open(dir.join(LOG_NAME), cfg)?;
+    let max_level = writer
+        .modules
+        .iter()
+        .map(|(_, level)| *level)
+        .fold(writer.level, Ord::max);
+    Builder::with_level(max_level.as_str())
+        .with_default_writer(Box::new(writer))
+        .init();
+    Ok(())
+}
+
+fn parse_level(level: &str) -> LevelFilter {
+    level.parse().unwrap_or(LevelFilter::Info)
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+/// JSON lines writer rotating `mkube.log` to `mkube.log.1`, `.2`... once it gets too big.
+///
+/// It also applies the module levels, the logger only knows a single one.
+struct RotatingWriter {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    level: LevelFilter,
+    /// Module levels, the longest module first so that it overrides its parents.
+    modules: Vec<(String, LevelFilter)>,
+    file: Mutex<LogFile>,
+}
+
+impl RotatingWriter {
+    fn open(path: PathBuf, cfg: &Logging) -> Result<Self> {
+        let mut modules: Vec<_> = cfg
+            .modules
+            .iter()
+            .map(|(module, level)| (module.clone(), parse_level(level)))
+            .collect();
+        modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        let file = open_append(&path).context(format!("Failed to open {}", path.display()))?;
+        Ok(Self {
+            path,
+            max_size: cfg.max_size,
+            max_files: cfg.max_files,
+            level: parse_level(&cfg.level),
+            modules,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn enabled(&self, target: &str, level: Level) -> bool {
+        let filter = self
+            .modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.level, |(_, level)| *level);
+        level <= filter
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&self, log: &mut LogFile) -> io::Result<()> {
+        if self.max_files == 0 {
+            log.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let _ = fs::rename(self.rotated(index), self.rotated(index + 1));
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+            log.file = open_append(&self.path)?.file;
+        }
+        log.size = 0;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<LogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(LogFile { file, size })
+}
+
+impl Writer for RotatingWriter {
+    fn write_log(&self, value: &BTreeMap<Key, Value>) -> Result<(), io::Error> {
+        let field = |key: &str| value.get(&Key::from(key)).map(ToString::to_string);
+        let level = field("level").and_then(|level| level.parse().ok());
+        if let (Some(target), Some(level)) = (field("target"), level) {
+            if !self.enabled(&target, level) {
+                return Ok(());
+            }
+        }
+        let mut buf = serde_json::to_vec(value)?;
+        buf.push(b'\n');
+        let mut log = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        if self.max_size > 0 && log.size > 0 && log.size + buf.len() as u64 > self.max_size {
+            self.rotate(&mut log)?;
+        }
+        log.file.write_all(&buf)?;
+        log.size += buf.len() as u64;
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use futures_util::stream::FuturesUnordered;
 use futures_util::{FutureExt, StreamExt};
 use std::collections::HashMap;
+use std::future::Future;
 use std::io;
-use tmdb_api::client::Client as TmdbClient;
-use tokio::sync::{mpsc::unbounded_channel, Mutex};
-use tokio::task::JoinSet;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::SystemTime;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::sync::Mutex;
 use tokio::time::{self, Duration};
 
 use crossterm::event::{
@@ -14,71 +19,163 @@ use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
-use tui::{backend::CrosstermBackend, terminal::Terminal, widgets::Paragraph};
+use tui::{backend::CrosstermBackend, terminal::Terminal};
+use tui::{
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::Paragraph,
+};
 
 #[cfg(feature = "secrets")]
 use oo7::Keyring;
 
-use mkube::config::{ConfigLibrary, Credentials};
+use mkube::config::{ConfigLibrary, Heartbeat};
+use mkube::provider::{FixtureProvider, MetadataProvider, TmdbProvider};
+use mkube::recent::RecentMovies;
+use mkube::stats::SessionStats;
+#[cfg(feature = "secrets")]
+use mkube::config::{Credentials, SecretStorage, Secrets};
+use mkube::views::movie_manager::batch;
 use mkube::views::AppEvent;
 use mkube::{multifs, views, ConnectionPool};
 
-use multifs::MultiFs;
+use multifs::ConnectionStatus;
 
 const APP_NAME: &'static str = "mkube";
-const CONFIG_NAME: Option<&'static str> = Some("config");
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    init_logger().await;
+/// Tasks started by the events, polled along the main loop as they borrow the clients and the
+/// connections of the profile. They are dropped with them when switching profiles.
+type PendingFutures<'a> =
+    FuturesUnordered<Pin<Box<dyn Future<Output = std::thread::Result<Vec<AppEvent>>> + 'a>>>;
+
+fn push_pending<'a>(pending: &PendingFutures<'a>, fut: impl Future<Output = Vec<AppEvent>> + 'a) {
+    pending.push(Box::pin(AssertUnwindSafe(fut).catch_unwind()));
+}
+
+/// Why the main loop stopped.
+enum Exit {
+    Quit,
+    /// Restart the main loop with another profile.
+    SwitchProfile(Option<String>),
+}
+
+/// Command line arguments.
+struct Args {
+    /// Profile given with `--profile NAME`, None for the default one.
+    profile: Option<String>,
+    /// `--demo`, sample library and TMDB answers, the configuration file is left untouched.
+    demo: bool,
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+    let config_name = mkube::config::profile_config_name(args.profile.as_deref());
+    let config_path = confy::get_configuration_file_path(APP_NAME, config_name.as_str())?;
+    mkube::logging::init(&mkube::config::read_logging(&config_path))?;
     log::info!("Hello!");
+    if let Some(proxy) = mkube::config::read_http(&config_path).proxy {
+        // The TMDB client builds its own reqwest client, which only reads the proxy from the
+        // environment. Only safe to set before the runtime threads are started.
+        std::env::set_var("HTTP_PROXY", &proxy);
+        std::env::set_var("HTTPS_PROXY", &proxy);
+    }
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run_app(args))
+}
 
+async fn run_app(Args { mut profile, demo }: Args) -> Result<()> {
     // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    setup_terminal()?;
+    // Restore the terminal on panic as well, otherwise the shell stays garbled.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        log::error!("MKube panicked: {}", info);
+        default_hook(info);
+    }));
+    let guard = scopeguard::guard((), |_| restore_terminal());
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     log::info!("Terminal successfully prepared!");
 
-    match tokio::task::LocalSet::new()
-        .run_until(run(&mut terminal))
-        .await
-    {
-        Ok(()) => {
-            log::info!("Exit success.");
-        }
-        Err(err) => {
-            log::error!("Exit failed, caused:\n{:?}", err);
+    let (sender, mut receiver) = unbounded_channel();
+    mkube::MESSAGE_SENDER
+        .set(sender)
+        .map_err(|err| anyhow!("Failed to init MESSAGE_SENDER, causes:\n{:?}", err))?;
+    loop {
+        match tokio::task::LocalSet::new()
+            .run_until(run(&mut terminal, profile.clone(), demo, &mut receiver))
+            .await
+        {
+            Ok(Exit::Quit) => {
+                log::info!("Exit success.");
+                break;
+            }
+            Ok(Exit::SwitchProfile(next)) => {
+                log::info!("Switching to the profile {:?}.", next);
+                // The messages left are about the libraries of the previous profile.
+                while receiver.try_recv().is_ok() {}
+                terminal.clear()?;
+                profile = next;
+            }
+            Err(err) => {
+                log::error!("Exit failed, caused:\n{:?}", err);
+                break;
+            }
         }
     }
 
     // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    drop(guard);
     terminal.show_cursor()?;
-
     log::info!("Terminal successfully restored!");
 
     Ok(())
 }
 
-async fn init_logger() {
-    use structured_logger::{async_json::new_writer, Builder};
-    let log_file = tokio::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("mkube.log")
-        .await
-        .unwrap();
-    Builder::new()
-        .with_default_writer(new_writer(log_file))
-        .init();
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args {
+        profile: None,
+        demo: false,
+    };
+    while let Some(arg) = args.next() {
+        let name = match arg.strip_prefix("--profile=") {
+            Some(name) => name.to_owned(),
+            None if arg == "--profile" || arg == "-p" => args
+                .next()
+                .ok_or(anyhow!("--profile needs a profile name"))?,
+            None if arg == "--demo" => {
+                if !cfg!(feature = "demo") {
+                    bail!("--demo needs a build with the `demo` feature");
+                }
+                parsed.demo = true;
+                continue;
+            }
+            None => bail!(
+                "Unknown argument `{}`, usage: mkube [--profile NAME] [--demo]",
+                arg
+            ),
+        };
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            bail!("Invalid profile name `{}`", name);
+        }
+        parsed.profile = Some(name);
+    }
+    Ok(parsed)
+}
+
+fn setup_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
 }
 
 #[cfg(feature = "secrets")]
@@ -122,7 +219,7 @@ async fn init_keyring() -> Result<Keyring> {
             };
             //kr.lock().await?;
             let keyring = oo7::portal::Keyring::load(
-                confy::get_configuration_file_path(APP_NAME, None)?.join("../keyring.key"),
+                confy::get_configuration_file_path(APP_NAME, None)?.with_file_name("keyring.key"),
                 Secret::from(key),
             )
             .await?;
@@ -131,85 +228,404 @@ async fn init_keyring() -> Result<Keyring> {
     }
 }
 
-async fn run<B>(terminal: &mut Terminal<B>) -> Result<()>
+#[cfg(feature = "secrets")]
+enum KeyringStartup {
+    Ready(Keyring),
+    Skipped,
+    Quit,
+}
+
+/// Unlock the keyring in a background task, showing a status screen until it is ready or skipped.
+#[cfg(feature = "secrets")]
+async fn wait_keyring<B>(
+    terminal: &mut Terminal<B>,
+    event_reader: &mut EventStream,
+) -> Result<KeyringStartup>
 where
     B: tui::backend::Backend,
 {
-    terminal.draw(|f| {
-        let size = f.size();
-        f.render_widget(
-            Paragraph::new("Initializing... (You might need to unlock your system KeyWallet.)"),
-            size,
-        );
-    })?;
-    let (sender, mut receiver) = unbounded_channel();
-    let tmdb_client: &'static TmdbClient = Box::leak(Box::new(TmdbClient::new(
-        "74a673b58f22dd90b8ac750b62e00b0b".into(),
-    )));
-    let http_client: &'static reqwest::Client = Box::leak(Box::new(reqwest::Client::new()));
-    let conns: &'static ConnectionPool = Box::leak(Box::new(Mutex::new(Vec::new())));
-    let keyring;
-    #[cfg(feature = "secrets")]
-    {
-        keyring = init_keyring().await?;
+    const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+    let mut task = tokio::task::spawn_local(async {
+        let keyring = init_keyring().await?;
+        keyring
+            .unlock()
+            .await
+            .map_err(|err| anyhow!("Failed to unlock keyring, causes:\n{:?}", err))?;
+        Ok::<_, anyhow::Error>(keyring)
+    });
+    let mut tick = time::interval(Duration::from_millis(100));
+    let mut frame = 0;
+    let startup = loop {
+        tokio::select! {
+            rst = &mut task => match rst {
+                Ok(Ok(keyring)) => break KeyringStartup::Ready(keyring),
+                Ok(Err(err)) => {
+                    log::error!("Keyring unavailable, continuing without it. Cause:\n{:?}", err);
+                    break KeyringStartup::Skipped;
+                }
+                Err(err) => {
+                    log::error!("Keyring task failed, continuing without it. Cause:\n{:?}", err);
+                    break KeyringStartup::Skipped;
+                }
+            },
+            _ = tick.tick() => {
+                frame = (frame + 1) % SPINNER.len();
+                terminal.draw(|f| {
+                    let text = vec![
+                        Spans::from(format!(
+                            " {} Unlocking the keyring... (You might need to unlock your system KeyWallet.)",
+                            SPINNER[frame]
+                        )),
+                        Spans::from(""),
+                        Spans::from(vec![
+                            Span::styled(" s ", Style::default().fg(Color::White).bg(Color::Magenta)),
+                            Span::raw(" Skip the keyring for this session    "),
+                            Span::styled(" Esc ", Style::default().fg(Color::White).bg(Color::Magenta)),
+                            Span::raw(" Quit"),
+                        ]),
+                    ];
+                    f.render_widget(Paragraph::new(text), f.size());
+                })?;
+            }
+            maybe_event = event_reader.next().fuse() => match maybe_event {
+                Some(Ok(Event::Key(kev))) => match kev.code {
+                    KeyCode::Char('c') if kev.modifiers == KeyModifiers::CONTROL => break KeyringStartup::Quit,
+                    KeyCode::Esc => break KeyringStartup::Quit,
+                    KeyCode::Char('s') => {
+                        log::warn!("Keyring skipped for this session.");
+                        break KeyringStartup::Skipped;
+                    }
+                    _ => {}
+                },
+                Some(Ok(_)) => {}
+                Some(Err(err)) => log::error!("Failed to read terminal events:\n{:?}", err),
+                None => break KeyringStartup::Quit,
+            }
+        }
+    };
+    task.abort();
+    Ok(startup)
+}
+
+/// Open the encrypted credentials file, asking for its passphrase until it is valid or skipped.
+#[cfg(feature = "secrets")]
+async fn prompt_passphrase<B>(
+    terminal: &mut Terminal<B>,
+    event_reader: &mut EventStream,
+    config_path: &Path,
+) -> Result<KeyringStartup>
+where
+    B: tui::backend::Backend,
+{
+    use std::sync::Arc;
+    let path = config_path.with_file_name("credentials.keyring");
+    let exists = path.exists();
+    let mut passphrase = String::new();
+    let mut error: Option<String> = None;
+    loop {
+        terminal.draw(|f| {
+            let title = if exists {
+                " Enter the passphrase of the encrypted credentials file:"
+            } else {
+                " Choose a passphrase for the new encrypted credentials file:"
+            };
+            let mut text = vec![
+                Spans::from(title),
+                Spans::from(format!(" > {}", "*".repeat(passphrase.chars().count()))),
+                Spans::from(""),
+                Spans::from(vec![
+                    Span::styled(
+                        " Enter ",
+                        Style::default().fg(Color::White).bg(Color::Magenta),
+                    ),
+                    Span::raw(" Unlock    "),
+                    Span::styled(
+                        " Esc ",
+                        Style::default().fg(Color::White).bg(Color::Magenta),
+                    ),
+                    Span::raw(" Continue without it"),
+                ]),
+            ];
+            if let Some(err) = &error {
+                text.push(Spans::from(""));
+                text.push(Spans::from(Span::styled(
+                    format!(" {}", err),
+                    Style::default().fg(Color::LightRed),
+                )));
+            }
+            f.render_widget(Paragraph::new(text), f.size());
+        })?;
+        let kev = match event_reader.next().await {
+            Some(Ok(Event::Key(kev))) => kev,
+            Some(Ok(_)) => continue,
+            Some(Err(err)) => {
+                log::error!("Failed to read terminal events:\n{:?}", err);
+                continue;
+            }
+            None => return Ok(KeyringStartup::Quit),
+        };
+        match kev.code {
+            KeyCode::Char('c') if kev.modifiers == KeyModifiers::CONTROL => {
+                return Ok(KeyringStartup::Quit)
+            }
+            KeyCode::Esc => {
+                log::warn!("Encrypted credentials file skipped for this session.");
+                return Ok(KeyringStartup::Skipped);
+            }
+            KeyCode::Char(c) => passphrase.push(c),
+            KeyCode::Backspace => {
+                passphrase.pop();
+            }
+            KeyCode::Enter if passphrase.is_empty() => {
+                error = Some("The passphrase can not be empty.".into());
+            }
+            KeyCode::Enter => match open_credentials_file(&path, &passphrase).await {
+                Ok(Some(file)) => {
+                    return Ok(KeyringStartup::Ready(Keyring::File(Arc::new(file))));
+                }
+                Ok(None) => {
+                    error = Some("Wrong passphrase.".into());
+                    passphrase.clear();
+                }
+                Err(err) => {
+                    log::error!("Failed to open the encrypted credentials file:\n{:?}", err);
+                    error = Some(format!("Failed to open {}: {}", path.display(), err));
+                }
+            },
+            _ => {}
+        }
     }
-    #[cfg(not(feature = "secrets"))]
-    {
-        keyring = ()
+}
+
+/// Item of the encrypted credentials file, which fails to decrypt with a wrong passphrase.
+#[cfg(feature = "secrets")]
+const PASSPHRASE_CHECK: [(&str, &str); 2] = [("app", APP_NAME), ("secret", "passphrase_check")];
+
+/// Open the encrypted credentials file at `path`, None if the passphrase is wrong.
+///
+/// The secret of the file is derived from the passphrase with PBKDF2 and the random salt stored
+/// next to it. A file from before the salt, opened with the SHA-512 of the passphrase, is
+/// written again with a salted secret.
+#[cfg(feature = "secrets")]
+async fn open_credentials_file(
+    path: &Path,
+    passphrase: &str,
+) -> Result<Option<oo7::portal::Keyring>> {
+    use oo7::portal::{Keyring as FileKeyring, Secret};
+    use sha2::{Digest, Sha512};
+    let salt_path = path.with_extension("salt");
+    let salt = match std::fs::read(&salt_path) {
+        Ok(salt) => Some(salt),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => bail!("Failed to read {}, causes:\n{:?}", salt_path.display(), err),
+    };
+    let legacy = salt.is_none() && path.exists();
+    let salt = salt.unwrap_or_else(|| rand::random::<[u8; 32]>().to_vec());
+    let secret = if legacy {
+        Sha512::digest(passphrase.as_bytes()).to_vec()
+    } else {
+        derive_file_secret(passphrase, &salt).await?
+    };
+    let file = FileKeyring::load(path, Secret::from(secret)).await?;
+    let items = file.items().await;
+    if !items.is_empty() && items.iter().all(|item| item.is_err()) {
+        return Ok(None);
     }
-    mkube::MESSAGE_SENDER
-        .set(sender.clone())
-        .map_err(|err| anyhow!("Failed to init MESSAGE_SENDER, causes:\n{:?}", err))?;
-    let cfg: mkube::config::Configuration = confy::load(APP_NAME, CONFIG_NAME)?;
+    if !legacy {
+        if !salt_path.exists() {
+            std::fs::write(&salt_path, &salt)?;
+        }
+        let check = HashMap::from(PASSPHRASE_CHECK);
+        if file.search_items(check.clone()).await?.is_empty() {
+            file.create_item("MKube passphrase check", check, b"", true)
+                .await?;
+        }
+        return Ok(Some(file));
+    }
+
+    log::info!("Encrypting the credentials file again with a salted passphrase.");
+    let secret = derive_file_secret(passphrase, &salt).await?;
+    let new_path = path.with_extension("keyring.new");
+    let _ = std::fs::remove_file(&new_path);
+    let new_file = FileKeyring::load(&new_path, Secret::from(secret.clone())).await?;
+    for item in items.into_iter().flatten() {
+        let attributes = item
+            .attributes()
+            .iter()
+            .map(|(key, value)| (key.as_str(), &**value))
+            .collect();
+        new_file
+            .create_item(item.label(), attributes, &*item.secret(), true)
+            .await?;
+    }
+    new_file
+        .create_item(
+            "MKube passphrase check",
+            HashMap::from(PASSPHRASE_CHECK),
+            b"",
+            true,
+        )
+        .await?;
+    let new_salt_path = salt_path.with_extension("salt.new");
+    std::fs::write(&new_salt_path, &salt)?;
+    std::fs::rename(&new_salt_path, &salt_path)?;
+    std::fs::rename(&new_path, path)?;
+    Ok(Some(FileKeyring::load(path, Secret::from(secret)).await?))
+}
+
+/// PBKDF2-HMAC-SHA512 of the passphrase, on a blocking thread as it is slow on purpose.
+#[cfg(feature = "secrets")]
+async fn derive_file_secret(passphrase: &str, salt: &[u8]) -> Result<Vec<u8>> {
+    const ROUNDS: u32 = 210_000;
+    let (passphrase, salt) = (passphrase.to_owned(), salt.to_owned());
+    let secret = tokio::task::spawn_blocking(move || {
+        let mut secret = vec![0; 64];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha512>(passphrase.as_bytes(), &salt, ROUNDS, &mut secret);
+        secret
+    })
+    .await?;
+    Ok(secret)
+}
+
+async fn run<B>(
+    terminal: &mut Terminal<B>,
+    profile: Option<String>,
+    demo: bool,
+    receiver: &mut UnboundedReceiver<views::AppMessage>,
+) -> Result<Exit>
+where
+    B: tui::backend::Backend,
+{
+    let conns: ConnectionPool = Mutex::new(Default::default());
+    let conns = &conns;
+    let mut event_reader = EventStream::new();
+    let config_name = mkube::config::profile_config_name(profile.as_deref());
+    let config_path = confy::get_configuration_file_path(APP_NAME, config_name.as_str())?;
+    let (cfg, config_issues) = if demo {
+        Default::default()
+    } else {
+        mkube::config::load(&config_path)?
+    };
+    mkube::downloads::configure(&cfg.downloads);
+    mkube::probing::configure(&cfg.probing);
+    mkube::backups::configure(&cfg.backups);
+    mkube::imagecache::configure(&cfg.image_cache);
+    mkube::asyncfs::configure(&cfg.timeouts);
+    let tmdb_client: Box<dyn MetadataProvider> = if demo {
+        Box::new(FixtureProvider::demo())
+    } else {
+        Box::new(TmdbProvider::new("74a673b58f22dd90b8ac750b62e00b0b".into()))
+    };
+    let tmdb_client = &*tmdb_client;
+    let http_client = &cfg
+        .http
+        .build_client()
+        .map_err(|err| anyhow!("Failed to build the HTTP client, causes:\n{:?}", err))?;
+    #[cfg(feature = "secrets")]
+    let secrets = {
+        // The demo library has no credentials.
+        let keyring = if demo {
+            None
+        } else {
+            match wait_keyring(terminal, &mut event_reader).await? {
+                KeyringStartup::Ready(kr) => Some(kr),
+                KeyringStartup::Skipped => None,
+                KeyringStartup::Quit => return Ok(Exit::Quit),
+            }
+        };
+        let needs_file = !demo
+            && (cfg.secret_storage == SecretStorage::EncryptedFile
+                || cfg
+                    .libraries
+                    .iter()
+                    .flatten()
+                    .any(|lib| lib.password == Credentials::EncryptedFile));
+        let file = if needs_file {
+            match prompt_passphrase(terminal, &mut event_reader, &config_path).await? {
+                KeyringStartup::Ready(kr) => Some(kr),
+                KeyringStartup::Skipped => None,
+                KeyringStartup::Quit => return Ok(Exit::Quit),
+            }
+        } else {
+            None
+        };
+        Secrets {
+            keyring,
+            file,
+            storage: cfg.secret_storage,
+        }
+    };
+    let mut config_mtime = config_modified(&config_path);
+    // Libraries edited in the file while running, only loaded on restart.
+    let mut libraries_edited = false;
     let app = views::App {
         settings_page: views::settings::SettingsPage::new(),
         movie_manager: Default::default(),
     };
     let mut state = views::AppState {
         config: cfg,
+        profiles: config_path
+            .parent()
+            .map(mkube::config::list_profiles)
+            .unwrap_or_default(),
+        profile,
+        batch_journals: (!demo)
+            .then(|| config_path.with_file_name(format!("{}.batches", config_name))),
+        recent: (!demo)
+            .then(RecentMovies::cache_file)
+            .flatten()
+            .map(RecentMovies::load)
+            .unwrap_or_default(),
         ..Default::default()
     };
-    let mut event_reader = EventStream::new();
-    let mut pending_futures: JoinSet<Vec<AppEvent>> = JoinSet::new();
+    let dispatcher = views::Dispatcher {
+        http_client,
+        tmdb_client,
+        conns,
+    };
+    if !config_issues.is_empty() {
+        state.notice = Some(format!("Configuration issues: {}", config_issues.join(" ")));
+    }
+    #[cfg(feature = "secrets")]
+    if !demo && secrets.keyring.is_none() && secrets.file.is_none() {
+        state.notice =
+            Some("Keyring disabled for this session, saved passwords are not loaded.".into());
+    }
+    let mut pending_futures = PendingFutures::new();
     let tick = time::interval(Duration::from_millis(1000 / 15));
     tokio::pin!(tick);
+    let config_watch = time::interval(Duration::from_secs(2));
+    tokio::pin!(config_watch);
+    let heartbeat = heartbeat_interval(&state.config.heartbeat);
+    tokio::pin!(heartbeat);
 
     // Load libraries from config.
     #[cfg(feature = "secrets")]
     {
-        keyring
-            .unlock()
-            .await
-            .map_err(|err| anyhow!("Failed to unlock keyring, causes:\n{:?}", err))?;
+        if let Some(keyring) = &secrets.keyring {
+            keyring
+                .unlock()
+                .await
+                .map_err(|err| anyhow!("Failed to unlock keyring, causes:\n{:?}", err))?;
+        }
         for lib in state.config.libraries.iter_mut().flatten() {
-            if let Credentials::ToKeyring(c) = lib.password.clone() {
-                let path = lib.path.display().to_string();
-                let attributes = HashMap::from([
-                    ("fs_type", lib.fs_type.to_scheme()),
-                    ("host", lib.host.as_deref().unwrap_or("")),
-                    ("username", lib.username.as_deref().unwrap_or("")),
-                    ("path", &path),
-                ]);
-                match keyring.create_item(&lib.name, attributes, &c, true).await {
-                    Ok(()) => lib.password = Credentials::Keyring,
-                    Err(err) => {
-                        log::error!("Failed to save credentials to keyring, the credentials will be saved as clear text temporary. Cause:\n{:?}", err);
-                    }
-                }
-            }
+            lib.store_credentials(&secrets).await;
+        }
+        if let Some(keyring) = &secrets.keyring {
+            keyring
+                .lock()
+                .await
+                .map_err(|err| anyhow!("Failed to lock keyring, causes:\n{:?}", err))?;
         }
-        keyring
-            .lock()
-            .await
-            .map_err(|err| anyhow!("Failed to lock keyring, causes:\n{:?}", err))?;
     }
     {
         let mut conns_lock = conns.lock().await;
+        let mut prompted = Vec::new();
         for lib in state.config.libraries.iter().flatten() {
             let lib_;
             #[cfg(feature = "secrets")]
             {
-                lib_ = ConfigLibrary::try_into_with_keyring(lib.clone(), &keyring).await?;
+                lib_ = ConfigLibrary::try_into_with_keyring(lib.clone(), &secrets).await?;
             }
 
             #[cfg(not(feature = "secrets"))]
@@ -217,11 +633,17 @@ where
                 lib_ = ConfigLibrary::into(lib.clone());
             }
 
-            if let Ok(mut conn) = MultiFs::try_from(&lib_) {
-                if !conn.as_mut_rfs().is_connected() {
-                    let _ = conn.as_mut_rfs().connect();
-                }
-                conns_lock.push(Some(conn));
+            let ttl = state.config.listing_cache.for_library(&lib_);
+            if lib.password == mkube::config::Credentials::Prompt {
+                // Opened once its password is entered.
+                state
+                    .library_status
+                    .insert(lib_.id, ConnectionStatus::Offline);
+                state.prompted.insert(lib_.id);
+                prompted.push(lib_.clone());
+                state.libraries.push(Some(lib_));
+            } else if let Some(status) = mkube::open_library(&mut conns_lock, &lib_, ttl).await {
+                state.library_status.insert(lib_.id, status);
                 if cfg!(feature = "secrets") {
                     state.libraries.push(Some(lib_));
                 } else {
@@ -229,30 +651,88 @@ where
                 }
             }
         }
+        // The first library is asked first, on top of the others.
+        for lib in prompted.into_iter().rev() {
+            state
+                .modals
+                .push(views::settings::password_prompt(lib, false));
+        }
+        #[cfg(feature = "demo")]
+        if demo {
+            let (lib, conn) = mkube::demo::library(tmdb_client).await;
+            state
+                .library_status
+                .insert(lib.id, ConnectionStatus::Connected);
+            conns_lock.insert(lib.id, conn);
+            state.libraries.push(Some(lib));
+            state.notice =
+                Some("Demo mode, the library and the TMDB answers are samples.".into());
+        }
+    }
+    if let Some(dir) = &state.batch_journals {
+        let (journals, ops) = batch::interrupted_batches(dir);
+        if !ops.is_empty() {
+            log::warn!("{} batch operation(s) were interrupted.", ops.len());
+            state.modals.push(batch::resume_modal(journals, ops));
+        }
     }
 
+    let mut exit = Exit::Quit;
     loop {
         let event = event_reader.next().fuse();
 
         tokio::select! {
             _ = tick.tick() => {
+                state.pending_tasks = pending_futures.len();
                 terminal.draw(|f| {
                     let size = f.size();
                     f.render_stateful_widget(app.clone(), size, &mut state);
                 })?;
             }
+            _ = config_watch.tick(), if !demo => {
+                let mtime = config_modified(&config_path);
+                if mtime != config_mtime {
+                    config_mtime = mtime;
+                    match mkube::config::load(&config_path) {
+                        Ok((cfg, issues)) => {
+                            log::info!("Configuration file changed, reloading it.");
+                            config_mtime = config_modified(&config_path);
+                            if !issues.is_empty() {
+                                state.notice = Some(format!("Configuration issues: {}", issues.join(" ")));
+                            }
+                            let beat = state.config.heartbeat.clone();
+                            if state.config.apply_live(cfg) {
+                                libraries_edited = true;
+                                log::warn!("Libraries changed in the configuration file, they will only be reloaded on restart.");
+                                state.notice = Some("Libraries changed in the configuration, restart MKube to apply them.".into());
+                            }
+                            mkube::probing::configure(&state.config.probing);
+                            mkube::backups::configure(&state.config.backups);
+                            mkube::imagecache::configure(&state.config.image_cache);
+                            mkube::asyncfs::configure(&state.config.timeouts);
+                            if state.config.heartbeat != beat {
+                                heartbeat.set(heartbeat_interval(&state.config.heartbeat));
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Failed to reload configuration, causes:\n{:?}", err);
+                        }
+                    }
+                }
+            }
+            _ = heartbeat.tick(), if state.config.heartbeat.is_enabled() => {
+                push_pending(&pending_futures, async move {
+                    mkube::heartbeat(conns).await;
+                    vec![]
+                });
+            }
             maybe_event = event => {
                 match maybe_event {
                     Some(Ok(event)) => {
                         if let Event::Key(kev) = event {
-                            if kev.code == KeyCode::Char('c') && kev.modifiers == KeyModifiers::CONTROL {
+                            if state.press_key(kev) {
                                 break;
                             }
-                            state.register_event(mkube::AppEvent::KeyEvent(kev));
-                        }
-
-                        if event == Event::Key(KeyCode::Esc.into()) {
-                            break;
                         }
                     }
                     Some(Err(e)) => println!("Error: {:?}\r", e),
@@ -261,87 +741,112 @@ where
             }
             msg = receiver.recv() => {
                 if let Some(msg) = msg {
-                    use mkube::{AppMessage, AppEvent, views::settings::{SettingsMessage, SettingsEvent}};
-                    use mkube::{ views::movie_manager::{MovieManagerMessage}};
-                    match msg {
-                        AppMessage::Closure(closure) => {
-                            for evt in closure(&mut state) {
-                                state.register_event(evt);
-                            }
-                        }
-                        AppMessage::Future(builder) => {
-                            let _ = pending_futures.spawn(builder(&mut state));
-                        },
-                        AppMessage::AppFuture(builder) => {
-                            for evt in builder(&mut state).await {
+                    use mkube::views::{Dispatch, settings::SettingsEvent};
+                    match dispatcher.dispatch(&mut state, msg).await {
+                        Dispatch::Events(evts) => {
+                            for evt in evts {
                                 state.register_event(evt);
                             }
                         },
-                        AppMessage::IOFuture(builder) => {
-                            let _ = pending_futures.spawn_local(builder(&mut state, &http_client, &tmdb_client, &conns));
-                        },
-                        AppMessage::HttpFuture(builder) => {
-                            let _ = pending_futures.spawn(builder(&mut state, &http_client, &tmdb_client));
-                        },
-                        AppMessage::TriggerEvent(evt) => {
-                            state.register_event(evt);
-                        },
-                        AppMessage::SettingsMessage(SettingsMessage::EditExisting(lib)) => {
-                            if let Some((ind, _)) = state.libraries.iter().enumerate().filter(|(_, l)| l.is_some() && l.as_ref().unwrap() == &lib).next() {
-                                let l = state.libraries[ind].clone().unwrap();
-                                // Safety: Delete conn first, otherwise the app might panic if a future try to access this library.
-                                conns.lock().await[ind] = None;
-                                state.libraries[ind] = None;
-                                state.config.libraries[ind] = None;
-                                state.register_event(AppEvent::SettingsEvent(SettingsEvent::EditExisting(l)));
+                        Dispatch::Spawn(fut) => push_pending(&pending_futures, fut),
+                        Dispatch::SpawnLocal(fut) => push_pending(&pending_futures, fut),
+                        Dispatch::SaveLibrary(lib) => {
+                            let lib = *lib;
+                            let ttl = state.config.listing_cache.for_library(&lib);
+                            // Still asked at runtime unless a password was entered in the form.
+                            let prompt = state.prompted.contains(&lib.id) && lib.password.is_none();
+                            let status = if prompt {
+                                state.modals.push(views::settings::password_prompt(lib.clone(), false));
+                                Some(ConnectionStatus::Offline)
                             } else {
-                                log::error!("Invalid library editing, message ignored.");
-                            }
-                        },
-                        AppMessage::SettingsMessage(SettingsMessage::SaveLibrary(lib)) => {
-                            if let Ok(mut conn) = MultiFs::try_from(&lib) {
-                                if !conn.as_mut_rfs().is_connected() { let _ = conn.as_mut_rfs().connect(); }
-                                conns.lock().await.push(Some(conn));
+                                state.prompted.remove(&lib.id);
+                                mkube::open_library(&mut *conns.lock().await, &lib, ttl).await
+                            };
+                            if let Some(status) = status {
+                                state.library_status.insert(lib.id, status);
                                 state.libraries.push(Some(lib.clone()));
+                                let mut config_lib: ConfigLibrary;
                                 #[cfg(feature = "secrets")]
                                 {
-                                    state.config.libraries.push(Some(ConfigLibrary::from_with_keyring(lib, &keyring).await));
+                                    config_lib = ConfigLibrary::from_with_keyring(lib, &secrets).await;
                                 }
                                 #[cfg(not(feature = "secrets"))]
                                 {
-                                    state.config.libraries.push(Some(lib.into()));
+                                    config_lib = lib.into();
+                                }
+                                if prompt {
+                                    config_lib.password = mkube::config::Credentials::Prompt;
+                                }
+                                state.config.libraries.push(Some(config_lib));
+                                if !demo {
+                                    if let Err(err) = mkube::config::store(&config_path, &state.config) {
+                                        log::error!("Failed to save configuration, causes:\n{:?}", err);
+                                    }
+                                    config_mtime = config_modified(&config_path);
                                 }
-                                if let Err(err) = confy::store(APP_NAME, CONFIG_NAME, &state.config) {
+                            }
+                            state.register_event(AppEvent::SettingsEvent(SettingsEvent::OpenMenu(state.library_entries())));
+                        },
+                        Dispatch::AuditCredentials => {
+                            let audit;
+                            #[cfg(feature = "secrets")]
+                            {
+                                // The stores are shared by the profiles, their libraries are still used.
+                                let others = mkube::config::other_profiles_libraries(&config_path)
+                                    .map_err(|err| log::error!("Failed to read the other profiles, causes:\n{:?}", err))
+                                    .ok();
+                                audit = mkube::config::audit_credentials(&mut state.config.libraries, others.as_deref(), &secrets).await;
+                            }
+                            #[cfg(not(feature = "secrets"))]
+                            {
+                                audit = mkube::config::CredentialsAudit::clear_only(&state.config.libraries);
+                            }
+                            log::info!("Saved passwords audit: {:?}", audit);
+                            if !audit.migrated.is_empty() && !demo {
+                                // The backup of the previous file still has the clear passwords.
+                                let stored = mkube::config::store(&config_path, &state.config)
+                                    .and_then(|()| mkube::config::refresh_backup(&config_path));
+                                if let Err(err) = stored {
                                     log::error!("Failed to save configuration, causes:\n{:?}", err);
                                 }
+                                config_mtime = config_modified(&config_path);
+                            }
+                            state.notice = Some(audit.summary());
+                            if !audit.orphans.is_empty() {
+                                state.modals.push(views::settings::orphans_modal(audit.orphans));
                             }
-                            state.register_event(AppEvent::SettingsEvent(SettingsEvent::OpenMenu(state.libraries.iter().flatten().cloned().collect())));
                         },
-                        AppMessage::SettingsMessage(SettingsMessage::OpenMenu)
-                        | AppMessage::SettingsMessage(SettingsMessage::TestLibrary(_))
-                        | AppMessage::MovieManagerMessage(MovieManagerMessage::RefreshMovies)
-                        | AppMessage::MovieManagerMessage(MovieManagerMessage::SearchTitle(_))
-                        | AppMessage::MovieManagerMessage(MovieManagerMessage::CreateNfo(_))
-                        | AppMessage::MovieManagerMessage(MovieManagerMessage::SaveNfo(_))
-                        | AppMessage::MovieManagerMessage(MovieManagerMessage::RetrieveArtworks(_)) => {
-                            panic!("Deprecated");
+                        Dispatch::DeleteCredentials(orphans) => {
+                            #[cfg(feature = "secrets")]
+                            {
+                                let deleted = mkube::config::delete_credentials(&orphans, &secrets).await;
+                                state.notice = Some(format!("{} of {} unused saved passwords deleted.", deleted, orphans.len()));
+                            }
+                            #[cfg(not(feature = "secrets"))]
+                            log::error!("No secret store to delete {} passwords from.", orphans.len());
+                        },
+                        Dispatch::SwitchProfile(next) => {
+                            exit = Exit::SwitchProfile(next);
+                            break;
                         },
-                        AppMessage::Close => {
+                        Dispatch::Close => {
                             break;
                         },
-                        _ => { unimplemented!(); }
                     }
                 } else {
                     break;
                 }
             },
-            task = pending_futures.join_next(), if !pending_futures.is_empty() => match task {
+            task = pending_futures.next(), if !pending_futures.is_empty() => match task {
                 Some(task) => match task {
                     Ok(evt) => for e in evt {
                         state.register_event(e);
                     },
-                    Err(err) => {
-                        log::error!("pending_futures has returned an error:\n{:?}", err);
+                    Err(_) => {
+                        log::error!("A pending future panicked.");
+                        // The panic hook restored the terminal, take it back.
+                        setup_terminal()?;
+                        terminal.clear()?;
                     },
                 },
                 None => {
@@ -351,9 +856,77 @@ where
         }
     }
 
-    if let Err(err) = confy::store(APP_NAME, CONFIG_NAME, &state.config) {
-        log::error!("Failed to save configuration, causes:\n{:?}", err);
+    if !demo {
+        // Keep the libraries of the file when edited by hand, saving ours would revert them.
+        let mut save = true;
+        if libraries_edited || config_modified(&config_path) != config_mtime {
+            match mkube::config::load(&config_path) {
+                Ok((cfg, _)) => state.config.libraries = cfg.libraries,
+                Err(err) => {
+                    log::error!(
+                        "Configuration file not saved, it can not be read back, causes:\n{:?}",
+                        err
+                    );
+                    save = false;
+                }
+            }
+        }
+        if save {
+            if let Err(err) = mkube::config::store(&config_path, &state.config) {
+                log::error!("Failed to save configuration, causes:\n{:?}", err);
+            }
+        }
+    }
+
+    log::info!("Session summary: {}.", state.stats.lines().join(", "));
+    if matches!(exit, Exit::Quit) && !state.stats.is_empty() {
+        show_stats(terminal, &mut event_reader, &state.stats).await?;
     }
 
-    Ok(())
+    Ok(exit)
+}
+
+/// Summary of the session, shown until a key is pressed.
+async fn show_stats<B>(
+    terminal: &mut Terminal<B>,
+    event_reader: &mut EventStream,
+    stats: &SessionStats,
+) -> Result<()>
+where
+    B: tui::backend::Backend,
+{
+    let mut text = vec![
+        Spans::from(Span::styled(
+            " Session summary",
+            Style::default().fg(Color::Yellow),
+        )),
+        Spans::from(""),
+    ];
+    text.extend(stats.lines().into_iter().map(|line| {
+        let style = if line.starts_with("Errors") && stats.errors > 0 {
+            Style::default().fg(Color::LightRed)
+        } else {
+            Style::default()
+        };
+        Spans::from(Span::styled(format!("   {}", line), style))
+    }));
+    text.push(Spans::from(""));
+    text.push(Spans::from(" Press any key to quit."));
+    terminal.draw(|f| f.render_widget(Paragraph::new(text), f.size()))?;
+    loop {
+        match event_reader.next().fuse().await {
+            Some(Ok(Event::Key(_))) | Some(Err(_)) | None => return Ok(()),
+            Some(Ok(_)) => {}
+        }
+    }
+}
+
+/// Ticks of the library heartbeat, the first one after a whole interval.
+fn heartbeat_interval(heartbeat: &Heartbeat) -> time::Interval {
+    let period = Duration::from_secs(heartbeat.interval.max(1));
+    time::interval_at(time::Instant::now() + period, period)
+}
+
+fn config_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
 }
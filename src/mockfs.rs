@@ -0,0 +1,374 @@
+use remotefs::fs::{File, FileType, Metadata, ReadStream, UnixPex, Welcome, WriteStream};
+use remotefs::{RemoteError, RemoteErrorType, RemoteFs, RemoteResult};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+enum Node {
+    Dir,
+    File(Vec<u8>),
+}
+
+#[derive(Debug)]
+struct MockState {
+    nodes: BTreeMap<PathBuf, Node>,
+    latency: Duration,
+    /// Paths failing with their error, their children included.
+    failures: Vec<(PathBuf, RemoteErrorType)>,
+    online: bool,
+}
+
+/// In-memory filesystem for the tests and the demo mode, without stream support like most
+/// remote backends.
+///
+/// Clones share the same tree, so a test can inspect it once the filesystem is in use.
+#[derive(Clone, Debug)]
+pub struct MockFs {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockFs {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                nodes: BTreeMap::from([(PathBuf::from("/"), Node::Dir)]),
+                latency: Duration::ZERO,
+                failures: Vec::new(),
+                online: true,
+            })),
+        }
+    }
+
+    /// Add a file and its missing parents.
+    pub fn with_file(self, path: impl AsRef<Path>, data: impl Into<Vec<u8>>) -> Self {
+        let path = normalize(path.as_ref());
+        let mut state = self.state();
+        if let Some(parent) = path.parent() {
+            state.create_dirs(parent);
+        }
+        state.nodes.insert(path, Node::File(data.into()));
+        drop(state);
+        self
+    }
+
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        self.state().create_dirs(&normalize(path.as_ref()));
+        self
+    }
+
+    /// Delay applied to every operation.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.state().latency = latency;
+        self
+    }
+
+    /// Make every operation on `path` or below fail with `kind`.
+    pub fn fail(&self, path: impl AsRef<Path>, kind: RemoteErrorType) {
+        self.state().failures.push((normalize(path.as_ref()), kind));
+    }
+
+    pub fn clear_failures(&self) {
+        self.state().failures.clear();
+    }
+
+    /// Offline, the connection is lost and reconnecting fails.
+    pub fn set_online(&self, online: bool) {
+        self.state().online = online;
+    }
+
+    /// Content of the file at `path`, None if it is missing or a directory.
+    pub fn read(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        match self.state().nodes.get(&normalize(path.as_ref())) {
+            Some(Node::File(data)) => Some(data.clone()),
+            _ => None,
+        }
+    }
+
+    /// Every file and directory, sorted.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.state().nodes.keys().cloned().collect()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Lock the tree for an operation on `paths`, once the latency and the failures are applied.
+    fn op(&self, paths: &[&Path]) -> RemoteResult<MutexGuard<'_, MockState>> {
+        let latency = self.state().latency;
+        if !latency.is_zero() {
+            std::thread::sleep(latency);
+        }
+        let state = self.state();
+        if !state.online {
+            return Err(RemoteError::new(RemoteErrorType::NotConnected));
+        }
+        for path in paths {
+            let path = normalize(path);
+            if let Some((_, kind)) = state.failures.iter().find(|(p, _)| path.starts_with(p)) {
+                return Err(RemoteError::new_ex(*kind, "injected failure"));
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl MockState {
+    fn create_dirs(&mut self, path: &Path) {
+        for dir in path.ancestors() {
+            self.nodes.entry(dir.to_path_buf()).or_insert(Node::Dir);
+        }
+    }
+
+    fn file(&self, path: &Path) -> RemoteResult<File> {
+        let (file_type, size) = match self.nodes.get(path) {
+            Some(Node::Dir) => (FileType::Directory, 0),
+            Some(Node::File(data)) => (FileType::File, data.len() as u64),
+            None => return Err(RemoteError::new(RemoteErrorType::StatFailed)),
+        };
+        Ok(File {
+            path: path.to_path_buf(),
+            metadata: Metadata {
+                file_type,
+                size,
+                ..Default::default()
+            },
+        })
+    }
+
+    fn has_dir(&self, path: &Path) -> bool {
+        matches!(self.nodes.get(path), Some(Node::Dir))
+    }
+
+    fn children(&self, path: &Path) -> Vec<PathBuf> {
+        self.nodes
+            .keys()
+            .filter(|p| p.parent() == Some(path))
+            .cloned()
+            .collect()
+    }
+
+    /// The node at `path` and all its children.
+    fn subtree(&self, path: &Path) -> Vec<PathBuf> {
+        self.nodes
+            .keys()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    fn write(&mut self, path: &Path, mut reader: Box<dyn Read>, append: bool) -> RemoteResult<u64> {
+        if !path.parent().is_some_and(|parent| self.has_dir(parent)) || self.has_dir(path) {
+            return Err(RemoteError::new(RemoteErrorType::FileCreateDenied));
+        }
+        let mut data = Vec::new();
+        let written = reader
+            .read_to_end(&mut data)
+            .map_err(|err| RemoteError::new_ex(RemoteErrorType::IoError, err))?;
+        match self.nodes.get_mut(path) {
+            Some(Node::File(content)) if append => content.extend(data),
+            _ => {
+                self.nodes.insert(path.to_path_buf(), Node::File(data));
+            }
+        }
+        Ok(written as u64)
+    }
+}
+
+/// Absolute path without `.` or `..`, the mock has no working directory.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            _ => {}
+        }
+    }
+    normalized
+}
+
+impl RemoteFs for MockFs {
+    fn connect(&mut self) -> RemoteResult<Welcome> {
+        self.op(&[]).map(|_| Welcome::default())
+    }
+
+    fn disconnect(&mut self) -> RemoteResult<()> {
+        Ok(())
+    }
+
+    fn is_connected(&mut self) -> bool {
+        self.state().online
+    }
+
+    fn pwd(&mut self) -> RemoteResult<PathBuf> {
+        self.op(&[]).map(|_| PathBuf::from("/"))
+    }
+
+    fn change_dir(&mut self, _dir: &Path) -> RemoteResult<PathBuf> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn list_dir(&mut self, path: &Path) -> RemoteResult<Vec<File>> {
+        let state = self.op(&[path])?;
+        let path = normalize(path);
+        if !state.has_dir(&path) {
+            return Err(RemoteError::new(RemoteErrorType::CouldNotOpenFile));
+        }
+        state
+            .children(&path)
+            .iter()
+            .map(|child| state.file(child))
+            .collect()
+    }
+
+    fn stat(&mut self, path: &Path) -> RemoteResult<File> {
+        self.op(&[path])?.file(&normalize(path))
+    }
+
+    fn setstat(&mut self, _path: &Path, _metadata: Metadata) -> RemoteResult<()> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn exists(&mut self, path: &Path) -> RemoteResult<bool> {
+        Ok(self.op(&[path])?.nodes.contains_key(&normalize(path)))
+    }
+
+    fn remove_file(&mut self, path: &Path) -> RemoteResult<()> {
+        let mut state = self.op(&[path])?;
+        match state.nodes.get(&normalize(path)) {
+            Some(Node::File(_)) => {
+                state.nodes.remove(&normalize(path));
+                Ok(())
+            }
+            _ => Err(RemoteError::new(RemoteErrorType::CouldNotRemoveFile)),
+        }
+    }
+
+    fn remove_dir(&mut self, path: &Path) -> RemoteResult<()> {
+        let mut state = self.op(&[path])?;
+        let path = normalize(path);
+        if !state.has_dir(&path) || !state.children(&path).is_empty() {
+            return Err(RemoteError::new(RemoteErrorType::CouldNotRemoveFile));
+        }
+        state.nodes.remove(&path);
+        Ok(())
+    }
+
+    fn remove_dir_all(&mut self, path: &Path) -> RemoteResult<()> {
+        let mut state = self.op(&[path])?;
+        let path = normalize(path);
+        if !state.nodes.contains_key(&path) {
+            return Err(RemoteError::new(RemoteErrorType::StatFailed));
+        }
+        for node in state.subtree(&path) {
+            state.nodes.remove(&node);
+        }
+        Ok(())
+    }
+
+    fn create_dir(&mut self, path: &Path, _mode: UnixPex) -> RemoteResult<()> {
+        let mut state = self.op(&[path])?;
+        let path = normalize(path);
+        if state.nodes.contains_key(&path) {
+            return Err(RemoteError::new(RemoteErrorType::DirectoryAlreadyExists));
+        }
+        if !path.parent().is_some_and(|parent| state.has_dir(parent)) {
+            return Err(RemoteError::new(RemoteErrorType::FileCreateDenied));
+        }
+        state.nodes.insert(path, Node::Dir);
+        Ok(())
+    }
+
+    fn symlink(&mut self, _path: &Path, _target: &Path) -> RemoteResult<()> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn copy(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let mut state = self.op(&[src, dest])?;
+        let data = match state.nodes.get(&normalize(src)) {
+            Some(Node::File(data)) => data.clone(),
+            _ => return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory)),
+        };
+        state.write(
+            &normalize(dest),
+            Box::new(std::io::Cursor::new(data)),
+            false,
+        )?;
+        Ok(())
+    }
+
+    fn mov(&mut self, src: &Path, dest: &Path) -> RemoteResult<()> {
+        let mut state = self.op(&[src, dest])?;
+        let (src, dest) = (normalize(src), normalize(dest));
+        if !state.nodes.contains_key(&src) {
+            return Err(RemoteError::new(RemoteErrorType::NoSuchFileOrDirectory));
+        }
+        if state.nodes.contains_key(&dest) || !dest.parent().is_some_and(|p| state.has_dir(p)) {
+            return Err(RemoteError::new(RemoteErrorType::FileCreateDenied));
+        }
+        for node in state.subtree(&src) {
+            let moved = dest.join(node.strip_prefix(&src).unwrap());
+            let content = state.nodes.remove(&node).unwrap();
+            state.nodes.insert(moved, content);
+        }
+        Ok(())
+    }
+
+    fn exec(&mut self, _cmd: &str) -> RemoteResult<(u32, String)> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn append_file(
+        &mut self,
+        path: &Path,
+        _metadata: &Metadata,
+        reader: Box<dyn Read>,
+    ) -> RemoteResult<u64> {
+        self.op(&[path])?.write(&normalize(path), reader, true)
+    }
+
+    fn create_file(
+        &mut self,
+        path: &Path,
+        _metadata: &Metadata,
+        reader: Box<dyn Read>,
+    ) -> RemoteResult<u64> {
+        self.op(&[path])?.write(&normalize(path), reader, false)
+    }
+
+    fn open_file(&mut self, path: &Path, mut dest: Box<dyn Write + Send>) -> RemoteResult<u64> {
+        let state = self.op(&[path])?;
+        match state.nodes.get(&normalize(path)) {
+            Some(Node::File(data)) => {
+                dest.write_all(data)
+                    .map_err(|err| RemoteError::new_ex(RemoteErrorType::IoError, err))?;
+                Ok(data.len() as u64)
+            }
+            _ => Err(RemoteError::new(RemoteErrorType::CouldNotOpenFile)),
+        }
+    }
+
+    fn append(&mut self, _path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn create(&mut self, _path: &Path, _metadata: &Metadata) -> RemoteResult<WriteStream> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+
+    fn open(&mut self, _path: &Path) -> RemoteResult<ReadStream> {
+        Err(RemoteError::new(RemoteErrorType::UnsupportedFeature))
+    }
+}
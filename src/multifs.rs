@@ -1,32 +1,377 @@
+use crate::cachedfs::CachedFs;
 use crate::localfs::LocalFs;
+#[cfg(feature = "s3")]
+use crate::s3fs::S3Fs;
+use crate::{AppEvent, AppMessage, MESSAGE_SENDER};
 use anyhow::{anyhow, Result};
 use metadata::MediaFileMetadata;
-use remotefs::fs::RemoteFs;
+use remotefs::fs::{Metadata, ReadStream, RemoteFs, WriteStream};
+use remotefs::{RemoteError, RemoteErrorType};
 #[cfg(feature = "ftp")]
 use remotefs_ftp::client::FtpFs;
 #[cfg(feature = "smb")]
 use remotefs_smb::SmbFs;
+use std::collections::HashMap;
 use std::io::{self, Cursor, Read, Result as IoResult, Seek, SeekFrom, Write};
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const CHUNK_SIZE: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+/// Bytes copied locally when ffmpeg can not open a media through the library URL.
+const PROBE_PREFIX_SIZE: u64 = 32 * 1024 * 1024;
+/// Packets read looking for HDR10+ metadata, only carried along the frames.
+const HDR_PROBE_PACKETS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ConnectionStatus {
+    #[default]
+    Connected,
+    Degraded,
+    Offline,
+}
+
+impl std::fmt::Display for ConnectionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionStatus::Connected => write!(f, "connected"),
+            ConnectionStatus::Degraded => write!(f, "degraded"),
+            ConnectionStatus::Offline => write!(f, "offline"),
+        }
+    }
+}
 
 pub enum MultiFs {
     Local(LocalFs),
     #[cfg(feature = "ftp")]
-    Ftp(FtpFs),
+    Ftp(SendFtpFs),
     #[cfg(feature = "smb")]
     Smb(SmbFs),
+    #[cfg(feature = "s3")]
+    S3(S3Fs),
+    /// In-memory filesystem of the tests and of the `--demo` mode.
+    #[cfg(any(test, feature = "demo"))]
+    Mock(crate::mockfs::MockFs),
+    /// Another filesystem with its listings cached.
+    Cached(Box<CachedFs>),
+}
+
+/// FTP connection which can be moved to the blocking threads of tokio.
+///
+/// suppaftp boxes the TLS connector of FTPS as a `dyn TlsConnector` without a Send bound,
+/// although the native-tls connector it is built with is Send.
+#[cfg(feature = "ftp")]
+pub struct SendFtpFs(FtpFs);
+
+// SAFETY: the only field which is not Send is the native-tls connector, which is. A connection
+// is moved between threads, never shared.
+#[cfg(feature = "ftp")]
+unsafe impl Send for SendFtpFs {}
+
+#[cfg(feature = "ftp")]
+impl From<FtpFs> for SendFtpFs {
+    fn from(ftp: FtpFs) -> Self {
+        Self(ftp)
+    }
 }
 
 impl MultiFs {
+    /// Cache the directory listings for `ttl`, None leaves the filesystem as is.
+    pub fn cached(self, ttl: Option<Duration>) -> MultiFs {
+        match ttl {
+            Some(ttl) => MultiFs::Cached(Box::new(CachedFs::new(self, ttl))),
+            None => self,
+        }
+    }
+
     pub fn as_mut_rfs(&mut self) -> &mut dyn RemoteFs {
         match self {
             MultiFs::Local(lfs) => lfs,
             #[cfg(feature = "ftp")]
-            MultiFs::Ftp(ftp) => ftp,
+            MultiFs::Ftp(ftp) => &mut ftp.0,
             #[cfg(feature = "smb")]
             MultiFs::Smb(smb) => smb,
+            #[cfg(feature = "s3")]
+            MultiFs::S3(s3) => s3,
+            #[cfg(any(test, feature = "demo"))]
+            MultiFs::Mock(mock) => mock,
+            MultiFs::Cached(cached) => cached.as_mut(),
+        }
+    }
+
+    /// Canonical path of a directory, when the filesystem follows symlinks.
+    pub fn real_path(&self, path: &Path) -> Option<PathBuf> {
+        match self {
+            MultiFs::Local(lfs) => lfs.real_path(path),
+            MultiFs::Cached(cached) => cached.inner.real_path(path),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Location ffmpeg opens `path` from, `ffmpeg_base` being [`Library::ffmpeg_base`].
+    ///
+    /// A `file:` base of a remote library is the local mount of its filesystem root.
+    ///
+    /// [`Library::ffmpeg_base`]: crate::library::Library::ffmpeg_base
+    pub fn media_url(&self, mut ffmpeg_base: url::Url, path: &Path) -> Result<String> {
+        if let MultiFs::Cached(cached) = self {
+            return cached.inner.media_url(ffmpeg_base, path);
+        }
+        if ffmpeg_base.scheme() == "file" && !matches!(self, MultiFs::Local(_)) {
+            let mount = ffmpeg_base
+                .to_file_path()
+                .map_err(|_| anyhow!("invalid ffmpeg path mapping {}", ffmpeg_base))?;
+            let relative = path.strip_prefix("/").unwrap_or(path);
+            return Ok(mount.join(relative).display().to_string());
+        }
+        #[cfg(feature = "s3")]
+        if let MultiFs::S3(s3) = self {
+            return Ok(s3.presigned_url(path, Duration::from_secs(3600)));
+        }
+        let decoded_path = urlencoding::decode(ffmpeg_base.path())
+            .map_err(|err| anyhow!("failed to decode path (url-encoding), causes:\n{:?}", err))?;
+        let mut root = PathBuf::from(&*decoded_path);
+        ffmpeg_base.set_path("/");
+        root.push(path);
+        Ok(format!("{}/{}", ffmpeg_base, root.display()))
+    }
+
+    /// Whether the connection looks alive, `probe` also checking it with a call to the server.
+    pub fn is_alive(&mut self, probe: bool) -> bool {
+        let rfs = self.as_mut_rfs();
+        rfs.is_connected() && (!probe || rfs.pwd().is_ok())
+    }
+
+    /// Drop the connection and connect again, a single attempt.
+    ///
+    /// The pool retries it with a backoff, see [`crate::asyncfs`].
+    pub fn reconnect(&mut self) -> Result<()> {
+        let rfs = self.as_mut_rfs();
+        let _ = rfs.disconnect();
+        rfs.connect()
+            .map(|_| ())
+            .map_err(|err| anyhow!("Reconnection failed, causes:\n{:?}", err))
+    }
+
+    /// Open a file for streaming writes, truncating it.
+    ///
+    /// Filesystems without stream support get the data appended chunk by chunk.
+    pub fn create_writer(&mut self, path: &Path) -> Result<RemoteWriter<'_>> {
+        let stream = match self.as_mut_rfs().create(path, &Metadata::default()) {
+            Ok(stream) => Some(stream),
+            Err(err) if is_unsupported(&err) => None,
+            Err(err) => {
+                return Err(anyhow!(
+                    "Failed to create file {}, causes:\n{:?}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        Ok(RemoteWriter {
+            fs: self,
+            path: path.to_path_buf(),
+            stream,
+            created: false,
+        })
+    }
+
+    /// Open a file for streaming reads.
+    ///
+    /// Filesystems without stream support are read through a memory buffer as they
+    /// do not allow partial reads.
+    pub fn open_reader(&mut self, path: &Path) -> Result<RemoteReader<'_>> {
+        let stream = match self.as_mut_rfs().open(path) {
+            Ok(stream) => Some(stream),
+            Err(err) if is_unsupported(&err) => None,
+            Err(err) => {
+                return Err(anyhow!(
+                    "Failed to open file {}, causes:\n{:?}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        let buffer = OwnedCursor::new();
+        if stream.is_none() {
+            let mut buffer = buffer.clone();
+            self.as_mut_rfs()
+                .open_file(path, Box::new(buffer.clone()))
+                .map_err(|err| {
+                    anyhow!("Failed to open file {}, causes:\n{:?}", path.display(), err)
+                })?;
+            buffer.rewind()?;
+        }
+        Ok(RemoteReader {
+            fs: self,
+            stream,
+            buffer,
+        })
+    }
+}
+
+fn is_unsupported(err: &RemoteError) -> bool {
+    err.kind == RemoteErrorType::UnsupportedFeature
+}
+
+/// Writer over a remote file, call `finish` once everything is written.
+pub struct RemoteWriter<'a> {
+    fs: &'a mut MultiFs,
+    path: PathBuf,
+    stream: Option<WriteStream>,
+    created: bool,
+}
+
+impl<'a> RemoteWriter<'a> {
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.flush()?;
+            self.fs.as_mut_rfs().on_written(stream).map_err(|err| {
+                anyhow!(
+                    "Failed to finalize file {}, causes:\n{:?}",
+                    self.path.display(),
+                    err
+                )
+            })?;
+        } else if !self.created {
+            // Nothing was written, still create the empty file.
+            self.write_all(&[])?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Write for RemoteWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if let Some(stream) = self.stream.as_mut() {
+            return stream.write(buf);
+        }
+        let chunk = Box::new(Cursor::new(buf.to_vec()));
+        let rfs = self.fs.as_mut_rfs();
+        let res = if self.created {
+            rfs.append_file(&self.path, &Metadata::default(), chunk)
+        } else {
+            rfs.create_file(&self.path, &Metadata::default(), chunk)
+        };
+        res.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        self.created = true;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self.stream.as_mut() {
+            Some(stream) => stream.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reader over a remote file, call `finish` once everything is read.
+pub struct RemoteReader<'a> {
+    fs: &'a mut MultiFs,
+    stream: Option<ReadStream>,
+    buffer: OwnedCursor,
+}
+
+impl<'a> RemoteReader<'a> {
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(stream) = self.stream.take() {
+            self.fs
+                .as_mut_rfs()
+                .on_read(stream)
+                .map_err(|err| anyhow!("Failed to close the file, causes:\n{:?}", err))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Read for RemoteReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self.stream.as_mut() {
+            Some(stream) => stream.read(buf),
+            None => self.buffer.read(buf),
+        }
+    }
+}
+
+/// Bytes done of a file transfer, sent as an AppEvent::TransferProgress.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransferProgress {
+    pub id: usize,
+    pub label: String,
+    pub done: u64,
+    pub total: u64,
+    pub finished: bool,
+}
+
+static NEXT_TRANSFER: AtomicUsize = AtomicUsize::new(0);
+
+/// Copy between two streams, reporting its progress to the UI at most every PROGRESS_INTERVAL.
+///
+/// Transfers shorter than the interval are never reported. Once reported, the transfer is
+/// marked as finished on drop, even if it failed.
+pub struct Transfer {
+    progress: TransferProgress,
+    last_report: Instant,
+    reported: bool,
+}
+
+impl Transfer {
+    pub fn new(label: impl Into<String>, total: u64) -> Self {
+        Self {
+            progress: TransferProgress {
+                id: NEXT_TRANSFER.fetch_add(1, Ordering::Relaxed),
+                label: label.into(),
+                total,
+                ..Default::default()
+            },
+            last_report: Instant::now(),
+            reported: false,
+        }
+    }
+
+    /// Copy everything from `reader` to `writer`, returns the number of bytes copied.
+    pub fn copy<R: Read + ?Sized, W: Write + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> IoResult<u64> {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            writer.write_all(&buf[..len])?;
+            self.progress.done += len as u64;
+            if self.last_report.elapsed() >= PROGRESS_INTERVAL {
+                self.report();
+            }
+        }
+        Ok(self.progress.done)
+    }
+
+    fn report(&mut self) {
+        self.last_report = Instant::now();
+        self.reported = true;
+        if let Some(sender) = MESSAGE_SENDER.get() {
+            let _ = sender.send(AppMessage::TriggerEvent(AppEvent::TransferProgress(
+                self.progress.clone(),
+            )));
+        }
+    }
+}
+
+impl Drop for Transfer {
+    fn drop(&mut self) {
+        if self.reported {
+            self.progress.finished = true;
+            self.report();
         }
     }
 }
@@ -77,11 +422,253 @@ impl Seek for OwnedCursor {
     }
 }
 
-pub fn open_multifs_media(
-    mfs: &mut dyn RemoteFs,
-    mut ffmpeg_base: url::Url,
+/// Temporary local copy of the beginning of a remote media, removed on drop.
+///
+/// Most containers describe their streams in the header, so it is enough for ffmpeg to probe
+/// them. The duration might be missing for the few formats storing it at the end of the file.
+struct ProbeCopy {
+    path: PathBuf,
+}
+
+impl ProbeCopy {
+    fn download(mfs: &mut MultiFs, path: &Path) -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let mut name = format!(
+            "mkube-probe-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            name = format!("{}.{}", name, ext);
+        }
+        let copy = Self {
+            path: std::env::temp_dir().join(name),
+        };
+        let mut file = std::fs::File::create(&copy.path)?;
+        let mut reader = mfs.open_reader(path)?;
+        io::copy(&mut (&mut reader).take(PROBE_PREFIX_SIZE), &mut file)?;
+        if let Err(err) = reader.finish() {
+            // Expected on some protocols as the file was not read until the end.
+            log::debug!(
+                "Partial read of {} not closed cleanly: {:?}",
+                path.display(),
+                err
+            );
+        }
+        Ok(copy)
+    }
+}
+
+impl Drop for ProbeCopy {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::warn!(
+                "Failed to remove the temporary file {}: {:?}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Decode the frame at `timestamp` seconds of a video and encode it as a JPEG.
+///
+/// Media shorter than twice `timestamp` are grabbed at half of their duration.
+pub fn grab_frame(
+    mfs: &mut MultiFs,
+    ffmpeg_base: url::Url,
+    path: &Path,
+    timestamp: u64,
+) -> Result<Vec<u8>> {
+    use ffmpeg::software::scaling;
+    use ffmpeg::{codec, format::Pixel, frame, media::Type, Packet};
+    use ffmpeg_next as ffmpeg;
+
+    let ff_path = PathBuf::from(mfs.media_url(ffmpeg_base, path)?);
+    let mut input = ffmpeg::format::input(&ff_path).map_err(|err| {
+        anyhow!(
+            "FFMpeg error: open failed for {}, causes:\n{:?}",
+            path.display(),
+            err
+        )
+    })?;
+    let (index, parameters) = input
+        .streams()
+        .best(Type::Video)
+        .map(|s| (s.index(), s.parameters()))
+        .ok_or_else(|| anyhow!("No video stream in {}.", path.display()))?;
+    let mut decoder = codec::context::Context::from_parameters(parameters)?
+        .decoder()
+        .video()?;
+
+    let time_base = ffmpeg::ffi::AV_TIME_BASE as i64;
+    let mut seconds = timestamp as i64;
+    if input.duration() > 0 {
+        seconds = seconds.min(input.duration() / time_base / 2);
+    }
+    let position = seconds * time_base;
+    input.seek(position, ..position)?;
+
+    let mut decoded = frame::Video::empty();
+    let mut grabbed = false;
+    for (stream, packet) in input.packets() {
+        if stream.index() != index {
+            continue;
+        }
+        decoder.send_packet(&packet)?;
+        if decoder.receive_frame(&mut decoded).is_ok() {
+            grabbed = true;
+            break;
+        }
+    }
+    if !grabbed {
+        decoder.send_eof()?;
+        decoder.receive_frame(&mut decoded).map_err(|err| {
+            anyhow!(
+                "No frame decoded at {}s of {}, causes:\n{:?}",
+                seconds,
+                path.display(),
+                err
+            )
+        })?;
+    }
+
+    let mut scaler = scaling::Context::get(
+        decoded.format(),
+        decoded.width(),
+        decoded.height(),
+        Pixel::YUVJ420P,
+        decoded.width(),
+        decoded.height(),
+        scaling::Flags::BILINEAR,
+    )?;
+    let mut picture = frame::Video::empty();
+    scaler.run(&decoded, &mut picture)?;
+    picture.set_pts(Some(0));
+
+    let jpeg_codec =
+        ffmpeg::encoder::find(codec::Id::MJPEG).ok_or(anyhow!("FFMpeg has no JPEG encoder."))?;
+    let mut encoder = codec::context::Context::new().encoder().video()?;
+    encoder.set_width(decoded.width());
+    encoder.set_height(decoded.height());
+    encoder.set_format(Pixel::YUVJ420P);
+    encoder.set_time_base((1, 25));
+    let mut encoder = encoder.open_as(jpeg_codec)?;
+    encoder.send_frame(&picture)?;
+    encoder.send_eof()?;
+    let mut packet = Packet::empty();
+    encoder.receive_packet(&mut packet)?;
+    packet
+        .data()
+        .map(<[u8]>::to_vec)
+        .ok_or(anyhow!("FFMpeg produced an empty JPEG."))
+}
+
+/// Dynamic HDR formats of a video stream, only signalled by ffmpeg side data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HdrSideData {
+    pub dolby_vision: bool,
+    pub hdr10_plus: bool,
+}
+
+fn probe_hdr_side_data(
+    format_ctx: &mut ffmpeg_next::format::context::Input,
+) -> HashMap<usize, HdrSideData> {
+    use ffmpeg_next::codec::packet::side_data::Type;
+    use ffmpeg_next::media::Type as Medium;
+
+    let mut found: HashMap<usize, HdrSideData> = format_ctx
+        .streams()
+        .filter(|s| s.parameters().medium() == Medium::Video)
+        .map(|s| {
+            let mut hdr = HdrSideData::default();
+            for side_data in s.side_data() {
+                match side_data.kind() {
+                    Type::DOVI_CONF => hdr.dolby_vision = true,
+                    Type::DYNAMIC_HDR10_PLUS => hdr.hdr10_plus = true,
+                    _ => {}
+                }
+            }
+            (s.index(), hdr)
+        })
+        .collect();
+    for (stream, packet) in format_ctx.packets().take(HDR_PROBE_PACKETS) {
+        if let Some(hdr) = found.get_mut(&stream.index()) {
+            hdr.hdr10_plus |= packet
+                .side_data()
+                .any(|side_data| side_data.kind() == Type::DYNAMIC_HDR10_PLUS);
+        }
+    }
+    found
+}
+
+/// A media ready to be probed by ffmpeg, without the connection of its library.
+pub struct MediaSource {
     path: PathBuf,
-) -> Result<MediaFileMetadata> {
+    url: PathBuf,
+    file_size: u64,
+    copy: Option<ProbeCopy>,
+}
+
+impl MediaSource {
+    pub fn new(mfs: &mut MultiFs, ffmpeg_base: url::Url, path: PathBuf) -> Result<Self> {
+        let url = PathBuf::from(mfs.media_url(ffmpeg_base, &path)?);
+        let file_size = mfs
+            .as_mut_rfs()
+            .stat(&path)
+            .map_err(|err| anyhow!("Remotefs error: failed to read metadata {:?}", err))?
+            .metadata
+            .size;
+        Ok(Self {
+            path,
+            url,
+            file_size,
+            copy: None,
+        })
+    }
+
+    /// Copy the header of the media locally, for ffmpeg to probe it instead of the library URL.
+    pub fn download_copy(&mut self, mfs: &mut MultiFs) -> Result<()> {
+        self.copy = Some(ProbeCopy::download(mfs, &self.path)?);
+        Ok(())
+    }
+
+    /// Run ffmpeg on the media, blocking. None when ffmpeg can not open it from the library
+    /// URL, its [`download_copy`](Self::download_copy) is probed next.
+    pub fn probe(&self) -> Result<Option<(MediaFileMetadata, HashMap<usize, HdrSideData>)>> {
+        use ffmpeg_next as ffmpeg;
+
+        let input = match &self.copy {
+            Some(copy) => &copy.path,
+            None => &self.url,
+        };
+        let format_ctx = match ffmpeg::format::input(input) {
+            Ok(ctx) => ctx,
+            Err(err) if self.copy.is_none() => {
+                log::warn!(
+                    "FFMpeg cannot open {} from the library URL, probing a local copy of its header instead. Causes:\n{:?}",
+                    self.path.display(),
+                    err
+                );
+                return Ok(None);
+            }
+            Err(err) => {
+                return Err(anyhow!(
+                    "FFMpeg error: open failed for {}, causes:\n{:?}",
+                    self.path.display(),
+                    err
+                ))
+            }
+        };
+        read_media(format_ctx, &self.path, self.file_size).map(Some)
+    }
+}
+
+fn read_media(
+    mut format_ctx: ffmpeg_next::format::context::Input,
+    path: &Path,
+    file_size: u64,
+) -> Result<(MediaFileMetadata, HashMap<usize, HdrSideData>)> {
     use ffmpeg::media::Type;
     use ffmpeg_next as ffmpeg;
     use metadata::media_file::{MediaFileMetadataOptions, StreamTags};
@@ -91,28 +678,7 @@ pub fn open_multifs_media(
     use metadata::tags::ToTags;
     use metadata::util;
 
-    let decoded_path = urlencoding::decode(ffmpeg_base.path())
-        .map_err(|err| anyhow!("failed to decode path (url-encoding), causes:\n{:?}", err))?;
-    let mut root = PathBuf::from_str(&decoded_path).unwrap();
-    ffmpeg_base.set_path("/");
-    root.push(&path);
-    let ff_path =
-        PathBuf::from_str(&format!("{}/{}", ffmpeg_base.to_string(), root.display())).unwrap();
-
-    let mut format_ctx = ffmpeg::format::input(&ff_path).map_err(|err| {
-        anyhow!(
-            "FFMpeg error: open failed for {}, causes:\n{:?}",
-            ff_path.display(),
-            err
-        )
-    })?;
-
     let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
-    let file_size = mfs
-        .stat(&path)
-        .map_err(|err| anyhow!("Remotefs error: failed to read metadata {:?}", err))?
-        .metadata
-        .size;
     let file_size_base10 = util::human_size(file_size, util::Base::Base10);
     let file_size_base2 = util::human_size(file_size, util::Base::Base2);
 
@@ -126,6 +692,7 @@ pub fn open_multifs_media(
     let duration = _duration.map(util::format_seconds);
 
     let _scan_type = { scan::get_scan_type(&mut format_ctx)? };
+    let hdr_side_data = probe_hdr_side_data(&mut format_ctx);
     let scan_type = _scan_type.clone().map(|s| s.to_string());
 
     let _bit_rate = match format_ctx.bit_rate() {
@@ -212,7 +779,7 @@ pub fn open_multifs_media(
         })
         .collect();
 
-    Ok(MediaFileMetadata {
+    let meta = MediaFileMetadata {
         options: MediaFileMetadataOptions {
             include_checksum: false,
             include_tags: false,
@@ -248,5 +815,19 @@ pub fn open_multifs_media(
         filtered_tags,
         streams_tags,
         streams_filtered_tags,
-    })
+    };
+    Ok((meta, hdr_side_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_copy() {
+        let mut copy = Vec::new();
+        let copied = Transfer::new("data", 5).copy(&mut &b"hello"[..], &mut copy);
+        assert_eq!(copied.unwrap(), 5);
+        assert_eq!(copy, b"hello");
+    }
 }
@@ -1,5 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{HashAlgorithm, NfoDialect, RefreshFields};
+
+/// Minutes of difference always tolerated between the NFO runtime and the file duration.
+const RUNTIME_TOLERANCE: u64 = 5;
+
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
 #[serde(rename = "movie")]
 pub struct Movie {
@@ -7,6 +14,10 @@ pub struct Movie {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub original_title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub ratings: Option<Ratings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outline: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub plot: Option<String>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -41,13 +52,312 @@ pub struct Movie {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub thumb: Vec<Thumb>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub fanart: Option<Fanart>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub runtime: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tagline: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub mpaa: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fileinfo: Option<FileInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    /// Cut of the movie, `Director's Cut`, `Extended`...
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmdbid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dateadded: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockdata: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playcount: Option<u32>,
+    /// Date and time of the last play, `2023-04-01 21:30:00`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastplayed: Option<String>,
+    /// Watched flag of the older Kodi NFOs, superseded by `<playcount>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watched: Option<bool>,
+    /// Checksum of the movie file, to detect corrupted files (mkube extension).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Checksum>,
+}
+
+impl Movie {
+    /// Adjust the tags for the media center reading the NFO, before it is written.
+    pub fn for_dialect(mut self, dialect: NfoDialect) -> Movie {
+        match dialect {
+            NfoDialect::Kodi => self.tmdbid = None,
+            NfoDialect::Jellyfin => {
+                self.tmdbid = self.tmdb_id().map(|id| id.to_string());
+                self.dateadded
+                    .get_or_insert_with(|| format_datetime(SystemTime::now()));
+                self.lockdata.get_or_insert(false);
+                // Jellyfin takes the first id as the default one.
+                self.uniqueid.sort_by_key(|uid| !uid.default);
+            }
+        }
+        self
+    }
+
+    pub fn is_watched(&self) -> bool {
+        self.playcount
+            .map_or(self.watched == Some(true), |count| count > 0)
+    }
+
+    /// Mark the movie as played once, or as never played.
+    pub fn set_watched(&mut self, watched: bool) {
+        if watched {
+            self.playcount = Some(self.playcount.unwrap_or_default().max(1));
+            self.lastplayed
+                .get_or_insert_with(|| format_datetime(SystemTime::now()));
+        } else {
+            self.playcount = Some(0);
+            self.lastplayed = None;
+        }
+        self.watched = self.watched.map(|_| watched);
+    }
+
+    /// Take the play count and dates of `previous`, the NFO this one replaces.
+    pub fn keep_watch_state(&mut self, previous: &Movie) {
+        self.playcount = previous.playcount;
+        self.lastplayed = previous.lastplayed.clone();
+        self.watched = previous.watched;
+    }
+
+    pub fn tmdb_id(&self) -> Option<u64> {
+        self.uniqueid
+            .iter()
+            .find(|uid| uid.id_type == "tmdb")
+            .and_then(|uid| uid.value.parse().ok())
+    }
+
+    /// Update the fields selected in `fields` with the ones of `fresh`, keeping the others.
+    pub fn refresh_from(&mut self, fresh: Movie, fields: &RefreshFields) {
+        if fields.title {
+            self.title = fresh.title;
+            self.original_title = fresh.original_title;
+        }
+        if fields.plot {
+            self.plot = fresh.plot;
+        }
+        if fields.tagline {
+            self.tagline = fresh.tagline;
+        }
+        if fields.ratings {
+            self.ratings = fresh.ratings;
+        }
+        if fields.artworks {
+            self.thumb = fresh.thumb;
+            self.fanart = fresh.fanart;
+        }
+        if fields.mpaa {
+            self.mpaa = fresh.mpaa;
+        }
+        if fields.runtime {
+            self.runtime = fresh.runtime;
+        }
+        if fields.premiered {
+            self.premiered = fresh.premiered;
+        }
+        if fields.genre {
+            self.genre = fresh.genre;
+        }
+        if fields.country {
+            self.country = fresh.country;
+        }
+        if fields.studio {
+            self.studio = fresh.studio;
+        }
+        if fields.credits {
+            self.actor = fresh.actor;
+            self.director = fresh.director;
+            self.producer = fresh.producer;
+            self.credits = fresh.credits;
+        }
+    }
+
+    /// The artworks of the movie with their type.
+    ///
+    /// The older NFOs, without `<fanart>`, stored the fanart as the `landscape` thumb.
+    pub fn artworks(&self) -> Vec<(ArtworkKind, &Thumb)> {
+        let fanart = self.fanart.iter().flat_map(|f| f.thumb.first());
+        let thumbs = self.thumb.iter().filter_map(|th| {
+            match ArtworkKind::from_aspect(th.aspect.as_deref()?)? {
+                ArtworkKind::Landscape if self.fanart.is_none() => Some((ArtworkKind::Fanart, th)),
+                kind => Some((kind, th)),
+            }
+        });
+        fanart
+            .map(|th| (ArtworkKind::Fanart, th))
+            .chain(thumbs)
+            .collect()
+    }
+
+    /// Duration of the probed file in minutes, when it is far from the NFO runtime.
+    ///
+    /// A large gap hints at a wrong TMDB match or an incomplete file.
+    pub fn runtime_mismatch(&self) -> Option<u64> {
+        let runtime = self.runtime.filter(|r| *r > 0)?;
+        let probed = self
+            .fileinfo
+            .as_ref()?
+            .streamdetails
+            .video
+            .first()?
+            .duration_in_seconds?
+            / 60;
+        let tolerance = (runtime / 10).max(RUNTIME_TOLERANCE);
+        (runtime.abs_diff(probed) > tolerance).then_some(probed)
+    }
+
+    pub fn default_rating(&self) -> Option<&Rating> {
+        let ratings = &self.ratings.as_ref()?.rating;
+        ratings.iter().find(|r| r.default).or(ratings.first())
+    }
+
+    /// Replace the value of the default rating, or remove it if value is None.
+    pub fn set_default_rating(&mut self, value: Option<f64>, votes: Option<u64>) {
+        let ratings = &mut self.ratings.get_or_insert_with(Default::default).rating;
+        let pos = ratings
+            .iter()
+            .position(|r| r.default)
+            .or(if ratings.is_empty() { None } else { Some(0) });
+        match (pos, value) {
+            (Some(i), Some(value)) => {
+                ratings[i].value = value;
+                ratings[i].votes = votes;
+            }
+            (Some(i), None) => {
+                ratings.remove(i);
+            }
+            (None, Some(value)) => ratings.push(Rating {
+                name: "themoviedb".into(),
+                max: Some(10),
+                default: true,
+                value,
+                votes,
+            }),
+            (None, None) => {}
+        }
+        if ratings.is_empty() {
+            self.ratings = None;
+        }
+    }
+}
+
+/// Kodi episode NFO, stored next to the episode file.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename = "episodedetails")]
+pub struct Episode {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub showtitle: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aired: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uniqueid: Vec<UniqueId>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub thumb: Vec<Thumb>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fileinfo: Option<FileInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Replace the `fileinfo` of the NFO `source`, the other elements are kept as written.
+///
+/// A round-trip through [`Episode`] would drop the Kodi elements it does not know.
+pub fn with_fileinfo(source: &str, fileinfo: &FileInfo) -> Result<String, quick_xml::DeError> {
+    use quick_xml::events::Event;
+    let fileinfo = quick_xml::se::to_string_with_root("fileinfo", fileinfo)?;
+    let mut reader = quick_xml::Reader::from_str(source);
+    let mut depth = 0;
+    let mut existing = None;
+    let mut root_end = None;
+    loop {
+        let position = reader.buffer_position();
+        match reader.read_event()? {
+            Event::Start(start) if depth == 1 && start.name().as_ref() == b"fileinfo" => {
+                reader.read_to_end(start.name())?;
+                existing.get_or_insert(position..reader.buffer_position());
+            }
+            Event::Empty(empty) if depth == 1 && empty.name().as_ref() == b"fileinfo" => {
+                existing.get_or_insert(position..reader.buffer_position());
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    root_end = Some(position);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    let range = existing
+        .or(root_end.map(|end| end..end))
+        .ok_or_else(|| quick_xml::DeError::Custom("No root element".into()))?;
+    Ok(format!(
+        "{}{}{}",
+        &source[..range.start],
+        fileinfo,
+        &source[range.end..]
+    ))
+}
+
+/// Kodi show NFO (`tvshow.nfo`), only the fields used to identify the show.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename = "tvshow")]
+pub struct TvShow {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub uniqueid: Vec<UniqueId>,
+}
+
+impl TvShow {
+    pub fn tmdb_id(&self) -> Option<u64> {
+        self.uniqueid
+            .iter()
+            .find(|uid| uid.id_type == "tmdb")
+            .and_then(|uid| uid.value.parse().ok())
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Ratings {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rating: Vec<Rating>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Rating {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "@max")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+    #[serde(rename = "@default", default)]
+    pub default: bool,
+    pub value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub votes: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
@@ -60,6 +370,14 @@ pub struct UniqueId {
     pub value: String,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Checksum {
+    #[serde(rename = "@algorithm")]
+    pub algorithm: HashAlgorithm,
+    #[serde(rename = "$value")]
+    pub value: String,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
 pub struct Actor {
     pub name: String,
@@ -83,6 +401,52 @@ pub struct CrewPerson {
     pub thumb: Option<Thumb>,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Fanart {
+    #[serde(default)]
+    pub thumb: Vec<Thumb>,
+}
+
+/// Artwork types, see the `aspect` of the Kodi thumbs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArtworkKind {
+    Poster,
+    Fanart,
+    Landscape,
+    Logo,
+    Banner,
+    Clearart,
+    Discart,
+}
+
+impl ArtworkKind {
+    /// Kodi aspect, also used to name the downloaded files.
+    pub fn aspect(&self) -> &'static str {
+        match self {
+            ArtworkKind::Poster => "poster",
+            ArtworkKind::Fanart => "fanart",
+            ArtworkKind::Landscape => "landscape",
+            ArtworkKind::Logo => "clearlogo",
+            ArtworkKind::Banner => "banner",
+            ArtworkKind::Clearart => "clearart",
+            ArtworkKind::Discart => "discart",
+        }
+    }
+
+    pub fn from_aspect(aspect: &str) -> Option<ArtworkKind> {
+        match aspect.to_lowercase().as_str() {
+            "poster" => Some(ArtworkKind::Poster),
+            "fanart" => Some(ArtworkKind::Fanart),
+            "landscape" => Some(ArtworkKind::Landscape),
+            "clearlogo" | "logo" => Some(ArtworkKind::Logo),
+            "banner" => Some(ArtworkKind::Banner),
+            "clearart" => Some(ArtworkKind::Clearart),
+            "discart" | "disc" => Some(ArtworkKind::Discart),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
 pub struct Thumb {
     #[serde(rename = "@aspect")]
@@ -145,3 +509,140 @@ pub struct SubtitleTrack {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
 }
+
+/// UTC date and time as written by Jellyfin, e.g. `2023-04-01 18:30:00`.
+pub(crate) fn format_datetime(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // Civil date from the days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn episode_fileinfo() {
+        let fileinfo = FileInfo {
+            streamdetails: StreamDetails {
+                video: vec![VideoTrack {
+                    codec: "hevc".into(),
+                    width: Some(1920),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        };
+        let written = "<fileinfo><streamdetails><video><codec>hevc</codec>\
+            <width>1920</width></video></streamdetails></fileinfo>";
+        // The elements unknown to Episode stay as they are.
+        let source = "<?xml version=\"1.0\"?>\n<episodedetails>\n  <title>Pilot</title>\n  \
+            <ratings><rating name=\"tvdb\"><value>8</value></rating></ratings>\n  \
+            <fileinfo><streamdetails/></fileinfo>\n  <director>Someone</director>\n\
+            </episodedetails>\n";
+        assert_eq!(
+            with_fileinfo(source, &fileinfo).unwrap(),
+            source.replace("<fileinfo><streamdetails/></fileinfo>", written)
+        );
+        // Added at the end of the root element when missing.
+        let source =
+            "<episodedetails><title>Pilot</title><premiered>2020</premiered></episodedetails>";
+        assert_eq!(
+            with_fileinfo(source, &fileinfo).unwrap(),
+            format!(
+                "<episodedetails><title>Pilot</title><premiered>2020</premiered>{}</episodedetails>",
+                written
+            )
+        );
+        assert!(with_fileinfo("no xml", &fileinfo).is_err());
+    }
+
+    #[test]
+    fn nfo_dialects() {
+        let movie = Movie {
+            title: "Movie".into(),
+            uniqueid: vec![
+                UniqueId {
+                    id_type: "imdb".into(),
+                    default: false,
+                    value: "tt01".into(),
+                },
+                UniqueId {
+                    id_type: "tmdb".into(),
+                    default: true,
+                    value: "42".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        let jellyfin = movie.clone().for_dialect(NfoDialect::Jellyfin);
+        assert_eq!(jellyfin.uniqueid[0].id_type, "tmdb");
+        assert_eq!(jellyfin.tmdbid.as_deref(), Some("42"));
+        assert_eq!(jellyfin.lockdata, Some(false));
+        let added = jellyfin.dateadded.clone().unwrap();
+        assert_eq!((added.len(), &added[4..5], &added[10..11]), (19, "-", " "));
+        let kodi = jellyfin.for_dialect(NfoDialect::Kodi);
+        assert_eq!(kodi.tmdbid, None);
+        assert_eq!(kodi.dateadded, Some(added));
+    }
+
+    #[test]
+    fn watch_state() {
+        let legacy: Movie =
+            quick_xml::de::from_str("<movie><title>A</title><watched>true</watched></movie>")
+                .unwrap();
+        assert!(legacy.is_watched());
+        let mut played: Movie = quick_xml::de::from_str(
+            "<movie><title>B</title><playcount>2</playcount>\
+            <lastplayed>2023-04-01 21:30:00</lastplayed></movie>",
+        )
+        .unwrap();
+        assert!(played.is_watched());
+
+        // A new NFO replacing this one keeps its watch state.
+        let mut identified = Movie {
+            title: "B".into(),
+            ..Default::default()
+        };
+        identified.keep_watch_state(&played);
+        assert_eq!(identified.playcount, Some(2));
+        assert_eq!(
+            identified.lastplayed.as_deref(),
+            Some("2023-04-01 21:30:00")
+        );
+
+        played.set_watched(false);
+        assert!(!played.is_watched());
+        assert_eq!(
+            (played.playcount, played.lastplayed.as_deref()),
+            (Some(0), None)
+        );
+        played.set_watched(true);
+        assert_eq!(played.playcount, Some(1));
+        assert!(played.lastplayed.is_some());
+        assert_eq!(played.watched, None);
+        let xml = quick_xml::se::to_string(&played).unwrap();
+        assert!(xml.contains("<playcount>1</playcount>"), "{}", xml);
+    }
+}
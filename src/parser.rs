@@ -0,0 +1,764 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::VIDEO_EXTENSIONS;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedName {
+    pub title: String,
+    pub year: Option<u16>,
+    pub resolution: Option<String>,
+    pub source: Option<String>,
+    pub release_group: Option<String>,
+    pub edition: Option<String>,
+}
+
+const RESOLUTIONS: &[(&str, &str)] = &[
+    ("480p", "480p"),
+    ("576p", "576p"),
+    ("720p", "720p"),
+    ("1080p", "1080p"),
+    ("1080i", "1080p"),
+    ("2160p", "2160p"),
+    ("4k", "2160p"),
+    ("uhd", "2160p"),
+];
+
+const SOURCES: &[(&str, &str)] = &[
+    ("bluray", "Bluray"),
+    ("blu-ray", "Bluray"),
+    ("bdrip", "Bluray"),
+    ("brrip", "Bluray"),
+    ("bdremux", "Bluray"),
+    ("remux", "Bluray"),
+    ("web", "WEB"),
+    ("web-dl", "WEB"),
+    ("webdl", "WEB"),
+    ("webrip", "WEB"),
+    ("dvd", "DVD"),
+    ("dvdrip", "DVD"),
+    ("dvd-r", "DVD"),
+    ("dvd9", "DVD"),
+    ("hdtv", "TV"),
+    ("pdtv", "TV"),
+    ("tvrip", "TV"),
+];
+
+const OTHER_TAGS: &[&str] = &[
+    "x264",
+    "x265",
+    "h264",
+    "h265",
+    "hevc",
+    "avc",
+    "xvid",
+    "divx",
+    "10bit",
+    "hdr",
+    "hdr10",
+    "dv",
+    "aac",
+    "ac3",
+    "dts",
+    "ddp5",
+    "dd5",
+    "truehd",
+    "atmos",
+    "proper",
+    "repack",
+    "extended",
+    "unrated",
+    "remastered",
+    "multi",
+    "vostfr",
+    "vff",
+    "vfq",
+    "french",
+    "truefrench",
+    "subbed",
+    "imax",
+];
+
+/// Cuts of a movie after the (lowercase) tokens naming them, the longest first.
+const EDITIONS: &[(&[&str], &str)] = &[
+    (&["directors", "cut"], "Director's Cut"),
+    (&["director's", "cut"], "Director's Cut"),
+    (&["extended", "cut"], "Extended"),
+    (&["extended", "edition"], "Extended"),
+    (&["final", "cut"], "Final Cut"),
+    (&["theatrical", "cut"], "Theatrical"),
+    (&["special", "edition"], "Special Edition"),
+    (&["ultimate", "edition"], "Ultimate Edition"),
+    (&["collectors", "edition"], "Collector's Edition"),
+    (&["anniversary", "edition"], "Anniversary Edition"),
+    (&["extended"], "Extended"),
+    (&["theatrical"], "Theatrical"),
+    (&["unrated"], "Unrated"),
+    (&["uncut"], "Uncut"),
+    (&["imax"], "IMAX"),
+    (&["remastered"], "Remastered"),
+    (&["criterion"], "Criterion"),
+];
+
+/// The edition starting at the first of `tokens`, with the number of tokens naming it.
+fn edition_at(tokens: &[&str]) -> Option<(usize, &'static str)> {
+    EDITIONS.iter().find_map(|(words, edition)| {
+        let matched = words.len() <= tokens.len()
+            && words
+                .iter()
+                .zip(tokens)
+                .all(|(word, token)| token.eq_ignore_ascii_case(word));
+        matched.then_some((words.len(), *edition))
+    })
+}
+
+/// The Plex `{edition-Name}` tag of a file name, and the name without it.
+fn plex_edition(stem: &str) -> Option<(String, String)> {
+    let start = stem.find("{edition-")?;
+    let end = start + stem[start..].find('}')?;
+    let edition = stem[start + "{edition-".len()..end].trim();
+    if edition.is_empty() {
+        return None;
+    }
+    Some((edition.to_owned(), format!("{}{}", &stem[..start], &stem[end + 1..])))
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '.' | '_' | ' ' | '(' | ')' | '[' | ']' | '{' | '}')
+}
+
+fn as_year(token: &str) -> Option<u16> {
+    if token.len() == 4 && token.chars().all(|c| c.is_ascii_digit()) {
+        token
+            .parse::<u16>()
+            .ok()
+            .filter(|y| (1900..=2099).contains(y))
+    } else {
+        None
+    }
+}
+
+fn lookup(table: &[(&str, &'static str)], token: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(tag, _)| token.eq_ignore_ascii_case(tag))
+        .map(|(_, value)| *value)
+}
+
+// Only resolutions and sources end the title, other tags may be legit words.
+fn is_strong_tag(token: &str) -> bool {
+    lookup(RESOLUTIONS, token).is_some() || lookup(SOURCES, token).is_some()
+}
+
+fn is_tag(token: &str) -> bool {
+    is_strong_tag(token) || OTHER_TAGS.iter().any(|t| token.eq_ignore_ascii_case(t))
+}
+
+/// Parse a (scene-like) release name, for instance `The.Movie.2019.1080p.WEB-DL.x264-GRP.mkv`.
+pub fn parse_filename(name: &str) -> ParsedName {
+    let stem = match name.rsplit_once('.') {
+        Some((stem, ext)) if VIDEO_EXTENSIONS.iter().any(|v| ext.eq_ignore_ascii_case(v)) => stem,
+        _ => name,
+    };
+    let mut parsed = ParsedName::default();
+    let plex = plex_edition(stem);
+    let stem = match &plex {
+        Some((edition, rest)) => {
+            parsed.edition = Some(edition.clone());
+            rest.as_str()
+        }
+        None => stem,
+    };
+    let mut tokens: Vec<&str> = stem.split(is_separator).filter(|t| !t.is_empty()).collect();
+
+    if let Some(last) = tokens.last().copied() {
+        if !is_tag(last) {
+            if let Some((tag, group)) = last.rsplit_once('-') {
+                let tagged = tokens[..tokens.len() - 1]
+                    .iter()
+                    .any(|t| is_tag(t) || as_year(t).is_some());
+                if tagged && !tag.is_empty() && !group.is_empty() {
+                    parsed.release_group = Some(group.to_owned());
+                    let len = tokens.len();
+                    tokens[len - 1] = tag;
+                }
+            }
+        }
+    }
+
+    let first_tag = tokens
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, t)| is_strong_tag(t))
+        .map(|(i, _)| i)
+        .unwrap_or(tokens.len());
+    let year_pos = tokens[..first_tag]
+        .iter()
+        .enumerate()
+        .skip(1)
+        .rev()
+        .find(|(_, t)| as_year(t).is_some())
+        .map(|(i, _)| i);
+    let mut title_end = year_pos.unwrap_or(first_tag);
+    parsed.year = year_pos.and_then(|i| as_year(tokens[i]));
+    if parsed.edition.is_none() {
+        parsed.edition = (title_end..tokens.len())
+            .find_map(|i| edition_at(&tokens[i..]))
+            .map(|(_, edition)| edition.to_owned());
+    }
+    // `Alien.Directors.Cut.1979`, the edition is not part of the title.
+    if parsed.edition.is_none() {
+        let trailing = (1..title_end).find_map(|i| {
+            edition_at(&tokens[i..title_end])
+                .filter(|(len, _)| i + len == title_end)
+                .map(|(_, edition)| (i, edition))
+        });
+        if let Some((i, edition)) = trailing {
+            parsed.edition = Some(edition.to_owned());
+            title_end = i;
+        }
+    }
+    parsed.title = tokens[..title_end].join(" ");
+
+    for token in &tokens[title_end..] {
+        if parsed.resolution.is_none() {
+            parsed.resolution = lookup(RESOLUTIONS, token).map(str::to_owned);
+        }
+        if parsed.source.is_none() {
+            parsed.source = lookup(SOURCES, token).map(str::to_owned);
+        }
+    }
+    if parsed.source.as_deref() == Some("Bluray") && parsed.resolution.as_deref() == Some("2160p") {
+        parsed.source = Some("UHD Bluray".into());
+    }
+    if parsed.title.is_empty() {
+        parsed.title = stem.to_owned();
+    }
+    parsed
+}
+
+/// Season and episode numbers of an episode file name, like `Show.S01E02.mkv` or `Show 1x02.mkv`.
+///
+/// Only the first episode of multi-episode files (`S01E02E03`) is returned. The `1x02` form is
+/// only tried without any `S01E02` token, with at most 2 digits for the season and 3 for the
+/// episode, so that a resolution like `1920x1080` is not taken for an episode.
+pub fn parse_episode(name: &str) -> Option<(u32, u32)> {
+    let is_number = |s: &str, max_len: usize| {
+        (1..=max_len).contains(&s.len()) && s.chars().all(|c| c.is_ascii_digit())
+    };
+    let leading_number = |s: &str| {
+        let digits: String = s.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u32>().ok()
+    };
+    let tokens: Vec<String> = name.split(is_separator).map(str::to_lowercase).collect();
+    let tagged = tokens.iter().find_map(|token| {
+        let (season, episode) = token.strip_prefix('s')?.split_once('e')?;
+        if !is_number(season, 4) {
+            return None;
+        }
+        Some((season.parse().ok()?, leading_number(episode)?))
+    });
+    tagged.or_else(|| {
+        tokens.iter().find_map(|token| {
+            let (season, episode) = token.split_once('x')?;
+            if !is_number(season, 2) || !is_number(episode, 3) {
+                return None;
+            }
+            Some((season.parse().ok()?, episode.parse().ok()?))
+        })
+    })
+}
+
+/// Season number of a season folder, like `Season 1`, `S01` or `Specials` (season 0).
+pub fn parse_season_dir(name: &str) -> Option<u32> {
+    let name = name.trim().to_lowercase();
+    if name == "specials" {
+        return Some(0);
+    }
+    let number = match name.strip_prefix("season") {
+        Some(rest) => rest.trim_start_matches(is_separator),
+        None => name.strip_prefix('s')?,
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// Parse the file name of a movie, falling back on its directory name for the title and year.
+pub fn parse_path(path: &Path) -> ParsedName {
+    let mut parsed = parse_filename(
+        &path
+            .file_name()
+            .map(|s| s.to_string_lossy())
+            .unwrap_or_default(),
+    );
+    if parsed.year.is_none() {
+        if let Some(dir) = path.parent().and_then(Path::file_name) {
+            let parent = parse_filename(&dir.to_string_lossy());
+            if parent.year.is_some() {
+                parsed.title = parent.title;
+                parsed.year = parent.year;
+            }
+        }
+    }
+    parsed
+}
+
+/// Folders of bonus content, next to the movie file (Kodi naming).
+const EXTRA_DIRS: &[&str] = &[
+    "extras",
+    "behind the scenes",
+    "deleted scenes",
+    "featurettes",
+    "interviews",
+    "scenes",
+    "shorts",
+    "trailers",
+    "other",
+];
+
+/// File suffixes of bonus content, e.g. `Movie-featurette.mkv`.
+const EXTRA_SUFFIXES: &[&str] = &[
+    "-behindthescenes",
+    "-deleted",
+    "-featurette",
+    "-interview",
+    "-scene",
+    "-short",
+    "-trailer",
+    "-other",
+    "-extra",
+];
+
+pub fn is_extras_dir(name: &str) -> bool {
+    EXTRA_DIRS.iter().any(|dir| name.eq_ignore_ascii_case(dir))
+}
+
+pub fn is_extra_file(name: &str) -> bool {
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    let stem = stem.to_ascii_lowercase();
+    EXTRA_SUFFIXES
+        .iter()
+        .any(|suffix| stem.len() > suffix.len() && stem.ends_with(suffix))
+}
+
+/// Part of a multi-part movie, like `Movie-cd2.mkv` or `Movie.part2.mkv`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackPart {
+    /// The file stem without the part suffix.
+    pub base: String,
+    /// The part suffix with its separator, e.g. `-cd2`.
+    pub suffix: String,
+    pub number: u32,
+}
+
+const PART_KEYWORDS: &[&str] = &["cd", "dvd", "part", "pt", "disc", "disk"];
+
+fn is_part_separator(c: char) -> bool {
+    matches!(c, '-' | '.' | '_' | ' ')
+}
+
+/// Parse the part suffix of a file stem, e.g. `Movie - CD 2`, `Movie.part2` or `Movie_disc1`.
+pub fn parse_part(stem: &str) -> Option<StackPart> {
+    // ASCII lowercase keeps the byte offsets of the stem.
+    let lower = stem.to_ascii_lowercase();
+    let digits = lower.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let number = lower[digits..].parse().ok()?;
+    let rest = lower[..digits].trim_end_matches(is_part_separator);
+    let keyword = PART_KEYWORDS.iter().find(|k| rest.ends_with(*k))?;
+    let before = &rest[..rest.len() - keyword.len()];
+    let base = before.trim_end_matches(is_part_separator).len();
+    if base == 0 || base == before.len() {
+        return None;
+    }
+    Some(StackPart {
+        base: stem[..base].to_owned(),
+        suffix: stem[base..].to_owned(),
+        number,
+    })
+}
+
+/// Identity of the stack of a video part: its directory, base name and extension.
+pub fn stack_key(path: &Path) -> Option<(PathBuf, String, String)> {
+    let part = parse_part(&path.file_stem()?.to_string_lossy())?;
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    Some((path.parent()?.to_path_buf(), part.base.to_lowercase(), ext))
+}
+
+/// Group the parts of the multi-part movies, as (first part, number of parts).
+///
+/// The other videos are kept as single part movies, in their original order.
+pub fn group_stacks(paths: Vec<PathBuf>) -> Vec<(PathBuf, usize)> {
+    let mut groups: Vec<(PathBuf, usize)> = Vec::new();
+    let mut stacks: HashMap<(PathBuf, String, String), usize> = HashMap::new();
+    for path in paths {
+        let key = match stack_key(&path) {
+            Some(key) => key,
+            None => {
+                groups.push((path, 1));
+                continue;
+            }
+        };
+        match stacks.get(&key) {
+            Some(&i) => {
+                let (first, count) = &mut groups[i];
+                *count += 1;
+                if part_number(&path) < part_number(first) {
+                    *first = path;
+                }
+            }
+            None => {
+                stacks.insert(key, groups.len());
+                groups.push((path, 1));
+            }
+        }
+    }
+    groups
+}
+
+fn part_number(path: &Path) -> Option<u32> {
+    parse_part(&path.file_stem()?.to_string_lossy()).map(|part| part.number)
+}
+
+/// Number of parts of the movie at `path` among the files of its directory, 1 if it is not stacked.
+pub fn count_parts(path: &Path, files: &[PathBuf]) -> usize {
+    let parent = path.parent().unwrap_or(Path::new(""));
+    match stack_key(path) {
+        Some(key) => files
+            .iter()
+            .filter_map(|f| f.file_name())
+            .filter(|name| stack_key(&parent.join(name)).as_ref() == Some(&key))
+            .count()
+            .max(1),
+        None => 1,
+    }
+}
+
+/// The stem to rename for the movie at `path`: the stack base if other parts are among `files`.
+pub fn stack_stem(path: &Path, files: &[PathBuf]) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy().into_owned();
+    if count_parts(path, files) > 1 {
+        parse_part(&stem).map(|part| part.base)
+    } else {
+        Some(stem)
+    }
+}
+
+fn normalize(title: &str) -> Vec<char> {
+    deunicode::deunicode(title)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// Similarity between two titles, from 0 (different) to 1 (same), based on the edit distance.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize(a), normalize(b));
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 0.0;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    1.0 - prev[b.len()] as f64 / max_len as f64
+}
+
+/// Confidence that a TMDB result is the movie described by `parsed`.
+pub fn match_confidence(
+    parsed: &ParsedName,
+    title: &str,
+    original_title: &str,
+    year: Option<u16>,
+) -> f64 {
+    let similarity =
+        title_similarity(&parsed.title, title).max(title_similarity(&parsed.title, original_title));
+    let year_factor = match (parsed.year, year) {
+        (Some(a), Some(b)) if a == b => 1.0,
+        (Some(a), Some(b)) if a.abs_diff(b) == 1 => 0.9,
+        (Some(_), _) => 0.5,
+        (None, _) => 0.8,
+    };
+    similarity * year_factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn episode_numbers() {
+        let cases = [
+            ("Show.S01E02.mkv", Some((1, 2))),
+            ("show.s2024e113.1080p.mkv", Some((2024, 113))),
+            ("Show.S01E02E03.mkv", Some((1, 2))),
+            ("Show 1x02.mkv", Some((1, 2))),
+            ("Show [10x113].mkv", Some((10, 113))),
+            // A resolution is not an episode, the tagged form is preferred.
+            ("Show.1920x1080.S03E04.mkv", Some((3, 4))),
+            ("Show.1920x1080.mkv", None),
+            ("Show 123x02.mkv", None),
+            ("Show 1x1234.mkv", None),
+            ("Show.Special.mkv", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(parse_episode(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn release_names() {
+        // Name, then the title, year, resolution, source and release group parsed from it.
+        let cases = [
+            (
+                "The.Movie.2019.1080p.WEB-DL.x264-GRP.mkv",
+                "The Movie",
+                Some(2019),
+                Some("1080p"),
+                Some("WEB"),
+                Some("GRP"),
+            ),
+            (
+                "Movie_Name_1999_DVDRip_XviD-ABC.avi",
+                "Movie Name",
+                Some(1999),
+                None,
+                Some("DVD"),
+                Some("ABC"),
+            ),
+            (
+                "Dune [2021] [2160p] [BluRay].mkv",
+                "Dune",
+                Some(2021),
+                Some("2160p"),
+                Some("UHD Bluray"),
+                None,
+            ),
+            // A year in the title, the last one is the release year.
+            (
+                "2001.A.Space.Odyssey.1968.720p.BluRay.mkv",
+                "2001 A Space Odyssey",
+                Some(1968),
+                Some("720p"),
+                Some("Bluray"),
+                None,
+            ),
+            (
+                "Blade.Runner.2049.2017.mkv",
+                "Blade Runner 2049",
+                Some(2017),
+                None,
+                None,
+                None,
+            ),
+            ("1917 (2019).mkv", "1917", Some(2019), None, None, None),
+            ("2012.mkv", "2012", None, None, None, None),
+            // Without a year, the first resolution or source ends the title.
+            (
+                "Movie.Without.Year.720p.HDTV.mkv",
+                "Movie Without Year",
+                None,
+                Some("720p"),
+                Some("TV"),
+                None,
+            ),
+            ("Amélie.mkv", "Amélie", None, None, None, None),
+            // A dash only splits a release group after the tags.
+            ("Spider-Man.mkv", "Spider-Man", None, None, None, None),
+            (
+                "Spider-Man.2002.mkv",
+                "Spider-Man",
+                Some(2002),
+                None,
+                None,
+                None,
+            ),
+        ];
+        for (name, title, year, resolution, source, group) in cases {
+            let parsed = parse_filename(name);
+            assert_eq!(parsed.title, title, "{}", name);
+            assert_eq!(parsed.year, year, "{}", name);
+            assert_eq!(parsed.resolution.as_deref(), resolution, "{}", name);
+            assert_eq!(parsed.source.as_deref(), source, "{}", name);
+            assert_eq!(parsed.release_group.as_deref(), group, "{}", name);
+        }
+    }
+
+    #[test]
+    fn folder_fallback() {
+        // Path, then the title, year and resolution parsed from it.
+        let cases = [
+            (
+                "/movies/The Matrix (1999)/matrix.mkv",
+                "The Matrix",
+                Some(1999),
+                None,
+            ),
+            // The tags of the file are kept with the title of its folder.
+            (
+                "/movies/Alien (1979)/alien.1080p.mkv",
+                "Alien",
+                Some(1979),
+                Some("1080p"),
+            ),
+            // A file name with a year is not overridden.
+            (
+                "/movies/Heat (1995)/Heat.1986.mkv",
+                "Heat",
+                Some(1986),
+                None,
+            ),
+            // Nor is one in a folder without a year.
+            (
+                "/movies/Unsorted/some.movie.720p.mkv",
+                "some movie",
+                None,
+                Some("720p"),
+            ),
+            ("movie.mkv", "movie", None, None),
+        ];
+        for (path, title, year, resolution) in cases {
+            let parsed = parse_path(Path::new(path));
+            assert_eq!(parsed.title, title, "{}", path);
+            assert_eq!(parsed.year, year, "{}", path);
+            assert_eq!(parsed.resolution.as_deref(), resolution, "{}", path);
+        }
+    }
+
+    #[test]
+    fn multi_part_stacks() {
+        let part = parse_part("The.Movie.2019 - CD 2").unwrap();
+        assert_eq!(
+            (part.base.as_str(), part.suffix.as_str()),
+            ("The.Movie.2019", " - CD 2")
+        );
+        assert_eq!(part.number, 2);
+        for single in ["Movie", "Movie 2", "Movie-cd", "cd1", "Moviecd1"] {
+            assert_eq!(parse_part(single), None, "{} is not a part", single);
+        }
+        let paths = [
+            "a/Movie.part2.mkv",
+            "a/Movie.part1.mkv",
+            "a/Other-cd1.avi",
+            "b/Movie.part3.mkv",
+        ];
+        let groups = group_stacks(paths.iter().map(PathBuf::from).collect());
+        assert_eq!(
+            groups,
+            vec![
+                (PathBuf::from("a/Movie.part1.mkv"), 2),
+                (PathBuf::from("a/Other-cd1.avi"), 1),
+                (PathBuf::from("b/Movie.part3.mkv"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn edition_names() {
+        let parsed = parse_filename("Blade.Runner.1982.Final.Cut.1080p.BluRay.x264-GRP.mkv");
+        assert_eq!(parsed.title, "Blade Runner");
+        assert_eq!(parsed.edition.as_deref(), Some("Final Cut"));
+        let parsed = parse_filename("Alien.Directors.Cut.1979.720p.mkv");
+        assert_eq!((parsed.title.as_str(), parsed.year), ("Alien", Some(1979)));
+        assert_eq!(parsed.edition.as_deref(), Some("Director's Cut"));
+        let parsed = parse_filename("Dune (2021) {edition-IMAX Enhanced}.mkv");
+        assert_eq!((parsed.title.as_str(), parsed.year), ("Dune", Some(2021)));
+        assert_eq!(parsed.edition.as_deref(), Some("IMAX Enhanced"));
+        assert_eq!(parse_filename("The.Movie.2019.1080p.mkv").edition, None);
+    }
+
+    #[test]
+    fn title_similarities() {
+        let cases = [
+            // Diacritics and punctuation are ignored.
+            ("Amélie", "Amelie", 1.0),
+            ("Léon: The Professional", "leon the professional", 1.0),
+            ("The.Matrix", "The Matrix!", 1.0),
+            ("Mad Max: Fury Road", "Mad Max - Fury Road", 1.0),
+            ("Alien", "Aliens", 1.0 - 1.0 / 6.0),
+            ("Heat", "Up", 0.0),
+            ("", "", 0.0),
+            ("...", "Alien", 0.0),
+        ];
+        for (a, b, expected) in cases {
+            let similarity = title_similarity(a, b);
+            assert!((similarity - expected).abs() < 1e-9, "{} / {}", a, b);
+            assert_eq!(similarity, title_similarity(b, a), "{} / {}", a, b);
+        }
+    }
+
+    #[test]
+    fn match_confidences() {
+        let threshold = crate::config::AutoMatch::default().min_confidence;
+        let parsed = |title: &str, year| ParsedName {
+            title: title.into(),
+            year,
+            ..Default::default()
+        };
+        let sunshine = "Eternal Sunshine of the Spotless Mind";
+        // Parsed title and year, TMDB title, original title and year, then if it is identified.
+        let cases = [
+            (parsed("Amelie", Some(2001)), "Amélie", "", Some(2001), true),
+            // Only the original title matches.
+            (
+                parsed("Le Fabuleux Destin d'Amelie Poulain", Some(2001)),
+                "Amélie",
+                "Le Fabuleux Destin d'Amélie Poulain",
+                Some(2001),
+                true,
+            ),
+            // A year off by one is accepted, not with a typo in a short title.
+            (
+                parsed(sunshine, Some(2005)),
+                sunshine,
+                sunshine,
+                Some(2004),
+                true,
+            ),
+            (
+                parsed("Eternal Sunshine of the Spotles Mind", Some(2005)),
+                sunshine,
+                "",
+                Some(2004),
+                true,
+            ),
+            (parsed("Alien", Some(1980)), "Aliens", "", Some(1979), false),
+            (parsed("Alien", Some(1979)), "Aliens", "", Some(1986), false),
+            // Neither two years apart, nor without a release date.
+            (
+                parsed(sunshine, Some(2006)),
+                sunshine,
+                "",
+                Some(2004),
+                false,
+            ),
+            (parsed(sunshine, Some(2004)), sunshine, "", None, false),
+            // Without a year, even the same title is left to review.
+            (parsed(sunshine, None), sunshine, "", Some(2004), false),
+        ];
+        for (parsed, title, original_title, year, identified) in cases {
+            let confidence = match_confidence(&parsed, title, original_title, year);
+            assert_eq!(
+                confidence >= threshold,
+                identified,
+                "{:?} for {} ({:?}): {}",
+                parsed,
+                title,
+                year,
+                confidence
+            );
+        }
+    }
+}
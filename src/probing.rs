@@ -0,0 +1,136 @@
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Semaphore;
+use url::Url;
+
+use crate::asyncfs::{AsyncMultiFs, Operation};
+use crate::config::MediaProbing;
+use crate::error::MkubeError;
+use crate::library::LibraryId;
+use crate::multifs::MediaSource;
+use crate::nfo::FileInfo;
+use crate::ConnectionPool;
+
+static POOL: RwLock<Option<Arc<ProbePool>>> = RwLock::new(None);
+
+/// Set up the probing pool, again each time the configuration is reloaded.
+///
+/// The media already being probed finish in the previous pool.
+pub fn configure(settings: &MediaProbing) {
+    let workers = settings.workers.max(1);
+    let mut pool = POOL.write().unwrap();
+    if pool.as_ref().map(|pool| pool.workers()) != Some(workers) {
+        *pool = Some(Arc::new(ProbePool::new(workers)));
+    }
+}
+
+/// The probing pool shared by all the tasks.
+pub fn pool() -> Arc<ProbePool> {
+    POOL.write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(ProbePool::new(MediaProbing::default().workers)))
+        .clone()
+}
+
+/// Runs ffmpeg on the blocking threads, a bounded number of media at a time.
+///
+/// The library connection is only locked to prepare the media and, when ffmpeg can not open
+/// it from the library URL, to copy its header. Other tasks use the connection meanwhile.
+pub struct ProbePool {
+    permits: Semaphore,
+    workers: usize,
+}
+
+impl ProbePool {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            permits: Semaphore::new(workers),
+            workers,
+        }
+    }
+
+    /// How many media are probed at the same time.
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub async fn probe(
+        &self,
+        conns: &ConnectionPool,
+        fs_id: LibraryId,
+        base_url: Url,
+        path: PathBuf,
+    ) -> Result<FileInfo, MkubeError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|err| MkubeError::Other(err.into()))?;
+        let fs = AsyncMultiFs::new(conns, fs_id);
+        let media_path = path.clone();
+        let source = fs
+            .run(move |fs| {
+                MediaSource::new(fs, base_url, media_path.clone())
+                    .map_err(|err| io_error(&media_path, err))
+            })
+            .await?;
+        let (mut source, probed) = probe_blocking(source).await?;
+        if let Some(info) = probed.map_err(|err| io_error(&path, err))? {
+            return Ok(info);
+        }
+        let media_path = path.clone();
+        let source = fs
+            .for_operation(Operation::Read)
+            .run(move |fs| {
+                source
+                    .download_copy(fs)
+                    .map_err(|err| io_error(&media_path, err))?;
+                Ok::<_, MkubeError>(source)
+            })
+            .await?;
+        let (_, probed) = probe_blocking(source).await?;
+        probed
+            .map_err(|err| io_error(&path, err))?
+            .ok_or_else(|| io_error(&path, anyhow!("FFMpeg can not open the local copy")))
+    }
+}
+
+fn io_error(path: &Path, err: anyhow::Error) -> MkubeError {
+    MkubeError::Io {
+        path: path.to_owned(),
+        reason: format!("Unable to get metadata, causes:\n{:?}", err),
+    }
+}
+
+type Probed = anyhow::Result<Option<FileInfo>>;
+
+async fn probe_blocking(source: MediaSource) -> Result<(MediaSource, Probed), MkubeError> {
+    tokio::task::spawn_blocking(move || {
+        let probed = source.probe().map(|media| {
+            media.map(|(meta, hdr_side_data)| crate::file_info(meta, hdr_side_data))
+        });
+        (source, probed)
+    })
+    .await
+    .map_err(|err| MkubeError::Other(err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn probe_pool() {
+        assert_eq!(ProbePool::new(0).workers(), 1);
+        let pool = ProbePool::new(2);
+        let conns: ConnectionPool = Default::default();
+        let fs_id = LibraryId::new();
+        let url = Url::parse("file:///movies").unwrap();
+        let res = pool
+            .probe(&conns, fs_id, url, PathBuf::from("movie.mkv"))
+            .await;
+        assert!(matches!(res, Err(MkubeError::UnknownLibrary(id)) if id == fs_id));
+    }
+}
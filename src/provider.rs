@@ -0,0 +1,127 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use tmdb_api::client::Client as TmdbClient;
+use tmdb_api::prelude::Command;
+
+use crate::error::MkubeError;
+
+/// The canned TMDB answers of the demo mode and the tests.
+pub const DEMO_FIXTURES: &str = include_str!("../fixtures/tmdb.json");
+
+pub type ProviderFuture<'a> = Pin<Box<dyn Future<Output = Result<Value, MkubeError>> + Send + 'a>>;
+
+/// Source of the TMDB answers, the real API or canned fixtures.
+pub trait MetadataProvider: Send + Sync {
+    /// JSON answer of the TMDB endpoint at `path` (`/movie/603`, `/search/movie`...).
+    fn get<'a>(
+        &'a self,
+        path: &'a str,
+        params: Vec<(&'static str, Cow<'a, str>)>,
+    ) -> ProviderFuture<'a>;
+}
+
+impl<'p> dyn MetadataProvider + 'p {
+    /// Run a tmdb-api command against this provider.
+    pub async fn execute<C: Command + Sync>(&self, cmd: &C) -> Result<C::Output, MkubeError> {
+        let path = cmd.path();
+        self.fetch(&path, cmd.params()).await
+    }
+
+    /// Answer of the endpoint at `path`, for the requests tmdb-api does not cover.
+    pub async fn fetch<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: Vec<(&'static str, Cow<'_, str>)>,
+    ) -> Result<T, MkubeError> {
+        let value = self.get(path, params).await?;
+        serde_json::from_value(value).map_err(|err| MkubeError::Provider {
+            request: format!("TMDB request {}", path),
+            reason: format!("{:?}", err),
+            transient: false,
+        })
+    }
+}
+
+/// The TMDB API.
+pub struct TmdbProvider {
+    client: TmdbClient,
+}
+
+impl TmdbProvider {
+    pub fn new(api_key: String) -> TmdbProvider {
+        TmdbProvider {
+            client: TmdbClient::new(api_key),
+        }
+    }
+}
+
+impl MetadataProvider for TmdbProvider {
+    fn get<'a>(
+        &'a self,
+        path: &'a str,
+        params: Vec<(&'static str, Cow<'a, str>)>,
+    ) -> ProviderFuture<'a> {
+        Box::pin(async move {
+            self.client
+                .execute(path, params)
+                .await
+                .map_err(|err| MkubeError::Provider {
+                    request: format!("TMDB request {}", path),
+                    reason: format!("{:?}", err),
+                    transient: true,
+                })
+        })
+    }
+}
+
+/// Canned answers keyed by their endpoint path, whatever the parameters.
+///
+/// Nothing goes through the network, so the same request always gets the same answer.
+#[derive(Clone, Debug, Default)]
+pub struct FixtureProvider {
+    responses: HashMap<String, Value>,
+}
+
+impl FixtureProvider {
+    /// Fixtures from a JSON object mapping each endpoint path to its answer.
+    pub fn from_json(json: &str) -> Result<FixtureProvider, MkubeError> {
+        let responses = serde_json::from_str(json).map_err(|err| MkubeError::Parse {
+            path: "TMDB fixtures".into(),
+            reason: format!("{:?}", err),
+        })?;
+        Ok(FixtureProvider { responses })
+    }
+
+    /// The fixtures shipped with MKube, with a few well-known movies.
+    pub fn demo() -> FixtureProvider {
+        FixtureProvider::from_json(DEMO_FIXTURES).expect("the demo fixtures are valid")
+    }
+
+    pub fn with_response(mut self, path: impl Into<String>, response: Value) -> FixtureProvider {
+        self.responses.insert(path.into(), response);
+        self
+    }
+}
+
+impl MetadataProvider for FixtureProvider {
+    fn get<'a>(
+        &'a self,
+        path: &'a str,
+        _params: Vec<(&'static str, Cow<'a, str>)>,
+    ) -> ProviderFuture<'a> {
+        let response = self
+            .responses
+            .get(path)
+            .cloned()
+            .ok_or_else(|| MkubeError::Provider {
+                request: format!("TMDB request {}", path),
+                reason: "no fixture for this endpoint".into(),
+                transient: false,
+            });
+        Box::pin(async move { response })
+    }
+}
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::library::LibraryId;
+use crate::views::movie_manager::MovieManagerEvent;
+use crate::AppEvent;
+
+/// Movies kept in the recent list, the pinned ones are not counted.
+pub const RECENT_LIMIT: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct RecentMovie {
+    pub fs_id: LibraryId,
+    pub path: PathBuf,
+    pub title: String,
+}
+
+/// Movies whose NFO was written lately and the pinned ones, kept across sessions.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct RecentMovies {
+    /// File the list is saved to on each change, None to only keep it in memory.
+    #[serde(skip)]
+    file: Option<PathBuf>,
+    #[serde(default)]
+    pinned: Vec<RecentMovie>,
+    /// The last written first.
+    #[serde(default)]
+    recent: Vec<RecentMovie>,
+}
+
+impl RecentMovies {
+    /// `recent.json` in the cache directory of MKube.
+    pub fn cache_file() -> Option<PathBuf> {
+        directories::ProjectDirs::from("", "", "mkube")
+            .map(|dirs| dirs.cache_dir().join("recent.json"))
+    }
+
+    /// The list saved in `file`, empty if it can not be read.
+    pub fn load(file: PathBuf) -> RecentMovies {
+        let mut movies = match std::fs::read(&file) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+                log::warn!(
+                    "Ignoring the unreadable recent movies {}:\n{:?}",
+                    file.display(),
+                    err
+                );
+                RecentMovies::default()
+            }),
+            Err(_) => RecentMovies::default(),
+        };
+        movies.file = Some(file);
+        movies
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let res = file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(file, serde_json::to_vec(self)?));
+        if let Err(err) = res {
+            log::warn!(
+                "Failed to save the recent movies {}:\n{:?}",
+                file.display(),
+                err
+            );
+        }
+    }
+
+    /// Keep track of the NFOs written and of the movies moved.
+    pub fn record(&mut self, evt: &AppEvent) {
+        match evt {
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((movie, fs_id, path))) => {
+                self.recent
+                    .retain(|entry| entry.fs_id != *fs_id || entry.path != *path);
+                self.recent.insert(
+                    0,
+                    RecentMovie {
+                        fs_id: *fs_id,
+                        path: path.clone(),
+                        title: movie.title.clone(),
+                    },
+                );
+                self.recent.truncate(RECENT_LIMIT);
+                self.save();
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved((fs_id, from, to))) => {
+                let mut moved = false;
+                for entry in self.pinned.iter_mut().chain(self.recent.iter_mut()) {
+                    if entry.fs_id == *fs_id && entry.path == *from {
+                        entry.path = to.clone();
+                        moved = true;
+                    }
+                }
+                if moved {
+                    self.save();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_pinned(&self, fs_id: LibraryId, path: &Path) -> bool {
+        self.pinned
+            .iter()
+            .any(|entry| entry.fs_id == fs_id && entry.path == path)
+    }
+
+    /// Pin the movie, or unpin it if it already is. Returns whether it is pinned now.
+    pub fn toggle_pin(&mut self, fs_id: LibraryId, path: &Path, title: &str) -> bool {
+        let pinned = !self.is_pinned(fs_id, path);
+        if pinned {
+            self.pinned.push(RecentMovie {
+                fs_id,
+                path: path.to_owned(),
+                title: title.to_owned(),
+            });
+        } else {
+            self.pinned
+                .retain(|entry| entry.fs_id != fs_id || entry.path != path);
+        }
+        self.save();
+        pinned
+    }
+
+    /// The pinned movies in their pinning order, then the recent ones not pinned.
+    pub fn entries(&self) -> Vec<(bool, &RecentMovie)> {
+        self.pinned
+            .iter()
+            .map(|entry| (true, entry))
+            .chain(
+                self.recent
+                    .iter()
+                    .filter(|entry| !self.is_pinned(entry.fs_id, &entry.path))
+                    .map(|entry| (false, entry)),
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfo::Movie;
+    use crate::test_util::library;
+
+    #[test]
+    fn recent_movies() {
+        let file = std::env::temp_dir().join(format!("mkube-recent-{}.json", std::process::id()));
+        let fs_id = library().id;
+        let updated = |title: &str| {
+            let movie = Movie {
+                title: title.into(),
+                ..Default::default()
+            };
+            let path = PathBuf::from(format!("/movies/{}.mkv", title));
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((movie, fs_id, path)))
+        };
+        let titles = |recent: &RecentMovies| -> Vec<(bool, String)> {
+            recent
+                .entries()
+                .into_iter()
+                .map(|(pinned, entry)| (pinned, entry.title.clone()))
+                .collect()
+        };
+
+        let mut recent = RecentMovies::load(file.clone());
+        recent.record(&updated("Alpha"));
+        recent.record(&updated("Bravo"));
+        recent.record(&updated("Alpha"));
+        assert!(recent.toggle_pin(fs_id, Path::new("/movies/Bravo.mkv"), "Bravo"));
+        let moved = (
+            fs_id,
+            "/movies/Bravo.mkv".into(),
+            "/movies/Bravo (1999).mkv".into(),
+        );
+        recent.record(&AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved(
+            moved,
+        )));
+        assert_eq!(
+            titles(&recent),
+            vec![(true, "Bravo".into()), (false, "Alpha".into())]
+        );
+        assert!(recent.is_pinned(fs_id, Path::new("/movies/Bravo (1999).mkv")));
+
+        // Kept across sessions.
+        let reloaded = RecentMovies::load(file.clone());
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(reloaded, recent);
+
+        // Only the last edited movies are kept, the pinned ones stay.
+        for i in 0..RECENT_LIMIT + 5 {
+            recent.record(&updated(&format!("Movie {}", i)));
+        }
+        let entries = titles(&recent);
+        assert_eq!(entries.len(), RECENT_LIMIT + 1);
+        assert_eq!(entries[0], (true, "Bravo".into()));
+        assert_eq!(entries[1], (false, format!("Movie {}", RECENT_LIMIT + 4)));
+        assert!(!recent.toggle_pin(fs_id, Path::new("/movies/Bravo (1999).mkv"), "Bravo"));
+        std::fs::remove_file(&file).unwrap();
+    }
+}
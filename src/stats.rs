@@ -0,0 +1,85 @@
+use crate::views::movie_manager::MovieManagerEvent;
+use crate::AppEvent;
+
+/// What was done during the session, shown on exit and appended to the log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    pub scanned: usize,
+    pub nfo_written: usize,
+    pub renamed: usize,
+    pub downloaded_bytes: u64,
+    pub errors: usize,
+}
+
+impl SessionStats {
+    /// Count the operation reported by `evt`, if any.
+    pub fn record(&mut self, evt: &AppEvent) {
+        match evt {
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered(_)) => {
+                self.scanned += 1
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated(_)) => {
+                self.nfo_written += 1
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved(_)) => self.renamed += 1,
+            AppEvent::MovieManagerEvent(MovieManagerEvent::BatchDone(summary)) => {
+                self.errors += summary.failed.len()
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == SessionStats::default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("Movies scanned: {}", self.scanned),
+            format!("NFOs written: {}", self.nfo_written),
+            format!("Files renamed: {}", self.renamed),
+            format!(
+                "Downloaded: {:.1} MB",
+                self.downloaded_bytes as f64 / 1_000_000f64
+            ),
+            format!("Errors: {}", self.errors),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entry, library};
+    use crate::views::movie_manager::batch::BatchSummary;
+
+    #[test]
+    fn session_stats() {
+        let mut stats = SessionStats::default();
+        assert!(stats.is_empty());
+        for evt in [
+            MovieManagerEvent::MovieDiscovered(entry()),
+            MovieManagerEvent::MovieDiscovered(entry()),
+            MovieManagerEvent::MovieUpdated(entry()),
+            MovieManagerEvent::MovieMoved((library().id, "a.mkv".into(), "b.mkv".into())),
+            MovieManagerEvent::BatchDone(BatchSummary {
+                touched: vec![],
+                failed: vec!["Save NFO for a.mkv".into()],
+            }),
+            MovieManagerEvent::OpenTable,
+        ] {
+            stats.record(&AppEvent::MovieManagerEvent(evt));
+        }
+        stats.downloaded_bytes = 2_500_000;
+        assert_eq!(
+            stats.lines(),
+            vec![
+                "Movies scanned: 2",
+                "NFOs written: 1",
+                "Files renamed: 1",
+                "Downloaded: 2.5 MB",
+                "Errors: 1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Fixtures shared by the tests of the different modules.
+
+use crate::library::{Library, LibraryFlavor, LibraryId, LibraryType};
+use crate::multifs::ConnectionStatus;
+use crate::nfo::Movie;
+use crate::views::settings::SettingsPage;
+use crate::views::{App, AppState};
+use crate::MESSAGE_SENDER;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use unicode_width::UnicodeWidthStr;
+
+/// Set the message sender used by the views, its messages are dropped.
+pub fn message_sender() {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    if MESSAGE_SENDER.set(sender).is_ok() {
+        // Keep the channel open for the messages sent by the closures.
+        Box::leak(Box::new(receiver));
+    }
+}
+
+pub fn library() -> Library {
+    Library {
+        id: "3f2b8c1e-5d4a-4e6f-9a7b-1c2d3e4f5a6b".parse().unwrap(),
+        fs_type: LibraryType::Local,
+        flavor: LibraryFlavor::Movie,
+        name: "Movies".into(),
+        host: None,
+        username: None,
+        password: None,
+        path: std::env::temp_dir(),
+        prefered_lang: None,
+        prefered_country: None,
+        port: None,
+        secure: false,
+        passive_mode: true,
+        smb_domain: None,
+        smb_share: None,
+        s3_bucket: None,
+        s3_region: None,
+        probe_media: true,
+        max_depth: 4,
+        follow_symlinks: false,
+        asset_naming: Default::default(),
+        fs_profile: Default::default(),
+        keep_unicode: false,
+        read_only: false,
+        ffmpeg_path_mapping: None,
+    }
+}
+
+/// An application connected to the `library()` only.
+pub fn state() -> AppState {
+    AppState {
+        libraries: vec![Some(library())],
+        library_status: HashMap::from([(library().id, ConnectionStatus::Connected)]),
+        ..Default::default()
+    }
+}
+
+pub fn entry() -> (Movie, LibraryId, PathBuf) {
+    (
+        Movie::default(),
+        library().id,
+        PathBuf::from("movie/movie.mkv"),
+    )
+}
+
+/// Text of the `state` rendered on a `width` x `height` terminal, without trailing spaces.
+pub fn render_app(state: &mut AppState, width: u16, height: u16) -> String {
+    let mut terminal = tui::Terminal::new(tui::backend::TestBackend::new(width, height)).unwrap();
+    let app = App {
+        settings_page: SettingsPage::new(),
+        movie_manager: Default::default(),
+    };
+    terminal
+        .draw(|f| f.render_stateful_widget(app, f.size(), state))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..height)
+        .map(|y| {
+            let mut line = String::new();
+            let mut x = 0;
+            // The cells covered by a wide character are left out.
+            while x < width {
+                let symbol = buffer.get(x, y).symbol.as_str();
+                line.push_str(symbol);
+                x += symbol.width().max(1) as u16;
+            }
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
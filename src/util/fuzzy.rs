@@ -0,0 +1,60 @@
+/// Score of `candidate` for the fuzzy `query`, None if the query is not a subsequence of it.
+///
+/// The match is case-insensitive, consecutive characters and word starts score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut streak = 0;
+    for c in candidate.chars() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        let word_start = previous.map_or(true, |p| !p.is_alphanumeric());
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            query.next();
+            streak += 1;
+            score += 1 + streak * 5 + if word_start { 8 } else { 0 };
+        } else {
+            streak = 0;
+            score -= 1;
+        }
+        previous = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// Indices of the `items` matching `query`, the best matches first.
+pub fn fuzzy_filter(query: &str, items: &[String]) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| fuzzy_score(query, item).map(|score| (score, i)))
+        .collect();
+    matches.sort_by_key(|(score, i)| (std::cmp::Reverse(*score), *i));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_quick_open() {
+        let items: Vec<String> = [
+            "The Matrix /movies/The Matrix (1999)/matrix.mkv",
+            "Mad Max: Fury Road /movies/mad.max.2015.mkv",
+            "Amélie (Le Fabuleux Destin d'Amélie Poulain) /movies/amelie.mkv",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(fuzzy_filter("max", &items)[0], 1);
+        assert_eq!(fuzzy_filter("fury", &items), vec![1]);
+        assert_eq!(fuzzy_filter("fabuleux", &items), vec![2]);
+        assert_eq!(fuzzy_filter("matrix.mkv", &items), vec![0]);
+        assert!(fuzzy_filter("xyz", &items).is_empty());
+        assert_eq!(fuzzy_filter("", &items), vec![0, 1, 2]);
+        assert!(fuzzy_score("MAT", "the matrix") > fuzzy_score("mat", "mad max: tokyo"));
+    }
+}
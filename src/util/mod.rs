@@ -1,5 +1,9 @@
+pub mod fuzzy;
 pub mod owned_span;
 pub mod rt_fmt_str;
+pub mod width;
 
+pub use fuzzy::{fuzzy_filter, fuzzy_score};
 pub use owned_span::{OwnedSpan, OwnedSpans};
 pub use rt_fmt_str::FmtStr;
+pub use width::truncate_width;
@@ -0,0 +1,44 @@
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// `text` cut to fit in `max` terminal columns, ending with an ellipsis when it is cut.
+///
+/// CJK characters and emojis take two columns, and the graphemes are never split.
+pub fn truncate_width(text: &str, max: usize) -> Cow<'_, str> {
+    if text.width() <= max {
+        return Cow::Borrowed(text);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let mut width = 0;
+    let mut end = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        width += grapheme.width();
+        // One column is left for the ellipsis.
+        if width > max - 1 {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    Cow::Owned(format!("{}…", &text[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_truncation() {
+        assert_eq!(truncate_width("Alien", 5), "Alien");
+        assert_eq!(truncate_width("Alien", 4), "Ali…");
+        assert_eq!(truncate_width("Alien", 0), "");
+        // A wide character never straddles the limit.
+        assert_eq!(truncate_width("映画のタイトル", 6), "映画…");
+        assert_eq!(truncate_width("映画のタイトル", 7), "映画の…");
+        // Nor is a grapheme split from its combining marks.
+        assert_eq!(truncate_width("Ame\u{301}lie", 4), "Ame\u{301}…");
+        assert_eq!(truncate_width("🦊🦊🦊", 4), "🦊…");
+    }
+}
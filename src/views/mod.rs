@@ -1,23 +1,31 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::pin::Pin;
-use tui::widgets::{Block, Borders, StatefulWidget, Tabs, Widget};
+use tui::widgets::{Block, BorderType, Borders, Clear, Paragraph, StatefulWidget, Tabs, Widget};
 use tui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     symbols::DOT,
-    text::Spans,
+    text::{Span, Spans},
 };
 
+pub mod modal;
 pub mod movie_manager;
 pub mod settings;
 pub mod widgets;
 
-use crate::library::Library;
+use crate::downloads::DownloadProgress;
+use crate::keys::{Action, KeyContext, Keybindings, HELP_KEY};
+use crate::library::{Library, LibraryId};
+use crate::multifs::{ConnectionStatus, TransferProgress};
+use crate::provider::MetadataProvider;
 use crate::{ConnectionPool, MESSAGE_SENDER};
+use modal::{Modal, ModalOutcome, ModalPopup};
 use movie_manager::{MovieManager, MovieManagerEvent, MovieManagerMessage, MovieManagerState};
 use settings::{SettingsMessage, SettingsPage, SettingsState};
+use widgets::Progress;
 
 pub enum AppMessage {
     Closure(Box<dyn FnOnce(&mut AppState) -> Vec<AppEvent> + Send + Sync>),
@@ -43,7 +51,7 @@ pub enum AppMessage {
             dyn for<'a, 'out> FnOnce(
                     &'out mut AppState,
                     &'a reqwest::Client,
-                    &'a tmdb_api::client::Client,
+                    &'a dyn MetadataProvider,
                 )
                     -> Pin<Box<dyn Future<Output = Vec<AppEvent>> + Send + 'a>>
                 + Send
@@ -55,7 +63,7 @@ pub enum AppMessage {
             dyn for<'a, 'out> FnOnce(
                     &'out mut AppState,
                     &'a reqwest::Client,
-                    &'a tmdb_api::client::Client,
+                    &'a dyn MetadataProvider,
                     &'a ConnectionPool,
                 )
                     -> Pin<Box<dyn Future<Output = Vec<AppEvent>> + 'a>>
@@ -66,6 +74,7 @@ pub enum AppMessage {
     TriggerEvent(AppEvent),
     SettingsMessage(SettingsMessage),
     MovieManagerMessage(MovieManagerMessage),
+    SwitchProfile(Option<String>),
     Close,
 }
 
@@ -73,6 +82,9 @@ impl std::fmt::Debug for AppMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         match self {
             AppMessage::Close => write!(f, "AppMessage::Close"),
+            AppMessage::SwitchProfile(profile) => {
+                write!(f, "AppMessage::SwitchProfile({:?})", profile)
+            }
             AppMessage::Closure(_) => write!(f, "AppMessage::Closure(<builder>)"),
             AppMessage::Future(_) => write!(f, "AppMessage::Future(<builder>)"),
             AppMessage::AppFuture(_) => write!(f, "AppMessage::AppFuture(<builder>)"),
@@ -110,7 +122,7 @@ pub enum AppEvent {
             dyn for<'a, 'out> FnOnce(
                     &'out mut AppState,
                     &'a reqwest::Client,
-                    &'a tmdb_api::client::Client,
+                    &'a dyn MetadataProvider,
                 )
                     -> Pin<Box<dyn Future<Output = Vec<AppEvent>> + Send + 'a>>
                 + Send
@@ -122,8 +134,8 @@ pub enum AppEvent {
             dyn for<'a, 'out> FnOnce(
                     &'out mut AppState,
                     &'a reqwest::Client,
-                    &'a tmdb_api::client::Client,
-                    &'a tokio::sync::Mutex<Vec<Option<crate::multifs::MultiFs>>>,
+                    &'a dyn MetadataProvider,
+                    &'a ConnectionPool,
                 )
                     -> Pin<Box<dyn Future<Output = Vec<AppEvent>> + 'a>>
                 + Send
@@ -131,6 +143,7 @@ pub enum AppEvent {
         >,
     ),
     KeyEvent(KeyEvent),
+    TransferProgress(TransferProgress),
     SettingsEvent(settings::SettingsEvent),
     MovieManagerEvent(MovieManagerEvent),
 }
@@ -155,15 +168,147 @@ impl Default for TabState {
     }
 }
 
+fn read_only_notice(library: &Library) -> String {
+    format!(
+        "Library {} is read-only, nothing is written to it.",
+        library.name
+    )
+}
+
+fn offline_notice(keys: &Keybindings) -> String {
+    format!(
+        "Offline mode, this action needs TMDB (toggle with {}).",
+        keys.key(Action::ToggleOffline)
+    )
+}
+
 #[derive(Default)]
 pub struct AppState {
     pub tab: TabState,
     pub saved_movie_state: Option<MovieManagerState>,
     pub libraries: Vec<Option<Library>>,
+    pub library_status: HashMap<LibraryId, ConnectionStatus>,
+    /// Libraries with their password asked on each start, with `Credentials::Prompt`.
+    pub prompted: HashSet<LibraryId>,
     pub config: crate::config::Configuration,
+    pub notice: Option<String>,
+    pub show_help: bool,
+    pub show_tasks: bool,
+    pub downloads: DownloadProgress,
+    /// File transfers in progress, in their starting order.
+    pub transfers: Vec<TransferProgress>,
+    /// Popups above the active tab, the last one gets the key events.
+    pub modals: Vec<Modal>,
+    /// Active configuration profile, None for the default one.
+    pub profile: Option<String>,
+    /// Profiles found next to the configuration file.
+    pub profiles: Vec<String>,
+    /// Background futures still running, updated by main.
+    pub pending_tasks: usize,
+    /// Directory of the journals of the running batches, None to not keep them.
+    pub batch_journals: Option<std::path::PathBuf>,
+    pub stats: crate::stats::SessionStats,
+    pub recent: crate::recent::RecentMovies,
 }
 
 impl AppState {
+    /// View getting the key events, for the help popup.
+    pub fn key_context(&self) -> KeyContext {
+        match &self.tab {
+            TabState::Settings(state) => state.key_context(),
+            TabState::MovieManager(state) => state.key_context(),
+        }
+    }
+
+    pub fn library(&self, fs_id: LibraryId) -> Option<&Library> {
+        self.libraries.iter().flatten().find(|lib| lib.id == fs_id)
+    }
+
+    /// Library of the movie selected in the table, when it is read-only.
+    pub fn read_only_selection(&self) -> Option<&Library> {
+        let TabState::MovieManager(state) = &self.tab else {
+            return None;
+        };
+        let (_, fs_id, _) = state.selected_movie().filter(|_| state.is_table())?;
+        self.library(*fs_id).filter(|lib| lib.read_only)
+    }
+
+    pub fn library_entries(&self) -> Vec<(Library, ConnectionStatus)> {
+        self.libraries
+            .iter()
+            .flatten()
+            .map(|lib| (lib.clone(), self.status(lib.id)))
+            .collect()
+    }
+
+    pub fn status(&self, fs_id: LibraryId) -> ConnectionStatus {
+        self.library_status.get(&fs_id).copied().unwrap_or_default()
+    }
+
+    pub fn set_library_status(
+        &mut self,
+        fs_id: LibraryId,
+        status: ConnectionStatus,
+    ) -> Vec<AppEvent> {
+        let previous = self.library_status.insert(fs_id, status);
+        if previous == Some(status) {
+            return vec![];
+        }
+        let Some(lib) = self.library(fs_id).cloned() else {
+            return vec![];
+        };
+        let mut events = vec![];
+        if previous == Some(ConnectionStatus::Offline) {
+            log::info!("Library {} is back online.", lib.name);
+            self.notice = Some(format!("{} is back online.", lib.name));
+            events.push(AppEvent::MovieManagerEvent(
+                MovieManagerEvent::LibraryOnline(fs_id),
+            ));
+        }
+        events.push(AppEvent::SettingsEvent(
+            settings::SettingsEvent::LibraryStatus((lib, status)),
+        ));
+        events
+    }
+
+    pub fn set_download_progress(&mut self, progress: DownloadProgress) -> Vec<AppEvent> {
+        if progress.is_finished() {
+            self.downloads = DownloadProgress::default();
+            if progress.failed > 0 {
+                self.notice = Some(format!(
+                    "{} artwork download(s) failed, see the logs.",
+                    progress.failed
+                ));
+            }
+        } else {
+            self.downloads = progress;
+        }
+        vec![]
+    }
+
+    pub fn set_transfer_progress(&mut self, progress: TransferProgress) {
+        let current = self.transfers.iter().position(|t| t.id == progress.id);
+        match current {
+            Some(i) if progress.finished => {
+                self.transfers.remove(i);
+            }
+            Some(i) => self.transfers[i] = progress,
+            None if !progress.finished => self.transfers.push(progress),
+            None => {}
+        }
+    }
+
+    /// Handle a key pressed by the user, true when it quits MKube.
+    ///
+    /// Esc only quits when no view used it, to close a popup for instance.
+    pub fn press_key(&mut self, kev: KeyEvent) -> bool {
+        if kev.code == KeyCode::Char('c') && kev.modifiers == KeyModifiers::CONTROL {
+            return true;
+        }
+        let consumed = self.register_event(AppEvent::KeyEvent(kev));
+        !consumed && kev == KeyCode::Esc.into()
+    }
+
     pub fn register_event(&mut self, evt: AppEvent) -> bool {
         let sender = MESSAGE_SENDER.get().unwrap();
         match evt {
@@ -183,8 +328,36 @@ impl AppState {
                 sender.send(AppMessage::HttpFuture(builder)).unwrap();
                 true
             }
+            AppEvent::TransferProgress(progress) => {
+                self.set_transfer_progress(progress);
+                true
+            }
             AppEvent::KeyEvent(kev) => {
-                if kev.code == KeyCode::Char('s') && kev.modifiers == KeyModifiers::ALT {
+                let keys = &self.config.keybindings;
+                if self.show_help {
+                    self.show_help = false;
+                    true
+                } else if self.show_tasks {
+                    self.show_tasks = false;
+                    true
+                } else if let Some(modal) = self.modals.last_mut() {
+                    match modal.input(kev) {
+                        ModalOutcome::Pending => {}
+                        ModalOutcome::Cancelled => {
+                            self.modals.pop();
+                        }
+                        ModalOutcome::Done(msg) => {
+                            self.modals.pop();
+                            sender.send(*msg).unwrap();
+                        }
+                    }
+                    true
+                } else if HELP_KEY.matches(&kev)
+                    || (keys.is(Action::Help, &kev) && Action::Help.applies_to(self.key_context()))
+                {
+                    self.show_help = true;
+                    true
+                } else if keys.is(Action::OpenSettings, &kev) {
                     if let TabState::MovieManager(state) = &self.tab {
                         self.saved_movie_state = Some(state.clone());
                     }
@@ -192,7 +365,7 @@ impl AppState {
                     sender
                         .send(crate::AppMessage::Future(Box::new(
                             |appstate: &mut AppState| {
-                                let libs = appstate.libraries.iter().flatten().cloned().collect();
+                                let libs = appstate.library_entries();
                                 Box::pin(async move {
                                     vec![AppEvent::SettingsEvent(
                                         settings::SettingsEvent::OpenMenu(libs),
@@ -202,28 +375,82 @@ impl AppState {
                         )))
                         .unwrap();
                     true
-                } else if kev.code == KeyCode::Char('h') && kev.modifiers == KeyModifiers::ALT {
+                } else if keys.is(Action::OpenHome, &kev) {
                     if let TabState::MovieManager(ref mut mstate) = self.tab {
-                        mstate.input(AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable))
+                        mstate.input(
+                            AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable),
+                            keys,
+                        )
                     } else {
                         self.tab = TabState::MovieManager(
                             self.saved_movie_state.clone().unwrap_or_default(),
                         );
                         true
                     }
+                } else if keys.is(Action::Tasks, &kev) {
+                    self.show_tasks = true;
+                    true
+                } else if keys.is(Action::KodiScan, &kev) || keys.is(Action::KodiClean, &kev) {
+                    if self.config.kodi.is_enabled() {
+                        let method = if keys.is(Action::KodiScan, &kev) {
+                            crate::kodi::SCAN
+                        } else {
+                            crate::kodi::CLEAN
+                        };
+                        let _ = MESSAGE_SENDER
+                            .get()
+                            .unwrap()
+                            .send(crate::kodi::message(method));
+                    } else {
+                        self.notice = Some("No Kodi host is configured.".into());
+                    }
+                    true
+                } else if keys.is(Action::SwitchProfile, &kev) {
+                    self.modals.push(profile_picker(self));
+                    true
+                } else if keys.is(Action::ToggleOffline, &kev) {
+                    self.config.offline = !self.config.offline;
+                    self.notice = Some(if self.config.offline {
+                        "Offline mode enabled, TMDB and artworks are not reachable.".into()
+                    } else {
+                        "Offline mode disabled.".into()
+                    });
+                    true
                 } else if let TabState::Settings(ref mut state) = self.tab {
-                    state.press_key(kev)
+                    state.press_key(kev, keys)
+                } else if let Some(notice) = self
+                    .read_only_selection()
+                    .filter(|_| {
+                        keys.action(&Action::ALL, &kev)
+                            .is_some_and(|a| a.writes_library())
+                    })
+                    .map(read_only_notice)
+                {
+                    self.notice = Some(notice);
+                    true
                 } else if let TabState::MovieManager(ref mut state) = self.tab {
-                    state.input(evt)
+                    let action = keys.action(&Action::ALL, &kev);
+                    if self.config.offline
+                        && state.is_table()
+                        && action.is_some_and(|a| a.needs_network())
+                    {
+                        self.notice = Some(offline_notice(keys));
+                        true
+                    } else {
+                        state.input(evt, keys)
+                    }
                 } else {
                     false
                 }
             }
             _ => {
+                self.stats.record(&evt);
+                self.recent.record(&evt);
+                let keys = &self.config.keybindings;
                 if let TabState::Settings(ref mut sstate) = self.tab {
-                    sstate.input(evt)
+                    sstate.input(evt, keys)
                 } else if let TabState::MovieManager(ref mut state) = self.tab {
-                    state.input(evt)
+                    state.input(evt, keys)
                 } else {
                     false
                 }
@@ -232,6 +459,196 @@ impl AppState {
     }
 }
 
+/// Pick the profile to switch to, the default one first.
+fn profile_picker(state: &AppState) -> Modal {
+    let profiles: Vec<Option<String>> = std::iter::once(None)
+        .chain(state.profiles.iter().cloned().map(Some))
+        .collect();
+    let items = profiles
+        .iter()
+        .map(|profile| {
+            let name = profile.as_deref().unwrap_or("default");
+            if *profile == state.profile {
+                format!("{} (current)", name)
+            } else {
+                name.to_owned()
+            }
+        })
+        .collect();
+    Modal::picker(
+        "Switch profile",
+        "New profiles are created with `mkube --profile NAME`.",
+        items,
+        move |app_state: &mut AppState, index: usize| {
+            let profile = profiles.into_iter().nth(index).flatten();
+            if profile != app_state.profile {
+                let _ = MESSAGE_SENDER
+                    .get()
+                    .unwrap()
+                    .send(AppMessage::SwitchProfile(profile));
+            }
+            vec![]
+        },
+    )
+}
+
+/// What the main loop has to do once a message is dispatched.
+pub enum Dispatch<'a> {
+    Events(Vec<AppEvent>),
+    Spawn(Pin<Box<dyn Future<Output = Vec<AppEvent>> + Send + 'a>>),
+    SpawnLocal(Pin<Box<dyn Future<Output = Vec<AppEvent>> + 'a>>),
+    /// Saving a library needs the keyring and the configuration file, both owned by main.
+    SaveLibrary(Box<Library>),
+    /// The secret stores are only opened by main.
+    AuditCredentials,
+    DeleteCredentials(Vec<crate::config::OrphanedCredential>),
+    /// The libraries and connections of the new profile are only loaded by main.
+    SwitchProfile(Option<String>),
+    Close,
+}
+
+impl std::fmt::Debug for Dispatch<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            Dispatch::Events(evts) => write!(f, "Dispatch::Events(<{} events>)", evts.len()),
+            Dispatch::Spawn(_) => write!(f, "Dispatch::Spawn(<future>)"),
+            Dispatch::SpawnLocal(_) => write!(f, "Dispatch::SpawnLocal(<future>)"),
+            Dispatch::SaveLibrary(lib) => write!(f, "Dispatch::SaveLibrary({:?})", lib),
+            Dispatch::AuditCredentials => write!(f, "Dispatch::AuditCredentials"),
+            Dispatch::DeleteCredentials(orphans) => {
+                write!(f, "Dispatch::DeleteCredentials({:?})", orphans)
+            }
+            Dispatch::SwitchProfile(profile) => write!(f, "Dispatch::SwitchProfile({:?})", profile),
+            Dispatch::Close => write!(f, "Dispatch::Close"),
+        }
+    }
+}
+
+/// Turns every AppMessage into events or futures, with the shared clients and connections.
+/// `msg` without its writes to the read-only libraries, None when nothing is left to do.
+///
+/// The operations of a batch are skipped one by one, the other messages are refused whole.
+fn skip_read_only(state: &mut AppState, msg: MovieManagerMessage) -> Option<MovieManagerMessage> {
+    let is_read_only = |fs_id| state.library(fs_id).is_some_and(|lib| lib.read_only);
+    let (msg, notice) = match msg {
+        MovieManagerMessage::Batch(ops) | MovieManagerMessage::RunBatch(ops)
+            if ops.iter().any(|op| is_read_only(op.library())) =>
+        {
+            let total = ops.len();
+            let ops: Vec<_> = ops
+                .into_iter()
+                .filter(|op| !is_read_only(op.library()))
+                .collect();
+            let notice = format!(
+                "{} operations skipped, their library is read-only.",
+                total - ops.len()
+            );
+            let msg = (!ops.is_empty()).then_some(MovieManagerMessage::Batch(ops));
+            (msg, Some(notice))
+        }
+        msg => match msg
+            .written_libraries()
+            .into_iter()
+            .find_map(|fs_id| state.library(fs_id).filter(|lib| lib.read_only))
+        {
+            Some(lib) => (None, Some(read_only_notice(lib))),
+            None => (Some(msg), None),
+        },
+    };
+    if let Some(notice) = notice {
+        log::warn!("{}", notice);
+        state.notice = Some(notice);
+    }
+    msg
+}
+
+#[derive(Clone, Copy)]
+pub struct Dispatcher<'a> {
+    pub http_client: &'a reqwest::Client,
+    pub tmdb_client: &'a dyn MetadataProvider,
+    pub conns: &'a ConnectionPool,
+}
+
+impl<'a> Dispatcher<'a> {
+    pub async fn dispatch(&self, state: &mut AppState, msg: AppMessage) -> Dispatch<'a> {
+        let msg = match msg {
+            AppMessage::MovieManagerMessage(msg) if state.config.offline && msg.needs_network() => {
+                log::warn!("Offline mode, a TMDB or artwork task was not started.");
+                state.notice = Some(offline_notice(&state.config.keybindings));
+                return Dispatch::Events(vec![]);
+            }
+            AppMessage::MovieManagerMessage(msg) => match skip_read_only(state, msg) {
+                Some(msg) => msg.into_task(),
+                None => return Dispatch::Events(vec![]),
+            },
+            AppMessage::SettingsMessage(msg) => msg.into(),
+            msg => msg,
+        };
+        match msg {
+            AppMessage::Closure(closure) => Dispatch::Events(closure(state)),
+            AppMessage::Future(builder) => Dispatch::Spawn(builder(state)),
+            AppMessage::AppFuture(builder) => Dispatch::Events(builder(state).await),
+            AppMessage::HttpFuture(builder) => {
+                Dispatch::Spawn(builder(state, self.http_client, self.tmdb_client))
+            }
+            AppMessage::IOFuture(builder) => Dispatch::SpawnLocal(builder(
+                state,
+                self.http_client,
+                self.tmdb_client,
+                self.conns,
+            )),
+            AppMessage::TriggerEvent(evt) => Dispatch::Events(vec![evt]),
+            AppMessage::SettingsMessage(SettingsMessage::EditExisting(lib)) => {
+                match state
+                    .libraries
+                    .iter()
+                    .position(|l| l.as_ref() == Some(&lib))
+                {
+                    Some(ind) => {
+                        // Safety: Close conn first, the running scans of this library stop on their next access.
+                        self.conns.lock().await.close(lib.id);
+                        state.libraries[ind] = None;
+                        for cl in state.config.libraries.iter_mut() {
+                            if cl.as_ref().is_some_and(|cl| cl.id == lib.id) {
+                                *cl = None;
+                            }
+                        }
+                        Dispatch::Events(vec![AppEvent::SettingsEvent(
+                            settings::SettingsEvent::EditExisting(lib),
+                        )])
+                    }
+                    None => {
+                        log::error!("Invalid library editing, message ignored.");
+                        Dispatch::Events(vec![])
+                    }
+                }
+            }
+            AppMessage::SettingsMessage(SettingsMessage::SaveLibrary(lib)) => {
+                Dispatch::SaveLibrary(Box::new(lib))
+            }
+            AppMessage::SettingsMessage(SettingsMessage::AuditCredentials) => {
+                Dispatch::AuditCredentials
+            }
+            AppMessage::SettingsMessage(SettingsMessage::DeleteCredentials(orphans)) => {
+                Dispatch::DeleteCredentials(orphans)
+            }
+            AppMessage::SettingsMessage(msg) => {
+                log::error!("Unexpected settings message {:?}, message ignored.", msg);
+                Dispatch::Events(vec![])
+            }
+            AppMessage::MovieManagerMessage(msg) => {
+                log::error!(
+                    "Unexpected movie manager message {:?}, message ignored.",
+                    msg
+                );
+                Dispatch::Events(vec![])
+            }
+            AppMessage::SwitchProfile(profile) => Dispatch::SwitchProfile(profile),
+            AppMessage::Close => Dispatch::Close,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct App {
     pub settings_page: SettingsPage,
@@ -245,7 +662,14 @@ impl StatefulWidget for App {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(0)
-            .constraints([Constraint::Length(3), Constraint::Percentage(90)].as_ref())
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
             .split(area.clone());
 
         let titles = ["Home (Alt+H)", "Settings (Alt+S)"]
@@ -253,8 +677,35 @@ impl StatefulWidget for App {
             .cloned()
             .map(Spans::from)
             .collect();
+        let mut title = vec![Span::raw("MKube")];
+        if let Some(profile) = state.profile.as_deref() {
+            title.push(Span::raw(format!(" [{}]", profile)));
+        }
+        if state.config.offline {
+            title.push(Span::raw(" - "));
+            title.push(Span::styled(
+                "Offline",
+                Style::default().fg(Color::LightRed),
+            ));
+        }
+        if state.downloads.total > 0 {
+            let progress = state.downloads;
+            title.push(Span::raw(" - "));
+            title.push(Span::styled(
+                format!(
+                    "Artworks: {}/{}",
+                    progress.done + progress.failed,
+                    progress.total
+                ),
+                Style::default().fg(Color::LightCyan),
+            ));
+        }
         let tabs = Tabs::new(titles)
-            .block(Block::default().title("MKube").borders(Borders::ALL))
+            .block(
+                Block::default()
+                    .title(Spans::from(title))
+                    .borders(Borders::ALL),
+            )
             .style(Style::default().fg(Color::White))
             .highlight_style(Style::default().fg(Color::Yellow))
             .select((&state.tab).into())
@@ -274,10 +725,833 @@ impl StatefulWidget for App {
             TabState::Settings(ref mut state) => {
                 self.settings_page.render(chunks[1], buf, state);
             }
-            TabState::MovieManager(ref mut state) => {
-                self.movie_manager.render(chunks[1], buf, state);
+            TabState::MovieManager(ref mut mstate) => {
+                self.movie_manager
+                    .with_keys(state.config.keybindings.clone())
+                    .with_offline(state.config.offline)
+                    .with_transfers(&state.transfers)
+                    .with_columns(
+                        &state.config.table.columns,
+                        state
+                            .libraries
+                            .iter()
+                            .flatten()
+                            .map(|lib| (lib.id, lib.name.clone()))
+                            .collect(),
+                    )
+                    .render(chunks[1], buf, mstate);
             }
         }
         tabs.render(chunks[0], buf);
+        StatusBar { state }.render(chunks[2], buf);
+        for modal in state.modals.iter_mut() {
+            ModalPopup::default().render(chunks[1], buf, modal);
+        }
+        if state.show_tasks {
+            TasksPopup {
+                transfers: &state.transfers,
+                downloads: state.downloads,
+            }
+            .render(area, buf);
+        }
+        if state.show_help {
+            HelpPopup {
+                keys: &state.config.keybindings,
+                context: state.key_context(),
+                offline: state.config.offline,
+                read_only: state.read_only_selection().is_some(),
+            }
+            .render(area, buf);
+        }
+    }
+}
+
+/// Popup listing the key bindings of the current view, then the global ones.
+pub struct HelpPopup<'a> {
+    pub keys: &'a Keybindings,
+    pub context: KeyContext,
+    /// Grey out the actions unavailable offline.
+    pub offline: bool,
+    /// Grey out the actions writing to the library of the selected movie.
+    pub read_only: bool,
+}
+
+impl<'a> HelpPopup<'a> {
+    fn action_line(&self, action: Action) -> Spans<'static> {
+        let key = format!(" {:^9} ", self.keys.key(action));
+        let unavailable = if self.offline && action.needs_network() {
+            Some("offline")
+        } else if self.read_only && action.writes_library() {
+            Some("read-only")
+        } else {
+            None
+        };
+        if let Some(reason) = unavailable {
+            let style = Style::default().fg(Color::DarkGray);
+            Spans::from(vec![
+                Span::styled(key, style),
+                Span::raw(" "),
+                Span::styled(format!("{} ({})", action.description(), reason), style),
+            ])
+        } else {
+            Spans::from(vec![
+                Span::styled(key, help_key_style()),
+                Span::raw(" "),
+                Span::raw(action.description()),
+            ])
+        }
+    }
+
+    fn lines(&self) -> Vec<Spans<'static>> {
+        let heading = |title: &str| {
+            Spans::from(Span::styled(
+                title.to_owned(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        };
+        let mut lines = vec![heading(self.context.name())];
+        lines.extend(self.context.fixed_keys().iter().map(|(key, description)| {
+            Spans::from(vec![
+                Span::styled(format!(" {:^9} ", key), help_key_style()),
+                Span::raw(" "),
+                Span::raw(*description),
+            ])
+        }));
+        lines.extend(
+            Action::ALL
+                .iter()
+                .filter(|a| a.context() == Some(self.context))
+                .map(|a| self.action_line(*a)),
+        );
+        if Action::Help.applies_to(self.context) {
+            lines.push(self.action_line(Action::Help));
+        }
+        lines.push(Spans::default());
+        lines.push(heading("Everywhere"));
+        lines.push(Spans::from(vec![
+            Span::styled(format!(" {:^9} ", HELP_KEY), help_key_style()),
+            Span::raw(" "),
+            Span::raw(Action::Help.description()),
+        ]));
+        lines.extend(
+            Action::ALL
+                .iter()
+                .filter(|a| a.context().is_none())
+                .map(|a| self.action_line(*a)),
+        );
+        lines
+    }
+}
+
+fn help_key_style() -> Style {
+    Style::default().fg(Color::White).bg(Color::Magenta)
+}
+
+impl<'a> Widget for HelpPopup<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let content = self.lines();
+        let width = area.width.min(60);
+        let height = area.height.min(content.len() as u16 + 2);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" Key bindings (press any key to close) ");
+        Clear.render(popup, buf);
+        Paragraph::new(content).block(block).render(popup, buf);
+    }
+}
+
+/// Bottom line with the libraries, the loaded movies, the running tasks and the last notice.
+pub struct StatusBar<'a> {
+    pub state: &'a AppState,
+}
+
+impl<'a> Widget for StatusBar<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let state = self.state;
+        let separator = || Span::styled(" | ", Style::default().fg(Color::DarkGray));
+        let libraries: Vec<ConnectionStatus> = state
+            .libraries
+            .iter()
+            .flatten()
+            .map(|lib| state.status(lib.id))
+            .collect();
+        let mut spans = vec![Span::raw(format!(" {} libraries", libraries.len()))];
+        for (status, color) in [
+            (ConnectionStatus::Connected, Color::LightGreen),
+            (ConnectionStatus::Degraded, Color::LightYellow),
+            (ConnectionStatus::Offline, Color::LightRed),
+        ] {
+            let count = libraries.iter().filter(|s| **s == status).count();
+            if count > 0 {
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    format!("{} {}", count, status),
+                    Style::default().fg(color),
+                ));
+                // The heartbeat tries to reconnect them.
+                if status == ConnectionStatus::Offline && state.config.heartbeat.is_enabled() {
+                    spans.push(Span::styled(
+                        " (reconnecting)",
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+            }
+        }
+        let movies = match &state.tab {
+            TabState::MovieManager(mstate) => Some(mstate),
+            TabState::Settings(_) => state.saved_movie_state.as_ref(),
+        }
+        .map_or(0, MovieManagerState::movie_count);
+        spans.push(separator());
+        spans.push(Span::raw(format!("{} movies", movies)));
+        let running = state.transfers.iter().filter(|t| !t.finished).count();
+        spans.push(separator());
+        spans.push(Span::raw(format!(
+            "{} tasks, {} transfers",
+            state.pending_tasks, running
+        )));
+        if let Some(notice) = state.notice.as_deref() {
+            spans.push(separator());
+            spans.push(Span::styled(
+                notice.to_owned(),
+                Style::default().fg(Color::LightYellow),
+            ));
+        }
+        Paragraph::new(Spans::from(spans)).render(area, buf);
+    }
+}
+
+/// Popup listing the running transfers and artwork downloads.
+pub struct TasksPopup<'a> {
+    pub transfers: &'a [TransferProgress],
+    pub downloads: DownloadProgress,
+}
+
+impl<'a> Widget for TasksPopup<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let downloads = usize::from(self.downloads.total > 0);
+        let lines = Ord::max(self.transfers.len() + downloads, 1) as u16;
+        let width = area.width.min(70);
+        let height = area.height.min(lines + 2);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" Tasks (press any key to close) ");
+        let inner = block.inner(popup);
+        Clear.render(popup, buf);
+        block.render(popup, buf);
+        if self.transfers.is_empty() && downloads == 0 {
+            Paragraph::new("No running task.").render(inner, buf);
+            return;
+        }
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1); lines as usize])
+            .split(inner);
+        let gauges = self
+            .transfers
+            .iter()
+            .map(|t| Progress::new(t.label.as_str(), t.done, t.total))
+            .chain((downloads > 0).then(|| {
+                let progress = self.downloads;
+                Progress::new(
+                    "Artworks",
+                    (progress.done + progress.failed) as u64,
+                    progress.total as u64,
+                )
+                .with_count()
+            }));
+        for (gauge, row) in gauges.zip(rows.iter()) {
+            gauge.render(*row, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Credentials;
+    use crate::mockfs::MockFs;
+    use crate::multifs::MultiFs;
+    use crate::nfo::Movie;
+    use crate::test_util::{entry, library, message_sender, render_app, state};
+    use movie_manager::batch::BatchOp;
+    use settings::SettingsEvent;
+    use std::path::PathBuf;
+
+    fn dispatcher() -> Dispatcher<'static> {
+        message_sender();
+        Dispatcher {
+            http_client: Box::leak(Box::new(reqwest::Client::new())),
+            tmdb_client: Box::leak(Box::new(crate::provider::FixtureProvider::demo())),
+            conns: Box::leak(Box::new(tokio::sync::Mutex::new(Default::default()))),
+        }
+    }
+
+    async fn dispatch(msg: AppMessage) -> Dispatch<'static> {
+        dispatcher().dispatch(&mut state(), msg).await
+    }
+
+    fn events(dispatch: Dispatch) -> Vec<AppEvent> {
+        match dispatch {
+            Dispatch::Events(evts) => evts,
+            other => panic!("expected events, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn app_messages() {
+        let evts = events(
+            dispatch(AppMessage::Closure(Box::new(|_| {
+                vec![AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable)]
+            })))
+            .await,
+        );
+        assert_eq!(evts.len(), 1);
+        let evts = events(
+            dispatch(AppMessage::AppFuture(Box::new(|_| {
+                Box::pin(async { vec![AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable)] })
+            })))
+            .await,
+        );
+        assert_eq!(evts.len(), 1);
+        let evts = events(
+            dispatch(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                MovieManagerEvent::OpenTable,
+            )))
+            .await,
+        );
+        assert!(matches!(
+            evts[..],
+            [AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable)]
+        ));
+        assert!(matches!(
+            dispatch(AppMessage::Future(Box::new(|_| Box::pin(async { vec![] })))).await,
+            Dispatch::Spawn(_)
+        ));
+        assert!(matches!(
+            dispatch(AppMessage::HttpFuture(Box::new(|_, _, _| Box::pin(
+                async { vec![] }
+            ))))
+            .await,
+            Dispatch::Spawn(_)
+        ));
+        assert!(matches!(
+            dispatch(AppMessage::IOFuture(Box::new(|_, _, _, _| Box::pin(
+                async { vec![] }
+            ))))
+            .await,
+            Dispatch::SpawnLocal(_)
+        ));
+        assert!(matches!(dispatch(AppMessage::Close).await, Dispatch::Close));
+    }
+
+    #[tokio::test]
+    async fn settings_messages() {
+        let evts = events(dispatch(SettingsMessage::OpenMenu.into()).await);
+        assert!(matches!(
+            &evts[..],
+            [AppEvent::SettingsEvent(settings::SettingsEvent::OpenMenu(libs))] if libs.len() == 1
+        ));
+        assert!(matches!(
+            dispatch(SettingsMessage::TestLibrary(library()).into()).await,
+            Dispatch::Spawn(_)
+        ));
+        assert!(matches!(
+            dispatch(SettingsMessage::SaveLibrary(library()).into()).await,
+            Dispatch::SaveLibrary(lib) if *lib == library()
+        ));
+        assert!(matches!(
+            dispatch(SettingsMessage::AuditCredentials.into()).await,
+            Dispatch::AuditCredentials
+        ));
+        assert!(matches!(
+            dispatch(SettingsMessage::DeleteCredentials(vec![]).into()).await,
+            Dispatch::DeleteCredentials(orphans) if orphans.is_empty()
+        ));
+    }
+
+    #[tokio::test]
+    async fn edit_existing_library() {
+        let dispatcher = dispatcher();
+        let mut state = state();
+        let evts = events(
+            dispatcher
+                .dispatch(&mut state, SettingsMessage::EditExisting(library()).into())
+                .await,
+        );
+        assert!(matches!(
+            &evts[..],
+            [AppEvent::SettingsEvent(
+                settings::SettingsEvent::EditExisting(_)
+            )]
+        ));
+        assert_eq!(state.libraries, vec![None]);
+
+        let mut unknown = library();
+        unknown.name = "Unknown".into();
+        let evts = events(
+            dispatcher
+                .dispatch(&mut state, SettingsMessage::EditExisting(unknown).into())
+                .await,
+        );
+        assert!(evts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn movie_manager_messages() {
+        let evts = events(dispatch(MovieManagerMessage::RefreshMovies.into()).await);
+        assert_eq!(evts.len(), 2);
+        assert!(matches!(
+            evts[0],
+            AppEvent::MovieManagerEvent(MovieManagerEvent::ClearMovieList)
+        ));
+        assert!(matches!(evts[1], AppEvent::ContinuationIOFuture(_)));
+
+        assert!(matches!(
+            dispatch(MovieManagerMessage::SearchTitle(("Movie".into(), Some(2019), 1)).into())
+                .await,
+            Dispatch::Spawn(_)
+        ));
+        assert!(matches!(
+            dispatch(MovieManagerMessage::CreateNfo((1, entry().1, entry().2)).into()).await,
+            Dispatch::Spawn(_)
+        ));
+        assert!(matches!(
+            dispatch(MovieManagerMessage::AutoIdentify(vec![entry()]).into()).await,
+            Dispatch::Spawn(_)
+        ));
+        let (movie, fs_id, path) = entry();
+        for msg in [
+            MovieManagerMessage::RetrieveArtworks((movie, fs_id, path, false)),
+            MovieManagerMessage::SaveNfo(entry()),
+            MovieManagerMessage::Rename(entry()),
+            MovieManagerMessage::RefreshFromTmdb(entry()),
+            MovieManagerMessage::RunBatch(vec![BatchOp::SaveNfo(entry())]),
+        ] {
+            assert!(matches!(
+                dispatch(msg.into()).await,
+                Dispatch::SpawnLocal(_)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_messages() {
+        let evts = events(dispatch(MovieManagerMessage::Batch(vec![]).into()).await);
+        assert!(evts.is_empty());
+
+        let ops = vec![BatchOp::SaveNfo(entry()); 20];
+        let evts = events(dispatch(MovieManagerMessage::Batch(ops).into()).await);
+        assert!(matches!(
+            &evts[..],
+            [AppEvent::MovieManagerEvent(MovieManagerEvent::ConfirmBatch(ops))] if ops.len() == 20
+        ));
+
+        let ops = vec![BatchOp::SaveNfo(entry()); 200];
+        let evts = events(dispatch(MovieManagerMessage::Batch(ops).into()).await);
+        assert!(evts.is_empty());
+    }
+
+    #[test]
+    fn read_only_library() {
+        dispatcher();
+        let archive = Library {
+            id: LibraryId::new(),
+            name: "Archive".into(),
+            read_only: true,
+            ..library()
+        };
+        let mut state = state();
+        state.libraries.push(Some(archive.clone()));
+        let archived = (Movie::default(), archive.id, PathBuf::from("old/old.mkv"));
+        let refused = Some("Library Archive is read-only, nothing is written to it.".into());
+
+        let save = MovieManagerMessage::SaveNfo(archived.clone());
+        assert_eq!(skip_read_only(&mut state, save), None);
+        assert_eq!(state.notice, refused);
+        let move_to_archive =
+            MovieManagerMessage::MoveMovie((entry().0, entry().1, entry().2, archive.id));
+        assert_eq!(skip_read_only(&mut state, move_to_archive), None);
+        // Reading and the other libraries are left alone.
+        let verify = MovieManagerMessage::VerifyChecksum(archived.clone());
+        assert_eq!(skip_read_only(&mut state, verify.clone()), Some(verify));
+        let save = MovieManagerMessage::SaveNfo(entry());
+        assert_eq!(skip_read_only(&mut state, save.clone()), Some(save));
+
+        let batch = MovieManagerMessage::Batch(vec![
+            BatchOp::SaveNfo(entry()),
+            BatchOp::Rename(archived.clone()),
+        ]);
+        assert_eq!(
+            skip_read_only(&mut state, batch),
+            Some(MovieManagerMessage::Batch(vec![BatchOp::SaveNfo(entry())]))
+        );
+        assert_eq!(
+            state.notice.as_deref(),
+            Some("1 operations skipped, their library is read-only.")
+        );
+
+        // The writing keys are refused on its movies.
+        state.notice = None;
+        for evt in [
+            MovieManagerEvent::MovieDiscovered(archived.clone()),
+            MovieManagerEvent::SelectMovie((archive.id, archived.2.clone())),
+        ] {
+            state.register_event(AppEvent::MovieManagerEvent(evt));
+        }
+        assert_eq!(state.read_only_selection(), Some(&archive));
+        state.register_event(AppEvent::KeyEvent(KeyCode::Char('n').into()));
+        assert_eq!(state.notice, refused);
+
+        let config_lib = crate::config::ConfigLibrary::from(archive);
+        assert!(config_lib.with_password(None).read_only);
+    }
+
+    #[tokio::test]
+    async fn offline_messages() {
+        let dispatcher = dispatcher();
+        let mut state = state();
+        state.config.offline = true;
+        let evts = events(
+            dispatcher
+                .dispatch(
+                    &mut state,
+                    MovieManagerMessage::CreateNfo((1, entry().1, entry().2)).into(),
+                )
+                .await,
+        );
+        assert!(evts.is_empty());
+        assert!(state.notice.is_some());
+        assert!(matches!(
+            dispatcher
+                .dispatch(&mut state, MovieManagerMessage::SaveNfo(entry()).into())
+                .await,
+            Dispatch::SpawnLocal(_)
+        ));
+    }
+
+    #[test]
+    fn transfer_progress_events() {
+        dispatcher();
+        let mut state = AppState::default();
+        let progress = TransferProgress {
+            id: 7,
+            label: "movie.mkv".into(),
+            done: 10,
+            total: 100,
+            finished: false,
+        };
+        state.register_event(AppEvent::TransferProgress(progress.clone()));
+        state.register_event(AppEvent::TransferProgress(TransferProgress {
+            done: 60,
+            ..progress.clone()
+        }));
+        assert_eq!(state.transfers.len(), 1);
+        assert_eq!(state.transfers[0].done, 60);
+        state.register_event(AppEvent::TransferProgress(TransferProgress {
+            finished: true,
+            ..progress
+        }));
+        assert!(state.transfers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn library_heartbeat() {
+        dispatcher();
+        let mock = MockFs::new().with_file("/movies/alien/alien.mkv", "");
+        let conns: ConnectionPool = Default::default();
+        let fs_id = library().id;
+        conns
+            .lock()
+            .await
+            .insert(fs_id, MultiFs::Mock(mock.clone()));
+        assert_eq!(
+            crate::heartbeat(&conns).await,
+            vec![(fs_id, ConnectionStatus::Connected)]
+        );
+
+        let mut state = state();
+        state.set_library_status(fs_id, ConnectionStatus::Offline);
+        let rendered = render_app(&mut state, 100, 10);
+        assert!(rendered.contains("1 offline (reconnecting)"));
+        let events = state.set_library_status(fs_id, ConnectionStatus::Degraded);
+        assert!(matches!(
+            events[..],
+            [
+                AppEvent::MovieManagerEvent(MovieManagerEvent::LibraryOnline(id)),
+                AppEvent::SettingsEvent(SettingsEvent::LibraryStatus(_)),
+            ] if id == fs_id
+        ));
+        assert_eq!(state.notice.as_deref(), Some("Movies is back online."));
+        // Only the way back from offline is reported.
+        let events = state.set_library_status(fs_id, ConnectionStatus::Connected);
+        assert!(matches!(
+            events[..],
+            [AppEvent::SettingsEvent(SettingsEvent::LibraryStatus(_))]
+        ));
+        let mut mstate = MovieManagerState::default();
+        let keys = state.config.keybindings.clone();
+        assert!(mstate.input(
+            AppEvent::MovieManagerEvent(MovieManagerEvent::LibraryOnline(fs_id)),
+            &keys
+        ));
+    }
+
+    #[tokio::test]
+    async fn prompt_credentials() {
+        let dispatcher = dispatcher();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let password: Credentials = toml::Value::String("prompt".into()).try_into().unwrap();
+        assert_eq!(password, Credentials::Prompt);
+        assert_eq!(
+            toml::Value::try_from(&password).unwrap().as_str(),
+            Some("Prompt")
+        );
+
+        let mut state = state();
+        let fs_id = library().id;
+        state.set_library_status(fs_id, ConnectionStatus::Offline);
+        state.prompted.insert(fs_id);
+        state
+            .modals
+            .push(settings::password_prompt(library(), true));
+        for c in "hunter2".chars() {
+            state.register_event(AppEvent::KeyEvent(key(KeyCode::Char(c))));
+        }
+        let rendered = render_app(&mut state, 100, 20);
+        assert!(rendered.contains("Connection failed, check the password."));
+        assert!(rendered.contains("•••••••"));
+        assert!(!rendered.contains("hunter2"));
+        let reveal = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        state.register_event(AppEvent::KeyEvent(reveal));
+        let rendered = render_app(&mut state, 100, 20);
+        assert!(rendered.contains("hunter2"));
+        assert!(rendered.contains("Ctrl+R  Hide"));
+        state.register_event(AppEvent::KeyEvent(reveal));
+        assert!(!render_app(&mut state, 100, 20).contains("hunter2"));
+
+        let events = match state.modals[0].input(key(KeyCode::Enter)) {
+            ModalOutcome::Done(msg) => match *msg {
+                AppMessage::Closure(action) => action(&mut state),
+                _ => panic!("the modal action is a closure"),
+            },
+            _ => panic!("the password is submitted"),
+        };
+        let Some(AppEvent::ContinuationIOFuture(builder)) = events.into_iter().next() else {
+            panic!("the library is opened with the password");
+        };
+        let events = builder(
+            &mut state,
+            dispatcher.http_client,
+            dispatcher.tmdb_client,
+            dispatcher.conns,
+        )
+        .await;
+        let Some(AppEvent::ContinuationFuture(builder)) = events.into_iter().next() else {
+            panic!("the status is set back");
+        };
+        let events = builder(&mut state).await;
+        assert_eq!(
+            state.library_status.get(&fs_id),
+            Some(&ConnectionStatus::Connected)
+        );
+        assert!(matches!(
+            events[0],
+            AppEvent::MovieManagerEvent(movie_manager::MovieManagerEvent::LibraryOnline(id))
+                if id == fs_id
+        ));
+    }
+
+    /// The pages of the snapshot tests, the library path is fixed to keep them reproducible.
+    fn snapshot_pages() -> Vec<(&'static str, AppState)> {
+        let keys = Keybindings::default();
+        let lib = Library {
+            path: "/movies".into(),
+            ..library()
+        };
+        let movie = Movie {
+            title: "The Quick Brown Fox".into(),
+            original_title: Some("Le Renard Brun".into()),
+            premiered: Some("2001-02-03".into()),
+            plot: Some("A fox jumps over the lazy dog, again and again.".into()),
+            genre: vec!["Comedy".into()],
+            ..Default::default()
+        };
+        let path = PathBuf::from("/movies/The Quick Brown Fox (2001)/fox.mkv");
+        let movies = |page: Option<MovieManagerEvent>| {
+            let mut mstate = MovieManagerState::default();
+            let discovered = [
+                (movie.clone(), path.clone()),
+                (
+                    Movie {
+                        title: "映画のタイトル".into(),
+                        ..Default::default()
+                    },
+                    PathBuf::from("/movies/eiga/eiga.mkv"),
+                ),
+            ];
+            for (movie, path) in discovered {
+                mstate.input(
+                    AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered((
+                        movie, lib.id, path,
+                    ))),
+                    &keys,
+                );
+            }
+            if let Some(page) = page {
+                mstate.input(AppEvent::MovieManagerEvent(page), &keys);
+            }
+            AppState {
+                tab: TabState::MovieManager(mstate),
+                libraries: vec![Some(lib.clone())],
+                ..state()
+            }
+        };
+        let settings = |page: SettingsEvent| {
+            let mut sstate = SettingsState::default();
+            sstate.input(AppEvent::SettingsEvent(page), &keys);
+            AppState {
+                tab: TabState::Settings(sstate),
+                libraries: vec![Some(lib.clone())],
+                ..state()
+            }
+        };
+        let entry = (movie.clone(), lib.id, path.clone());
+        vec![
+            ("table", movies(None)),
+            (
+                "editor",
+                movies(Some(MovieManagerEvent::EditMovie(entry.clone()))),
+            ),
+            (
+                "search",
+                movies(Some(MovieManagerEvent::SearchMovie(entry))),
+            ),
+            (
+                "settings",
+                settings(SettingsEvent::OpenMenu(vec![(
+                    lib.clone(),
+                    ConnectionStatus::Connected,
+                )])),
+            ),
+            (
+                "settings_library",
+                settings(SettingsEvent::EditExisting(lib.clone())),
+            ),
+        ]
+    }
+
+    /// Compare with `src/views/snapshots/<name>.txt`, rewritten when UPDATE_SNAPSHOTS is set.
+    fn assert_snapshot(name: &str, text: &str) {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/views/snapshots")
+            .join(format!("{}.txt", name));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, text).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "Missing snapshot {}, run the tests with UPDATE_SNAPSHOTS=1.",
+                path.display()
+            )
+        });
+        assert!(
+            expected == text,
+            "Snapshot {} changed, run the tests with UPDATE_SNAPSHOTS=1 if expected.\n{}",
+            name,
+            text
+        );
+    }
+
+    #[test]
+    fn page_snapshots() {
+        for (name, mut state) in snapshot_pages() {
+            for (width, height) in [(80, 24), (40, 12)] {
+                let text = render_app(&mut state, width, height);
+                assert_snapshot(&format!("{}_{}x{}", name, width, height), &text);
+            }
+        }
+    }
+
+    #[test]
+    fn pages_render_at_every_size() {
+        for (name, mut state) in snapshot_pages() {
+            for width in (1..=100).step_by(7) {
+                for height in [1, 2, 5, 8, 13, 30] {
+                    let rendered = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        render_app(&mut state, width, height)
+                    }));
+                    assert!(
+                        rendered.is_ok(),
+                        "{} panicked at {}x{}",
+                        name,
+                        width,
+                        height
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn help_follows_the_view() {
+        let keys = Keybindings::default();
+        let text = |context| {
+            HelpPopup {
+                keys: &keys,
+                context,
+                offline: false,
+                read_only: false,
+            }
+            .lines()
+            .iter()
+            .flat_map(|line| line.0.iter().map(|span| span.content.to_string()))
+            .collect::<String>()
+        };
+        let table = text(KeyContext::Table);
+        assert!(table.contains(Action::Rename.description()));
+        assert!(table.contains(Action::SwitchProfile.description()));
+        let editor = text(KeyContext::Editor);
+        assert!(editor.contains("ctrl+d"));
+        assert!(!editor.contains(Action::Rename.description()));
+        assert!(editor.contains(Action::OpenHome.description()));
+        assert!(editor.contains(&format!(" {:^9} ", HELP_KEY)));
+    }
+
+    #[test]
+    fn help_keys() {
+        message_sender();
+        let mut state = state();
+        let help = KeyCode::Char('?').into();
+        state.press_key(help);
+        assert!(state.show_help);
+        state.press_key(KeyCode::Esc.into());
+        assert!(!state.show_help);
+
+        // The settings forms type it, only the help key works there.
+        state.tab = TabState::Settings(SettingsState::Edit(Default::default()));
+        assert_eq!(state.key_context(), KeyContext::SettingsForm);
+        state.press_key(help);
+        assert!(!state.show_help);
+        state.press_key(HELP_KEY.code.into());
+        assert!(state.show_help);
     }
 }
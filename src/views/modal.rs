@@ -0,0 +1,392 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget, Wrap,
+    },
+};
+
+use crate::util::fuzzy_filter;
+use crate::views::widgets::{Input, InputState};
+use crate::{AppEvent, AppMessage, AppState, MESSAGE_SENDER};
+
+type ModalAction<T> = Box<dyn FnOnce(&mut AppState, T) -> Vec<AppEvent> + Send + Sync>;
+
+enum ModalKind {
+    Confirm(Option<ModalAction<()>>),
+    Prompt(Box<InputState>, Option<ModalAction<String>>),
+    /// A prompt drawing stars instead of the text.
+    Password(Box<InputState>, Option<ModalAction<String>>),
+    Picker(Vec<String>, ListState, Option<ModalAction<usize>>),
+    /// Items, the indices matching the query and their selection.
+    FuzzyPicker(
+        Box<InputState>,
+        Vec<String>,
+        Vec<usize>,
+        ListState,
+        Option<ModalAction<usize>>,
+    ),
+}
+
+/// A popup drawn above the active tab, it captures the key events until it is closed.
+///
+/// The modals are stacked in AppState, the action of a modal runs as an AppMessage::Closure.
+pub struct Modal {
+    pub title: String,
+    pub message: String,
+    kind: ModalKind,
+}
+
+pub enum ModalOutcome {
+    Pending,
+    Cancelled,
+    Done(Box<AppMessage>),
+}
+
+impl Modal {
+    /// Ask a yes/no question, `on_confirm` runs on Enter or y.
+    pub fn confirm<F>(title: impl Into<String>, message: impl Into<String>, on_confirm: F) -> Self
+    where
+        F: FnOnce(&mut AppState) -> Vec<AppEvent> + Send + Sync + 'static,
+    {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::Confirm(Some(Box::new(move |state, _| on_confirm(state)))),
+        }
+    }
+
+    /// Ask for a text, `on_submit` gets the value on Enter.
+    pub fn prompt<F>(
+        title: impl Into<String>,
+        message: impl Into<String>,
+        value: &str,
+        on_submit: F,
+    ) -> Self
+    where
+        F: FnOnce(&mut AppState, String) -> Vec<AppEvent> + Send + Sync + 'static,
+    {
+        let mut input = InputState::default();
+        input.set_value(value);
+        input.set_focus(true);
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::Prompt(Box::new(input), Some(Box::new(on_submit))),
+        }
+    }
+
+    /// Ask for a password, like [`Modal::prompt`] without showing it.
+    pub fn password<F>(title: impl Into<String>, message: impl Into<String>, on_submit: F) -> Self
+    where
+        F: FnOnce(&mut AppState, String) -> Vec<AppEvent> + Send + Sync + 'static,
+    {
+        let mut input = InputState::default();
+        input.set_focus(true);
+        input.set_masked(true);
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::Password(Box::new(input), Some(Box::new(on_submit))),
+        }
+    }
+
+    /// Pick one of the items, `on_pick` gets its index on Enter.
+    pub fn picker<F>(
+        title: impl Into<String>,
+        message: impl Into<String>,
+        items: Vec<String>,
+        on_pick: F,
+    ) -> Self
+    where
+        F: FnOnce(&mut AppState, usize) -> Vec<AppEvent> + Send + Sync + 'static,
+    {
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::Picker(items, list_state, Some(Box::new(on_pick))),
+        }
+    }
+
+    /// Pick one of the items after filtering them with a fuzzy query, `on_pick` gets its index.
+    pub fn fuzzy_picker<F>(
+        title: impl Into<String>,
+        message: impl Into<String>,
+        items: Vec<String>,
+        on_pick: F,
+    ) -> Self
+    where
+        F: FnOnce(&mut AppState, usize) -> Vec<AppEvent> + Send + Sync + 'static,
+    {
+        let mut input = InputState::default();
+        input.set_focus(true);
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        let matches = (0..items.len()).collect();
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind: ModalKind::FuzzyPicker(
+                Box::new(input),
+                items,
+                matches,
+                list_state,
+                Some(Box::new(on_pick)),
+            ),
+        }
+    }
+
+    /// Select an item of a picker, the first one by default.
+    pub fn select(mut self, index: usize) -> Self {
+        match &mut self.kind {
+            ModalKind::Picker(items, list_state, _) => {
+                list_state.select(Some(Ord::min(index, items.len().saturating_sub(1))))
+            }
+            ModalKind::FuzzyPicker(_, _, matches, list_state, _) => {
+                list_state.select(Some(Ord::min(index, matches.len().saturating_sub(1))))
+            }
+            _ => {}
+        }
+        self
+    }
+
+    pub fn input(&mut self, kev: KeyEvent) -> ModalOutcome {
+        if kev.code == KeyCode::Esc {
+            return ModalOutcome::Cancelled;
+        }
+        match &mut self.kind {
+            ModalKind::Confirm(action) => match kev.code {
+                KeyCode::Enter | KeyCode::Char('y') => done(action, ()),
+                KeyCode::Char('n') => ModalOutcome::Cancelled,
+                _ => ModalOutcome::Pending,
+            },
+            ModalKind::Prompt(input, action) | ModalKind::Password(input, action) => {
+                if kev.code == KeyCode::Enter {
+                    done(action, input.get_value().to_owned())
+                } else {
+                    input.input(kev);
+                    ModalOutcome::Pending
+                }
+            }
+            ModalKind::Picker(items, list_state, action) => {
+                let selected = list_state.selected().unwrap_or(0);
+                match kev.code {
+                    KeyCode::Up => list_state.select(Some(selected.saturating_sub(1))),
+                    KeyCode::Down => list_state
+                        .select(Some(Ord::min(selected + 1, items.len().saturating_sub(1)))),
+                    KeyCode::Enter if selected < items.len() => return done(action, selected),
+                    _ => {}
+                }
+                ModalOutcome::Pending
+            }
+            ModalKind::FuzzyPicker(input, items, matches, list_state, action) => {
+                let selected = list_state.selected().unwrap_or(0);
+                match kev.code {
+                    KeyCode::Up => list_state.select(Some(selected.saturating_sub(1))),
+                    KeyCode::Down => list_state.select(Some(Ord::min(
+                        selected + 1,
+                        matches.len().saturating_sub(1),
+                    ))),
+                    KeyCode::Enter => {
+                        if let Some(index) = matches.get(selected) {
+                            return done(action, *index);
+                        }
+                    }
+                    _ => {
+                        input.input(kev);
+                        *matches = fuzzy_filter(&input.get_value(), items);
+                        list_state.select(Some(0));
+                    }
+                }
+                ModalOutcome::Pending
+            }
+        }
+    }
+}
+
+fn done<T: Send + Sync + 'static>(action: &mut Option<ModalAction<T>>, value: T) -> ModalOutcome {
+    match action.take() {
+        Some(action) => ModalOutcome::Done(Box::new(AppMessage::Closure(Box::new(move |state| {
+            action(state, value)
+        })))),
+        None => ModalOutcome::Cancelled,
+    }
+}
+
+/// Open a modal above the active tab.
+pub fn open_modal(modal: Modal) {
+    if let Some(sender) = MESSAGE_SENDER.get() {
+        let _ = sender.send(AppMessage::Closure(Box::new(|app_state: &mut AppState| {
+            app_state.modals.push(modal);
+            vec![]
+        })));
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ModalPopup {}
+
+impl StatefulWidget for ModalPopup {
+    type State = Modal;
+
+    fn render(self, area: Rect, buf: &mut Buffer, modal: &mut Modal) {
+        let width = area.width.min(60);
+        let message_lines = (modal.message.len() as u16 / width.saturating_sub(2).max(1)) + 1;
+        let content_lines = match &modal.kind {
+            ModalKind::Confirm(_) => 0,
+            ModalKind::Prompt(..) | ModalKind::Password(..) => 2,
+            ModalKind::Picker(items, ..) => Ord::min(items.len() as u16, 10) + 1,
+            ModalKind::FuzzyPicker(..) => 12,
+        };
+        let height = area.height.min(message_lines + content_lines + 4);
+        let popup = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .border_type(BorderType::Rounded)
+            .title(format!(" {} ", modal.title));
+        let inner = block.inner(popup);
+        Clear.render(popup, buf);
+        block.render(popup, buf);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(message_lines),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(inner);
+        Paragraph::new(modal.message.as_str())
+            .wrap(Wrap { trim: true })
+            .render(chunks[0], buf);
+        let key_style = Style::default().fg(Color::White).bg(Color::Magenta);
+        let submit = match &mut modal.kind {
+            ModalKind::Confirm(_) => " Confirm",
+            ModalKind::Prompt(input, _) | ModalKind::Password(input, _) => {
+                let area = Rect {
+                    height: chunks[1].height.min(1),
+                    ..chunks[1]
+                };
+                let (text, style) = Input::default().render_text(area, input);
+                Paragraph::new(text).style(style).render(area, buf);
+                " Submit"
+            }
+            ModalKind::Picker(items, list_state, _) => {
+                let items: Vec<ListItem> =
+                    items.iter().map(|i| ListItem::new(i.as_str())).collect();
+                let list = List::new(items)
+                    .highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+                StatefulWidget::render(list, chunks[1], buf, list_state);
+                " Pick"
+            }
+            ModalKind::FuzzyPicker(input, items, matches, list_state, _) => {
+                let area = Rect {
+                    height: chunks[1].height.min(1),
+                    ..chunks[1]
+                };
+                let (text, style) = Input::default().render_text(area, input);
+                Paragraph::new(text).style(style).render(area, buf);
+                let list_area = Rect {
+                    y: chunks[1].y + area.height,
+                    height: chunks[1].height.saturating_sub(area.height),
+                    ..chunks[1]
+                };
+                let items: Vec<ListItem> = matches
+                    .iter()
+                    .map(|i| ListItem::new(items[*i].as_str()))
+                    .collect();
+                let list = List::new(items)
+                    .highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+                StatefulWidget::render(list, list_area, buf, list_state);
+                " Pick"
+            }
+        };
+        let mut footer = vec![
+            Span::styled(" Enter ", key_style),
+            Span::raw(submit),
+            Span::raw("    "),
+            Span::styled(" Esc ", key_style),
+            Span::raw(" Cancel"),
+        ];
+        if let ModalKind::Password(input, _) = &modal.kind {
+            footer.push(Span::raw("    "));
+            footer.push(Span::styled(" Ctrl+R ", key_style));
+            footer.push(Span::raw(if input.is_revealed() {
+                " Hide"
+            } else {
+                " Reveal"
+            }));
+        }
+        Paragraph::new(Spans::from(footer)).render(chunks[2], buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{message_sender, state};
+    use crate::views::TabState;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn modal_captures_keys() {
+        message_sender();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut state = AppState::default();
+        state.modals.push(Modal::prompt(
+            "Rename",
+            "New title",
+            "",
+            |app_state: &mut AppState, value| {
+                app_state.notice = Some(value);
+                vec![]
+            },
+        ));
+        state.register_event(AppEvent::KeyEvent(key(KeyCode::Char('h'))));
+        state.register_event(AppEvent::KeyEvent(key(KeyCode::Char('i'))));
+        assert!(matches!(state.tab, TabState::MovieManager(_)));
+        match state.modals[0].input(key(KeyCode::Enter)) {
+            ModalOutcome::Done(msg) => match *msg {
+                AppMessage::Closure(action) => {
+                    action(&mut state);
+                }
+                _ => panic!("the modal action is a closure"),
+            },
+            _ => panic!("the prompt is submitted"),
+        }
+        assert_eq!(state.notice.as_deref(), Some("hi"));
+        state.register_event(AppEvent::KeyEvent(key(KeyCode::Esc)));
+        assert!(state.modals.is_empty());
+    }
+
+    #[test]
+    fn esc_closes_the_popups_first() {
+        message_sender();
+        let mut state = state();
+        state.modals.push(Modal::confirm(
+            "Delete",
+            "Delete the NFO?",
+            |_: &mut AppState| vec![],
+        ));
+        assert!(!state.press_key(KeyCode::Esc.into()));
+        assert!(state.modals.is_empty());
+        state.show_help = true;
+        assert!(!state.press_key(KeyCode::Esc.into()));
+        assert!(!state.show_help);
+        assert!(state.press_key(KeyCode::Esc.into()));
+    }
+}
@@ -0,0 +1,1006 @@
+use anyhow::{anyhow, Result};
+use crossterm::event::KeyCode;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{
+        Block, BorderType, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+        Wrap,
+    },
+};
+use url::Url;
+
+use crate::asyncfs::{AsyncMultiFs, Operation};
+use crate::config::{
+    ArtworkTypes, NfoDialect, Normalization, Renamer, SafetyRails, TmdbPreferences,
+};
+use crate::library::{LibraryId, NameRules};
+use crate::multifs::MultiFs;
+use crate::nfo::Movie;
+use crate::provider::MetadataProvider;
+use crate::views::modal::Modal;
+use crate::views::movie_manager::{
+    keep_watch_state_and_store, rename_movie, store_nfo, MovieManagerEvent, MovieManagerMessage,
+};
+use crate::{AppEvent, AppMessage, AppState, ConnectionPool, MESSAGE_SENDER};
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum BatchOp {
+    CreateNfo((u64, LibraryId, PathBuf)), // tmdb_id, fs_id, movie_path
+    SaveNfo((Movie, LibraryId, PathBuf)),
+    Rename((Movie, LibraryId, PathBuf)),
+    ProbeEpisode((LibraryId, PathBuf)), // fs_id, episode_path
+}
+
+impl BatchOp {
+    pub fn is_nfo_write(&self) -> bool {
+        matches!(
+            self,
+            BatchOp::CreateNfo(_) | BatchOp::SaveNfo(_) | BatchOp::ProbeEpisode(_)
+        )
+    }
+
+    pub fn is_rename(&self) -> bool {
+        matches!(self, BatchOp::Rename(_))
+    }
+
+    pub fn library(&self) -> LibraryId {
+        match self {
+            BatchOp::CreateNfo((_, fs_id, _))
+            | BatchOp::SaveNfo((_, fs_id, _))
+            | BatchOp::Rename((_, fs_id, _))
+            | BatchOp::ProbeEpisode((fs_id, _)) => *fs_id,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            BatchOp::CreateNfo((tmdb_id, _, path)) => {
+                format!("Create NFO (tmdb: {}) for {}", tmdb_id, path.display())
+            }
+            BatchOp::SaveNfo((_, _, path)) => format!("Save NFO for {}", path.display()),
+            BatchOp::Rename((_, _, path)) => format!("Rename {}", path.display()),
+            BatchOp::ProbeEpisode((_, path)) => format!("Probe episode {}", path.display()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchSummary {
+    pub touched: Vec<PathBuf>,
+    pub failed: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchCheck {
+    Proceed,
+    Confirm,
+    Refuse(String),
+}
+
+pub fn check_batch(ops: &[BatchOp], rails: &SafetyRails) -> BatchCheck {
+    let writes = ops.iter().filter(|op| op.is_nfo_write()).count();
+    let renames = ops.iter().filter(|op| op.is_rename()).count();
+    if writes > rails.max_nfo_writes {
+        BatchCheck::Refuse(format!(
+            "Batch refused: {} NFO writes exceed the limit of {}.",
+            writes, rails.max_nfo_writes
+        ))
+    } else if renames > rails.max_renames {
+        BatchCheck::Refuse(format!(
+            "Batch refused: {} renames exceed the limit of {}.",
+            renames, rails.max_renames
+        ))
+    } else if writes + renames > rails.confirm_above {
+        BatchCheck::Confirm
+    } else {
+        BatchCheck::Proceed
+    }
+}
+
+/// Settings captured from the AppState before running a batch.
+#[derive(Clone, Debug)]
+pub struct BatchContext {
+    pub renamer: Renamer,
+    pub lib_urls: HashMap<LibraryId, Url>,
+    pub tmdb_preferences: HashMap<LibraryId, TmdbPreferences>,
+    pub probe_media: HashMap<LibraryId, bool>,
+    pub name_rules: HashMap<LibraryId, NameRules>,
+    pub nfo_dialect: NfoDialect,
+    pub normalization: Normalization,
+    pub artworks: ArtworkTypes,
+}
+
+impl From<&AppState> for BatchContext {
+    fn from(app_state: &AppState) -> Self {
+        Self {
+            renamer: app_state.config.renamer.clone(),
+            lib_urls: app_state
+                .libraries
+                .iter()
+                .flatten()
+                .filter_map(|lib| Some((lib.id, lib.ffmpeg_base().ok()?)))
+                .collect(),
+            tmdb_preferences: app_state
+                .libraries
+                .iter()
+                .flatten()
+                .map(|lib| {
+                    let prefs = app_state.config.tmdb_preferences.for_library(Some(lib));
+                    (lib.id, prefs)
+                })
+                .collect(),
+            probe_media: app_state
+                .libraries
+                .iter()
+                .flatten()
+                .map(|lib| (lib.id, app_state.config.probing.for_library(Some(lib))))
+                .collect(),
+            name_rules: app_state
+                .libraries
+                .iter()
+                .flatten()
+                .map(|lib| (lib.id, lib.name_rules()))
+                .collect(),
+            nfo_dialect: app_state.config.nfo_dialect,
+            normalization: app_state.config.normalization.clone(),
+            artworks: app_state.config.artworks.clone(),
+        }
+    }
+}
+
+/// One ProbeEpisode operation per video file of the season directory.
+pub fn probe_season_ops(
+    fs: &mut MultiFs,
+    fs_id: LibraryId,
+    season_dir: &Path,
+) -> Result<Vec<BatchOp>> {
+    let entries = fs.as_mut_rfs().list_dir(season_dir).map_err(|err| {
+        anyhow!(
+            "Failed to open directory {}, causes:\n{:?}",
+            season_dir.display(),
+            err
+        )
+    })?;
+    let mut episodes: Vec<PathBuf> = entries
+        .into_iter()
+        .filter(|entry| entry.is_file())
+        .map(|entry| entry.path().to_owned())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| {
+                crate::VIDEO_EXTENSIONS.contains(&ext.to_string_lossy().as_ref())
+            })
+        })
+        .collect();
+    episodes.sort();
+    Ok(episodes
+        .into_iter()
+        .map(|path| BatchOp::ProbeEpisode((fs_id, path)))
+        .collect())
+}
+
+/// One SaveNfo operation per movie whose countries or studios the normalization changes.
+pub fn normalize_ops(
+    movies: Vec<(Movie, LibraryId, PathBuf)>,
+    normalization: &Normalization,
+) -> Vec<BatchOp> {
+    movies
+        .into_iter()
+        .filter_map(|(mut movie, fs_id, path)| {
+            normalization
+                .apply(&mut movie)
+                .then(|| BatchOp::SaveNfo((movie, fs_id, path)))
+        })
+        .collect()
+}
+
+/// Operations of a running batch not done yet, kept on disk to resume them after a crash.
+///
+/// The journal is rewritten as the operations end and removed once the batch ends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchJournal {
+    path: PathBuf,
+}
+
+impl BatchJournal {
+    /// A new journal in `dir`, several batches can run at the same time.
+    pub fn create(dir: &Path) -> BatchJournal {
+        BatchJournal {
+            path: dir.join(format!("{:016x}.json", rand::random::<u64>())),
+        }
+    }
+
+    pub fn save(&self, ops: &[BatchOp]) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_vec(ops)?)?;
+        Ok(())
+    }
+
+    pub fn remove(self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            log::warn!(
+                "Failed to remove the batch journal {}:\n{:?}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Journals left in `dir` by the batches interrupted in a previous run, and their operations.
+pub fn interrupted_batches(dir: &Path) -> (Vec<PathBuf>, Vec<BatchOp>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (vec![], vec![]);
+    };
+    let mut journals: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    journals.sort();
+    let mut ops = Vec::new();
+    for path in &journals {
+        let parsed: Result<Vec<BatchOp>> = std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| serde_json::from_slice(&bytes).map_err(anyhow::Error::from));
+        match parsed {
+            Ok(journal_ops) => ops.extend(journal_ops),
+            Err(err) => log::warn!(
+                "Ignoring the unreadable batch journal {}:\n{:?}",
+                path.display(),
+                err
+            ),
+        }
+    }
+    (journals, ops)
+}
+
+/// Ask whether to resume the operations of the interrupted batches, their journals are
+/// removed either way (the resumed batch keeps its own).
+pub fn resume_modal(journals: Vec<PathBuf>, ops: Vec<BatchOp>) -> Modal {
+    let message = format!(
+        "MKube was closed before the end of a batch, {} operation(s) were not done.",
+        ops.len()
+    );
+    let items = vec![
+        format!("Resume the {} remaining operation(s)", ops.len()),
+        "Discard them".into(),
+    ];
+    Modal::picker(
+        "Interrupted batch",
+        message,
+        items,
+        move |_: &mut AppState, index: usize| {
+            for path in &journals {
+                let _ = std::fs::remove_file(path);
+            }
+            if index == 0 {
+                let _ = MESSAGE_SENDER
+                    .get()
+                    .unwrap()
+                    .send(MovieManagerMessage::RunBatch(ops).into());
+            }
+            vec![]
+        },
+    )
+}
+
+/// Run the operations of a batch, as many at a time as the [`probing::pool`] has workers.
+///
+/// The operations only hold the library connections while they use them, so ffmpeg probes
+/// run side by side. Their events are sent as soon as each one is done, only the summary is
+/// returned.
+///
+/// [`probing::pool`]: crate::probing::pool
+pub async fn run_batch(
+    ops: Vec<BatchOp>,
+    ctx: BatchContext,
+    journal: Option<BatchJournal>,
+    tmdb_client: &dyn MetadataProvider,
+    conns: &ConnectionPool,
+) -> Vec<AppEvent> {
+    let mut summary = BatchSummary::default();
+    let mut pending: BTreeMap<usize, BatchOp> = ops.iter().cloned().enumerate().collect();
+    let mut running = futures_util::stream::iter(ops.into_iter().enumerate())
+        .map(|(index, op)| {
+            let ctx = &ctx;
+            async move {
+                let description = op.describe();
+                (index, description, run_op(op, ctx, tmdb_client, conns).await)
+            }
+        })
+        .buffer_unordered(crate::probing::pool().workers());
+    if let Some(journal) = &journal {
+        save_journal(journal, &pending);
+    }
+    while let Some((index, description, res)) = running.next().await {
+        match res {
+            Ok((touched, evt)) => {
+                summary.touched.extend(touched);
+                if let (Some(evt), Some(sender)) = (evt, MESSAGE_SENDER.get()) {
+                    let evt = AppEvent::MovieManagerEvent(evt);
+                    let _ = sender.send(AppMessage::TriggerEvent(evt));
+                }
+            }
+            Err(err) => {
+                log::error!("Batch operation failed ({}):\n{:?}", &description, err);
+                summary.failed.push(description);
+            }
+        }
+        pending.remove(&index);
+        if let Some(journal) = &journal {
+            save_journal(journal, &pending);
+        }
+    }
+    log::info!(
+        "Batch done: {} file(s) touched, {} failure(s).",
+        summary.touched.len(),
+        summary.failed.len()
+    );
+    for path in &summary.touched {
+        log::info!("Batch touched: {}", path.display());
+    }
+    if let Some(journal) = journal {
+        journal.remove();
+    }
+    vec![AppEvent::MovieManagerEvent(MovieManagerEvent::BatchDone(
+        summary,
+    ))]
+}
+
+/// Keep the operations not done yet, the running ones included.
+fn save_journal(journal: &BatchJournal, pending: &BTreeMap<usize, BatchOp>) {
+    let ops: Vec<BatchOp> = pending.values().cloned().collect();
+    if let Err(err) = journal.save(&ops) {
+        log::warn!("Failed to save the batch journal:\n{:?}", err);
+    }
+}
+
+async fn run_op(
+    op: BatchOp,
+    ctx: &BatchContext,
+    tmdb_client: &dyn MetadataProvider,
+    conns: &ConnectionPool,
+) -> Result<(Vec<PathBuf>, Option<MovieManagerEvent>)> {
+    match op {
+        BatchOp::CreateNfo((tmdb_id, fs_id, path)) => {
+            let lib_url = ctx
+                .lib_urls
+                .get(&fs_id)
+                .cloned()
+                .ok_or(anyhow!("library {} creates an unexpected URL.", fs_id))?;
+            let prefs = ctx
+                .tmdb_preferences
+                .get(&fs_id)
+                .cloned()
+                .unwrap_or_default();
+            let mut movie_nfo = crate::transform_as_nfo(
+                tmdb_client,
+                tmdb_id,
+                &prefs.languages(),
+                prefs.certification_country(),
+                &ctx.artworks,
+            )
+            .await?;
+            if ctx.probe_media.get(&fs_id).copied().unwrap_or(true) {
+                movie_nfo.fileinfo =
+                    Some(crate::get_metadata(conns, fs_id, lib_url, path.clone()).await?);
+            }
+            let parsed = crate::parser::parse_path(&path);
+            movie_nfo.source = parsed.source;
+            movie_nfo.edition = parsed.edition;
+            ctx.normalization.apply(&mut movie_nfo);
+            let dialect = ctx.nfo_dialect;
+            let (movie_nfo, path) = AsyncMultiFs::new(conns, fs_id)
+                .run(move |fs| keep_watch_state_and_store(fs, movie_nfo, dialect, path))
+                .await?;
+            Ok((
+                vec![path.with_extension("nfo")],
+                Some(MovieManagerEvent::MovieUpdated((movie_nfo, fs_id, path))),
+            ))
+        }
+        BatchOp::SaveNfo((mut nfo, fs_id, path)) => {
+            ctx.normalization.apply(&mut nfo);
+            let nfo = nfo.for_dialect(ctx.nfo_dialect);
+            let (nfo, path, nfo_path) = AsyncMultiFs::new(conns, fs_id)
+                .run(move |fs| store_nfo(fs, &nfo, &path).map(|nfo_path| (nfo, path, nfo_path)))
+                .await?;
+            Ok((
+                vec![nfo_path],
+                Some(MovieManagerEvent::MovieUpdated((nfo, fs_id, path))),
+            ))
+        }
+        BatchOp::Rename((nfo, fs_id, path)) => {
+            let rules = ctx.name_rules.get(&fs_id).copied().unwrap_or_default();
+            let renamer = ctx.renamer.clone();
+            let (path, new_path, touched) = AsyncMultiFs::new(conns, fs_id)
+                .for_operation(Operation::Rename)
+                .run(move |fs| {
+                    rename_movie(fs, &renamer, &rules, &nfo, &path)
+                        .map(|(new_path, touched)| (path, new_path, touched))
+                })
+                .await?;
+            Ok((
+                touched,
+                Some(MovieManagerEvent::MovieMoved((fs_id, path, new_path))),
+            ))
+        }
+        BatchOp::ProbeEpisode((fs_id, path)) => {
+            let lib_url = ctx
+                .lib_urls
+                .get(&fs_id)
+                .cloned()
+                .ok_or(anyhow!("library {} creates an unexpected URL.", fs_id))?;
+            let (_, nfo_path) = crate::probe_episode(conns, fs_id, lib_url, path).await?;
+            Ok((vec![nfo_path], None))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchConfirm {}
+
+#[derive(Clone, Debug, Default)]
+pub struct BatchConfirmState {
+    pub ops: Vec<BatchOp>,
+    pub scroll: u16,
+}
+
+impl StatefulWidget for BatchConfirm {
+    type State = BatchConfirmState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" Confirm batch ");
+        let writes = state.ops.iter().filter(|op| op.is_nfo_write()).count();
+        let renames = state.ops.iter().filter(|op| op.is_rename()).count();
+        let mut content = vec![
+            Spans::from(Span::styled(
+                format!(
+                    "This batch will write {} NFO(s) and rename {} movie(s). Continue?",
+                    writes, renames
+                ),
+                Style::default().fg(Color::Yellow),
+            )),
+            Spans::from(vec![
+                Span::styled(" y ", Style::default().fg(Color::White).bg(Color::Magenta)),
+                Span::raw(" Run the batch"),
+                Span::raw("    "),
+                Span::styled(" n ", Style::default().fg(Color::White).bg(Color::Magenta)),
+                Span::raw(" Cancel"),
+            ]),
+            Spans::from(vec![]),
+        ];
+        content.extend(state.ops.iter().map(|op| Spans::from(op.describe())));
+        Paragraph::new(content)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .scroll((state.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+impl BatchConfirmState {
+    pub fn new(ops: Vec<BatchOp>) -> Self {
+        Self { ops, scroll: 0 }
+    }
+
+    pub fn input(&mut self, app_event: AppEvent) -> bool {
+        match app_event {
+            AppEvent::KeyEvent(kev) => match kev.code {
+                KeyCode::Char('y') => {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    let ops = std::mem::take(&mut self.ops);
+                    sender
+                        .send(MovieManagerMessage::RunBatch(ops).into())
+                        .unwrap();
+                    sender
+                        .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::OpenTable,
+                        )))
+                        .unwrap();
+                    true
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::OpenTable,
+                        )))
+                        .unwrap();
+                    true
+                }
+                KeyCode::Up => {
+                    self.scroll = self.scroll.saturating_sub(1);
+                    true
+                }
+                KeyCode::Down => {
+                    self.scroll = self.scroll.saturating_add(1);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// A movie matched by the auto-identify, its NFO is only created once the change is accepted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlannedChange {
+    pub op: BatchOp,
+    /// The title and year the movie has now.
+    pub current: String,
+    /// The title and year of the TMDB match.
+    pub matched: String,
+    pub changes: Vec<String>,
+    pub accepted: bool,
+}
+
+impl PlannedChange {
+    /// Create the NFO of the movie from its TMDB match, accepted until rejected in the review.
+    pub fn new(
+        movie: &Movie,
+        fs_id: LibraryId,
+        path: PathBuf,
+        found: &tmdb_api::movie::MovieShort,
+    ) -> Self {
+        let year: Option<u16> = found
+            .inner
+            .release_date
+            .and_then(|date| date.format("%Y").to_string().parse().ok());
+        let with_year = |title: &str, year: Option<String>| match year {
+            Some(year) => format!("{} ({})", title, year),
+            None => title.to_owned(),
+        };
+        let current_year = movie.premiered.as_deref().and_then(|date| date.get(..4));
+        Self {
+            op: BatchOp::CreateNfo((found.inner.id, fs_id, path)),
+            current: with_year(&movie.title, current_year.map(String::from)),
+            matched: with_year(&found.inner.title, year.map(|year| year.to_string())),
+            changes: planned_fields(
+                movie,
+                found.inner.id,
+                &found.inner.title,
+                &found.inner.original_title,
+                year,
+            ),
+            accepted: true,
+        }
+    }
+}
+
+/// The fields of `movie` that creating its NFO from the TMDB match will change.
+pub fn planned_fields(
+    movie: &Movie,
+    tmdb_id: u64,
+    title: &str,
+    original_title: &str,
+    year: Option<u16>,
+) -> Vec<String> {
+    let show = |value: Option<String>| value.unwrap_or_else(|| "(none)".into());
+    let current_year = movie.premiered.as_deref().and_then(|date| date.get(..4));
+    let original = Some(original_title).filter(|original| *original != title);
+    let mut changes = Vec::new();
+    if movie.title != title {
+        changes.push(format!("Title: {} -> {}", movie.title, title));
+    }
+    if movie.original_title.as_deref() != original {
+        changes.push(format!(
+            "Original title: {} -> {}",
+            show(movie.original_title.clone()),
+            show(original.map(String::from))
+        ));
+    }
+    let year = year.map(|year| year.to_string());
+    if current_year != year.as_deref() {
+        changes.push(format!(
+            "Year: {} -> {}",
+            show(current_year.map(String::from)),
+            show(year)
+        ));
+    }
+    if movie.tmdb_id() != Some(tmdb_id) {
+        changes.push(format!(
+            "TMDB id: {} -> {}",
+            show(movie.tmdb_id().map(|id| id.to_string())),
+            tmdb_id
+        ));
+    }
+    changes
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchReview {}
+
+#[derive(Clone, Debug, Default)]
+pub struct BatchReviewState {
+    pub planned: Vec<PlannedChange>,
+    pub list_state: ListState,
+}
+
+impl StatefulWidget for BatchReview {
+    type State = BatchReviewState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" Review the planned changes ");
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(inner);
+        let header = vec![
+            Spans::from(Span::styled(
+                format!(
+                    "{} of {} NFO(s) will be created.",
+                    state.accepted().len(),
+                    state.planned.len()
+                ),
+                Style::default().fg(Color::Yellow),
+            )),
+            Spans::from(vec![
+                Span::styled(
+                    " Space ",
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Accept/reject"),
+                Span::raw("    "),
+                Span::styled(" a ", Style::default().fg(Color::White).bg(Color::Magenta)),
+                Span::raw(" All/none"),
+                Span::raw("    "),
+                Span::styled(
+                    " Enter ",
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Run the accepted"),
+                Span::raw("    "),
+                Span::styled(
+                    " Esc ",
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Cancel"),
+            ]),
+        ];
+        Paragraph::new(header)
+            .wrap(Wrap { trim: true })
+            .render(chunks[0], buf);
+        let items: Vec<ListItem> = state
+            .planned
+            .iter()
+            .map(|change| {
+                let (mark, color) = if change.accepted {
+                    ("[x]", Color::LightGreen)
+                } else {
+                    ("[ ]", Color::Gray)
+                };
+                let mut lines = vec![Spans::from(vec![
+                    Span::styled(mark, Style::default().fg(color)),
+                    Span::raw(format!(" {} => {}", change.current, change.matched)),
+                ])];
+                lines.extend(
+                    change
+                        .changes
+                        .iter()
+                        .map(|field| Spans::from(format!("      {}", field))),
+                );
+                ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let list =
+            List::new(items).highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+        StatefulWidget::render(list, chunks[1], buf, &mut state.list_state);
+    }
+}
+
+impl BatchReviewState {
+    pub fn new(planned: Vec<PlannedChange>) -> Self {
+        let mut list_state = ListState::default();
+        list_state.select((!planned.is_empty()).then_some(0));
+        Self {
+            planned,
+            list_state,
+        }
+    }
+
+    /// The operations of the accepted changes.
+    pub fn accepted(&self) -> Vec<BatchOp> {
+        self.planned
+            .iter()
+            .filter(|change| change.accepted)
+            .map(|change| change.op.clone())
+            .collect()
+    }
+
+    pub fn input(&mut self, app_event: AppEvent) -> bool {
+        let AppEvent::KeyEvent(kev) = app_event else {
+            return false;
+        };
+        let selected = self.list_state.selected();
+        match kev.code {
+            KeyCode::Char(' ') => {
+                if let Some(change) = selected.and_then(|index| self.planned.get_mut(index)) {
+                    change.accepted = !change.accepted;
+                }
+                true
+            }
+            KeyCode::Char('a') => {
+                let accept = self.planned.iter().any(|change| !change.accepted);
+                for change in &mut self.planned {
+                    change.accepted = accept;
+                }
+                true
+            }
+            KeyCode::Enter => {
+                let sender = MESSAGE_SENDER.get().unwrap();
+                let ops = self.accepted();
+                log::info!(
+                    "Batch review: {} change(s) accepted, {} rejected.",
+                    ops.len(),
+                    self.planned.len() - ops.len()
+                );
+                sender
+                    .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                        MovieManagerEvent::OpenTable,
+                    )))
+                    .unwrap();
+                if !ops.is_empty() {
+                    sender.send(MovieManagerMessage::Batch(ops).into()).unwrap();
+                }
+                true
+            }
+            KeyCode::Esc => {
+                let sender = MESSAGE_SENDER.get().unwrap();
+                sender
+                    .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                        MovieManagerEvent::OpenTable,
+                    )))
+                    .unwrap();
+                true
+            }
+            KeyCode::Up => {
+                let index = selected.unwrap_or(0).saturating_sub(1);
+                self.list_state.select(Some(index));
+                true
+            }
+            KeyCode::Down if !self.planned.is_empty() => {
+                let index = selected.map_or(0, |index| index + 1);
+                self.list_state
+                    .select(Some(index.min(self.planned.len() - 1)));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchReport {}
+
+#[derive(Clone, Debug, Default)]
+pub struct BatchReportState {
+    pub summary: BatchSummary,
+    pub scroll: u16,
+}
+
+impl StatefulWidget for BatchReport {
+    type State = BatchReportState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" Batch summary ");
+        let mut content = vec![
+            Spans::from(Span::styled(
+                format!(
+                    "{} file(s) touched, {} failure(s). Press Enter to go back.",
+                    state.summary.touched.len(),
+                    state.summary.failed.len()
+                ),
+                Style::default().fg(Color::Yellow),
+            )),
+            Spans::from(vec![]),
+        ];
+        content.extend(
+            state
+                .summary
+                .touched
+                .iter()
+                .map(|path| Spans::from(path.display().to_string())),
+        );
+        content.extend(state.summary.failed.iter().map(|desc| {
+            Spans::from(Span::styled(
+                format!("FAILED: {}", desc),
+                Style::default().fg(Color::LightRed),
+            ))
+        }));
+        Paragraph::new(content)
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .scroll((state.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+impl BatchReportState {
+    pub fn new(summary: BatchSummary) -> Self {
+        Self { summary, scroll: 0 }
+    }
+
+    pub fn input(&mut self, app_event: AppEvent) -> bool {
+        match app_event {
+            AppEvent::KeyEvent(kev) => match kev.code {
+                KeyCode::Enter | KeyCode::Esc => {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::OpenTable,
+                        )))
+                        .unwrap();
+                    true
+                }
+                KeyCode::Up => {
+                    self.scroll = self.scroll.saturating_sub(1);
+                    true
+                }
+                KeyCode::Down => {
+                    self.scroll = self.scroll.saturating_add(1);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::Keybindings;
+    use crate::mockfs::MockFs;
+    use crate::provider::FixtureProvider;
+    use crate::test_util::{entry, library, message_sender, state};
+    use crate::views::movie_manager::MovieManagerState;
+    use crossterm::event::KeyEvent;
+
+    #[test]
+    fn season_probing() {
+        let mut fs = MultiFs::Mock(
+            MockFs::new()
+                .with_file("/shows/Show/Season 1/Show.S01E02.mkv", "")
+                .with_file("/shows/Show/Season 1/Show.S01E01.mkv", "")
+                .with_file("/shows/Show/Season 1/Show.S01E01.nfo", "")
+                .with_dir("/shows/Show/Season 1/Extras"),
+        );
+        let fs_id = LibraryId::new();
+        let ops = probe_season_ops(&mut fs, fs_id, Path::new("/shows/Show/Season 1")).unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                BatchOp::ProbeEpisode((fs_id, "/shows/Show/Season 1/Show.S01E01.mkv".into())),
+                BatchOp::ProbeEpisode((fs_id, "/shows/Show/Season 1/Show.S01E02.mkv".into())),
+            ]
+        );
+        assert!(probe_season_ops(&mut fs, fs_id, Path::new("/shows/Missing")).is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_journals() {
+        let dir = std::env::temp_dir().join(format!("mkube-batches-{}", std::process::id()));
+        let ops = vec![
+            BatchOp::CreateNfo((603, library().id, PathBuf::from("movie/movie.mkv"))),
+            BatchOp::SaveNfo(entry()),
+        ];
+        let journal = BatchJournal::create(&dir);
+        journal.save(&ops).unwrap();
+        let (journals, resumed) = interrupted_batches(&dir);
+        assert_eq!(journals.len(), 1);
+        assert_eq!(resumed, ops);
+        journal.remove();
+
+        // The journal is removed at the end of the batch, even when its operations fail.
+        let conns: ConnectionPool = Default::default();
+        let evts = run_batch(
+            vec![BatchOp::SaveNfo(entry())],
+            BatchContext::from(&state()),
+            Some(BatchJournal::create(&dir)),
+            &FixtureProvider::default(),
+            &conns,
+        )
+        .await;
+        let journals = interrupted_batches(&dir).0;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(journals.is_empty());
+        assert!(matches!(
+            &evts[..],
+            [AppEvent::MovieManagerEvent(MovieManagerEvent::BatchDone(summary))]
+                if summary.failed.len() == 1
+        ));
+    }
+
+    #[test]
+    fn batch_review() {
+        message_sender();
+        let mut movie = Movie {
+            title: "Alien".into(),
+            premiered: Some("1979-05-25".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            planned_fields(&movie, 348, "Alien", "Alien", Some(1979)),
+            vec!["TMDB id: (none) -> 348"]
+        );
+        movie.title = "alien.1979.1080p".into();
+        movie.premiered = None;
+        assert_eq!(
+            planned_fields(&movie, 348, "Alien", "Alien", Some(1979)),
+            vec![
+                "Title: alien.1979.1080p -> Alien",
+                "Year: (none) -> 1979",
+                "TMDB id: (none) -> 348",
+            ]
+        );
+
+        let planned: Vec<PlannedChange> = (1..=3)
+            .map(|tmdb_id| PlannedChange {
+                op: BatchOp::CreateNfo((tmdb_id, entry().1, entry().2)),
+                current: "movie".into(),
+                matched: format!("Movie {}", tmdb_id),
+                changes: vec![],
+                accepted: true,
+            })
+            .collect();
+        let mut review = BatchReviewState::new(planned);
+        let press = |review: &mut BatchReviewState, code| {
+            review.input(AppEvent::KeyEvent(KeyEvent::from(code)))
+        };
+        // The second and third movies are rejected, Down stops on the last one.
+        let (down, space) = (KeyCode::Down, KeyCode::Char(' '));
+        for code in [down, space, down, down, space] {
+            assert!(press(&mut review, code));
+        }
+        let accepted = |review: &BatchReviewState| -> Vec<u64> {
+            review
+                .accepted()
+                .into_iter()
+                .filter_map(|op| match op {
+                    BatchOp::CreateNfo((tmdb_id, _, _)) => Some(tmdb_id),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(accepted(&review), vec![1]);
+        assert!(press(&mut review, KeyCode::Char('a')));
+        assert_eq!(accepted(&review), vec![1, 2, 3]);
+        assert!(press(&mut review, KeyCode::Char('a')));
+        assert!(accepted(&review).is_empty());
+        assert!(press(&mut review, KeyCode::Enter));
+
+        let keys = Keybindings::default();
+        let mut mstate = MovieManagerState::default();
+        let review = AppEvent::MovieManagerEvent(MovieManagerEvent::ReviewBatch(vec![]));
+        assert!(mstate.input(review, &keys));
+        assert!(!mstate.is_table());
+        let open_table = AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable);
+        assert!(mstate.input(open_table, &keys));
+        assert!(mstate.is_table());
+    }
+}
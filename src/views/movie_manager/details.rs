@@ -8,17 +8,26 @@ use tui::{
 };
 
 use crate::nfo::Movie;
+use crate::util::truncate_width;
 use tmdb_api::movie::MovieShort;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct MovieDetails<'a> {
     pub movie: &'a Movie,
+    /// Bonus videos found next to the movie.
+    pub extras: usize,
+    /// Files of the movie, the runtime is only checked against single files.
+    pub parts: usize,
 }
 
 impl<'a> Widget for MovieDetails<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = match self.movie.edition.as_deref() {
+            Some(edition) => format!("{} ({})", self.movie.title, edition),
+            None => self.movie.title.clone(),
+        };
         let block = Block::default()
-            .title(format!(" {} ", self.movie.title))
+            .title(block_title(&title, area.width))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::White))
             .border_type(BorderType::Rounded);
@@ -29,6 +38,23 @@ impl<'a> Widget for MovieDetails<'a> {
             .split(block.inner(area.clone()));
         let label_style = Style::default().fg(Color::LightYellow);
         let value_style = Style::default().fg(Color::Gray);
+        let warning_style = Style::default().fg(Color::LightRed);
+        let mismatch = match self.parts {
+            0 | 1 => self.movie.runtime_mismatch(),
+            _ => None,
+        };
+        let runtime = self
+            .movie
+            .runtime
+            .map(format_duration)
+            .unwrap_or(" N/A ".into());
+        let duration = match mismatch {
+            Some(probed) => Span::styled(
+                format!("{} (file: {})", runtime, format_duration(probed)),
+                warning_style,
+            ),
+            None => Span::styled(runtime, value_style),
+        };
         let content = vec![
             Spans::from(vec![
                 Span::styled("Release date: ", label_style),
@@ -38,16 +64,13 @@ impl<'a> Widget for MovieDetails<'a> {
                 ),
                 Span::raw("    "),
                 Span::styled("Duration: ", label_style),
-                Span::styled(
-                    self.movie
-                        .runtime
-                        .map(format_duration)
-                        .unwrap_or(" N/A ".into()),
-                    value_style,
-                ),
+                duration,
                 Span::raw("    "),
                 Span::styled("Country: ", label_style),
                 Span::styled(self.movie.country.join(", "), value_style),
+                Span::raw("    "),
+                Span::styled("MPAA: ", label_style),
+                Span::styled(self.movie.mpaa.as_deref().unwrap_or("N/A"), value_style),
             ]),
             Spans::from(vec![
                 Span::styled("Genre: ", label_style),
@@ -87,6 +110,12 @@ impl<'a> Widget for MovieDetails<'a> {
                     format!("{:^6}", self.movie.source.as_deref().unwrap_or("N/A")),
                     value_style,
                 ),
+                Span::raw("    "),
+                Span::styled("Rating: ", label_style),
+                Span::styled(format_rating(self.movie), value_style),
+                Span::raw("    "),
+                Span::styled("Extras: ", label_style),
+                Span::styled(self.extras.to_string(), value_style),
             ]),
         ];
         let plot = Spans::from(vec![
@@ -103,6 +132,12 @@ impl<'a> Widget for MovieDetails<'a> {
     }
 }
 
+/// The title padded with spaces, cut to fit between the corners of a block `width` wide.
+fn block_title(title: &str, width: u16) -> String {
+    let max = width.saturating_sub(4) as usize;
+    format!(" {} ", truncate_width(title, max))
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct MovieSearchDetails<'a> {
     pub movie: &'a MovieShort,
@@ -111,7 +146,7 @@ pub struct MovieSearchDetails<'a> {
 impl<'a> Widget for MovieSearchDetails<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let block = Block::default()
-            .title(format!(" {} ", self.movie.inner.title))
+            .title(block_title(&self.movie.inner.title, area.width))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::White))
             .border_type(BorderType::Rounded);
@@ -160,6 +195,29 @@ pub fn format_duration(minutes: u64) -> String {
     }
 }
 
+pub fn format_rating(movie: &Movie) -> String {
+    if let Some(rating) = movie.default_rating() {
+        let mut rating_value = format!("{:.1}/{}", rating.value, rating.max.unwrap_or(10));
+        if let Some(votes) = rating.votes {
+            rating_value += &format!(" ({} votes)", votes);
+        }
+        rating_value
+    } else {
+        "N/A".into()
+    }
+}
+
+/// Display name of a Kodi `hdrtype`.
+pub fn format_hdr(hdr_type: &str) -> &str {
+    match hdr_type {
+        "hdr10" => "HDR10",
+        "hdr10plus" => "HDR10+",
+        "dolbyvision" => "DV",
+        "hlg" => "HLG",
+        other => other,
+    }
+}
+
 pub fn format_media<'a>(movie: &'a Movie) -> String {
     let mut media_value = String::new();
     if let Some(fi) = &movie.fileinfo {
@@ -168,6 +226,9 @@ pub fn format_media<'a>(movie: &'a Movie) -> String {
             if let Some(res) = vt.height {
                 media_value = format!("{} {}p", &media_value, res);
             }
+            if let Some(hdr) = vt.hdr_type.as_deref() {
+                media_value = format!("{} {}", &media_value, format_hdr(hdr));
+            }
         }
         let mut tmpcodec = String::new();
         let mut tmplang = String::new();
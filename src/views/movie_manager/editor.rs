@@ -1,4 +1,4 @@
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::path::PathBuf;
 use tui::{
     buffer::Buffer,
@@ -7,29 +7,44 @@ use tui::{
     symbols::DOT,
     text::{Span, Spans},
     widgets::{
-        Block, BorderType, Borders, Cell, Row, StatefulWidget, Table, TableState, Tabs, Widget,
+        Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row,
+        StatefulWidget, Table, TableState, Tabs, Widget,
     },
 };
 
+use crate::config::ImageSize;
+use crate::library::LibraryId;
 use crate::nfo::{Actor, CrewPerson, Movie, Thumb};
 use crate::views::movie_manager::{MovieManagerEvent, MovieManagerMessage};
-use crate::views::widgets::{Input, InputState};
-use crate::MESSAGE_SENDER;
-use crate::{AppEvent, AppMessage};
+use crate::views::widgets::{
+    DateInput, DateInputState, Input, InputState, TagPicker, TagPickerState, TextArea,
+    TextAreaState,
+};
+use crate::{AppEvent, AppMessage, AppState};
+use crate::{PersonResult, MESSAGE_SENDER};
 
 const FIELDS: [&'static str; 10] = [
     "Title",
     "Original Title",
     "Release Date",
-    "Tagline",
-    "Plot",
     "Genres",
     "Tags",
     "Studio",
     "Countries",
     "Source",
+    "Rating",
+    "Votes",
 ];
 
+/// Row of FIELDS edited with a DateInput, the others map to `fields_value`.
+const RELEASE_DATE: usize = 2;
+
+/// Rows of FIELDS picked among the TMDB genres and countries, mapped to `tag_value`.
+const TAG_FIELDS: [usize; 2] = [3, 6];
+
+/// Long text fields, shown after FIELDS with their height.
+const TEXT_FIELDS: [(&str, u16); 3] = [("Tagline", 2), ("Outline", 3), ("Plot", 6)];
+
 const TAB_NAMES: [&'static str; 6] = [
     "General",
     "Actors",
@@ -45,16 +60,40 @@ pub struct MovieEditor {}
 #[derive(Clone, Debug, Default)]
 pub struct MovieEditorState {
     pub movie_nfo: Movie,
-    pub movie_fs_id: usize,
+    pub movie_fs_id: LibraryId,
     pub movie_path: PathBuf,
     pub table_state: TableState,
-    pub fields_value: [InputState; 10],
+    pub fields_value: [InputState; 7],
+    pub release_date: DateInputState,
+    pub tag_value: [TagPickerState; 2],
+    /// Index in `tag_value` of the open picker.
+    pub open_picker: Option<usize>,
+    pub text_value: [TextAreaState; 3],
     pub actor_state: Vec<[InputState; 4]>,
     pub producer_state: Vec<[InputState; 3]>,
     pub director_state: Vec<[InputState; 3]>,
     pub open_tab: usize,
     pub selected_tab: Option<usize>,
     pub selected_column: usize,
+    /// Non-empty cast/crew row waiting for a delete confirmation.
+    pub pending_delete: Option<usize>,
+    pub person_picker: Option<PersonPicker>,
+    /// The NFO as opened, and the last one sent to be saved.
+    pub initial_nfo: Movie,
+    pub saving_nfo: Option<Movie>,
+    /// Selected button of the unsaved changes popup, when shown.
+    pub leave_prompt: Option<usize>,
+}
+
+const LEAVE_CHOICES: [&str; 3] = ["Save", "Discard", "Stay"];
+
+/// TMDB people matching the name of a cast/crew row, the selected one fills the row.
+#[derive(Clone, Debug, Default)]
+pub struct PersonPicker {
+    pub tab: usize,
+    pub row: usize,
+    pub results: Vec<PersonResult>,
+    pub list_state: ListState,
 }
 
 impl StatefulWidget for MovieEditor {
@@ -94,13 +133,53 @@ impl StatefulWidget for MovieEditor {
         .highlight_style(Style::default().fg(Color::Yellow))
         .divider(DOT);
 
+        let (tab_area, hint_area) = if (1..=3).contains(&state.open_tab) {
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(vec![Constraint::Min(1), Constraint::Length(1)])
+                .split(chunks[1]);
+            (parts[0], Some(parts[1]))
+        } else {
+            (chunks[1], None)
+        };
+        if let Some(hint_area) = hint_area {
+            let hint = match state.pending_delete {
+                Some(row) => Spans::from(Span::styled(
+                    format!(
+                        "Row {} is not empty, press y to delete it (any other key keeps it).",
+                        row + 1
+                    ),
+                    Style::default().fg(Color::Yellow),
+                )),
+                None => Spans::from(vec![
+                    Span::styled(
+                        " Ctrl+D ",
+                        Style::default().fg(Color::White).bg(Color::Magenta),
+                    ),
+                    Span::raw(" Delete row"),
+                    Span::raw("    "),
+                    Span::styled(
+                        " Alt+Up/Down ",
+                        Style::default().fg(Color::White).bg(Color::Magenta),
+                    ),
+                    Span::raw(" Move row"),
+                    Span::raw("    "),
+                    Span::styled(
+                        " Ctrl+F ",
+                        Style::default().fg(Color::White).bg(Color::Magenta),
+                    ),
+                    Span::raw(" Search TMDB (Name)"),
+                ]),
+            };
+            Paragraph::new(hint).render(hint_area, buf);
+        }
         match state.open_tab {
             1 => {
-                self.render_cast_tab(chunks[1], buf, state);
+                self.render_cast_tab(tab_area, buf, state);
             }
             2 => {
                 self.render_crew_tab(
-                    chunks[1],
+                    tab_area,
                     buf,
                     &mut state.producer_state,
                     &mut state.table_state,
@@ -109,7 +188,7 @@ impl StatefulWidget for MovieEditor {
             }
             3 => {
                 self.render_crew_tab(
-                    chunks[1],
+                    tab_area,
                     buf,
                     &mut state.director_state,
                     &mut state.table_state,
@@ -122,21 +201,64 @@ impl StatefulWidget for MovieEditor {
         }
         block.render(area, buf);
         tabs.render(inner, buf);
+        if let Some(picker) = state.person_picker.as_mut() {
+            render_person_picker(tab_area, buf, picker);
+        }
+        if let Some(selected) = state.leave_prompt {
+            render_leave_prompt(tab_area, buf, selected);
+        }
+        if let Some(picker) = state.open_picker {
+            let width = tab_area.width.min(50);
+            let height = tab_area.height.min(20);
+            let popup = Rect::new(
+                tab_area.x + (tab_area.width - width) / 2,
+                tab_area.y + (tab_area.height - height) / 2,
+                width,
+                height,
+            );
+            let title = FIELDS[TAG_FIELDS[picker]].to_owned();
+            StatefulWidget::render(
+                TagPicker {
+                    title,
+                    ..Default::default()
+                },
+                popup,
+                buf,
+                &mut state.tag_value[picker],
+            );
+        }
+    }
+}
+
+/// Index in `fields_value` of a general tab row.
+fn field_index(row: usize) -> Option<usize> {
+    if row == RELEASE_DATE || TAG_FIELDS.contains(&row) || row >= FIELDS.len() {
+        return None;
     }
+    let skipped = TAG_FIELDS.iter().filter(|r| **r < row).count() + (row > RELEASE_DATE) as usize;
+    Some(row - skipped)
 }
 
 impl MovieEditorState {
-    pub fn with(mut self, movie_nfo: Movie, movie_fs_id: usize, movie_path: PathBuf) -> Self {
+    pub fn with(mut self, movie_nfo: Movie, movie_fs_id: LibraryId, movie_path: PathBuf) -> Self {
         self.fields_value[0].set_value(&movie_nfo.title);
         self.fields_value[1].set_value(movie_nfo.original_title.as_deref().unwrap_or(""));
-        self.fields_value[2].set_value(movie_nfo.premiered.as_deref().unwrap_or(""));
-        self.fields_value[3].set_value(movie_nfo.tagline.as_deref().unwrap_or(""));
-        self.fields_value[4].set_value(movie_nfo.plot.as_deref().unwrap_or(""));
-        self.fields_value[5].set_value(movie_nfo.genre.join(", "));
-        self.fields_value[6].set_value(movie_nfo.tag.join(", "));
-        self.fields_value[7].set_value(movie_nfo.studio.join(", "));
-        self.fields_value[8].set_value(movie_nfo.country.join(", "));
-        self.fields_value[9].set_value(movie_nfo.source.as_deref().unwrap_or(""));
+        self.release_date
+            .set_value(movie_nfo.premiered.as_deref().unwrap_or(""));
+        self.tag_value[0].set_selected(movie_nfo.genre.clone());
+        self.fields_value[2].set_value(movie_nfo.tag.join(", "));
+        self.fields_value[3].set_value(movie_nfo.studio.join(", "));
+        self.tag_value[1].set_selected(movie_nfo.country.clone());
+        self.fields_value[4].set_value(movie_nfo.source.as_deref().unwrap_or(""));
+        if let Some(rating) = movie_nfo.default_rating() {
+            self.fields_value[5].set_value(format!("{}", rating.value));
+            if let Some(votes) = rating.votes {
+                self.fields_value[6].set_value(format!("{}", votes));
+            }
+        }
+        self.text_value[0].set_value(movie_nfo.tagline.as_deref().unwrap_or(""));
+        self.text_value[1].set_value(movie_nfo.outline.as_deref().unwrap_or(""));
+        self.text_value[2].set_value(movie_nfo.plot.as_deref().unwrap_or(""));
         self.actor_state = movie_nfo
             .actor
             .iter()
@@ -155,12 +277,108 @@ impl MovieEditorState {
             .collect();
         self.producer_state = movie_nfo.producer.iter().map(crew_to_inputs).collect();
         self.director_state = movie_nfo.director.iter().map(crew_to_inputs).collect();
-        Self {
+        let mut state = Self {
             movie_nfo,
             movie_fs_id,
             movie_path,
             ..self
+        };
+        state.initial_nfo = state.get_nfo();
+        state
+    }
+
+    /// Whether some edits are neither in the opened NFO nor being saved.
+    pub fn is_dirty(&self) -> bool {
+        let nfo = self.get_nfo();
+        nfo != self.initial_nfo && self.saving_nfo.as_ref() != Some(&nfo)
+    }
+
+    /// Ask to save or discard the edits before leaving the editor.
+    pub fn confirm_leave(&mut self) {
+        self.leave_prompt = Some(0);
+    }
+
+    fn save(&mut self) {
+        let sender = MESSAGE_SENDER.get().unwrap();
+        if let Err(err) = self.release_date.date() {
+            let notice = format!("Not saved, Release Date: {}", err);
+            sender
+                .send(AppMessage::Closure(Box::new(
+                    move |app_state: &mut AppState| {
+                        app_state.notice = Some(notice);
+                        vec![]
+                    },
+                )))
+                .unwrap();
+            return;
+        }
+        let nfo = self.get_nfo();
+        self.saving_nfo = Some(nfo.clone());
+        sender
+            .send(
+                MovieManagerMessage::SaveNfo((nfo, self.movie_fs_id, self.movie_path.clone()))
+                    .into(),
+            )
+            .unwrap();
+    }
+
+    fn leave_prompt_input(&mut self, kev: KeyEvent) {
+        let Some(selected) = self.leave_prompt else {
+            return;
+        };
+        let choice = match kev.code {
+            KeyCode::Left | KeyCode::BackTab => {
+                self.leave_prompt =
+                    Some((selected + LEAVE_CHOICES.len() - 1) % LEAVE_CHOICES.len());
+                return;
+            }
+            KeyCode::Right | KeyCode::Tab => {
+                self.leave_prompt = Some((selected + 1) % LEAVE_CHOICES.len());
+                return;
+            }
+            KeyCode::Enter => selected,
+            KeyCode::Char('s') => 0,
+            KeyCode::Char('d') => 1,
+            KeyCode::Esc => 2,
+            _ => return,
+        };
+        self.leave_prompt = None;
+        match choice {
+            0 => self.save(),
+            1 => {
+                // Back to the opened NFO, the editor can then be left.
+                *self = MovieEditorState::default().with(
+                    self.movie_nfo.clone(),
+                    self.movie_fs_id,
+                    self.movie_path.clone(),
+                );
+                MESSAGE_SENDER
+                    .get()
+                    .unwrap()
+                    .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                        MovieManagerEvent::OpenTable,
+                    )))
+                    .unwrap();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_taxonomy(&mut self, taxonomy: &crate::Taxonomy) {
+        self.tag_value[0].set_options(taxonomy.genres.clone());
+        self.tag_value[1].set_options(taxonomy.countries.clone());
+    }
+
+    fn open_picker(&mut self, picker: usize) {
+        if !self.tag_value[picker].has_options() {
+            MESSAGE_SENDER
+                .get()
+                .unwrap()
+                .send(MovieManagerMessage::FetchTaxonomy.into())
+                .unwrap();
         }
+        self.tag_value[picker].reset();
+        self.open_picker = Some(picker);
     }
 
     pub fn table_len(&self) -> usize {
@@ -168,7 +386,7 @@ impl MovieEditorState {
             1 => self.actor_state.len() + 1,
             2 => self.producer_state.len() + 1,
             3 => self.director_state.len() + 1,
-            _ => FIELDS.len(),
+            _ => FIELDS.len() + TEXT_FIELDS.len(),
         }
     }
 
@@ -180,23 +398,178 @@ impl MovieEditorState {
         }
     }
 
+    /// Delete or move the selected cast/crew row, returns false for other keys.
+    fn edit_rows(&mut self, kev: KeyEvent) -> bool {
+        let Some(row) = self
+            .table_state
+            .selected()
+            .filter(|v| *v + 1 < self.table_len())
+        else {
+            return false;
+        };
+        let delta = match kev.code {
+            KeyCode::Char('d') if kev.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.row_is_empty(row) {
+                    self.delete_row(row);
+                } else {
+                    self.pending_delete = Some(row);
+                }
+                return true;
+            }
+            KeyCode::Char('f') if kev.modifiers.contains(KeyModifiers::CONTROL) => {
+                let name = match self.open_tab {
+                    1 => self.actor_state.get(row).map(|r| r[0].get_value()),
+                    2 => self.producer_state.get(row).map(|r| r[0].get_value()),
+                    3 => self.director_state.get(row).map(|r| r[0].get_value()),
+                    _ => None,
+                };
+                if let Some(name) = name.filter(|n| !n.trim().is_empty()) {
+                    MESSAGE_SENDER
+                        .get()
+                        .unwrap()
+                        .send(
+                            MovieManagerMessage::SearchPerson((
+                                name.trim().to_owned(),
+                                self.open_tab,
+                                row,
+                            ))
+                            .into(),
+                        )
+                        .unwrap();
+                }
+                return true;
+            }
+            KeyCode::Up if kev.modifiers.contains(KeyModifiers::ALT) => -1,
+            KeyCode::Down if kev.modifiers.contains(KeyModifiers::ALT) => 1,
+            _ => return false,
+        };
+        let moved = match self.open_tab {
+            1 => move_row(&mut self.actor_state, row, delta),
+            2 => move_row(&mut self.producer_state, row, delta),
+            3 => move_row(&mut self.director_state, row, delta),
+            _ => None,
+        };
+        if let Some(moved) = moved {
+            self.table_state.select(Some(moved));
+        }
+        true
+    }
+
+    fn row_is_empty(&self, row: usize) -> bool {
+        let is_empty = |inputs: &[InputState]| inputs.iter().all(InputState::is_empty);
+        match self.open_tab {
+            1 => self.actor_state.get(row).map_or(true, |r| is_empty(r)),
+            2 => self.producer_state.get(row).map_or(true, |r| is_empty(r)),
+            3 => self.director_state.get(row).map_or(true, |r| is_empty(r)),
+            _ => true,
+        }
+    }
+
+    fn delete_row(&mut self, row: usize) {
+        match self.open_tab {
+            1 if row < self.actor_state.len() => {
+                self.actor_state.remove(row);
+            }
+            2 if row < self.producer_state.len() => {
+                self.producer_state.remove(row);
+            }
+            3 if row < self.director_state.len() => {
+                self.director_state.remove(row);
+            }
+            _ => {}
+        }
+    }
+
+    /// Fill the name, TMDB id and thumbnail of the row the person was searched for.
+    fn apply_person(&mut self, tab: usize, row: usize, person: &PersonResult) {
+        let inputs: Option<(&mut [InputState], usize)> = match tab {
+            1 => self.actor_state.get_mut(row).map(|r| (&mut r[..], 2)),
+            2 => self.producer_state.get_mut(row).map(|r| (&mut r[..], 1)),
+            3 => self.director_state.get_mut(row).map(|r| (&mut r[..], 1)),
+            _ => None,
+        };
+        let Some((inputs, id_col)) = inputs else {
+            return;
+        };
+        inputs[0].edit_value(&person.name);
+        inputs[id_col].edit_value(person.id.to_string());
+        if let Some(path) = &person.profile_path {
+            inputs[id_col + 1].edit_value(crate::tmdb_image_url(path, ImageSize::Original));
+        }
+    }
+
+    fn picker_input(&mut self, kev: KeyEvent) {
+        let Some(picker) = self.person_picker.as_mut() else {
+            return;
+        };
+        let selected = picker.list_state.selected().unwrap_or(0);
+        match kev.code {
+            KeyCode::Up => picker.list_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => picker
+                .list_state
+                .select(Some(Ord::min(selected + 1, picker.results.len() - 1))),
+            KeyCode::Enter => {
+                if let Some(picker) = self.person_picker.take() {
+                    if let Some(person) = picker.results.get(selected) {
+                        self.apply_person(picker.tab, picker.row, person);
+                    }
+                }
+            }
+            KeyCode::Esc => self.person_picker = None,
+            _ => {}
+        }
+    }
+
     pub fn input(&mut self, app_event: AppEvent) -> bool {
         match app_event {
+            AppEvent::MovieManagerEvent(MovieManagerEvent::PersonResults((tab, row, results))) => {
+                if results.is_empty() {
+                    MESSAGE_SENDER
+                        .get()
+                        .unwrap()
+                        .send(AppMessage::Closure(Box::new(|app_state: &mut AppState| {
+                            app_state.notice = Some("No person found on TMDB.".into());
+                            vec![]
+                        })))
+                        .unwrap();
+                } else {
+                    let mut list_state = ListState::default();
+                    list_state.select(Some(0));
+                    self.person_picker = Some(PersonPicker {
+                        tab,
+                        row,
+                        results,
+                        list_state,
+                    });
+                }
+                true
+            }
             AppEvent::KeyEvent(kev) => {
-                if kev.code == KeyCode::Enter {
+                if self.leave_prompt.is_some() {
+                    self.leave_prompt_input(kev);
+                    true
+                } else if self.person_picker.is_some() {
+                    self.picker_input(kev);
+                    true
+                } else if let Some(picker) = self.open_picker {
+                    if kev.code == KeyCode::Esc {
+                        self.open_picker = None;
+                    } else {
+                        self.tag_value[picker].input(kev);
+                    }
+                    true
+                } else if let Some(row) = self.pending_delete.take() {
+                    if kev.code == KeyCode::Char('y') {
+                        self.delete_row(row);
+                    }
+                    true
+                } else if (1..=3).contains(&self.open_tab) && self.edit_rows(kev) {
+                    true
+                } else if kev.code == KeyCode::Enter {
                     if let Some(selected) = self.selected_tab {
                         let sender = MESSAGE_SENDER.get().unwrap();
                         if selected == 4 {
-                            sender
-                                .send(
-                                    MovieManagerMessage::SaveNfo((
-                                        self.get_nfo(),
-                                        self.movie_fs_id,
-                                        self.movie_path.clone(),
-                                    ))
-                                    .into(),
-                                )
-                                .unwrap();
+                            self.save();
                         } else if selected == 5 {
                             sender
                                 .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
@@ -207,8 +580,14 @@ impl MovieEditorState {
                             self.open_tab = selected;
                             self.selected_column = 0;
                         }
-                    } else if self.table_state.selected().is_some() {
-                        self.selected_column = (self.selected_column + 1) % self.table_columns();
+                    } else if let Some(row) = self.table_state.selected() {
+                        match TAG_FIELDS.iter().position(|r| *r == row) {
+                            Some(picker) if self.open_tab == 0 => self.open_picker(picker),
+                            _ => {
+                                self.selected_column =
+                                    (self.selected_column + 1) % self.table_columns()
+                            }
+                        }
                     } else {
                         return false;
                     }
@@ -261,21 +640,38 @@ impl MovieEditorState {
                             if v == self.actor_state.len() {
                                 self.actor_state.push(Default::default());
                             }
-                            self.actor_state[v][self.selected_column].input(kev)
+                            self.actor_state
+                                .get_mut(v)
+                                .and_then(|row| row.get_mut(self.selected_column))
+                                .is_some_and(|input| input.input(kev))
                         }
                         2 => {
                             if v == self.producer_state.len() {
                                 self.producer_state.push(Default::default());
                             }
-                            self.producer_state[v][self.selected_column].input(kev)
+                            self.producer_state
+                                .get_mut(v)
+                                .and_then(|row| row.get_mut(self.selected_column))
+                                .is_some_and(|input| input.input(kev))
                         }
                         3 => {
                             if v == self.director_state.len() {
                                 self.director_state.push(Default::default());
                             }
-                            self.director_state[v][self.selected_column].input(kev)
+                            self.director_state
+                                .get_mut(v)
+                                .and_then(|row| row.get_mut(self.selected_column))
+                                .is_some_and(|input| input.input(kev))
                         }
-                        _ => self.fields_value[v].input(kev),
+                        _ if v == RELEASE_DATE => self.release_date.input(kev),
+                        _ if TAG_FIELDS.contains(&v) => false,
+                        _ => match field_index(v).and_then(|i| self.fields_value.get_mut(i)) {
+                            Some(input) => input.input(kev),
+                            None => self
+                                .text_value
+                                .get_mut(v - FIELDS.len())
+                                .is_some_and(|text| text.input(kev)),
+                        },
                     }
                 } else {
                     false
@@ -285,7 +681,7 @@ impl MovieEditorState {
         }
     }
 
-    pub fn get_nfo(&mut self) -> Movie {
+    pub fn get_nfo(&self) -> Movie {
         let mut nfo = self.movie_nfo.clone();
         nfo.title = self.fields_value[0].get_value().to_owned();
         nfo.original_title = if self.fields_value[1].is_empty() {
@@ -293,46 +689,48 @@ impl MovieEditorState {
         } else {
             Some(self.fields_value[1].get_value().to_owned())
         };
-        nfo.premiered = if self.fields_value[2].is_empty() {
+        nfo.premiered = self
+            .release_date
+            .date()
+            .ok()
+            .flatten()
+            .map(|d| d.to_string());
+        nfo.tagline = if self.text_value[0].is_empty() {
             None
         } else {
-            Some(self.fields_value[2].get_value().to_owned())
+            Some(self.text_value[0].get_value())
         };
-        nfo.tagline = if self.fields_value[3].is_empty() {
+        nfo.outline = if self.text_value[1].is_empty() {
             None
         } else {
-            Some(self.fields_value[3].get_value().to_owned())
+            Some(self.text_value[1].get_value())
         };
-        nfo.plot = if self.fields_value[4].is_empty() {
+        nfo.plot = if self.text_value[2].is_empty() {
             None
         } else {
-            Some(self.fields_value[4].get_value().to_owned())
+            Some(self.text_value[2].get_value())
         };
-        nfo.genre = self.fields_value[5]
+        nfo.genre = self.tag_value[0].get_value().to_vec();
+        nfo.tag = self.fields_value[2]
             .get_value()
             .split(",")
             .map(|s| s.trim().to_owned())
             .collect();
-        nfo.tag = self.fields_value[6]
+        nfo.studio = self.fields_value[3]
             .get_value()
             .split(",")
             .map(|s| s.trim().to_owned())
             .collect();
-        nfo.studio = self.fields_value[7]
-            .get_value()
-            .split(",")
-            .map(|s| s.trim().to_owned())
-            .collect();
-        nfo.country = self.fields_value[8]
-            .get_value()
-            .split(",")
-            .map(|s| s.trim().to_owned())
-            .collect();
-        nfo.source = if self.fields_value[9].is_empty() {
+        nfo.country = self.tag_value[1].get_value().to_vec();
+        nfo.source = if self.fields_value[4].is_empty() {
             None
         } else {
-            Some(self.fields_value[9].get_value().to_owned())
+            Some(self.fields_value[4].get_value().to_owned())
         };
+        nfo.set_default_rating(
+            self.fields_value[5].get_value().trim().parse().ok(),
+            self.fields_value[6].get_value().trim().parse().ok(),
+        );
         nfo.actor = self
             .actor_state
             .iter()
@@ -403,21 +801,59 @@ impl MovieEditor {
             .constraints(row_constraints.as_slice())
             .split(area.clone());
 
-        let rows: Vec<Row> = FIELDS
+        let selected = state.table_state.selected();
+        state.release_date.set_focus(selected == Some(RELEASE_DATE));
+        let (date_content, date_style) =
+            DateInput::default().render_text(row_chunks[1], &mut state.release_date);
+        let mut date_row = Some(Row::new(vec![
+            FIELDS[RELEASE_DATE].into(),
+            Cell::from(date_content).style(date_style),
+        ]));
+        let mut inputs = state.fields_value.iter_mut();
+        let mut rows: Vec<Row> = FIELDS
             .iter()
-            .zip(state.fields_value.iter_mut())
             .enumerate()
-            .map(|(ind, (name, input))| {
-                input.set_focus(false);
-                if let Some(s) = state.table_state.selected() {
-                    if ind == s {
-                        input.set_focus(true);
-                    }
+            .filter_map(|(ind, name)| {
+                if ind == RELEASE_DATE {
+                    return date_row.take();
+                }
+                if let Some(picker) = TAG_FIELDS.iter().position(|r| *r == ind) {
+                    let input = Input::default();
+                    let style = if selected == Some(ind) {
+                        input.focus_style
+                    } else {
+                        input.style
+                    };
+                    let tags = state.tag_value[picker].get_value().join(", ");
+                    return Some(Row::new(vec![
+                        (*name).into(),
+                        Cell::from(tags).style(style),
+                    ]));
                 }
+                let input = inputs.next()?;
+                input.set_focus(selected == Some(ind));
                 let (content, style) = Input::default().render_text(row_chunks[1], input);
-                Row::new(vec![(*name).into(), Cell::from(content).style(style)])
+                Some(Row::new(vec![
+                    (*name).into(),
+                    Cell::from(content).style(style),
+                ]))
             })
             .collect();
+        rows.extend(
+            TEXT_FIELDS
+                .iter()
+                .zip(state.text_value.iter_mut())
+                .enumerate()
+                .map(|(ind, ((name, height), text))| {
+                    text.set_focus(selected == Some(FIELDS.len() + ind));
+                    let text_area = Rect {
+                        height: *height,
+                        ..row_chunks[1]
+                    };
+                    let (content, style) = TextArea::default().render_text(text_area, text);
+                    Row::new(vec![(*name).into(), Cell::from(content).style(style)]).height(*height)
+                }),
+        );
 
         let table = Table::new(rows)
             .style(Style::default().fg(Color::White))
@@ -557,6 +993,76 @@ impl MovieEditor {
     }
 }
 
+fn render_leave_prompt(area: Rect, buf: &mut Buffer, selected: usize) {
+    let width = area.width.min(46);
+    let height = area.height.min(5);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let mut buttons = vec![];
+    for (i, choice) in LEAVE_CHOICES.iter().enumerate() {
+        let style = if i == selected {
+            Style::default().fg(Color::White).bg(Color::Magenta)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        buttons.push(Span::styled(format!(" {} ", choice), style));
+        buttons.push(Span::raw("   "));
+    }
+    let content = vec![
+        Spans::from("The movie has unsaved changes."),
+        Spans::from(""),
+        Spans::from(buttons),
+    ];
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .border_type(BorderType::Rounded)
+        .title(" Leave the editor? ");
+    Clear.render(popup, buf);
+    Paragraph::new(content).block(block).render(popup, buf);
+}
+
+fn render_person_picker(area: Rect, buf: &mut Buffer, picker: &mut PersonPicker) {
+    let width = area.width.min(60);
+    let height = area.height.min(picker.results.len() as u16 + 2);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let items: Vec<ListItem> = picker
+        .results
+        .iter()
+        .map(|person| match &person.known_for_department {
+            Some(department) => ListItem::new(format!("{} ({})", person.name, department)),
+            None => ListItem::new(person.name.as_str()),
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::White))
+                .border_type(BorderType::Rounded)
+                .title(" TMDB people (Enter to fill, Esc to close) "),
+        )
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+    Clear.render(popup, buf);
+    StatefulWidget::render(list, popup, buf, &mut picker.list_state);
+}
+
+/// Swap the row with its neighbour, returns the new index of the row.
+fn move_row<T>(rows: &mut [T], row: usize, delta: isize) -> Option<usize> {
+    let target = row.checked_add_signed(delta).filter(|t| *t < rows.len())?;
+    rows.swap(row, target);
+    Some(target)
+}
+
 fn crew_to_inputs(person: &CrewPerson) -> [InputState; 3] {
     let mut inputs: [InputState; 3] = Default::default();
     inputs[0].set_value(&person.name);
@@ -568,3 +1074,49 @@ fn crew_to_inputs(person: &CrewPerson) -> [InputState; 3] {
     }
     inputs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entry, message_sender, state};
+    use crate::views::movie_manager::{InnerState, MovieManagerState};
+    use crate::views::TabState;
+
+    fn editor(state: &mut AppState) -> &mut MovieEditorState {
+        match &mut state.tab {
+            TabState::MovieManager(MovieManagerState {
+                inner: InnerState::Editor(editor),
+                ..
+            }) => editor,
+            _ => panic!("the editor is open"),
+        }
+    }
+
+    #[test]
+    fn esc_closes_the_pickers_only() {
+        message_sender();
+        let (movie, fs_id, path) = entry();
+        let mut state = state();
+        state.tab = TabState::MovieManager(MovieManagerState {
+            inner: InnerState::Editor(MovieEditorState::default().with(movie, fs_id, path)),
+            ..Default::default()
+        });
+        editor(&mut state).person_picker = Some(PersonPicker {
+            tab: 1,
+            row: 0,
+            results: vec![PersonResult {
+                id: 6384,
+                name: "Keanu Reeves".into(),
+                profile_path: None,
+                known_for_department: None,
+            }],
+            list_state: ListState::default(),
+        });
+        assert!(!state.press_key(KeyCode::Esc.into()));
+        assert!(editor(&mut state).person_picker.is_none());
+
+        editor(&mut state).open_picker(0);
+        assert!(!state.press_key(KeyCode::Esc.into()));
+        assert_eq!(editor(&mut state).open_picker, None);
+    }
+}
@@ -0,0 +1,218 @@
+use anyhow::{anyhow, bail, Context, Result};
+use remotefs::fs::UnixPex;
+use std::path::{Path, PathBuf};
+
+use crate::error::MkubeError;
+use crate::multifs::{MultiFs, Transfer};
+
+/// What belongs to a movie on the filesystem.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MovieFiles {
+    /// The movie has its own folder.
+    Folder(PathBuf),
+    /// The movie shares its folder, only the files named after it are its own.
+    Files(Vec<PathBuf>),
+}
+
+fn is_video(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| crate::VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Find the folder or the files of the movie at `path`.
+///
+/// The folder is the movie's own unless it is the library root or it holds other videos.
+pub fn movie_files(fs: &mut MultiFs, lib_root: &Path, path: &Path) -> Result<MovieFiles> {
+    let parent = path
+        .parent()
+        .ok_or(anyhow!("No parent exists for path {}.", path.display()))?;
+    let entries = fs
+        .as_mut_rfs()
+        .list_dir(parent)
+        .context(format!("failed to list {}", parent.display()))?;
+    let paths: Vec<PathBuf> = entries.iter().map(|e| e.path.clone()).collect();
+    let stem = crate::parser::stack_stem(path, &paths)
+        .ok_or(anyhow!("Movie path does not contain a file stem."))?;
+    let shared = parent == lib_root
+        || entries
+            .iter()
+            .any(|e| !e.is_dir() && is_video(e.name().as_str()) && !e.name().starts_with(&stem));
+    if shared {
+        Ok(MovieFiles::Files(
+            entries
+                .into_iter()
+                .filter(|e| e.name().starts_with(&stem))
+                .map(|e| e.path)
+                .collect(),
+        ))
+    } else {
+        Ok(MovieFiles::Folder(parent.to_path_buf()))
+    }
+}
+
+impl MovieFiles {
+    fn paths(&self) -> Vec<&Path> {
+        match self {
+            MovieFiles::Folder(dir) => vec![dir.as_path()],
+            MovieFiles::Files(files) => files.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
+    /// Path of the movie once moved to `dest_root`.
+    fn moved_path(&self, path: &Path, dest_root: &Path) -> Result<PathBuf> {
+        let name = path
+            .file_name()
+            .ok_or(anyhow!("Oops, movie path does not contain a filename..."))?;
+        match self {
+            MovieFiles::Folder(dir) => Ok(dest_root.join(file_name(dir)?).join(name)),
+            MovieFiles::Files(_) => Ok(dest_root.join(name)),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> Result<&std::ffi::OsStr> {
+    path.file_name()
+        .ok_or(anyhow!("{} has no file name.", path.display()))
+}
+
+/// Remove the folder or the files of a movie.
+pub fn delete_movie(fs: &mut MultiFs, files: &MovieFiles) -> Result<()> {
+    for path in files.paths() {
+        fs.as_mut_rfs()
+            .remove_dir_all(path)
+            .context(format!("failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Rename every `(from, to)` in order, the renames already done are undone if one fails.
+pub fn move_all(fs: &mut MultiFs, moves: &[(PathBuf, PathBuf)]) -> Result<()> {
+    for (i, (from, to)) in moves.iter().enumerate() {
+        if from == to {
+            continue;
+        }
+        if let Err(err) = fs.as_mut_rfs().mov(from, to) {
+            for (done_from, done_to) in moves[..i].iter().rev() {
+                if done_from == done_to {
+                    continue;
+                }
+                if let Err(rollback) = fs.as_mut_rfs().mov(done_to, done_from) {
+                    log::error!(
+                        "Failed to move {} back to {}, causes:\n{:?}",
+                        done_to.display(),
+                        done_from.display(),
+                        rollback
+                    );
+                }
+            }
+            return Err(anyhow::Error::from(err).context(format!(
+                "failed to move {} to {}",
+                from.display(),
+                to.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Stream a file from a connection to another, then check both sizes are equal.
+fn copy_file(from: &mut MultiFs, src: &Path, to: &mut MultiFs, dest: &Path) -> Result<()> {
+    let expected = from
+        .as_mut_rfs()
+        .stat(src)
+        .context(format!("failed to stat {}", src.display()))?
+        .metadata()
+        .size;
+    let label = file_name(src)?.to_string_lossy().into_owned();
+    let mut reader = from.open_reader(src)?;
+    let mut writer = to.create_writer(dest)?;
+    Transfer::new(label, expected)
+        .copy(&mut reader, &mut writer)
+        .context(format!(
+            "failed to copy {} to {}",
+            src.display(),
+            dest.display()
+        ))?;
+    writer.finish()?;
+    reader.finish()?;
+    let copied = to
+        .as_mut_rfs()
+        .stat(dest)
+        .context(format!("failed to stat {}", dest.display()))?
+        .metadata()
+        .size;
+    if expected != copied {
+        bail!(
+            "{} has {} bytes but its copy {} has {} bytes.",
+            src.display(),
+            expected,
+            dest.display(),
+            copied
+        );
+    }
+    Ok(())
+}
+
+fn copy_tree(from: &mut MultiFs, src: &Path, to: &mut MultiFs, dest: &Path) -> Result<()> {
+    let file = from
+        .as_mut_rfs()
+        .stat(src)
+        .context(format!("failed to stat {}", src.display()))?;
+    if !file.is_dir() {
+        return copy_file(from, src, to, dest);
+    }
+    to.as_mut_rfs()
+        .create_dir(dest, UnixPex::from(0o755))
+        .context(format!("failed to create {}", dest.display()))?;
+    let entries = from
+        .as_mut_rfs()
+        .list_dir(src)
+        .context(format!("failed to list {}", src.display()))?;
+    for entry in entries {
+        copy_tree(from, entry.path(), to, &dest.join(entry.name()))?;
+    }
+    Ok(())
+}
+
+/// Move a movie to the root of another library, returns its new path.
+///
+/// On the same backend the files are simply renamed, otherwise they are copied,
+/// verified and only then removed from the source. Blocking, see
+/// [`AsyncMultiFs::run_with`](crate::asyncfs::AsyncMultiFs::run_with).
+pub fn move_movie(
+    (from, src_root): (&mut MultiFs, &Path),
+    path: &Path,
+    (to, dest_root): (&mut MultiFs, &Path),
+    same_backend: bool,
+) -> Result<PathBuf> {
+    let files = movie_files(from, src_root, path)?;
+    let targets = files
+        .paths()
+        .into_iter()
+        .map(|src| Ok((src, dest_root.join(file_name(src)?))))
+        .collect::<Result<Vec<_>>>()?;
+    for (_, dest) in &targets {
+        if to.as_mut_rfs().exists(dest).unwrap_or(false) {
+            return Err(MkubeError::Collision(dest.clone()).into());
+        }
+    }
+    if same_backend {
+        let moves: Vec<(PathBuf, PathBuf)> = targets
+            .iter()
+            .map(|(src, dest)| (src.to_path_buf(), dest.clone()))
+            .collect();
+        move_all(from, &moves)?;
+    } else {
+        for (i, (src, dest)) in targets.iter().enumerate() {
+            if let Err(err) = copy_tree(from, src, to, dest) {
+                // Leave the destination as it was, the source is untouched.
+                for (_, copied) in &targets[..=i] {
+                    let _ = to.as_mut_rfs().remove_dir_all(copied);
+                }
+                return Err(err);
+            }
+        }
+        delete_movie(from, &files)?;
+    }
+    files.moved_path(path, dest_root)
+}
@@ -0,0 +1,194 @@
+use crossterm::event::KeyCode;
+use std::path::PathBuf;
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{
+        Block, BorderType, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+        Wrap,
+    },
+};
+
+use crate::catalog::Catalog;
+use crate::library::LibraryId;
+use crate::nfo::Movie;
+use crate::views::movie_manager::{MovieManagerEvent, MovieManagerMessage};
+use crate::{AppEvent, AppMessage, MESSAGE_SENDER};
+
+/// The problems found by [`lint_nfo`] in the NFO of a movie.
+///
+/// [`lint_nfo`]: crate::lint::lint_nfo
+#[derive(Clone, Debug, PartialEq)]
+pub struct NfoIssue {
+    pub fs_id: LibraryId,
+    pub path: PathBuf,
+    pub title: String,
+    pub problems: Vec<String>,
+}
+
+/// The movies of the catalog sharing a TMDB id, one issue for each of them.
+pub fn duplicate_issues(catalog: &Catalog) -> Vec<NfoIssue> {
+    let mut issues = Vec::new();
+    for (tmdb_id, movies) in catalog.duplicates() {
+        for (fs_id, path) in &movies {
+            let others: Vec<String> = movies
+                .iter()
+                .filter(|other| other.1 != *path || other.0 != *fs_id)
+                .map(|(_, other)| other.display().to_string())
+                .collect();
+            issues.push(NfoIssue {
+                fs_id: *fs_id,
+                path: path.to_path_buf(),
+                title: catalog
+                    .get(*fs_id, path)
+                    .map(|movie| movie.title.clone())
+                    .unwrap_or_default(),
+                problems: vec![format!(
+                    "Same TMDB id {} as {}.",
+                    tmdb_id,
+                    others.join(", ")
+                )],
+            });
+        }
+    }
+    issues.sort_by(|a, b| (&a.title, &a.path).cmp(&(&b.title, &b.path)));
+    issues
+}
+
+/// The NFOs with structural problems and the duplicated movies, Enter opens the raw XML editor of the selected one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HealthReport {}
+
+#[derive(Clone, Debug, Default)]
+pub struct HealthReportState {
+    pub checked: usize,
+    pub issues: Vec<NfoIssue>,
+    pub list_state: ListState,
+}
+
+impl StatefulWidget for HealthReport {
+    type State = HealthReportState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded)
+            .title(" NFO health report ");
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(inner);
+        let header = vec![
+            Spans::from(Span::styled(
+                format!(
+                    "{} NFO(s) checked, {} with problems.",
+                    state.checked,
+                    state.issues.len()
+                ),
+                Style::default().fg(Color::Yellow),
+            )),
+            Spans::from(vec![
+                Span::styled(
+                    " Enter ",
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Fix in the NFO source"),
+                Span::raw("    "),
+                Span::styled(
+                    " Esc ",
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Back"),
+            ]),
+        ];
+        Paragraph::new(header)
+            .wrap(Wrap { trim: true })
+            .render(chunks[0], buf);
+        let items: Vec<ListItem> = state
+            .issues
+            .iter()
+            .map(|issue| {
+                let mut lines = vec![Spans::from(format!(
+                    "{} {}",
+                    issue.title,
+                    issue.path.display()
+                ))];
+                lines.extend(issue.problems.iter().map(|problem| {
+                    Spans::from(Span::styled(
+                        format!("    {}", problem),
+                        Style::default().fg(Color::LightRed),
+                    ))
+                }));
+                ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let list =
+            List::new(items).highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+        StatefulWidget::render(list, chunks[1], buf, &mut state.list_state);
+    }
+}
+
+impl HealthReportState {
+    pub fn new(checked: usize, issues: Vec<NfoIssue>) -> Self {
+        let mut list_state = ListState::default();
+        list_state.select((!issues.is_empty()).then_some(0));
+        Self {
+            checked,
+            issues,
+            list_state,
+        }
+    }
+
+    pub fn selected_issue(&self) -> Option<&NfoIssue> {
+        self.issues.get(self.list_state.selected()?)
+    }
+
+    pub fn input(&mut self, app_event: AppEvent) -> bool {
+        let AppEvent::KeyEvent(kev) = app_event else {
+            return false;
+        };
+        let sender = MESSAGE_SENDER.get().unwrap();
+        match kev.code {
+            KeyCode::Enter => {
+                if let Some(issue) = self.selected_issue() {
+                    let movie = Movie {
+                        title: issue.title.clone(),
+                        ..Default::default()
+                    };
+                    let msg = MovieManagerMessage::LoadNfoSource((
+                        movie,
+                        issue.fs_id,
+                        issue.path.clone(),
+                    ));
+                    sender.send(msg.into()).unwrap();
+                }
+                true
+            }
+            KeyCode::Esc => {
+                sender
+                    .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                        MovieManagerEvent::OpenTable,
+                    )))
+                    .unwrap();
+                true
+            }
+            KeyCode::Up => {
+                let index = self.list_state.selected().unwrap_or(0).saturating_sub(1);
+                self.list_state.select(Some(index));
+                true
+            }
+            KeyCode::Down if !self.issues.is_empty() => {
+                let index = self.list_state.selected().map_or(0, |index| index + 1);
+                self.list_state
+                    .select(Some(index.min(self.issues.len() - 1)));
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,24 +1,43 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use futures_util::stream::StreamExt;
-use remotefs::fs::Metadata;
-use rt_format::{NoPositionalArguments, ParsedFormat};
-use std::collections::HashMap;
-use std::io::{Cursor, Seek};
-use std::path::PathBuf;
-use tmdb_api::client::Client as TmdbClient;
-use tokio::io::AsyncWriteExt;
+use remotefs::fs::UnixPex;
+use remotefs::RemoteErrorType;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use tui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
 
+pub mod batch;
 pub mod details;
 pub mod editor;
+pub mod fileops;
+pub mod health;
 pub mod search;
+pub mod shows;
+pub mod source;
 pub mod table;
 
-use crate::util::FmtStr;
+use crate::asyncfs::{AsyncMultiFs, Operation};
+use crate::catalog::{Catalog, MovieAssets};
+use crate::config::{NfoDialect, RenameCollision, Renamer, TableColumn, TmdbPreferences};
+use crate::downloads::{self, Download};
+use crate::error::MkubeError;
+use crate::keys::{KeyContext, Keybindings};
+use crate::library::{AssetNaming, Library, LibraryId, NameRules};
+use crate::multifs::{MultiFs, TransferProgress};
+use crate::nfo::Movie;
+use crate::provider::MetadataProvider;
 use crate::views::widgets::InputState;
 use crate::{AppEvent, AppMessage, AppState, ConnectionPool};
+use batch::{
+    BatchConfirm, BatchConfirmState, BatchOp, BatchReport, BatchReportState, BatchReview,
+    BatchReviewState, BatchSummary, PlannedChange,
+};
 use editor::{MovieEditor, MovieEditorState};
+use health::{HealthReport, HealthReportState, NfoIssue};
 use search::{MovieSearch, MovieSearchState};
+use source::{NfoSourceEditor, NfoSourceEditorState};
 use table::{MovieTable, MovieTableState};
 
 #[derive(Clone, Debug, Default)]
@@ -26,6 +45,40 @@ pub struct MovieManager {
     table: MovieTable,
     search: MovieSearch,
     editor: MovieEditor,
+    source_editor: NfoSourceEditor,
+    batch_confirm: BatchConfirm,
+    batch_review: BatchReview,
+    batch_report: BatchReport,
+    health_report: HealthReport,
+    keys: Keybindings,
+}
+
+impl MovieManager {
+    pub fn with_keys(mut self, keys: Keybindings) -> Self {
+        self.source_editor.keys = keys.clone();
+        self.keys = keys;
+        self
+    }
+
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.table.offline = offline;
+        self
+    }
+
+    pub fn with_transfers(mut self, transfers: &[TransferProgress]) -> Self {
+        self.table.transfers = transfers.to_vec();
+        self
+    }
+
+    pub fn with_columns(
+        mut self,
+        columns: &[TableColumn],
+        libraries: Vec<(LibraryId, String)>,
+    ) -> Self {
+        self.table.columns = columns.to_vec();
+        self.table.libraries = libraries;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -34,33 +87,186 @@ enum InnerState {
     Table,
     Search(MovieSearchState),
     Editor(MovieEditorState),
+    SourceEditor(NfoSourceEditorState),
+    BatchConfirm(BatchConfirmState),
+    BatchReview(BatchReviewState),
+    BatchReport(BatchReportState),
+    HealthReport(HealthReportState),
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct MovieManagerState {
+    catalog: Catalog,
     table_state: MovieTableState,
     inner: InnerState,
+    /// TMDB genres and countries, fetched on the first use of the editor pickers.
+    taxonomy: Option<crate::Taxonomy>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum MovieManagerEvent {
     ClearMovieList,
-    MovieDiscovered((crate::nfo::Movie, usize, PathBuf)),
-    MovieUpdated((crate::nfo::Movie, usize, PathBuf)),
-    MovieMoved((usize, PathBuf, PathBuf)),
-    SearchMovie((crate::nfo::Movie, usize, PathBuf)),
-    EditMovie((crate::nfo::Movie, usize, PathBuf)),
-    SearchResults(Vec<tmdb_api::movie::MovieShort>),
+    /// Forget the movies of a library, before it gets scanned again.
+    ClearLibrary(LibraryId),
+    /// The scan of a library stopped, the library was edited or removed meanwhile.
+    ScanCancelled(LibraryId),
+    /// A library was reconnected after being offline.
+    LibraryOnline(LibraryId),
+    MovieDiscovered((crate::nfo::Movie, LibraryId, PathBuf)),
+    MovieUpdated((crate::nfo::Movie, LibraryId, PathBuf)),
+    MovieMoved((LibraryId, PathBuf, PathBuf)),
+    MovieRemoved((LibraryId, PathBuf)),
+    AssetsDetected((LibraryId, PathBuf, MovieAssets)),
+    SearchMovie((crate::nfo::Movie, LibraryId, PathBuf)),
+    EditMovie((crate::nfo::Movie, LibraryId, PathBuf)),
+    EditNfoSource((LibraryId, PathBuf, PathBuf, String)), // fs_id, movie_path, nfo_path, source
+    SearchResults(tmdb_api::common::PaginatedResult<tmdb_api::movie::MovieShort>),
+    PersonResults((usize, usize, Vec<crate::PersonResult>)), // tab, row, results
+    TaxonomyLoaded(crate::Taxonomy),
+    ConfirmBatch(Vec<BatchOp>),
+    /// Accept or reject the NFOs matched by the auto-identify before creating them.
+    ReviewBatch(Vec<PlannedChange>),
+    BatchDone(BatchSummary),
+    HealthReport((usize, Vec<NfoIssue>)), // NFOs checked, their issues
+    QueueReview(Vec<(crate::nfo::Movie, LibraryId, PathBuf)>),
+    /// Select the movie in the table, from the quick-open palette.
+    SelectMovie((LibraryId, PathBuf)),
     OpenTable,
 }
+
+impl MovieManagerEvent {
+    /// Update the catalog with the event, returns true if the event concerns it.
+    pub fn apply_to(&self, catalog: &mut Catalog) -> bool {
+        match self {
+            MovieManagerEvent::ClearMovieList => catalog.clear(),
+            MovieManagerEvent::ClearLibrary(fs_id) => catalog.clear_library(*fs_id),
+            MovieManagerEvent::ScanCancelled(_) => {}
+            MovieManagerEvent::MovieDiscovered((movie, fs_id, path)) => {
+                catalog.insert(movie.clone(), *fs_id, path.clone());
+            }
+            MovieManagerEvent::MovieUpdated((movie, fs_id, path)) => {
+                catalog.insert(movie.clone(), *fs_id, path.clone());
+                catalog.set_nfo(*fs_id, path);
+            }
+            MovieManagerEvent::MovieMoved((fs_id, path, new_path)) => {
+                catalog.relocate(*fs_id, path, new_path.clone());
+            }
+            MovieManagerEvent::MovieRemoved((fs_id, path)) => {
+                catalog.remove(*fs_id, path);
+            }
+            MovieManagerEvent::AssetsDetected((fs_id, path, assets)) => {
+                catalog.set_assets(*fs_id, path.clone(), *assets);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum MovieManagerMessage {
     RefreshMovies,
-    SearchTitle(String),
-    CreateNfo((u64, usize, PathBuf)), // tmdb_id, fs_id, movie_path
-    RetrieveArtworks((crate::nfo::Movie, usize, PathBuf)),
-    SaveNfo((crate::nfo::Movie, usize, PathBuf)),
-    Rename((crate::nfo::Movie, usize, PathBuf)),
+    RefreshLibrary(LibraryId),
+    SearchTitle((String, Option<u16>, u32)), // title, year, page
+    SearchPerson((String, usize, usize)),    // name, tab, row
+    FetchTaxonomy,
+    CreateNfo((u64, LibraryId, PathBuf)), // tmdb_id, fs_id, movie_path
+    RetrieveArtworks((crate::nfo::Movie, LibraryId, PathBuf, bool)), // ..., force
+    IdentifyShow((LibraryId, PathBuf, bool)), // fs_id, episode_path, force
+    RetrieveShowArtworks((LibraryId, PathBuf, u64, bool)), // fs_id, show_dir, tmdb_id, force
+    SaveNfo((crate::nfo::Movie, LibraryId, PathBuf)),
+    Rename((crate::nfo::Movie, LibraryId, PathBuf)),
+    /// Rename again once the user chose how to solve a collision.
+    RenameResolved((crate::nfo::Movie, LibraryId, PathBuf, RenameCollision)),
+    DeleteMovie((crate::nfo::Movie, LibraryId, PathBuf)),
+    MoveMovie((crate::nfo::Movie, LibraryId, PathBuf, LibraryId)), // ..., dest fs_id
+    RefreshFromTmdb((crate::nfo::Movie, LibraryId, PathBuf)),
+    ProbeMedia((crate::nfo::Movie, LibraryId, PathBuf)),
+    ProbeSeason((LibraryId, PathBuf)), // fs_id, season_dir
+    GrabThumb((crate::nfo::Movie, LibraryId, PathBuf)),
+    VerifyChecksum((crate::nfo::Movie, LibraryId, PathBuf)),
+    LoadNfoSource((crate::nfo::Movie, LibraryId, PathBuf)),
+    SaveNfoSource((LibraryId, PathBuf, PathBuf, String)), // fs_id, movie_path, nfo_path, source
+    /// Pick one of the backups of the NFO to restore.
+    ListNfoBackups((crate::nfo::Movie, LibraryId, PathBuf)),
+    RestoreNfo((LibraryId, PathBuf, PathBuf, PathBuf)), // fs_id, movie_path, nfo_path, backup
+    Batch(Vec<BatchOp>),
+    RunBatch(Vec<BatchOp>),
+    AutoIdentify(Vec<(crate::nfo::Movie, LibraryId, PathBuf)>),
+    /// Lint the NFOs of the movies, for the health report.
+    CheckNfos(Vec<(crate::nfo::Movie, LibraryId, PathBuf)>),
+}
+
+impl MovieManagerMessage {
+    /// Messages calling TMDB or downloading images, refused in offline mode.
+    pub fn needs_network(&self) -> bool {
+        match self {
+            MovieManagerMessage::SearchTitle(_)
+            | MovieManagerMessage::SearchPerson(_)
+            | MovieManagerMessage::FetchTaxonomy
+            | MovieManagerMessage::CreateNfo(_)
+            | MovieManagerMessage::RetrieveArtworks(_)
+            | MovieManagerMessage::IdentifyShow(_)
+            | MovieManagerMessage::RetrieveShowArtworks(_)
+            | MovieManagerMessage::RefreshFromTmdb(_)
+            | MovieManagerMessage::AutoIdentify(_) => true,
+            MovieManagerMessage::Batch(ops) | MovieManagerMessage::RunBatch(ops) => {
+                ops.iter().any(|op| matches!(op, BatchOp::CreateNfo(_)))
+            }
+            MovieManagerMessage::RefreshMovies
+            | MovieManagerMessage::RefreshLibrary(_)
+            | MovieManagerMessage::SaveNfo(_)
+            | MovieManagerMessage::Rename(_)
+            | MovieManagerMessage::RenameResolved(_)
+            | MovieManagerMessage::DeleteMovie(_)
+            | MovieManagerMessage::MoveMovie(_)
+            | MovieManagerMessage::ProbeMedia(_)
+            | MovieManagerMessage::ProbeSeason(_)
+            | MovieManagerMessage::GrabThumb(_)
+            | MovieManagerMessage::VerifyChecksum(_)
+            | MovieManagerMessage::LoadNfoSource(_)
+            | MovieManagerMessage::SaveNfoSource(_)
+            | MovieManagerMessage::ListNfoBackups(_)
+            | MovieManagerMessage::RestoreNfo(_)
+            | MovieManagerMessage::CheckNfos(_) => false,
+        }
+    }
+
+    /// Libraries the message writes to, refused when they are read-only.
+    ///
+    /// A checksum verification only stores the checksum when the library is writable.
+    pub fn written_libraries(&self) -> Vec<LibraryId> {
+        match self {
+            MovieManagerMessage::CreateNfo((_, fs_id, _))
+            | MovieManagerMessage::RetrieveArtworks((_, fs_id, _, _))
+            | MovieManagerMessage::IdentifyShow((fs_id, _, _))
+            | MovieManagerMessage::RetrieveShowArtworks((fs_id, _, _, _))
+            | MovieManagerMessage::SaveNfo((_, fs_id, _))
+            | MovieManagerMessage::Rename((_, fs_id, _))
+            | MovieManagerMessage::RenameResolved((_, fs_id, _, _))
+            | MovieManagerMessage::DeleteMovie((_, fs_id, _))
+            | MovieManagerMessage::RefreshFromTmdb((_, fs_id, _))
+            | MovieManagerMessage::ProbeMedia((_, fs_id, _))
+            | MovieManagerMessage::ProbeSeason((fs_id, _))
+            | MovieManagerMessage::GrabThumb((_, fs_id, _))
+            | MovieManagerMessage::SaveNfoSource((fs_id, _, _, _))
+            | MovieManagerMessage::RestoreNfo((fs_id, _, _, _)) => vec![*fs_id],
+            MovieManagerMessage::MoveMovie((_, fs_id, _, dest_id)) => vec![*fs_id, *dest_id],
+            MovieManagerMessage::Batch(ops) | MovieManagerMessage::RunBatch(ops) => {
+                ops.iter().map(BatchOp::library).collect()
+            }
+            MovieManagerMessage::RefreshMovies
+            | MovieManagerMessage::RefreshLibrary(_)
+            | MovieManagerMessage::SearchTitle(_)
+            | MovieManagerMessage::SearchPerson(_)
+            | MovieManagerMessage::FetchTaxonomy
+            | MovieManagerMessage::VerifyChecksum(_)
+            | MovieManagerMessage::LoadNfoSource(_)
+            | MovieManagerMessage::ListNfoBackups(_)
+            | MovieManagerMessage::AutoIdentify(_)
+            | MovieManagerMessage::CheckNfos(_) => vec![],
+        }
+    }
 }
 
 impl StatefulWidget for MovieManager {
@@ -69,7 +275,13 @@ impl StatefulWidget for MovieManager {
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
         match state.inner {
             InnerState::Table => {
-                StatefulWidget::render(self.table, area, buf, &mut state.table_state);
+                self.table.render(
+                    area,
+                    buf,
+                    &mut state.table_state,
+                    &state.catalog,
+                    &self.keys,
+                );
             }
             InnerState::Search(ref mut state) => {
                 StatefulWidget::render(self.search, area, buf, state);
@@ -77,13 +289,108 @@ impl StatefulWidget for MovieManager {
             InnerState::Editor(ref mut state) => {
                 StatefulWidget::render(self.editor, area, buf, state);
             }
+            InnerState::SourceEditor(ref mut state) => {
+                StatefulWidget::render(self.source_editor, area, buf, state);
+            }
+            InnerState::BatchConfirm(ref mut state) => {
+                StatefulWidget::render(self.batch_confirm, area, buf, state);
+            }
+            InnerState::BatchReview(ref mut state) => {
+                StatefulWidget::render(self.batch_review, area, buf, state);
+            }
+            InnerState::BatchReport(ref mut state) => {
+                StatefulWidget::render(self.batch_report, area, buf, state);
+            }
+            InnerState::HealthReport(ref mut state) => {
+                StatefulWidget::render(self.health_report, area, buf, state);
+            }
             _ => {}
         }
     }
 }
 
 impl MovieManagerState {
-    pub fn input(&mut self, app_event: AppEvent) -> bool {
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    pub fn movie_count(&self) -> usize {
+        self.catalog.len()
+    }
+
+    /// The movie selected in the table, None when a library header is.
+    pub fn selected_movie(&self) -> Option<&(crate::nfo::Movie, LibraryId, PathBuf)> {
+        self.table_state.selected_movie(&self.catalog)
+    }
+
+    pub fn key_context(&self) -> KeyContext {
+        match self.inner {
+            InnerState::Search(_) => KeyContext::Search,
+            InnerState::Editor(_) | InnerState::SourceEditor(_) => KeyContext::Editor,
+            _ => KeyContext::Table,
+        }
+    }
+
+    pub fn is_table(&self) -> bool {
+        matches!(self.inner, InnerState::Table)
+    }
+
+    pub fn input(&mut self, app_event: AppEvent, keys: &Keybindings) -> bool {
+        if let AppEvent::MovieManagerEvent(ref evt) = app_event {
+            self.table_state.catalog_changing(evt, &self.catalog);
+            if evt.apply_to(&mut self.catalog) {
+                return self.table_state.catalog_updated(evt, &self.catalog);
+            }
+        }
+        let app_event = match app_event {
+            AppEvent::MovieManagerEvent(MovieManagerEvent::ConfirmBatch(ops)) => {
+                self.inner = InnerState::BatchConfirm(BatchConfirmState::new(ops));
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::ReviewBatch(planned)) => {
+                self.inner = InnerState::BatchReview(BatchReviewState::new(planned));
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::BatchDone(summary)) => {
+                self.inner = InnerState::BatchReport(BatchReportState::new(summary));
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::HealthReport((checked, mut issues))) => {
+                issues.extend(health::duplicate_issues(&self.catalog));
+                self.inner = InnerState::HealthReport(HealthReportState::new(checked, issues));
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::TaxonomyLoaded(taxonomy)) => {
+                if let InnerState::Editor(ref mut state) = self.inner {
+                    state.set_taxonomy(&taxonomy);
+                }
+                self.taxonomy = Some(taxonomy);
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::LibraryOnline(fs_id)) => {
+                // Its scan failed while it was offline, scan it again.
+                if self.catalog.stats(fs_id).movies == 0 {
+                    let sender = crate::MESSAGE_SENDER.get().unwrap();
+                    let _ = sender.send(MovieManagerMessage::RefreshLibrary(fs_id).into());
+                }
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::QueueReview(entries)) => {
+                self.table_state.queue_review(entries);
+                return true;
+            }
+            AppEvent::MovieManagerEvent(MovieManagerEvent::SelectMovie((fs_id, path))) => {
+                self.table_state.select_movie(&self.catalog, fs_id, &path);
+                if matches!(
+                    self.inner,
+                    InnerState::Search(_) | InnerState::BatchReport(_)
+                ) {
+                    self.inner = InnerState::Table;
+                }
+                return true;
+            }
+            evt => evt,
+        };
         match self.inner {
             InnerState::Table => match app_event {
                 AppEvent::MovieManagerEvent(MovieManagerEvent::SearchMovie((
@@ -91,64 +398,117 @@ impl MovieManagerState {
                     fs_id,
                     path,
                 ))) => {
+                    let parsed = crate::parser::parse_path(&path);
+                    let year = movie
+                        .premiered
+                        .as_deref()
+                        .and_then(|date| date.get(..4))
+                        .and_then(|year| year.parse().ok())
+                        .or(parsed.year);
                     let mut query_state = InputState::default();
                     query_state.set_value(&movie.title);
+                    let mut year_state = InputState::default();
+                    if let Some(year) = year {
+                        year_state.set_value(year.to_string());
+                    }
                     let new_state = MovieSearchState {
                         movie_path: path,
                         movie_fs_id: fs_id,
                         query_state,
+                        year_state,
                         ..Default::default()
                     };
                     self.inner = InnerState::Search(new_state);
                     true
                 }
                 AppEvent::MovieManagerEvent(MovieManagerEvent::EditMovie((movie, fs_id, path))) => {
-                    let state = MovieEditorState::default().with(movie, fs_id, path);
+                    let mut state = MovieEditorState::default().with(movie, fs_id, path);
+                    if let Some(taxonomy) = &self.taxonomy {
+                        state.set_taxonomy(taxonomy);
+                    }
                     self.inner = InnerState::Editor(state);
                     true
                 }
-                _ => self.table_state.input(app_event),
+                AppEvent::MovieManagerEvent(MovieManagerEvent::EditNfoSource((
+                    fs_id,
+                    path,
+                    nfo_path,
+                    source,
+                ))) => {
+                    let state = NfoSourceEditorState::new(fs_id, path, nfo_path, &source);
+                    self.inner = InnerState::SourceEditor(state);
+                    true
+                }
+                _ => self.table_state.input(app_event, &self.catalog, keys),
             },
             InnerState::Search(ref mut state) => {
-                if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated(..)) = app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered(..)) =
-                    app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved(..)) =
-                    app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event
-                {
+                if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event {
                     self.inner = InnerState::Table;
                     true
                 } else {
                     state.input(app_event)
                 }
             }
-            InnerState::Editor(ref mut state) => {
-                if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated(..)) = app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered(..)) =
-                    app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved(..)) =
-                    app_event
-                {
-                    self.table_state.input(app_event)
-                } else if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event
-                {
+            InnerState::Editor(ref mut state) => match app_event {
+                AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) if state.is_dirty() => {
+                    state.confirm_leave();
+                    true
+                }
+                AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) => {
+                    self.inner = InnerState::Table;
+                    true
+                }
+                _ => state.input(app_event),
+            },
+            InnerState::SourceEditor(ref mut state) => {
+                if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event {
+                    self.inner = InnerState::Table;
+                    true
+                } else {
+                    state.input(app_event, keys)
+                }
+            }
+            InnerState::BatchConfirm(ref mut state) => {
+                if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event {
+                    self.inner = InnerState::Table;
+                    true
+                } else {
+                    state.input(app_event)
+                }
+            }
+            InnerState::BatchReview(ref mut state) => {
+                if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event {
                     self.inner = InnerState::Table;
                     true
                 } else {
                     state.input(app_event)
                 }
             }
+            InnerState::BatchReport(ref mut state) => {
+                if let AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) = app_event {
+                    self.inner = InnerState::Table;
+                    true
+                } else {
+                    state.input(app_event)
+                }
+            }
+            InnerState::HealthReport(ref mut state) => match app_event {
+                AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable) => {
+                    self.inner = InnerState::Table;
+                    true
+                }
+                AppEvent::MovieManagerEvent(MovieManagerEvent::EditNfoSource((
+                    fs_id,
+                    path,
+                    nfo_path,
+                    source,
+                ))) => {
+                    let state = NfoSourceEditorState::new(fs_id, path, nfo_path, &source);
+                    self.inner = InnerState::SourceEditor(state);
+                    true
+                }
+                _ => state.input(app_event),
+            },
             _ => false,
         }
     }
@@ -156,66 +516,249 @@ impl MovieManagerState {
 
 impl From<MovieManagerMessage> for AppMessage {
     fn from(value: MovieManagerMessage) -> AppMessage {
-        match value {
-            MovieManagerMessage::RefreshMovies => {
-                AppMessage::Closure(Box::new(|app_state: &mut AppState| {
-                    let mut futures : Vec<AppEvent> = app_state
-                        .libraries
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, lib)| lib.is_some())
-                        .map(|(i, lib)| (i, lib.as_ref().map(|l| l.path.clone()).unwrap()))
-                        .map(|(i, path)| {
-                            AppEvent::ContinuationIOFuture(Box::new(move |_,_,_,conns: &ConnectionPool| Box::pin(async move {
-                                let rst : Vec<Result<PathBuf>> = crate::analyze_library((conns, i), path, 4).collect().await;
-                                let mut events = Vec::new();
-                                for r in rst {
-                                    match r {
-                                        Ok(path) => {
-                                            let placeholder_title = format!("{}", path.file_name().map(|s| s.to_string_lossy().replace(&['.', '_'], " ")).unwrap_or("Invalid file name.".into()));
-                                            let movie = crate::try_open_nfo(conns.lock().await[i].as_mut().unwrap(), path.clone()).await.unwrap_or_else(|_| {
-                                                crate::nfo::Movie {
-                                                    title: placeholder_title,
-                                                    ..Default::default()
-                                                }
-                                            });
-                                            events.push(AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered((movie, i, path))));
-                                        },
-                                        Err(err) => { log::error!("An error occured while searching new titles:\n{:?}", err); },
+        AppMessage::MovieManagerMessage(value)
+    }
+}
+
+/// Log a failed task and tell the user why in the status bar.
+fn report_error(task: &str, err: &anyhow::Error) {
+    log::error!("{} failed due to the following error:\n{:?}", task, err);
+    let notice = match MkubeError::find(err) {
+        Some(err) => format!("{} failed: {}.", task, err.notice()),
+        None => format!("{} failed, see the logs.", task),
+    };
+    if let Some(sender) = crate::MESSAGE_SENDER.get() {
+        let _ = sender.send(AppMessage::Closure(Box::new(
+            move |app_state: &mut AppState| {
+                app_state.notice = Some(notice);
+                app_state.stats.errors += 1;
+                vec![]
+            },
+        )));
+    }
+}
+
+/// Scan the libraries again, or only the library `only`.
+fn refresh_libraries(only: Option<LibraryId>) -> AppMessage {
+    AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+        let hashing = app_state.config.hashing.clone();
+        let mut futures: Vec<AppEvent> = app_state
+            .libraries
+            .iter()
+            .flatten()
+            .filter(|lib| only.map_or(true, |only| only == lib.id))
+            .map(|lib| {
+                (
+                    lib.id,
+                    lib.name.clone(),
+                    lib.path.clone(),
+                    lib.scan_depth(),
+                    lib.read_only,
+                )
+            })
+            .map(|(i, name, path, depth, read_only)| {
+                let hashing = hashing.clone();
+                AppEvent::ContinuationIOFuture(Box::new(move |_, _, _, conns: &ConnectionPool| {
+                    Box::pin(async move {
+                        let cancelled = || {
+                            log::info!("Scan of library {} cancelled, it was edited.", name);
+                            vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::ScanCancelled(i),
+                            )]
+                        };
+                        let Some(handle) = conns.lock().await.handle(i) else {
+                            return cancelled();
+                        };
+                        let rst: Vec<Result<PathBuf, MkubeError>> =
+                            crate::analyze_library((conns, handle), path, depth)
+                                .collect()
+                                .await;
+                        let scan =
+                            AsyncMultiFs::with_handle(conns, handle).for_operation(Operation::List);
+                        let mut events = Vec::new();
+                        let mut paths = Vec::new();
+                        for r in rst {
+                            match r {
+                                Ok(path) => paths.push(path),
+                                Err(MkubeError::ScanCancelled(_)) => {
+                                    return cancelled();
+                                }
+                                Err(err) => {
+                                    log::error!(
+                                        "An error occured while searching new titles:\n{:?}",
+                                        err
+                                    );
+                                }
+                            }
+                        }
+                        // Multi-part movies are listed once, by their first part.
+                        for (path, parts) in crate::parser::group_stacks(paths) {
+                            let placeholder = match crate::parser::parse_part(
+                                &path.file_stem().unwrap_or_default().to_string_lossy(),
+                            ) {
+                                Some(part) if parts > 1 => {
+                                    crate::parser::parse_path(&path.with_file_name(part.base))
+                                }
+                                _ => crate::parser::parse_path(&path),
+                            };
+                            let movie_path = path.clone();
+                            let found = scan
+                                .run(move |fs| {
+                                    let nfo = crate::open_nfo(fs, &movie_path);
+                                    Ok((nfo, crate::detect_assets(fs, &movie_path)))
+                                })
+                                .await;
+                            let (nfo, assets) = match found {
+                                Ok(found) => found,
+                                Err(MkubeError::ScanCancelled(_)) => return cancelled(),
+                                Err(err) => {
+                                    log::error!(
+                                        "Failed to read {}, causes:\n{:?}",
+                                        path.display(),
+                                        err
+                                    );
+                                    continue;
+                                }
+                            };
+                            let movie = match nfo {
+                                Ok(movie)
+                                    if hashing.slow_scan
+                                        && !read_only
+                                        && movie.checksum.is_none() =>
+                                {
+                                    let (mut hashed, movie_path) = (movie.clone(), path.clone());
+                                    let stored = scan
+                                        .for_operation(Operation::Read)
+                                        .run(move |fs| {
+                                            store_checksum(
+                                                fs,
+                                                &mut hashed,
+                                                &movie_path,
+                                                hashing.algorithm,
+                                            )
+                                            .map(|_| hashed)
+                                        })
+                                        .await;
+                                    match stored {
+                                        Ok(hashed) => hashed,
+                                        Err(err) => {
+                                            log::error!(
+                                                "Failed to hash {}, causes:\n{:?}",
+                                                path.display(),
+                                                err
+                                            );
+                                            movie
+                                        }
                                     }
                                 }
-                                events
-                            })))
-                        })
-                        .collect();
-                    futures.insert(0, AppEvent::MovieManagerEvent(MovieManagerEvent::ClearMovieList));
-                    futures
+                                Ok(movie) => movie,
+                                Err(err) => {
+                                    if !matches!(err, MkubeError::NotFound { .. }) {
+                                        log::warn!("{}", err);
+                                    }
+                                    crate::nfo::Movie {
+                                        title: placeholder.title,
+                                        edition: placeholder.edition,
+                                        ..Default::default()
+                                    }
+                                }
+                            };
+                            events.push(AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::MovieDiscovered((movie, i, path.clone())),
+                            ));
+                            events.push(AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::AssetsDetected((i, path, assets)),
+                            ));
+                        }
+                        events
+                    })
                 }))
+            })
+            .collect();
+        let clear = match only {
+            Some(fs_id) => MovieManagerEvent::ClearLibrary(fs_id),
+            None => MovieManagerEvent::ClearMovieList,
+        };
+        futures.insert(0, AppEvent::MovieManagerEvent(clear));
+        futures
+    }))
+}
+
+impl MovieManagerMessage {
+    /// Build the task of this message, run by the dispatcher.
+    pub fn into_task(self) -> AppMessage {
+        match self {
+            MovieManagerMessage::RefreshMovies => refresh_libraries(None),
+            MovieManagerMessage::RefreshLibrary(fs_id) => refresh_libraries(Some(fs_id)),
+            MovieManagerMessage::SearchTitle((title, year, page)) => {
+                AppMessage::HttpFuture(Box::new(
+                    move |app_state: &mut AppState,
+                          _: &reqwest::Client,
+                          tmdb_client: &dyn MetadataProvider| {
+                        use tmdb_api::movie::search::MovieSearch;
+                        let ms = MovieSearch::new(title.clone())
+                            .with_language(Some(
+                                app_state.config.tmdb_preferences.prefered_lang.clone(),
+                            ))
+                            .with_region(Some(
+                                app_state.config.tmdb_preferences.prefered_country.clone(),
+                            ))
+                            .with_year(year)
+                            .with_page(Some(page));
+                        Box::pin(async move {
+                            match tmdb_client.execute(&ms).await {
+                                Ok(results) => {
+                                    vec![AppEvent::MovieManagerEvent(
+                                        MovieManagerEvent::SearchResults(results),
+                                    )]
+                                }
+                                Err(err) => {
+                                    log::error!(
+                                        "Movie search failed for title `{}` due to:\n{:?}",
+                                        title,
+                                        err
+                                    );
+                                    vec![]
+                                }
+                            }
+                        })
+                    },
+                ))
             }
-            MovieManagerMessage::SearchTitle(title) => AppMessage::HttpFuture(Box::new(
-                |app_state: &mut AppState, _: &reqwest::Client, tmdb_client: &TmdbClient| {
-                    use tmdb_api::movie::search::MovieSearch;
-                    use tmdb_api::prelude::Command;
-                    let ms = MovieSearch::new(title.clone())
-                        .with_language(Some(
-                            app_state.config.tmdb_preferences.prefered_lang.clone(),
-                        ))
-                        .with_region(Some(
-                            app_state.config.tmdb_preferences.prefered_country.clone(),
-                        ));
+            MovieManagerMessage::SearchPerson((name, tab, row)) => {
+                AppMessage::HttpFuture(Box::new(
+                    move |app_state: &mut AppState,
+                          _: &reqwest::Client,
+                          tmdb_client: &dyn MetadataProvider| {
+                        let lang = app_state.config.tmdb_preferences.prefered_lang.clone();
+                        Box::pin(async move {
+                            match crate::search_person(tmdb_client, &name, Some(lang)).await {
+                                Ok(results) => {
+                                    vec![AppEvent::MovieManagerEvent(
+                                        MovieManagerEvent::PersonResults((tab, row, results)),
+                                    )]
+                                }
+                                Err(err) => {
+                                    log::error!("{:?}", err);
+                                    vec![]
+                                }
+                            }
+                        })
+                    },
+                ))
+            }
+            MovieManagerMessage::FetchTaxonomy => AppMessage::HttpFuture(Box::new(
+                move |app_state: &mut AppState, _: &reqwest::Client, tmdb_client: &dyn MetadataProvider| {
+                    let lang = app_state.config.tmdb_preferences.prefered_lang.clone();
                     Box::pin(async move {
-                        match ms.execute(&tmdb_client).await {
-                            Ok(results) => {
+                        match crate::fetch_taxonomy(tmdb_client, Some(lang)).await {
+                            Ok(taxonomy) => {
                                 vec![AppEvent::MovieManagerEvent(
-                                    MovieManagerEvent::SearchResults(results.results),
+                                    MovieManagerEvent::TaxonomyLoaded(taxonomy),
                                 )]
                             }
                             Err(err) => {
-                                log::error!(
-                                    "Movie search failed for title `{}` due to:\n{:?}",
-                                    title,
-                                    err
-                                );
+                                log::error!("{:?}", err);
                                 vec![]
                             }
                         }
@@ -226,16 +769,24 @@ impl From<MovieManagerMessage> for AppMessage {
                 AppMessage::HttpFuture(Box::new(
                     move |app_state: &mut AppState,
                           _: &reqwest::Client,
-                          tmdb_client: &TmdbClient| {
-                        let prefered_lang = app_state.config.tmdb_preferences.prefered_lang.clone();
-                        let lib_url: Result<url::Url, ()> =
-                            app_state.libraries[fs_id].as_ref().unwrap().try_into();
+                          tmdb_client: &dyn MetadataProvider| {
+                        let lib = app_state.library(fs_id);
+                        let prefs = app_state.config.tmdb_preferences.for_library(lib);
+                        let probe = app_state.config.probing.for_library(lib);
+                        let dialect = app_state.config.nfo_dialect;
+                        let normalization = app_state.config.normalization.clone();
+                        let langs = prefs.languages();
+                        let certification_country = prefs.certification_country().to_owned();
+                        let artworks = app_state.config.artworks.clone();
+                        let lib_url = lib.ok_or(()).and_then(Library::ffmpeg_base);
                         Box::pin(async move {
                             if let Ok(lib_url) = lib_url {
                                 match crate::transform_as_nfo(
-                                    &tmdb_client,
+                                    tmdb_client,
                                     tmdb_id,
-                                    Some(prefered_lang),
+                                    &langs,
+                                    &certification_country,
+                                    &artworks,
                                 )
                                 .await
                                 {
@@ -246,32 +797,50 @@ impl From<MovieManagerMessage> for AppMessage {
                                             move |_, _, _, conns: &ConnectionPool| {
                                                 Box::pin(async move {
                                                     match async move {
-                                                    let mut conns_lock = conns.lock().await;
-                                                    if conns_lock[fs_id].is_none() {
-                                                        return Err(anyhow!("NFO creation failed because fs_id {} does not exist anymore.", fs_id));
+                                                        if probe {
+                                                            let mt = crate::get_metadata(
+                                                                conns,
+                                                                fs_id,
+                                                                lib_url,
+                                                                path.clone(),
+                                                            )
+                                                            .await?;
+                                                            movie_nfo.fileinfo = Some(mt);
+                                                        }
+                                                        let parsed =
+                                                            crate::parser::parse_path(&path);
+                                                        movie_nfo.source = parsed.source;
+                                                        movie_nfo.edition = parsed.edition;
+                                                        normalization.apply(&mut movie_nfo);
+                                                        let (movie_nfo, path) =
+                                                            AsyncMultiFs::new(conns, fs_id)
+                                                                .run(move |fs| {
+                                                                    keep_watch_state_and_store(
+                                                                        fs, movie_nfo, dialect,
+                                                                        path,
+                                                                    )
+                                                                })
+                                                                .await
+                                                                .context("NFO creation failed")?;
+                                                        Ok::<_, anyhow::Error>(vec![
+                                                            AppEvent::MovieManagerEvent(
+                                                                MovieManagerEvent::OpenTable,
+                                                            ),
+                                                            AppEvent::MovieManagerEvent(
+                                                                MovieManagerEvent::MovieUpdated((
+                                                                    movie_nfo, fs_id, path,
+                                                                )),
+                                                            ),
+                                                        ])
+                                                    }
+                                                    .await
+                                                    {
+                                                        Ok(ret) => ret,
+                                                        Err(err) => {
+                                                            report_error("NFO Creation", &err);
+                                                            vec![]
+                                                        }
                                                     }
-                                                    let mt = crate::get_metadata(conns_lock[fs_id].as_mut().unwrap(), lib_url, path.clone()).await?;
-                                                    movie_nfo.fileinfo = Some(mt);
-                                                    let nfo_string = quick_xml::se::to_string(&movie_nfo).map_err(|err| anyhow!("Failed to produce a valid NFO/XML, err:\n{:?}", err))?;
-                                                    let mut helper_path = path.clone();
-                                                    helper_path.set_extension("nfo");
-                                                    let mut buf = Cursor::new(Vec::new());
-                                                    buf.write_all(br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#).await?;
-                                                    buf.write_all(nfo_string.as_bytes()).await?;
-                                                    let _ = buf.rewind();
-                                                    let _ = conns_lock[fs_id].as_mut().unwrap().as_mut_rfs().create_file(&helper_path, &Metadata::default(), Box::new(buf))
-                                                        .map_err(|err| anyhow!("Can't open the nfo file., causes:\n{:?}", err))?;
-                                                    Ok(vec![
-                                                        AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable),
-                                                        AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((movie_nfo, fs_id, path)))
-                                                    ])
-                                                }.await {
-                                                    Ok(ret) => ret,
-                                                    Err(err) => {
-                                                        log::error!("NFO Creation failed due to the following error:\n{:?}", err);
-                                                        vec![]
-                                                    },
-                                                }
                                                 })
                                             },
                                         ))]
@@ -289,247 +858,1510 @@ impl From<MovieManagerMessage> for AppMessage {
                     },
                 ))
             }
-            MovieManagerMessage::RetrieveArtworks((nfo, fs_id, path)) => {
+            MovieManagerMessage::RetrieveArtworks((nfo, fs_id, path, force)) => {
                 AppMessage::IOFuture(Box::new(
-                    move |_, client: &reqwest::Client, _, conns: &ConnectionPool| {
+                    move |app_state, client: &reqwest::Client, _, conns: &ConnectionPool| {
+                        let lib = app_state.library(fs_id);
+                        let naming = lib.map(|l| l.asset_naming).unwrap_or_default();
+                        let lib_root = lib.map(|l| l.path.clone()).unwrap_or_default();
+                        let types = app_state.config.artworks.clone();
                         Box::pin(async move {
-                            let mut conns_lock = conns.lock().await;
-                            if conns_lock[fs_id].is_none() {
-                                log::error!("Failed to retrieve artworks on fs (id: {}), as it does not exist anymore.", fs_id);
-                                return vec![];
+                            let fs = AsyncMultiFs::new(conns, fs_id);
+                            let (movie, movie_path) = (nfo.clone(), path.clone());
+                            let found = fs
+                                .run(move |fs| {
+                                    let own_folder = naming == AssetNaming::Plex
+                                        && matches!(
+                                            fileops::movie_files(fs, &lib_root, &movie_path),
+                                            Ok(fileops::MovieFiles::Folder(_))
+                                        );
+                                    let actors = actor_thumb_downloads(fs, &movie, &movie_path);
+                                    Ok::<_, MkubeError>((own_folder, actors))
+                                })
+                                .await;
+                            let (own_folder, actors) = match found {
+                                Ok(found) => found,
+                                Err(err) => {
+                                    log::error!(
+                                        "Failed to retrieve artworks on fs (id: {}), causes:\n{:?}",
+                                        fs_id,
+                                        err
+                                    );
+                                    return vec![];
+                                }
+                            };
+                            let mut jobs = Vec::new();
+                            for (kind, th) in nfo.artworks() {
+                                if !types.is_enabled(kind) {
+                                    continue;
+                                }
+                                // Logos are PNG (or SVG), keep the format of the source.
+                                let ext = match th.path.rsplit_once('.') {
+                                    Some((_, ext)) if ext.len() <= 4 => ext.to_lowercase(),
+                                    _ => "jpg".into(),
+                                };
+                                let output =
+                                    naming.artwork_path(&path, kind.aspect(), &ext, own_folder);
+                                jobs.push(Download {
+                                    url: crate::tmdb_image_url(&th.path, types.size(kind)),
+                                    output,
+                                    label: format!("{} of {}", kind.aspect(), &nfo.title),
+                                });
                             }
-                            for th in nfo.thumb {
-                                if let Some(mut aspect) = th.aspect.clone() {
-                                    if aspect == "landscape" {
-                                        aspect = "fanart".into()
+                            jobs.extend(actors);
+                            downloads::manager().run(fs, client, jobs, force).await;
+                            let movie_path = path.clone();
+                            let assets = fs
+                                .run(move |fs| {
+                                    Ok::<_, MkubeError>(crate::detect_assets(fs, &movie_path))
+                                })
+                                .await
+                                .unwrap_or_default();
+                            vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::AssetsDetected((fs_id, path, assets)),
+                            )]
+                        })
+                    },
+                ))
+            }
+            MovieManagerMessage::IdentifyShow((fs_id, path, force)) => {
+                AppMessage::IOFuture(Box::new(
+                    move |app_state, _, tmdb_client: &dyn MetadataProvider, conns: &ConnectionPool| {
+                        use tmdb_api::tvshow::search::TVShowSearch;
+                        let lib = app_state.library(fs_id);
+                        let prefs = app_state.config.tmdb_preferences.for_library(lib);
+                        Box::pin(async move {
+                            match async move {
+                                let show_dir = shows::show_dir(&path)
+                                    .context(format!("No show folder for {}", path.display()))?;
+                                let dir = show_dir.clone();
+                                let show = AsyncMultiFs::new(conns, fs_id)
+                                    .run(move |fs| {
+                                        Ok::<_, MkubeError>(crate::read_show_nfo(fs, &dir))
+                                    })
+                                    .await
+                                    .context("Show identification failed")?;
+                                let sender = crate::MESSAGE_SENDER.get().unwrap();
+                                if let Some(tmdb_id) = show.as_ref().and_then(|s| s.tmdb_id()) {
+                                    sender
+                                        .send(
+                                            MovieManagerMessage::RetrieveShowArtworks((
+                                                fs_id, show_dir, tmdb_id, force,
+                                            ))
+                                            .into(),
+                                        )
+                                        .unwrap();
+                                    return Ok(vec![]);
+                                }
+                                let title = match show {
+                                    Some(show) if !show.title.is_empty() => show.title,
+                                    _ => {
+                                        crate::parser::parse_filename(
+                                            &show_dir
+                                                .file_name()
+                                                .map(|n| n.to_string_lossy())
+                                                .unwrap_or_default(),
+                                        )
+                                        .title
                                     }
-                                    let output = if let Some(name) =
-                                        path.file_stem().map(std::ffi::OsStr::to_string_lossy)
-                                    {
-                                        path.with_file_name(format!("{}-{}.jpg", name, &aspect))
-                                    } else {
-                                        path.with_file_name(&aspect)
-                                    };
-                                    match crate::download_file(
-                                        conns_lock[fs_id].as_mut().unwrap(),
-                                        &client,
-                                        output,
-                                        &*format!(
-                                            "https://image.tmdb.org/t/p/original{}",
-                                            &th.path
-                                        ),
-                                    )
+                                };
+                                let search = TVShowSearch::new(title.clone())
+                                    .with_language(Some(prefs.prefered_lang));
+                                let results = tmdb_client
+                                    .execute(&search)
                                     .await
-                                    {
-                                        Ok(()) => {}
-                                        Err(err) => {
-                                            log::error!(
-                                                "Failed to download {} ({}) for {}. Cause:\n{:?}",
-                                                &aspect,
-                                                &th.path,
-                                                &nfo.title,
-                                                err
-                                            );
+                                    .map_err(|err| {
+                                        anyhow!("Show search failed for `{}`:\n{:?}", title, err)
+                                    })?
+                                    .results;
+                                let shows: Vec<(u64, String)> = results
+                                    .into_iter()
+                                    .map(|s| {
+                                        let show = s.inner;
+                                        match show.first_air_date {
+                                            Some(date) => (
+                                                show.id,
+                                                format!("{} ({})", show.name, date.format("%Y")),
+                                            ),
+                                            None => (show.id, show.name),
                                         }
-                                    }
+                                    })
+                                    .collect();
+                                Ok::<_, anyhow::Error>(vec![AppEvent::ContinuationFuture(
+                                    Box::new(move |app_state: &mut AppState| {
+                                        if shows.is_empty() {
+                                            app_state.notice =
+                                                Some(format!("No show found for `{}`.", title));
+                                            return Box::pin(async { vec![] });
+                                        }
+                                        let names = shows.iter().map(|(_, n)| n.clone()).collect();
+                                        app_state.modals.push(crate::views::modal::Modal::picker(
+                                            "Identify show",
+                                            format!("Pick the show of {}:", show_dir.display()),
+                                            names,
+                                            move |_, picked| {
+                                                let sender = crate::MESSAGE_SENDER.get().unwrap();
+                                                sender
+                                                    .send(
+                                                        MovieManagerMessage::RetrieveShowArtworks(
+                                                            (
+                                                                fs_id,
+                                                                show_dir,
+                                                                shows[picked].0,
+                                                                force,
+                                                            ),
+                                                        )
+                                                        .into(),
+                                                    )
+                                                    .unwrap();
+                                                vec![]
+                                            },
+                                        ));
+                                        Box::pin(async { vec![] })
+                                    }),
+                                )])
+                            }
+                            .await
+                            {
+                                Ok(ret) => ret,
+                                Err(err) => {
+                                    report_error("Show identification", &err);
+                                    vec![]
+                                }
+                            }
+                        })
+                    },
+                ))
+            }
+            MovieManagerMessage::RetrieveShowArtworks((fs_id, show_dir, tmdb_id, force)) => {
+                AppMessage::IOFuture(Box::new(
+                    move |app_state,
+                          client: &reqwest::Client,
+                          tmdb_client: &dyn MetadataProvider,
+                          conns: &ConnectionPool| {
+                        let lib = app_state.library(fs_id);
+                        let prefs = app_state.config.tmdb_preferences.for_library(lib);
+                        let types = app_state.config.artworks.clone();
+                        Box::pin(async move {
+                            match async move {
+                                let dir = show_dir.clone();
+                                let episodes = AsyncMultiFs::new(conns, fs_id)
+                                    .for_operation(Operation::List)
+                                    .run(move |fs| shows::local_episodes(fs, &dir))
+                                    .await
+                                    .context("Show artworks retrieval failed")?;
+                                let jobs = shows::show_artwork_downloads(
+                                    tmdb_client,
+                                    tmdb_id,
+                                    Some(prefs.prefered_lang),
+                                    &show_dir,
+                                    &episodes,
+                                    &types,
+                                )
+                                .await?;
+                                let fs = AsyncMultiFs::new(conns, fs_id);
+                                let failed =
+                                    downloads::manager().run(fs, client, jobs, force).await;
+                                Ok::<_, anyhow::Error>(failed)
+                            }
+                            .await
+                            {
+                                Ok(0) => vec![],
+                                Ok(failed) => {
+                                    vec![AppEvent::ContinuationFuture(Box::new(
+                                        move |app_state: &mut AppState| {
+                                            app_state.notice = Some(format!(
+                                                "{} show artworks failed to download, see the logs.",
+                                                failed
+                                            ));
+                                            Box::pin(async { vec![] })
+                                        },
+                                    ))]
+                                }
+                                Err(err) => {
+                                    report_error("Show artworks retrieval", &err);
+                                    vec![]
                                 }
                             }
-                            return vec![];
                         })
                     },
                 ))
             }
-            MovieManagerMessage::SaveNfo((nfo, fs_id, path)) => {
+            MovieManagerMessage::SaveNfo((mut nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let kodi = app_state.config.kodi.clone();
+                    app_state.config.normalization.apply(&mut nfo);
+                    let nfo = nfo.for_dialect(app_state.config.nfo_dialect);
+                    Box::pin(async move {
+                        match async move {
+                            let (nfo, path) = AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| store_nfo(fs, &nfo, &path).map(|_| (nfo, path)))
+                                .await
+                                .context("NFO save failed")?;
+                            let mut events = vec![
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable),
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((
+                                    nfo, fs_id, path,
+                                ))),
+                            ];
+                            events.extend(crate::kodi::after_save(&kodi));
+                            Ok::<_, anyhow::Error>(events)
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("NFO save", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::LoadNfoSource((nfo, fs_id, path)) => {
                 AppMessage::IOFuture(Box::new(move |_, _, _, conns: &ConnectionPool| {
                     Box::pin(async move {
                         match async move {
-                            let mut conns_lock = conns.lock().await;
-                            if conns_lock[fs_id].is_none() {
-                                return Err(anyhow!(
-                                    "NFO save failed because fs_id {} does not exist anymore.",
-                                    fs_id
-                                ));
+                            let movie_path = path.clone();
+                            let found = AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| crate::read_nfo_source(fs, &movie_path))
+                                .await
+                                .context("NFO loading failed")?;
+                            let (nfo_path, source) = match found {
+                                Some(found) => found,
+                                // No NFO yet, start from the one write_nfo would produce.
+                                None => (
+                                    path.with_extension("nfo"),
+                                    format!(
+                                        "{}\n{}",
+                                        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                                        quick_xml::se::to_string(&nfo).map_err(|err| anyhow!(
+                                            "Failed to produce a valid NFO/XML, err:\n{:?}",
+                                            err
+                                        ))?
+                                    ),
+                                ),
+                            };
+                            Ok::<_, anyhow::Error>(vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::EditNfoSource((fs_id, path, nfo_path, source)),
+                            )])
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("NFO loading", &err);
+                                vec![]
                             }
-                            let nfo_string = quick_xml::se::to_string(&nfo).map_err(|err| {
-                                anyhow!("Failed to produce a valid NFO/XML, err:\n{:?}", err)
-                            })?;
-                            let mut helper_path = path.clone();
-                            helper_path.set_extension("nfo");
-                            let mut buf = Cursor::new(Vec::new());
-                            buf.write_all(
-                                br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
-                            )
-                            .await?;
-                            buf.write_all(nfo_string.as_bytes()).await?;
-                            let _ = buf.rewind();
-                            let _ = conns_lock[fs_id]
-                                .as_mut()
-                                .unwrap()
-                                .as_mut_rfs()
-                                .create_file(&helper_path, &Metadata::default(), Box::new(buf))
-                                .map_err(|err| {
-                                    anyhow!("Can't open the nfo file., causes:\n{:?}", err)
-                                })?;
-                            Ok(vec![
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::SaveNfoSource((fs_id, path, nfo_path, source)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let kodi = app_state.config.kodi.clone();
+                    Box::pin(async move {
+                        match async move {
+                            let nfo: Movie = quick_xml::de::from_str(&source)
+                                .map_err(|err| anyhow!("Invalid NFO/XML, causes:\n{:?}", err))?;
+                            AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| store_nfo_source(fs, &nfo_path, &source))
+                                .await
+                                .context("NFO save failed")?;
+                            let mut events = vec![
                                 AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable),
                                 AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((
                                     nfo, fs_id, path,
                                 ))),
-                            ])
+                            ];
+                            events.extend(crate::kodi::after_save(&kodi));
+                            Ok::<_, anyhow::Error>(events)
                         }
                         .await
                         {
                             Ok(ret) => ret,
                             Err(err) => {
-                                log::error!(
-                                    "NFO save failed due to the following error:\n{:?}",
-                                    err
-                                );
+                                report_error("NFO save", &err);
                                 vec![]
                             }
                         }
                     })
                 }))
             }
-            MovieManagerMessage::Rename((nfo, fs_id, path)) => {
+            MovieManagerMessage::ListNfoBackups((nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |_, _, _, conns: &ConnectionPool| {
+                    Box::pin(async move {
+                        let nfo_paths = [path.with_extension("nfo"), path.join("movie.nfo")];
+                        match AsyncMultiFs::new(conns, fs_id)
+                            .for_operation(Operation::List)
+                            .run(move |fs| {
+                                for nfo_path in nfo_paths {
+                                    let backups = match crate::backups::list(fs, &nfo_path) {
+                                        Ok(backups) if !backups.is_empty() => backups,
+                                        _ => continue,
+                                    };
+                                    return Ok::<_, anyhow::Error>(Some((nfo_path, backups)));
+                                }
+                                Ok(None)
+                            })
+                            .await
+                            .context("NFO backups listing failed")
+                        {
+                            Ok(Some((nfo_path, backups))) => {
+                                let items = backups
+                                    .iter()
+                                    .map(|backup| {
+                                        backup
+                                            .file_name()
+                                            .unwrap_or_default()
+                                            .to_string_lossy()
+                                            .into_owned()
+                                    })
+                                    .collect();
+                                let message = format!("Restore the NFO of {} from:", nfo.title);
+                                vec![AppEvent::ContinuationFuture(Box::new(
+                                    move |app_state: &mut AppState| {
+                                        app_state.modals.push(crate::views::modal::Modal::picker(
+                                            "Restore NFO",
+                                            message,
+                                            items,
+                                            move |_, picked| {
+                                                let sender = crate::MESSAGE_SENDER.get().unwrap();
+                                                let msg = MovieManagerMessage::RestoreNfo((
+                                                    fs_id,
+                                                    path,
+                                                    nfo_path,
+                                                    backups[picked].clone(),
+                                                ));
+                                                sender.send(msg.into()).unwrap();
+                                                vec![]
+                                            },
+                                        ));
+                                        Box::pin(async { vec![] })
+                                    },
+                                ))]
+                            }
+                            Ok(None) => vec![AppEvent::ContinuationFuture(Box::new(
+                                |app_state: &mut AppState| {
+                                    app_state.notice = Some("No backup of this NFO.".into());
+                                    Box::pin(async { vec![] })
+                                },
+                            ))],
+                            Err(err) => {
+                                report_error("NFO backups listing", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::RestoreNfo((fs_id, path, nfo_path, backup)) => {
                 AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
-                    let renamer = app_state.config.renamer.clone();
+                    let kodi = app_state.config.kodi.clone();
                     Box::pin(async move {
                         match async move {
-                            let mut conns_lock = conns.lock().await;
-                            if conns_lock[fs_id].is_none() {
-                                return Err(anyhow!(
-                                    "Rename task failed because fs_id {} does not exist anymore.",
-                                    fs_id
-                                ));
+                            let movie_path = path.clone();
+                            let nfo = AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| {
+                                    crate::backups::restore(fs, &backup, &nfo_path)?;
+                                    log::info!(
+                                        "Restored {} from {}.",
+                                        nfo_path.display(),
+                                        backup.display()
+                                    );
+                                    Ok::<_, anyhow::Error>(crate::open_nfo(fs, &movie_path)?)
+                                })
+                                .await
+                                .context("NFO restore failed")?;
+                            let mut events = vec![
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::OpenTable),
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((
+                                    nfo, fs_id, path,
+                                ))),
+                            ];
+                            events.extend(crate::kodi::after_save(&kodi));
+                            Ok::<_, anyhow::Error>(events)
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("NFO restore", &err);
+                                vec![]
                             }
-
-                            if let Some(parent) = path.parent() {
-                                let named = HashMap::from([
-                                    ("title", FmtStr::new(nfo.title.as_str())),
-                                    (
-                                        "original_title",
-                                        FmtStr::new(
-                                            nfo.original_title.as_deref().unwrap_or(&nfo.title),
-                                        ),
-                                    ),
-                                    (
-                                        "release_date",
-                                        FmtStr::new(
-                                            nfo.premiered.as_deref().unwrap_or("XXXX-XX-XX"),
-                                        ),
-                                    ),
-                                    (
-                                        "year",
-                                        FmtStr::new(
-                                            nfo.premiered
-                                                .as_deref()
-                                                .map(|date| date[..4].to_owned())
-                                                .unwrap_or("XXXX".into()),
-                                        ),
-                                    ),
-                                    (
-                                        "source",
-                                        FmtStr::new(nfo.source.as_deref().unwrap_or("NONE")),
-                                    ),
-                                ]);
-                                let dir_arg = ParsedFormat::parse(
-                                    &renamer.dir_format,
-                                    &NoPositionalArguments,
-                                    &named,
-                                )
-                                .or(Err(anyhow!("dir_format is invalid!")))?;
-                                let dir_name = deunicode::deunicode_with_tofu(
-                                    &format!("{}", dir_arg),
-                                    &renamer.dir_separator,
-                                )
-                                .replace(
-                                    &[' ', ':', '<', '>', '?', '!', '|', '/', '\\', '*', '"'],
-                                    &renamer.dir_separator,
-                                );
-                                let file_arg = ParsedFormat::parse(
-                                    &renamer.file_format,
-                                    &NoPositionalArguments,
-                                    &named,
-                                )
-                                .or(Err(anyhow!("file_format is invalid!")))?;
-                                let file_name = deunicode::deunicode_with_tofu(
-                                    &format!("{}", file_arg),
-                                    &renamer.file_separator,
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::Rename((nfo, fs_id, path)) => rename_task(nfo, fs_id, path, None),
+            MovieManagerMessage::RenameResolved((nfo, fs_id, path, collision)) => {
+                rename_task(nfo, fs_id, path, Some(collision))
+            }
+            MovieManagerMessage::DeleteMovie((nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let lib_root = app_state.library(fs_id).map(|lib| lib.path.clone());
+                    Box::pin(async move {
+                        match async move {
+                            let lib_root =
+                                lib_root.ok_or(anyhow!("Library {} does not exist.", fs_id))?;
+                            let movie_path = path.clone();
+                            AsyncMultiFs::new(conns, fs_id)
+                                .for_operation(Operation::Rename)
+                                .run(move |fs| {
+                                    let files = fileops::movie_files(fs, &lib_root, &movie_path)?;
+                                    fileops::delete_movie(fs, &files)
+                                })
+                                .await
+                                .context("Delete task failed")?;
+                            log::info!("{} deleted.", &nfo.title);
+                            Ok::<_, anyhow::Error>(vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::MovieRemoved((fs_id, path)),
+                            )])
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("Delete task", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::MoveMovie((nfo, fs_id, path, dest_id)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let lib = |id: LibraryId| app_state.library(id);
+                    let libs = lib(fs_id).zip(lib(dest_id)).map(|(src, dest)| {
+                        (src.path.clone(), dest.path.clone(), src.same_backend(dest))
+                    });
+                    Box::pin(async move {
+                        match async move {
+                            let (src_root, dest_root, same_backend) =
+                                libs.ok_or(anyhow!("Library does not exist."))?;
+                            if fs_id == dest_id {
+                                bail!("The movie is already in this library.");
+                            }
+                            // A copy reads the whole media, a rename is quick.
+                            let operation = if same_backend {
+                                Operation::Rename
+                            } else {
+                                Operation::Read
+                            };
+                            let movie_path = path.clone();
+                            let (new_path, assets) = AsyncMultiFs::new(conns, fs_id)
+                                .for_operation(operation)
+                                .run_with(dest_id, move |from, to| {
+                                    let new_path = fileops::move_movie(
+                                        (from, &src_root),
+                                        &movie_path,
+                                        (to, &dest_root),
+                                        same_backend,
+                                    )?;
+                                    let assets = crate::detect_assets(to, &new_path);
+                                    Ok::<_, anyhow::Error>((new_path, assets))
+                                })
+                                .await?;
+                            log::info!("{} moved to {}.", &nfo.title, new_path.display());
+                            Ok::<_, anyhow::Error>(vec![
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::MovieRemoved((
+                                    fs_id, path,
+                                ))),
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered((
+                                    nfo,
+                                    dest_id,
+                                    new_path.clone(),
+                                ))),
+                                AppEvent::MovieManagerEvent(MovieManagerEvent::AssetsDetected((
+                                    dest_id, new_path, assets,
+                                ))),
+                            ])
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("Move task", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::RefreshFromTmdb((mut nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(
+                    move |app_state, _, tmdb_client: &dyn MetadataProvider, conns: &ConnectionPool| {
+                        let fields = app_state.config.refresh.clone();
+                        let artworks = app_state.config.artworks.clone();
+                        let dialect = app_state.config.nfo_dialect;
+                        let normalization = app_state.config.normalization.clone();
+                        let prefs = app_state
+                            .config
+                            .tmdb_preferences
+                            .for_library(app_state.library(fs_id));
+                        Box::pin(async move {
+                            match async move {
+                                let tmdb_id = nfo
+                                    .tmdb_id()
+                                    .ok_or(anyhow!("{} has no TMDB id.", &nfo.title))?;
+                                let fresh = crate::transform_as_nfo(
+                                    tmdb_client,
+                                    tmdb_id,
+                                    &prefs.languages(),
+                                    prefs.certification_country(),
+                                    &artworks,
                                 )
-                                .replace(
-                                    &[' ', ':', '<', '>', '?', '!', '|', '/', '\\', '*', '"'],
-                                    &renamer.file_separator,
-                                );
-                                let new_dir = parent.with_file_name(dir_name);
-                                conns_lock[fs_id]
-                                    .as_mut()
-                                    .unwrap()
-                                    .as_mut_rfs()
-                                    .mov(&parent, &new_dir)
-                                    .context("failed to rename the parent dir")?;
-                                let entries = conns_lock[fs_id]
-                                    .as_mut()
-                                    .unwrap()
-                                    .as_mut_rfs()
-                                    .list_dir(&new_dir)
-                                    .context("failed to iterate the dir entry")?;
-                                let old_name = path
-                                    .file_stem()
-                                    .ok_or(anyhow!("Movie path does not contain a file stem."))?
-                                    .to_string_lossy()
-                                    .to_owned();
-                                for entry in entries {
-                                    if let Some(name) = entry.path.file_name() {
-                                        if name.to_string_lossy().starts_with(&*old_name) {
-                                            let new_name = name
-                                                .to_string_lossy()
-                                                .replacen(&*old_name, &file_name, 1);
-                                            let new_path = entry.path().with_file_name(new_name);
-                                            conns_lock[fs_id]
-                                                .as_mut()
-                                                .unwrap()
-                                                .as_mut_rfs()
-                                                .mov(&entry.path(), &new_path)
-                                                .context(format!(
-                                                    "failed to move {} to {}!",
-                                                    entry.path.display(),
-                                                    new_path.display()
-                                                ))?;
-                                        }
-                                    }
-                                }
-                                let movie_name = path
-                                    .file_name()
-                                    .ok_or(anyhow!(
-                                        "Oops, movie path does not contain a filename..."
-                                    ))?
-                                    .to_owned();
-                                let new_path = new_dir.join(PathBuf::from(movie_name));
-                                Ok(vec![AppEvent::MovieManagerEvent(
-                                    MovieManagerEvent::MovieMoved((fs_id, path, new_path)),
+                                .await?;
+                                nfo.refresh_from(fresh, &fields);
+                                normalization.apply(&mut nfo);
+                                let nfo = nfo.for_dialect(dialect);
+                                let (nfo, path) = AsyncMultiFs::new(conns, fs_id)
+                                    .run(move |fs| store_nfo(fs, &nfo, &path).map(|_| (nfo, path)))
+                                    .await
+                                    .context("NFO refresh failed")?;
+                                Ok::<_, anyhow::Error>(vec![AppEvent::MovieManagerEvent(
+                                    MovieManagerEvent::MovieUpdated((nfo, fs_id, path)),
                                 )])
-                            } else {
-                                return Err(anyhow!(
-                                    "Rename task failed because no parent exists for path {}.",
-                                    path.display()
+                            }
+                            .await
+                            {
+                                Ok(ret) => ret,
+                                Err(err) => {
+                                    report_error("NFO refresh", &err);
+                                    vec![]
+                                }
+                            }
+                        })
+                    },
+                ))
+            }
+            MovieManagerMessage::ProbeMedia((mut nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let dialect = app_state.config.nfo_dialect;
+                    let lib_url = app_state
+                        .library(fs_id)
+                        .ok_or(())
+                        .and_then(Library::ffmpeg_base);
+                    Box::pin(async move {
+                        match async move {
+                            let lib_url = lib_url.map_err(|_| {
+                                anyhow!("library {} creates an unexpected URL.", fs_id)
+                            })?;
+                            nfo.fileinfo = Some(
+                                crate::get_metadata(conns, fs_id, lib_url, path.clone())
+                                    .await
+                                    .context("Media probing failed")?,
+                            );
+                            let nfo = nfo.for_dialect(dialect);
+                            let (nfo, path) = AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| store_nfo(fs, &nfo, &path).map(|_| (nfo, path)))
+                                .await
+                                .context("Media probing failed")?;
+                            Ok::<_, anyhow::Error>(vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::MovieUpdated((nfo, fs_id, path)),
+                            )])
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("Media probing", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::GrabThumb((nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let lib = app_state.library(fs_id);
+                    let naming = lib.map(|l| l.asset_naming).unwrap_or_default();
+                    let lib_root = lib.map(|l| l.path.clone()).unwrap_or_default();
+                    let lib_url = lib.ok_or(()).and_then(Library::ffmpeg_base);
+                    let timestamp = app_state.config.frame_grab.timestamp;
+                    Box::pin(async move {
+                        match async move {
+                            let lib_url = lib_url.map_err(|_| {
+                                anyhow!("library {} creates an unexpected URL.", fs_id)
+                            })?;
+                            let movie_path = path.clone();
+                            let (output, assets) = AsyncMultiFs::new(conns, fs_id)
+                                .for_operation(Operation::Read)
+                                .run(move |fs| {
+                                    let path = movie_path;
+                                    let jpeg =
+                                        crate::multifs::grab_frame(fs, lib_url, &path, timestamp)?;
+                                    let own_folder = naming == AssetNaming::Plex
+                                        && matches!(
+                                            fileops::movie_files(fs, &lib_root, &path),
+                                            Ok(fileops::MovieFiles::Folder(_))
+                                        );
+                                    let output =
+                                        naming.artwork_path(&path, "thumb", "jpg", own_folder);
+                                    let mut writer = fs.create_writer(&output)?;
+                                    writer.write_all(&jpeg)?;
+                                    writer.finish()?;
+                                    let assets = crate::detect_assets(fs, &path);
+                                    Ok::<_, anyhow::Error>((output, assets))
+                                })
+                                .await
+                                .context("Thumbnail grab failed")?;
+                            log::info!(
+                                "Thumbnail of {} saved to {}.",
+                                &nfo.title,
+                                output.display()
+                            );
+                            Ok::<_, anyhow::Error>(vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::AssetsDetected((fs_id, path, assets)),
+                            )])
+                        }
+                        .await
+                        {
+                            Ok(ret) => ret,
+                            Err(err) => {
+                                report_error("Thumbnail grab", &err);
+                                vec![]
+                            }
+                        }
+                    })
+                }))
+            }
+            MovieManagerMessage::VerifyChecksum((nfo, fs_id, path)) => {
+                AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+                    let algorithm = app_state.config.hashing.algorithm;
+                    let read_only = app_state.library(fs_id).is_some_and(|lib| lib.read_only);
+                    Box::pin(async move {
+                        match async move {
+                            let (notice, updated) = AsyncMultiFs::new(conns, fs_id)
+                                .for_operation(Operation::Read)
+                                .run(move |fs| {
+                                    verify_checksum(fs, nfo, &path, algorithm, read_only)
+                                })
+                                .await
+                                .context("Checksum verification failed")?;
+                            let mut events = Vec::new();
+                            if let Some((nfo, path)) = updated {
+                                events.push(AppEvent::MovieManagerEvent(
+                                    MovieManagerEvent::MovieUpdated((nfo, fs_id, path)),
                                 ));
                             }
+                            events.push(AppEvent::ContinuationFuture(Box::new(
+                                move |app_state: &mut AppState| {
+                                    app_state.notice = Some(notice);
+                                    Box::pin(async { vec![] })
+                                },
+                            )));
+                            Ok::<_, anyhow::Error>(events)
                         }
                         .await
                         {
                             Ok(ret) => ret,
                             Err(err) => {
-                                log::error!(
-                                    "Rename task failed due to the following error:\n{:?}",
-                                    err
-                                );
+                                report_error("Checksum verification", &err);
                                 vec![]
                             }
                         }
                     })
                 }))
             }
+            MovieManagerMessage::ProbeSeason((fs_id, season_dir)) => {
+                AppMessage::IOFuture(Box::new(move |_, _, _, conns: &ConnectionPool| {
+                    Box::pin(async move {
+                        let dir = season_dir.clone();
+                        match AsyncMultiFs::new(conns, fs_id)
+                            .for_operation(Operation::List)
+                            .run(move |fs| batch::probe_season_ops(fs, fs_id, &dir))
+                            .await
+                            .context("Season probing failed")
+                        {
+                            Ok(ops) if ops.is_empty() => {
+                                log::info!("No episode found in {}.", season_dir.display());
+                            }
+                            Ok(ops) => {
+                                let sender = crate::MESSAGE_SENDER.get().unwrap();
+                                sender.send(MovieManagerMessage::Batch(ops).into()).unwrap();
+                            }
+                            Err(err) => report_error("Season probing", &err),
+                        }
+                        vec![]
+                    })
+                }))
+            }
+            MovieManagerMessage::Batch(ops) => {
+                AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                    match batch::check_batch(&ops, &app_state.config.safety) {
+                        batch::BatchCheck::Proceed => {
+                            let sender = crate::MESSAGE_SENDER.get().unwrap();
+                            sender
+                                .send(MovieManagerMessage::RunBatch(ops).into())
+                                .unwrap();
+                            vec![]
+                        }
+                        batch::BatchCheck::Confirm => {
+                            vec![AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::ConfirmBatch(ops),
+                            )]
+                        }
+                        batch::BatchCheck::Refuse(reason) => {
+                            log::error!("{}", &reason);
+                            app_state.notice = Some(reason);
+                            vec![]
+                        }
+                    }
+                }))
+            }
+            MovieManagerMessage::AutoIdentify(movies) => AppMessage::HttpFuture(Box::new(
+                move |app_state: &mut AppState, _: &reqwest::Client, tmdb_client: &dyn MetadataProvider| {
+                    let min_confidence = app_state.config.auto_match.min_confidence;
+                    let prefs: HashMap<LibraryId, TmdbPreferences> = app_state
+                        .libraries
+                        .iter()
+                        .flatten()
+                        .map(|lib| {
+                            let prefs = app_state.config.tmdb_preferences.for_library(Some(lib));
+                            (lib.id, prefs)
+                        })
+                        .collect();
+                    Box::pin(async move {
+                        let mut planned = Vec::new();
+                        let mut review = Vec::new();
+                        for (movie, fs_id, path) in movies {
+                            let parsed = crate::parser::parse_path(&path);
+                            let prefs = prefs.get(&fs_id).cloned().unwrap_or_default();
+                            match auto_match(tmdb_client, &parsed, &prefs).await {
+                                Ok(Some(found))
+                                    if crate::parser::match_confidence(
+                                        &parsed,
+                                        &found.inner.title,
+                                        &found.inner.original_title,
+                                        found
+                                            .inner
+                                            .release_date
+                                            .and_then(|d| d.format("%Y").to_string().parse().ok()),
+                                    ) >= min_confidence =>
+                                {
+                                    planned.push(PlannedChange::new(&movie, fs_id, path, &found));
+                                }
+                                Ok(_) => review.push((movie, fs_id, path)),
+                                Err(err) => {
+                                    log::error!(
+                                        "Auto-identify search failed for `{}` due to:\n{:?}",
+                                        &parsed.title,
+                                        err
+                                    );
+                                    review.push((movie, fs_id, path));
+                                }
+                            }
+                        }
+                        log::info!(
+                            "Auto-identify: {} movie(s) matched, {} queued for manual review.",
+                            planned.len(),
+                            review.len()
+                        );
+                        let mut events = vec![AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::QueueReview(review),
+                        )];
+                        if !planned.is_empty() {
+                            events.push(AppEvent::MovieManagerEvent(
+                                MovieManagerEvent::ReviewBatch(planned),
+                            ));
+                        }
+                        events
+                    })
+                },
+            )),
+            MovieManagerMessage::CheckNfos(movies) => {
+                AppMessage::IOFuture(Box::new(move |_, _, _, conns: &ConnectionPool| {
+                    Box::pin(async move {
+                        let mut checked = 0;
+                        let mut issues = Vec::new();
+                        for (movie, fs_id, path) in movies {
+                            let movie_path = path.clone();
+                            let source = AsyncMultiFs::new(conns, fs_id)
+                                .run(move |fs| crate::read_nfo_source(fs, &movie_path))
+                                .await;
+                            match source {
+                                Ok(Some((_, source))) => {
+                                    checked += 1;
+                                    let problems = crate::lint::lint_nfo(&source);
+                                    if !problems.is_empty() {
+                                        issues.push(NfoIssue {
+                                            fs_id,
+                                            path,
+                                            title: movie.title,
+                                            problems,
+                                        });
+                                    }
+                                }
+                                Ok(None) => {}
+                                Err(err) => log::error!(
+                                    "Failed to read the NFO of {}, causes:\n{:?}",
+                                    path.display(),
+                                    err
+                                ),
+                            }
+                        }
+                        log::info!(
+                            "NFO health: {} NFO(s) checked, {} with problems.",
+                            checked,
+                            issues.len()
+                        );
+                        vec![AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::HealthReport((checked, issues)),
+                        )]
+                    })
+                }))
+            }
+            MovieManagerMessage::RunBatch(ops) => AppMessage::IOFuture(Box::new(
+                move |app_state, _, tmdb_client: &dyn MetadataProvider, conns: &ConnectionPool| {
+                    let ctx = batch::BatchContext::from(&*app_state);
+                    let journal = app_state
+                        .batch_journals
+                        .as_deref()
+                        .map(batch::BatchJournal::create);
+                    let kodi = app_state.config.kodi.clone();
+                    Box::pin(async move {
+                        let mut events =
+                            batch::run_batch(ops, ctx, journal, tmdb_client, conns).await;
+                        events.extend(crate::kodi::after_save(&kodi));
+                        events
+                    })
+                },
+            )),
+        }
+    }
+}
+
+/// Rename a movie, asking how to solve a collision if the renamer does not say it.
+fn rename_task(
+    nfo: Movie,
+    fs_id: LibraryId,
+    path: PathBuf,
+    collision: Option<RenameCollision>,
+) -> AppMessage {
+    AppMessage::IOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+        let mut renamer = app_state.config.renamer.clone();
+        if let Some(collision) = collision {
+            renamer.collision = collision;
         }
+        let rules = app_state
+            .library(fs_id)
+            .map(Library::name_rules)
+            .unwrap_or_default();
+        Box::pin(async move {
+            let renamed = {
+                let (renamer, nfo, path) = (renamer.clone(), nfo.clone(), path.clone());
+                AsyncMultiFs::new(conns, fs_id)
+                    .for_operation(Operation::Rename)
+                    .run(move |fs| rename_movie(fs, &renamer, &rules, &nfo, &path))
+                    .await
+                    .context("Rename task failed")
+            };
+            match renamed {
+                Ok((new_path, _)) => vec![AppEvent::MovieManagerEvent(
+                    MovieManagerEvent::MovieMoved((fs_id, path, new_path)),
+                )],
+                Err(err) => match MkubeError::find(&err) {
+                    Some(MkubeError::Collision(taken))
+                        if renamer.collision == RenameCollision::Ask =>
+                    {
+                        let message = format!(
+                            "{} already exists, rename {} anyway?",
+                            taken.display(),
+                            nfo.title
+                        );
+                        let choices = [RenameCollision::Suffix, RenameCollision::Merge];
+                        vec![AppEvent::ContinuationFuture(Box::new(
+                            move |app_state: &mut AppState| {
+                                app_state.modals.push(crate::views::modal::Modal::picker(
+                                    "Rename collision",
+                                    message,
+                                    vec![
+                                        "Add a (1) suffix to the new names".into(),
+                                        "Merge into the existing folder".into(),
+                                    ],
+                                    move |_, picked| {
+                                        let sender = crate::MESSAGE_SENDER.get().unwrap();
+                                        sender
+                                            .send(
+                                                MovieManagerMessage::RenameResolved((
+                                                    nfo,
+                                                    fs_id,
+                                                    path,
+                                                    choices[picked],
+                                                ))
+                                                .into(),
+                                            )
+                                            .unwrap();
+                                        vec![]
+                                    },
+                                ));
+                                Box::pin(async { vec![] })
+                            },
+                        ))]
+                    }
+                    _ => {
+                        report_error("Rename task", &err);
+                        vec![]
+                    }
+                },
+            }
+        })
+    }))
+}
+
+/// Search TMDB for a parsed movie name, retrying without the year if nothing was found.
+async fn auto_match(
+    tmdb_client: &dyn MetadataProvider,
+    parsed: &crate::parser::ParsedName,
+    prefs: &TmdbPreferences,
+) -> Result<Option<tmdb_api::movie::MovieShort>> {
+    use tmdb_api::movie::search::MovieSearch;
+    let search = |year: Option<u16>| {
+        MovieSearch::new(parsed.title.clone())
+            .with_language(Some(prefs.prefered_lang.clone()))
+            .with_region(Some(prefs.prefered_country.clone()))
+            .with_year(year)
+    };
+    let mut results = tmdb_client
+        .execute(&search(parsed.year))
+        .await
+        .map_err(|err| anyhow!("{:?}", err))?
+        .results;
+    if results.is_empty() && parsed.year.is_some() {
+        results = tmdb_client
+            .execute(&search(None))
+            .await
+            .map_err(|err| anyhow!("{:?}", err))?
+            .results;
+    }
+    Ok(results.into_iter().next())
+}
+
+/// Download the actor thumbnails in the `.actors` folder, next to the movie file (as Kodi expects).
+fn actor_thumb_downloads(fs: &mut MultiFs, nfo: &Movie, path: &Path) -> Vec<Download> {
+    let actors: Vec<_> = nfo
+        .actor
+        .iter()
+        .filter_map(|actor| actor.thumb.as_ref().map(|th| (&actor.name, &th.path)))
+        .collect();
+    if actors.is_empty() {
+        return vec![];
+    }
+    let actors_dir = path.with_file_name(".actors");
+    match fs
+        .as_mut_rfs()
+        .create_dir(&actors_dir, UnixPex::from(0o755))
+    {
+        Ok(()) => {}
+        Err(err) if err.kind == RemoteErrorType::DirectoryAlreadyExists => {}
+        Err(err) => {
+            log::error!(
+                "Failed to create {}, causes:\n{:?}",
+                actors_dir.display(),
+                err
+            );
+            return vec![];
+        }
+    }
+    actors
+        .into_iter()
+        .map(|(name, url)| Download {
+            url: url.clone(),
+            output: actors_dir.join(format!("{}.jpg", actor_file_name(name))),
+            label: format!("the thumbnail of {} for {}", name, &nfo.title),
+        })
+        .collect()
+}
+
+/// Kodi names actor thumbnails after the actor, with spaces replaced by underscores.
+fn actor_file_name(name: &str) -> String {
+    name.replace([' ', ':', '<', '>', '?', '|', '/', '\\', '*', '"'], "_")
+}
+
+pub async fn write_nfo<T: Serialize>(fs: &mut MultiFs, nfo: &T, path: &Path) -> Result<PathBuf> {
+    store_nfo(fs, nfo, path)
+}
+
+/// [`write_nfo`] for the blocking calls of an [`AsyncMultiFs`].
+///
+/// [`AsyncMultiFs`]: crate::asyncfs::AsyncMultiFs
+pub fn store_nfo<T: Serialize>(fs: &mut MultiFs, nfo: &T, path: &Path) -> Result<PathBuf> {
+    let nfo_string = quick_xml::se::to_string(nfo)
+        .map_err(|err| anyhow!("Failed to produce a valid NFO/XML, err:\n{:?}", err))?;
+    let mut helper_path = path.to_path_buf();
+    helper_path.set_extension("nfo");
+    crate::backups::before_overwrite(fs, &helper_path)?;
+    let mut writer = fs.create_writer(&helper_path)?;
+    writer
+        .write_all(br#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#)
+        .and_then(|_| writer.write_all(nfo_string.as_bytes()))
+        .map_err(|err| anyhow!("Can't write the nfo file., causes:\n{:?}", err))?;
+    writer.finish()?;
+    Ok(helper_path)
+}
+
+/// Store a new NFO of the movie at `path`, with the watch state of the previous one.
+fn keep_watch_state_and_store(
+    fs: &mut MultiFs,
+    mut nfo: Movie,
+    dialect: NfoDialect,
+    path: PathBuf,
+) -> Result<(Movie, PathBuf)> {
+    if let Ok(previous) = crate::open_nfo(fs, &path) {
+        nfo.keep_watch_state(&previous);
+    }
+    let nfo = nfo.for_dialect(dialect);
+    store_nfo(fs, &nfo, &path)?;
+    Ok((nfo, path))
+}
+
+/// Write the text of an NFO as it is, after a backup of the previous one.
+pub fn store_nfo_source(fs: &mut MultiFs, nfo_path: &Path, source: &str) -> Result<()> {
+    crate::backups::before_overwrite(fs, nfo_path)?;
+    let mut writer = fs.create_writer(nfo_path)?;
+    writer
+        .write_all(source.as_bytes())
+        .map_err(|err| anyhow!("Can't write the nfo file., causes:\n{:?}", err))?;
+    writer.finish()
+}
+
+/// Check the movie file against the checksum of its NFO, or store it when missing.
+///
+/// Returns the notice to show, with the NFO when it was updated.
+fn verify_checksum(
+    fs: &mut MultiFs,
+    nfo: Movie,
+    path: &Path,
+    algorithm: crate::config::HashAlgorithm,
+    read_only: bool,
+) -> Result<(String, Option<(Movie, PathBuf)>)> {
+    let title = nfo.title.clone();
+    let notice = match &nfo.checksum {
+        Some(expected) => {
+            let actual = crate::checksum::compute(fs, path, expected.algorithm)?;
+            if &actual == expected {
+                format!("{}: checksum verified.", title)
+            } else {
+                log::error!(
+                    "Checksum mismatch for {}, {} expected but {} found.",
+                    path.display(),
+                    expected.value,
+                    actual.value
+                );
+                format!("{}: checksum mismatch, the file may be corrupted!", title)
+            }
+        }
+        None if read_only => {
+            let checksum = crate::checksum::compute(fs, path, algorithm)?;
+            format!(
+                "{}: checksum {}, not stored in the read-only library.",
+                title, checksum.value
+            )
+        }
+        None if crate::read_nfo_source(fs, path)?.is_none() => {
+            let checksum = crate::checksum::compute(fs, path, algorithm)?;
+            format!(
+                "{}: checksum {}, no NFO to store it.",
+                title, checksum.value
+            )
+        }
+        None => {
+            let mut nfo = nfo;
+            store_checksum(fs, &mut nfo, path, algorithm)?;
+            let notice = format!("{}: checksum stored in the NFO.", title);
+            return Ok((notice, Some((nfo, path.to_owned()))));
+        }
+    };
+    Ok((notice, None))
+}
+
+/// Hash the movie file and save the checksum in its NFO.
+fn store_checksum(
+    fs: &mut MultiFs,
+    nfo: &mut Movie,
+    path: &Path,
+    algorithm: crate::config::HashAlgorithm,
+) -> Result<()> {
+    let checksum = crate::checksum::compute(fs, path, algorithm)?;
+    log::info!("Checksum of {}: {}", path.display(), checksum.value);
+    nfo.checksum = Some(checksum);
+    store_nfo(fs, nfo, path)?;
+    Ok(())
+}
+
+/// Suffixes tried before giving up on a colliding rename.
+const MAX_SUFFIX: usize = 99;
+
+/// Rename the movie dir and files, returns the new movie path and every renamed path.
+///
+/// The collisions and the path lengths allowed by `rules` are checked before anything is
+/// renamed, the collisions are handled after `renamer.collision`. A failed rename is rolled back.
+pub fn rename_movie(
+    fs: &mut MultiFs,
+    renamer: &Renamer,
+    rules: &NameRules,
+    nfo: &Movie,
+    path: &Path,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
+    let parent = path.parent().ok_or(anyhow!(
+        "Rename task failed because no parent exists for path {}.",
+        path.display()
+    ))?;
+    let (dir_name, mut file_name) = renamer.new_names(nfo, rules)?;
+    let entries = fs
+        .as_mut_rfs()
+        .list_dir(parent)
+        .context("failed to iterate the dir entry")?;
+    let movie_name = path
+        .file_name()
+        .ok_or(anyhow!("Oops, movie path does not contain a filename..."))?
+        .to_string_lossy()
+        .into_owned();
+    // The parts of a multi-part movie are renamed after their shared stem, keeping their suffix.
+    let paths: Vec<PathBuf> = entries.iter().map(|e| e.path.clone()).collect();
+    let old_name = crate::parser::stack_stem(path, &paths)
+        .ok_or(anyhow!("Movie path does not contain a file stem."))?;
+    let exists = |fs: &mut MultiFs, path: &Path| {
+        fs.as_mut_rfs()
+            .exists(path)
+            .context(format!("failed to check {}", path.display()))
+    };
+
+    let mut new_dir = parent.with_file_name(&dir_name);
+    let mut merge = false;
+    if new_dir != parent && exists(fs, &new_dir)? {
+        match renamer.collision {
+            RenameCollision::Ask | RenameCollision::Abort => {
+                return Err(MkubeError::Collision(new_dir).into())
+            }
+            RenameCollision::Suffix => {
+                new_dir = (1..=MAX_SUFFIX)
+                    .map(|n| parent.with_file_name(suffixed(&dir_name, &renamer.dir_separator, n)))
+                    .find(|dir| !exists(fs, dir).unwrap_or(true))
+                    .ok_or(MkubeError::Collision(new_dir))?;
+            }
+            RenameCollision::Merge => merge = true,
+        }
+    }
+    // Names already used in the destination folder, by the files that are not renamed.
+    let taken: HashSet<String> = if merge {
+        fs.as_mut_rfs()
+            .list_dir(&new_dir)
+            .context("failed to iterate the dir entry")?
+            .iter()
+            .map(|e| e.name())
+            .collect()
+    } else {
+        entries
+            .iter()
+            .map(|e| e.name())
+            .filter(|name| !name.starts_with(&*old_name))
+            .collect()
+    };
+    let renamed = |file_name: &str| -> Vec<(PathBuf, String)> {
+        entries
+            .iter()
+            .filter(|e| merge || e.name().starts_with(&*old_name))
+            .map(|e| {
+                let name = e.name();
+                let new_name = if name.starts_with(&*old_name) {
+                    name.replacen(&*old_name, file_name, 1)
+                } else {
+                    name
+                };
+                (e.path.clone(), new_name)
+            })
+            .collect()
+    };
+    let collision = |files: &[(PathBuf, String)]| {
+        files
+            .iter()
+            .find(|(_, name)| taken.contains(name))
+            .map(|(_, name)| new_dir.join(name))
+    };
+    let mut files = renamed(&file_name);
+    if let Some(path) = collision(&files) {
+        match renamer.collision {
+            RenameCollision::Suffix => {
+                let (name, suffixed_files) = (1..=MAX_SUFFIX)
+                    .map(|n| suffixed(&file_name, &renamer.file_separator, n))
+                    .map(|name| {
+                        let files = renamed(&name);
+                        (name, files)
+                    })
+                    .find(|(_, files)| collision(files).is_none())
+                    .ok_or(MkubeError::Collision(path))?;
+                file_name = name;
+                files = suffixed_files;
+            }
+            _ => return Err(MkubeError::Collision(path).into()),
+        }
+    }
+
+    // The folder is renamed first, then its files, all undone if any of them fails.
+    let mut moves = Vec::new();
+    if !merge {
+        moves.push((parent.to_path_buf(), new_dir.clone()));
+    }
+    let mut touched = vec![new_dir.clone()];
+    for (from, name) in files {
+        // Without a merge, the files are in the renamed folder by then.
+        let from = if merge {
+            from
+        } else {
+            new_dir.join(from.file_name().unwrap_or_default())
+        };
+        let to = new_dir.join(name);
+        rules.check_path(&to)?;
+        touched.push(to.clone());
+        moves.push((from, to));
+    }
+    fileops::move_all(fs, &moves)?;
+    if merge {
+        if let Err(err) = fs.as_mut_rfs().remove_dir(parent) {
+            log::warn!(
+                "Failed to remove {} once merged, causes:\n{:?}",
+                parent.display(),
+                err
+            );
+        }
+    }
+    let new_path = new_dir.join(movie_name.replacen(&*old_name, &file_name, 1));
+    Ok((new_path, touched))
+}
+
+/// `name` followed by `(n)`, joined by the renamer separator.
+fn suffixed(name: &str, separator: &str, n: usize) -> String {
+    format!("{}{}({})", name, separator, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockfs::MockFs;
+    use crate::test_util::{entry, library};
+    use crossterm::event::KeyCode;
+
+    #[tokio::test]
+    async fn mock_nfo_save_and_rename() {
+        let mock = MockFs::new()
+            .with_file("/movies/alpha/alpha.mkv", "video")
+            .with_file("/movies/alpha/alpha-fanart.jpg", "")
+            .with_file("/movies/beta/beta.mkv", "");
+        let mut fs = MultiFs::Mock(mock.clone());
+        let path = PathBuf::from("/movies/alpha/alpha.mkv");
+        let nfo = Movie {
+            title: "Alpha".into(),
+            premiered: Some("2001-02-03".into()),
+            ..Default::default()
+        };
+        let nfo_path = write_nfo(&mut fs, &nfo, &path).await.unwrap();
+        assert_eq!(nfo_path, PathBuf::from("/movies/alpha/alpha.nfo"));
+        let saved = crate::try_open_nfo(&mut fs, path.clone()).await.unwrap();
+        assert_eq!(saved.title, "Alpha");
+        assert_eq!(saved.premiered.as_deref(), Some("2001-02-03"));
+
+        let rules = NameRules::default();
+        let (new_path, touched) =
+            rename_movie(&mut fs, &Renamer::default(), &rules, &nfo, &path).unwrap();
+        assert_eq!(
+            new_path,
+            PathBuf::from("/movies/Alpha (2001)/Alpha.2001.NONE.mkv")
+        );
+        assert_eq!(touched.len(), 4);
+        assert_eq!(
+            mock.read("/movies/Alpha (2001)/Alpha.2001.NONE.mkv")
+                .as_deref(),
+            Some(&b"video"[..])
+        );
+        assert!(mock
+            .read("/movies/Alpha (2001)/Alpha.2001.NONE-fanart.jpg")
+            .is_some());
+        assert!(crate::try_open_nfo(&mut fs, new_path).await.is_ok());
+        assert!(!mock.paths().iter().any(|p| p.starts_with("/movies/alpha")));
+
+        mock.fail("/movies/beta", RemoteErrorType::ProtocolError);
+        let beta = Movie {
+            title: "Beta".into(),
+            ..Default::default()
+        };
+        let renamed = rename_movie(
+            &mut fs,
+            &Renamer::default(),
+            &rules,
+            &beta,
+            &PathBuf::from("/movies/beta/beta.mkv"),
+        );
+        assert!(renamed.is_err());
+        assert!(mock.read("/movies/beta/beta.mkv").is_some());
+    }
+
+    #[test]
+    fn rename_collisions() {
+        let mock = MockFs::new()
+            .with_file("/movies/alpha/alpha.mkv", "")
+            .with_file("/movies/alpha/alpha.nfo", "")
+            .with_file("/movies/Alpha (2001)/Alpha.2001.NONE.mkv", "");
+        let mut fs = MultiFs::Mock(mock.clone());
+        let nfo = Movie {
+            title: "Alpha".into(),
+            premiered: Some("2001-02-03".into()),
+            ..Default::default()
+        };
+        let path = PathBuf::from("/movies/alpha/alpha.mkv");
+        let rules = NameRules::default();
+        let mut renamer = Renamer::default();
+        let err = rename_movie(&mut fs, &renamer, &rules, &nfo, &path).unwrap_err();
+        assert!(matches!(
+            MkubeError::find(&err),
+            Some(MkubeError::Collision(taken)) if taken == &PathBuf::from("/movies/Alpha (2001)")
+        ));
+        assert!(mock.read("/movies/alpha/alpha.mkv").is_some());
+
+        renamer.collision = RenameCollision::Merge;
+        let err = rename_movie(&mut fs, &renamer, &rules, &nfo, &path).unwrap_err();
+        assert!(matches!(
+            MkubeError::find(&err),
+            Some(MkubeError::Collision(taken))
+                if taken == &PathBuf::from("/movies/Alpha (2001)/Alpha.2001.NONE.mkv")
+        ));
+
+        renamer.collision = RenameCollision::Suffix;
+        let (new_path, _) = rename_movie(&mut fs, &renamer, &rules, &nfo, &path).unwrap();
+        assert_eq!(
+            new_path,
+            PathBuf::from("/movies/Alpha (2001) (1)/Alpha.2001.NONE.mkv")
+        );
+        assert!(mock
+            .read("/movies/Alpha (2001) (1)/Alpha.2001.NONE.nfo")
+            .is_some());
+
+        let mock = MockFs::new()
+            .with_file("/movies/beta/beta.mkv", "")
+            .with_file("/movies/beta/beta.nfo", "")
+            .with_file("/movies/Beta (XXXX)/poster.jpg", "");
+        let mut fs = MultiFs::Mock(mock.clone());
+        let beta = Movie {
+            title: "Beta".into(),
+            ..Default::default()
+        };
+        renamer.collision = RenameCollision::Merge;
+        let (new_path, _) = rename_movie(
+            &mut fs,
+            &renamer,
+            &rules,
+            &beta,
+            &PathBuf::from("/movies/beta/beta.mkv"),
+        )
+        .unwrap();
+        assert_eq!(
+            new_path,
+            PathBuf::from("/movies/Beta (XXXX)/Beta.XXXX.NONE.mkv")
+        );
+        assert!(mock.read("/movies/Beta (XXXX)/poster.jpg").is_some());
+        assert!(!mock.paths().iter().any(|p| p.starts_with("/movies/beta")));
+
+        // The folder and the files already renamed are moved back.
+        let mock = MockFs::new()
+            .with_file("/movies/gamma/gamma.mkv", "")
+            .with_file("/movies/gamma/gamma.nfo", "");
+        mock.fail(
+            "/movies/Gamma (XXXX)/Gamma.XXXX.NONE.nfo",
+            RemoteErrorType::ProtocolError,
+        );
+        let mut fs = MultiFs::Mock(mock.clone());
+        let gamma = Movie {
+            title: "Gamma".into(),
+            ..Default::default()
+        };
+        assert!(rename_movie(
+            &mut fs,
+            &renamer,
+            &rules,
+            &gamma,
+            &PathBuf::from("/movies/gamma/gamma.mkv"),
+        )
+        .is_err());
+        assert!(mock.read("/movies/gamma/gamma.mkv").is_some());
+        assert!(mock.read("/movies/gamma/gamma.nfo").is_some());
+        assert!(!mock
+            .paths()
+            .iter()
+            .any(|p| p.starts_with("/movies/Gamma (XXXX)")));
+    }
+
+    #[test]
+    fn table_selection_survives_refresh() {
+        let keys = Keybindings::default();
+        let entries: Vec<_> = ["Alpha", "Bravo", "Charlie"]
+            .into_iter()
+            .map(|title| {
+                let movie = Movie {
+                    title: title.into(),
+                    ..Default::default()
+                };
+                let path = PathBuf::from(format!("/movies/{}.mkv", title));
+                (movie, library().id, path)
+            })
+            .collect();
+        let mut mstate = MovieManagerState::default();
+        let send = |mstate: &mut MovieManagerState, evt: MovieManagerEvent| {
+            mstate.input(AppEvent::MovieManagerEvent(evt), &keys);
+        };
+        for entry in &entries {
+            send(
+                &mut mstate,
+                MovieManagerEvent::MovieDiscovered(entry.clone()),
+            );
+        }
+        let selected = (library().id, entries[1].2.clone());
+        send(&mut mstate, MovieManagerEvent::SelectMovie(selected));
+        assert_eq!(mstate.selected_movie(), Some(&entries[1]));
+
+        send(&mut mstate, MovieManagerEvent::ClearMovieList);
+        assert_eq!(mstate.selected_movie(), None);
+        for entry in entries.iter().rev() {
+            send(
+                &mut mstate,
+                MovieManagerEvent::MovieDiscovered(entry.clone()),
+            );
+        }
+        assert_eq!(mstate.selected_movie(), Some(&entries[1]));
+
+        // Once a key is pressed, the previous selection is forgotten.
+        send(&mut mstate, MovieManagerEvent::ClearMovieList);
+        mstate.input(AppEvent::KeyEvent(KeyCode::Up.into()), &keys);
+        for entry in &entries {
+            send(
+                &mut mstate,
+                MovieManagerEvent::MovieDiscovered(entry.clone()),
+            );
+        }
+        assert_eq!(mstate.selected_movie(), None);
+    }
+
+    #[test]
+    fn health_report_opens_the_source() {
+        // The report opens the raw editor of the NFOs, and goes back to the table.
+        let keys = Keybindings::default();
+        let mut mstate = MovieManagerState::default();
+        let issue = NfoIssue {
+            fs_id: library().id,
+            path: entry().2,
+            title: "Alien".into(),
+            problems: vec!["Empty <title>.".into()],
+        };
+        let report = MovieManagerEvent::HealthReport((2, vec![issue]));
+        assert!(mstate.input(AppEvent::MovieManagerEvent(report), &keys));
+        assert!(!mstate.is_table());
+        let source = MovieManagerEvent::EditNfoSource((
+            library().id,
+            entry().2,
+            entry().2.with_extension("nfo"),
+            "<movie><title></title></movie>".into(),
+        ));
+        assert!(mstate.input(AppEvent::MovieManagerEvent(source), &keys));
+        assert_eq!(mstate.key_context(), KeyContext::Editor);
     }
 }
@@ -10,6 +10,8 @@ use tui::{
     },
 };
 
+use crate::library::LibraryId;
+use crate::util::truncate_width;
 use crate::views::movie_manager::{
     details::MovieSearchDetails, MovieManagerEvent, MovieManagerMessage,
 };
@@ -17,9 +19,13 @@ use crate::views::widgets::{Button, ButtonState, Input, InputState};
 use crate::AppEvent;
 use crate::MESSAGE_SENDER;
 
+/// Width of the title column of the results.
+const TITLE_WIDTH: u16 = 50;
+
 #[derive(Clone, Debug)]
 pub struct MovieSearch {
     query: Input,
+    year: Input,
     send: Button,
 }
 
@@ -27,8 +33,13 @@ impl Default for MovieSearch {
     fn default() -> MovieSearch {
         let mut input = Input::default();
         input.placeholder = Some("Movie title".into());
+        let year = Input {
+            placeholder: Some("Year".into()),
+            ..Default::default()
+        };
         MovieSearch {
             query: input,
+            year,
             send: Button::new("Search"),
         }
     }
@@ -40,10 +51,16 @@ pub struct MovieSearchState {
     pub results: Vec<MovieShort>,
     pub is_loading: bool,
     pub query_state: InputState,
+    pub year_state: InputState,
     pub send_state: ButtonState,
     pub selected: usize,
     pub movie_path: PathBuf,
-    pub movie_fs_id: usize,
+    pub movie_fs_id: LibraryId,
+    /// The submitted title and year, the pages are fetched for them.
+    pub query: Option<(String, Option<u16>)>,
+    pub page: u32,
+    pub total_pages: u32,
+    pub total_results: u64,
 }
 
 impl StatefulWidget for MovieSearch {
@@ -54,14 +71,23 @@ impl StatefulWidget for MovieSearch {
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::White))
             .border_type(BorderType::Rounded)
-            .title(" Search ");
+            .title(if state.total_pages > 1 {
+                format!(
+                    " Search · {} results · page {}/{} (PgUp/PgDn) ",
+                    state.total_results, state.page, state.total_pages
+                )
+            } else if state.query.is_some() && !state.is_loading {
+                format!(" Search · {} results ", state.total_results)
+            } else {
+                " Search ".into()
+            });
         let mut search_chunk = area.clone();
         if area.height > 14 {
             if let Some(movie) = state.table_state.selected() {
                 let chunks = Layout::default()
                     .direction(Direction::Vertical)
                     .constraints(vec![
-                        Constraint::Min(area.height - 8),
+                        Constraint::Min(area.height.saturating_sub(8)),
                         Constraint::Percentage(100),
                     ])
                     .split(area.clone());
@@ -81,14 +107,16 @@ impl StatefulWidget for MovieSearch {
         let search_bar = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![
-                Constraint::Length(chunks[0].width - 10),
+                Constraint::Length(chunks[0].width.saturating_sub(18)),
+                Constraint::Min(2),
+                Constraint::Min(6),
                 Constraint::Min(2),
                 Constraint::Min(8),
             ])
             .split(chunks[0]);
         let search_block = Block::default()
             .borders(Borders::LEFT | Borders::RIGHT)
-            .border_style(Style::default().fg(if state.selected == 2 {
+            .border_style(Style::default().fg(if state.selected == 3 {
                 Color::LightRed
             } else {
                 Color::Gray
@@ -97,15 +125,19 @@ impl StatefulWidget for MovieSearch {
         let inner = search_block.inner(chunks[1].clone());
 
         state.query_state.set_focus(state.selected == 0);
-        state.send_state.focus(state.selected == 1);
+        state.year_state.set_focus(state.selected == 1);
+        state.send_state.focus(state.selected == 2);
         StatefulWidget::render(self.query, search_bar[0], buf, &mut state.query_state);
-        StatefulWidget::render(self.send, search_bar[2], buf, &mut state.send_state);
+        StatefulWidget::render(self.year, search_bar[2], buf, &mut state.year_state);
+        StatefulWidget::render(self.send, search_bar[4], buf, &mut state.send_state);
         search_block.render(chunks[1], buf);
         if state.is_loading {
             Paragraph::new("Searching...").render(inner, buf);
         } else if state.results.len() == 0 {
             Paragraph::new("No result found.").render(inner, buf);
         } else {
+            // The title, the year and the spacing between the columns.
+            let overview_width = inner.width.saturating_sub(TITLE_WIDTH + 4 + 2) as usize;
             let rows: Vec<_> = state
                 .results
                 .iter()
@@ -115,7 +147,11 @@ impl StatefulWidget for MovieSearch {
                         .release_date
                         .map(|rd| rd.format("%Y").to_string())
                         .unwrap_or("".into());
-                    Row::new(vec![m.inner.title.clone(), yr, m.inner.overview.clone()])
+                    Row::new(vec![
+                        truncate_width(&m.inner.title, TITLE_WIDTH as usize).into_owned(),
+                        yr,
+                        truncate_width(&m.inner.overview, overview_width).into_owned(),
+                    ])
                 })
                 .collect();
 
@@ -132,12 +168,12 @@ impl StatefulWidget for MovieSearch {
                         .bottom_margin(1),
                 )
                 .widths(&[
-                    Constraint::Length(50),
+                    Constraint::Length(TITLE_WIDTH),
                     Constraint::Length(4),
                     Constraint::Percentage(100),
                 ])
                 .column_spacing(1)
-                .highlight_style(Style::default().bg(if state.selected == 2 {
+                .highlight_style(Style::default().bg(if state.selected == 3 {
                     Color::LightRed
                 } else {
                     Color::Gray
@@ -148,23 +184,30 @@ impl StatefulWidget for MovieSearch {
 }
 
 impl MovieSearchState {
+    fn fetch_page(&mut self, page: u32) -> bool {
+        let Some((title, year)) = self.query.clone() else {
+            return false;
+        };
+        MESSAGE_SENDER
+            .get()
+            .unwrap()
+            .send(MovieManagerMessage::SearchTitle((title, year, page)).into())
+            .unwrap();
+        self.is_loading = true;
+        true
+    }
+
     pub fn input(&mut self, app_event: AppEvent) -> bool {
         match app_event {
             AppEvent::KeyEvent(kev) => {
                 if kev.code == KeyCode::Enter {
-                    if self.selected == 0 || self.selected == 1 {
-                        let sender = MESSAGE_SENDER.get().unwrap();
-                        sender
-                            .send(
-                                MovieManagerMessage::SearchTitle(
-                                    self.query_state.get_value().to_owned(),
-                                )
-                                .into(),
-                            )
-                            .unwrap();
-                        self.is_loading = true;
-                        true
-                    } else if self.selected == 2 {
+                    if self.selected <= 2 {
+                        self.query = Some((
+                            self.query_state.get_value().to_owned(),
+                            self.year_state.get_value().trim().parse().ok(),
+                        ));
+                        self.fetch_page(1)
+                    } else if self.selected == 3 {
                         if let Some(index) = self.table_state.selected() {
                             let sender = MESSAGE_SENDER.get().unwrap();
                             sender
@@ -183,14 +226,14 @@ impl MovieSearchState {
                     } else {
                         false
                     }
-                } else if self.selected == 2 && kev.code == KeyCode::Up && self.results.len() > 0 {
+                } else if self.selected == 3 && kev.code == KeyCode::Up && self.results.len() > 0 {
                     self.table_state.select(
                         self.table_state
                             .selected()
                             .map(|c| (c + self.results.len() - 1) % self.results.len()),
                     );
                     true
-                } else if self.selected == 2 && kev.code == KeyCode::Down && self.results.len() > 0
+                } else if self.selected == 3 && kev.code == KeyCode::Down && self.results.len() > 0
                 {
                     self.table_state.select(
                         self.table_state
@@ -199,16 +242,22 @@ impl MovieSearchState {
                             .or(Some(0)),
                     );
                     true
+                } else if kev.code == KeyCode::PageDown && self.page < self.total_pages {
+                    self.fetch_page(self.page + 1)
+                } else if kev.code == KeyCode::PageUp && self.page > 1 {
+                    self.fetch_page(self.page - 1)
                 } else if kev.code == KeyCode::Tab {
-                    self.selected = (self.selected + 1) % 3;
+                    self.selected = (self.selected + 1) % 4;
                     true
                 } else if kev.code == KeyCode::BackTab {
-                    self.selected = (self.selected + 2) % 3;
+                    self.selected = (self.selected + 3) % 4;
                     true
                 } else {
                     if self.selected == 0 {
                         self.query_state.input(kev)
                     } else if self.selected == 1 {
+                        self.year_state.input(kev)
+                    } else if self.selected == 2 {
                         self.send_state.input(kev)
                     } else {
                         false
@@ -216,7 +265,10 @@ impl MovieSearchState {
                 }
             }
             AppEvent::MovieManagerEvent(MovieManagerEvent::SearchResults(results)) => {
-                self.results = results;
+                self.results = results.results;
+                self.page = results.page as u32;
+                self.total_pages = results.total_pages as u32;
+                self.total_results = results.total_results;
                 self.table_state.select(None);
                 self.is_loading = false;
                 true
@@ -0,0 +1,214 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use tmdb_api::prelude::Command;
+
+use crate::config::{ArtworkTypes, ImageSize};
+use crate::downloads::Download;
+use crate::multifs::MultiFs;
+use crate::parser;
+use crate::provider::MetadataProvider;
+
+/// Show directory of an episode, above its season directory if it has one.
+pub fn show_dir(episode: &Path) -> Option<PathBuf> {
+    let parent = episode.parent()?;
+    let in_season = parent
+        .file_name()
+        .is_some_and(|name| parser::parse_season_dir(&name.to_string_lossy()).is_some());
+    if in_season {
+        parent.parent().map(Path::to_path_buf)
+    } else {
+        Some(parent.to_path_buf())
+    }
+}
+
+/// Kodi name of a season poster, `season01-poster.jpg` or `season-specials-poster.jpg`.
+pub fn season_poster_name(season: u32) -> String {
+    if season == 0 {
+        "season-specials-poster.jpg".into()
+    } else {
+        format!("season{:02}-poster.jpg", season)
+    }
+}
+
+// tmdb-api fails on the shows missing some of its required fields, only the artworks are read.
+struct ShowArtworks {
+    tv_id: u64,
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ShowArtworksResult {
+    poster_path: Option<String>,
+    backdrop_path: Option<String>,
+    #[serde(default)]
+    seasons: Vec<SeasonPoster>,
+}
+
+#[derive(Deserialize)]
+struct SeasonPoster {
+    season_number: u32,
+    poster_path: Option<String>,
+}
+
+impl Command for ShowArtworks {
+    type Output = ShowArtworksResult;
+
+    fn path(&self) -> Cow<'static, str> {
+        Cow::Owned(format!("/tv/{}", self.tv_id))
+    }
+
+    fn params(&self) -> Vec<(&'static str, Cow<'_, str>)> {
+        match &self.language {
+            Some(lang) => vec![("language", Cow::Borrowed(lang.as_str()))],
+            None => Vec::new(),
+        }
+    }
+}
+
+struct SeasonStills {
+    tv_id: u64,
+    season_number: u32,
+}
+
+#[derive(Deserialize)]
+struct SeasonStillsResult {
+    #[serde(default)]
+    episodes: Vec<EpisodeStill>,
+}
+
+#[derive(Deserialize)]
+struct EpisodeStill {
+    episode_number: u32,
+    still_path: Option<String>,
+}
+
+impl Command for SeasonStills {
+    type Output = SeasonStillsResult;
+
+    fn path(&self) -> Cow<'static, str> {
+        Cow::Owned(format!("/tv/{}/season/{}", self.tv_id, self.season_number))
+    }
+
+    fn params(&self) -> Vec<(&'static str, Cow<'_, str>)> {
+        Vec::new()
+    }
+}
+
+/// The episodes in the show directory and its season directories, with their numbers.
+pub fn local_episodes(fs: &mut MultiFs, show_dir: &Path) -> Result<Vec<(PathBuf, (u32, u32))>> {
+    let mut dirs = vec![show_dir.to_path_buf()];
+    let mut episodes = Vec::new();
+    while let Some(dir) = dirs.pop() {
+        let entries = fs
+            .as_mut_rfs()
+            .list_dir(&dir)
+            .context(format!("failed to list {}", dir.display()))?;
+        for entry in entries {
+            let name = entry.name();
+            if entry.is_dir() {
+                if dir == show_dir && parser::parse_season_dir(&name).is_some() {
+                    dirs.push(entry.path);
+                }
+            } else if crate::is_video_file(&entry.path) {
+                if let Some(numbers) = parser::parse_episode(&name) {
+                    episodes.push((entry.path, numbers));
+                }
+            }
+        }
+    }
+    episodes.sort();
+    Ok(episodes)
+}
+
+/// Downloads of the show poster and fanart, the season posters and the episode thumbs.
+///
+/// Only the seasons with a local episode are fetched.
+pub async fn show_artwork_downloads(
+    tmdb_client: &dyn MetadataProvider,
+    tv_id: u64,
+    language: Option<String>,
+    show_dir: &Path,
+    episodes: &[(PathBuf, (u32, u32))],
+    types: &ArtworkTypes,
+) -> Result<Vec<Download>> {
+    let show = tmdb_client
+        .execute(&ShowArtworks { tv_id, language })
+        .await
+        .map_err(|err| anyhow!("Failed to get show (id: {}), causes:\n{:?}", tv_id, err))?;
+    let mut jobs = Vec::new();
+    let mut push = |url: &str, size: ImageSize, output: PathBuf, label: String| {
+        jobs.push(Download {
+            url: crate::tmdb_image_url(url, size),
+            output,
+            label,
+        })
+    };
+    if let (true, Some(poster)) = (types.poster, &show.poster_path) {
+        push(
+            poster,
+            types.poster_size.into(),
+            show_dir.join("poster.jpg"),
+            "show poster".into(),
+        );
+    }
+    if let (true, Some(backdrop)) = (types.fanart, &show.backdrop_path) {
+        push(
+            backdrop,
+            types.fanart_size.into(),
+            show_dir.join("fanart.jpg"),
+            "show fanart".into(),
+        );
+    }
+    let mut seasons: Vec<u32> = episodes.iter().map(|(_, (season, _))| *season).collect();
+    seasons.dedup();
+    for season in show
+        .seasons
+        .iter()
+        .filter(|s| seasons.contains(&s.season_number))
+    {
+        if let (true, Some(poster)) = (types.poster, &season.poster_path) {
+            push(
+                poster,
+                types.poster_size.into(),
+                show_dir.join(season_poster_name(season.season_number)),
+                format!("season {} poster", season.season_number),
+            );
+        }
+    }
+    for season in seasons {
+        let request = SeasonStills {
+            tv_id,
+            season_number: season,
+        };
+        let stills = match tmdb_client.execute(&request).await {
+            Ok(stills) => stills,
+            Err(err) => {
+                log::warn!(
+                    "Failed to get season {} (show id: {}), causes:\n{:?}",
+                    season,
+                    tv_id,
+                    err
+                );
+                continue;
+            }
+        };
+        for (path, (_, number)) in episodes.iter().filter(|(_, (s, _))| *s == season) {
+            let still = stills
+                .episodes
+                .iter()
+                .find(|e| e.episode_number == *number)
+                .and_then(|e| e.still_path.as_deref());
+            if let (Some(still), Some(stem)) = (still, path.file_stem()) {
+                push(
+                    still,
+                    ImageSize::Original,
+                    path.with_file_name(format!("{}-thumb.jpg", stem.to_string_lossy())),
+                    format!("thumb of S{:02}E{:02}", season, number),
+                );
+            }
+        }
+    }
+    Ok(jobs)
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::keys::{Action, Keybindings};
+use crate::library::LibraryId;
+use crate::nfo::Movie;
+use crate::views::movie_manager::{MovieManagerEvent, MovieManagerMessage};
+use crate::views::widgets::{TextArea, TextAreaState};
+use crate::{AppEvent, AppMessage, MESSAGE_SENDER};
+
+/// Raw XML editor of a movie NFO, for the elements the movie editor does not expose.
+#[derive(Clone, Debug, Default)]
+pub struct NfoSourceEditor {
+    pub keys: Keybindings,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NfoSourceEditorState {
+    pub movie_fs_id: LibraryId,
+    pub movie_path: PathBuf,
+    pub nfo_path: PathBuf,
+    pub text_state: TextAreaState,
+    pub error: Option<String>,
+}
+
+impl StatefulWidget for NfoSourceEditor {
+    type State = NfoSourceEditorState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .title(format!(" NFO Source: {} ", state.nfo_path.display()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::White))
+            .border_type(BorderType::Rounded);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)].as_ref())
+            .split(inner);
+        StatefulWidget::render(TextArea::default(), chunks[0], buf, &mut state.text_state);
+        let footer = match &state.error {
+            Some(err) => Spans::from(Span::styled(
+                err.as_str(),
+                Style::default().fg(Color::LightRed),
+            )),
+            None => Spans::from(vec![
+                Span::styled(
+                    format!(" {} ", self.keys.key(Action::SaveSource)),
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Check & save"),
+                Span::raw("    "),
+                Span::styled(
+                    format!(" {} ", self.keys.key(Action::CloseSource)),
+                    Style::default().fg(Color::White).bg(Color::Magenta),
+                ),
+                Span::raw(" Cancel"),
+            ]),
+        };
+        Paragraph::new(footer)
+            .wrap(Wrap { trim: true })
+            .render(chunks[1], buf);
+    }
+}
+
+impl NfoSourceEditorState {
+    pub fn new(
+        movie_fs_id: LibraryId,
+        movie_path: PathBuf,
+        nfo_path: PathBuf,
+        source: &str,
+    ) -> Self {
+        let mut text_state = TextAreaState::default();
+        text_state.set_value(source);
+        text_state.set_focus(true);
+        Self {
+            movie_fs_id,
+            movie_path,
+            nfo_path,
+            text_state,
+            error: None,
+        }
+    }
+
+    pub fn input(&mut self, app_event: AppEvent, keys: &Keybindings) -> bool {
+        match app_event {
+            AppEvent::KeyEvent(kev) => {
+                if keys.is(Action::SaveSource, &kev) {
+                    let source = self.text_state.get_value();
+                    match quick_xml::de::from_str::<Movie>(&source) {
+                        Ok(_) => {
+                            self.error = None;
+                            let sender = MESSAGE_SENDER.get().unwrap();
+                            sender
+                                .send(
+                                    MovieManagerMessage::SaveNfoSource((
+                                        self.movie_fs_id,
+                                        self.movie_path.clone(),
+                                        self.nfo_path.clone(),
+                                        source,
+                                    ))
+                                    .into(),
+                                )
+                                .unwrap();
+                        }
+                        Err(err) => self.error = Some(format!("Invalid NFO, not saved: {}", err)),
+                    }
+                    true
+                } else if keys.is(Action::CloseSource, &kev) {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                            MovieManagerEvent::OpenTable,
+                        )))
+                        .unwrap();
+                    true
+                } else {
+                    self.text_state.input(kev)
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{message_sender, state};
+    use crate::views::movie_manager::{InnerState, MovieManagerState};
+    use crate::views::TabState;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn rebound_save_key() {
+        let keys = Keybindings::from(BTreeMap::from([(
+            "save_source".to_owned(),
+            "ctrl+w".to_owned(),
+        )]));
+        let ctrl = |c| AppEvent::KeyEvent(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+        let mut state = NfoSourceEditorState::new(
+            LibraryId::new(),
+            "/movies/alpha.mkv".into(),
+            "/movies/alpha.nfo".into(),
+            "<movie><title>Alpha</movie>",
+        );
+        state.input(ctrl('s'), &keys);
+        assert_eq!(state.error, None);
+        assert!(state.input(ctrl('w'), &keys));
+        assert!(state.error.is_some());
+    }
+
+    #[test]
+    fn default_close_key_keeps_mkube_open() {
+        message_sender();
+        let mut state = state();
+        let editor = NfoSourceEditorState::new(
+            LibraryId::new(),
+            "/movies/alpha.mkv".into(),
+            "/movies/alpha.nfo".into(),
+            "<movie><title>Alpha</title></movie>",
+        );
+        state.tab = TabState::MovieManager(MovieManagerState {
+            inner: InnerState::SourceEditor(editor),
+            ..Default::default()
+        });
+        assert!(!state.press_key(KeyCode::Esc.into()));
+    }
+}
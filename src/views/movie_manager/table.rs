@@ -1,5 +1,6 @@
 use crossterm::event::KeyCode;
-use std::path::PathBuf;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use tui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
@@ -10,32 +11,101 @@ use tui::{
     },
 };
 
+use crate::catalog::{Catalog, MovieAssets};
+use crate::config::{TableColumn, TableColumns};
+use crate::keys::{Action, Keybindings};
+use crate::library::{LibraryFlavor, LibraryId};
+use crate::multifs::TransferProgress;
 use crate::nfo::Movie;
-use crate::views::movie_manager::{details::MovieDetails, MovieManagerEvent, MovieManagerMessage};
+use crate::recent::RecentMovies;
+use crate::util::truncate_width;
+use crate::views::modal::{self, Modal};
+use crate::views::movie_manager::{
+    batch::normalize_ops,
+    details::{self, MovieDetails},
+    MovieManagerEvent, MovieManagerMessage,
+};
+use crate::views::widgets::{format_size, Progress};
 use crate::MESSAGE_SENDER;
-use crate::{AppEvent, AppMessage};
+use crate::{AppEvent, AppMessage, AppState};
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Clone, Debug, Default, PartialEq)]
-pub struct MovieTable {}
+pub struct MovieTable {
+    pub offline: bool,
+    pub columns: Vec<TableColumn>,
+    /// Library names, in the configuration order.
+    pub libraries: Vec<(LibraryId, String)>,
+    /// Shown as a gauge below the table.
+    pub transfers: Vec<TransferProgress>,
+}
 #[derive(Clone, Debug, Default)]
 pub struct MovieTableState {
     table_state: TableState,
-    movies: Vec<(Movie, usize, PathBuf)>,
     is_loading: bool,
+    sort: SortColumn,
+    review: VecDeque<(Movie, LibraryId, PathBuf)>,
+    /// Movies listed under a header per library.
+    grouped: bool,
+    /// Libraries whose group is folded.
+    collapsed: HashSet<LibraryId>,
+    /// Order of the library groups, the configuration one.
+    library_order: Vec<LibraryId>,
+    /// Row selected before the movie list was cleared, with the scroll of the table then.
+    ///
+    /// It is selected again as the movies are discovered, until a key is pressed.
+    restore: Option<(RowKey, TableState)>,
+}
+
+/// A row across the refreshes, its index changes: library and movie path, None for the header.
+type RowKey = (LibraryId, Option<PathBuf>);
+
+/// A line of the table, a library header only appears when grouping by library.
+#[derive(Clone, Copy, Debug)]
+pub enum TableRow<'a> {
+    Library(LibraryId),
+    Movie(&'a (Movie, LibraryId, PathBuf)),
 }
 
-impl StatefulWidget for MovieTable {
-    type State = MovieTableState;
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortColumn {
+    #[default]
+    Title,
+    Year,
+    Rating,
+}
 
-    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+impl SortColumn {
+    pub fn next(self) -> SortColumn {
+        match self {
+            SortColumn::Title => SortColumn::Year,
+            SortColumn::Year => SortColumn::Rating,
+            SortColumn::Rating => SortColumn::Title,
+        }
+    }
+}
+
+impl MovieTable {
+    pub fn render(
+        self,
+        area: Rect,
+        buf: &mut Buffer,
+        state: &mut MovieTableState,
+        catalog: &Catalog,
+        keys: &Keybindings,
+    ) {
+        let offline = self.offline;
         if state.is_loading {
             Paragraph::new("Loading...").render(area, buf);
             return;
         }
-        if state.movies.len() == 0 {
+        if catalog.is_empty() {
             Paragraph::new(vec![
                 Spans::from(Span::styled(
-                    "No movie found. You might need to relooad the libraries (press 'r').",
+                    format!(
+                        "No movie found. You might need to relooad the libraries (press '{}').",
+                        keys.key(Action::Reload)
+                    ),
                     Style::default().fg(Color::Yellow),
                 )),
                 Spans::from(vec![]),
@@ -44,26 +114,66 @@ impl StatefulWidget for MovieTable {
                     Style::default().fg(Color::White).bg(Color::Magenta),
                 )),
                 Spans::from(vec![]),
-                Spans::from(vec![
-                    Span::styled(" r ", Style::default().fg(Color::White).bg(Color::Magenta)),
-                    Span::raw(" Reload libraries"),
-                    Span::raw("    "),
-                    Span::styled(" s ", Style::default().fg(Color::White).bg(Color::Magenta)),
-                    Span::raw(" Search movie (on TMDB)"),
-                    Span::raw("    "),
-                    Span::styled(" e ", Style::default().fg(Color::White).bg(Color::Magenta)),
-                    Span::raw(" Edit movie NFO"),
-                    Span::raw("    "),
-                    Span::styled(" a ", Style::default().fg(Color::White).bg(Color::Magenta)),
-                    Span::raw(" Download artworks"),
-                    Span::raw("    "),
-                    Span::styled(
-                        " t/b/d/u/w ",
-                        Style::default().fg(Color::White).bg(Color::Magenta),
-                    ),
-                    Span::raw(" Set source as TV/Bluray/DVD/4K Bluray/WEB"),
-                    Span::raw("    "),
-                ]),
+                Spans::from(
+                    [
+                        key_tip(
+                            keys,
+                            offline,
+                            &[Action::Reload, Action::ReloadLibrary],
+                            "Reload all libraries/the selected one",
+                        ),
+                        key_tip(keys, offline, &[Action::Search], "Search movie (on TMDB)"),
+                        key_tip(keys, offline, &[Action::Edit], "Edit movie NFO"),
+                        key_tip(keys, offline, &[Action::EditSource], "Edit raw NFO (XML)"),
+                        key_tip(keys, offline, &[Action::RestoreNfo], "Restore NFO backup"),
+                        key_tip(
+                            keys,
+                            offline,
+                            &[Action::DownloadArtworks, Action::ForceDownloadArtworks],
+                            "Download missing/all artworks",
+                        ),
+                        key_tip(
+                            keys,
+                            offline,
+                            &[Action::RefreshMetadata],
+                            "Refresh metadata from TMDB",
+                        ),
+                        key_tip(keys, offline, &[Action::ProbeMedia], "Probe stream details"),
+                        key_tip(keys, offline, &[Action::GrabThumb], "Grab a thumbnail"),
+                        key_tip(keys, offline, &[Action::VerifyChecksum], "Verify checksum"),
+                        key_tip(
+                            keys,
+                            offline,
+                            &[
+                                Action::SourceTv,
+                                Action::SourceBluray,
+                                Action::SourceDvd,
+                                Action::SourceUhdBluray,
+                                Action::SourceWeb,
+                            ],
+                            "Set source as TV/Bluray/DVD/4K Bluray/WEB",
+                        ),
+                        key_tip(
+                            keys,
+                            offline,
+                            &[Action::DeleteMovie, Action::MoveMovie],
+                            "Delete/move movie files",
+                        ),
+                        key_tip(keys, offline, &[Action::Sort], "Sort by title/year/rating"),
+                        key_tip(keys, offline, &[Action::Columns], "Choose the columns"),
+                        key_tip(keys, offline, &[Action::GroupByLibrary], "Group by library"),
+                        key_tip(keys, offline, &[Action::QuickOpen], "Jump to a movie"),
+                        key_tip(keys, offline, &[Action::AutoIdentify], "Auto-identify all"),
+                        key_tip(
+                            keys,
+                            offline,
+                            &[Action::Review],
+                            "Review next unmatched movie",
+                        ),
+                        key_tip(keys, offline, &[Action::Help], "Show all key bindings"),
+                    ]
+                    .concat(),
+                ),
                 Spans::from(vec![
                     Span::styled(
                         " (Shift+)TAB ",
@@ -95,15 +205,42 @@ impl StatefulWidget for MovieTable {
             return;
         }
 
+        let area = if self.transfers.is_empty() {
+            area
+        } else {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+                .split(area);
+            let label = match self.transfers.as_slice() {
+                [transfer] => transfer.label.clone(),
+                transfers => format!("{} transfers", transfers.len()),
+            };
+            let done = self.transfers.iter().map(|t| t.done).sum();
+            let total = self.transfers.iter().map(|t| t.total).sum();
+            Progress::new(label, done, total).render(chunks[1], buf);
+            chunks[0]
+        };
+        let title = if state.review.is_empty() {
+            " Movies ".to_owned()
+        } else {
+            format!(" Movies ({} to review, press 'v') ", state.review.len())
+        };
         let block = Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::White))
             .border_type(BorderType::Rounded)
-            .title(" Movies ");
+            .title(title);
 
+        state.library_order = self.libraries.iter().map(|(id, _)| *id).collect();
+        let table_rows = state.rows(catalog);
         let mut movie_chunk = area.clone();
         if area.height > 18 {
-            if let Some(movie) = state.table_state.selected() {
+            if let Some(TableRow::Movie((movie, fs_id, path))) = state
+                .table_state
+                .selected()
+                .and_then(|s| table_rows.get(s).copied())
+            {
                 let chunks = Layout::default()
                     .direction(Direction::Vertical)
                     .constraints(vec![
@@ -112,8 +249,14 @@ impl StatefulWidget for MovieTable {
                     ])
                     .split(area.clone());
                 movie_chunk = chunks[0];
+                let (extras, parts) = catalog
+                    .assets(*fs_id, path)
+                    .map(|a| (a.extras, a.parts))
+                    .unwrap_or_default();
                 MovieDetails {
-                    movie: &state.movies[movie].0,
+                    movie,
+                    extras,
+                    parts,
                 }
                 .render(chunks[1], buf);
             }
@@ -121,30 +264,105 @@ impl StatefulWidget for MovieTable {
 
         let inner = block.inner(movie_chunk.clone());
 
-        let rows: Vec<_> = state
-            .movies
+        let columns = if self.columns.is_empty() {
+            TableColumns::default().columns
+        } else {
+            self.columns.clone()
+        };
+        let cells: Vec<Vec<String>> = table_rows
             .iter()
-            .map(|(m, _, _)| {
-                let title = m.title.clone();
-                let year = m.premiered.as_deref().unwrap_or("".into());
-                let source = m.source.as_deref().unwrap_or("".into());
-                let res = m
-                    .fileinfo
-                    .as_ref()
-                    .map(|fi| fi.streamdetails.video.get(0))
-                    .flatten()
-                    .map(|vt| vt.height)
-                    .flatten()
-                    .map(|h| format!("{}p", h))
-                    .unwrap_or("".into());
-                Row::new(vec![title, year.to_owned(), source.to_owned(), res])
+            .map(|row| match row {
+                TableRow::Library(fs_id) => {
+                    let fold = if state.collapsed.contains(fs_id) {
+                        '▸'
+                    } else {
+                        '▾'
+                    };
+                    let name = self.library_name(*fs_id);
+                    let count = catalog.stats(*fs_id).movies;
+                    vec![format!("{} {} ({})", fold, name, count)]
+                }
+                TableRow::Movie((m, fs_id, path)) => {
+                    let assets = catalog.assets(*fs_id, path);
+                    columns
+                        .iter()
+                        .map(|column| self.cell(*column, m, *fs_id, path, assets))
+                        .collect()
+                }
+            })
+            .collect();
+        let header: Vec<String> = columns
+            .iter()
+            .map(|column| {
+                let sort = match column {
+                    TableColumn::Title => Some(SortColumn::Title),
+                    TableColumn::Year => Some(SortColumn::Year),
+                    TableColumn::Rating => Some(SortColumn::Rating),
+                    _ => None,
+                };
+                if sort == Some(state.sort) {
+                    format!("{}*", column.header())
+                } else {
+                    column.header().to_owned()
+                }
+            })
+            .collect();
+        let lengths: Vec<u16> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let content = cells
+                    .iter()
+                    .filter_map(|row| row.get(i).map(|cell| cell.width()))
+                    .chain(std::iter::once(header[i].width()))
+                    .max()
+                    .unwrap_or(0);
+                Ord::min(content as u16, column.max_width())
+            })
+            .collect();
+        let widths: Vec<Constraint> = lengths.iter().copied().map(Constraint::Length).collect();
+        // Alternate backgrounds, starting over below each library header.
+        let mut stripe = false;
+        let rows: Vec<Row> = cells
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .zip(&lengths)
+                    .map(|(cell, length)| truncate_width(&cell, *length as usize).into_owned())
+                    .collect::<Vec<String>>()
+            })
+            .zip(&table_rows)
+            .map(|(cells, row)| match row {
+                TableRow::Library(_) => {
+                    stripe = false;
+                    Row::new(cells).style(
+                        Style::default()
+                            .fg(Color::Magenta)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                }
+                TableRow::Movie((m, fs_id, path)) => {
+                    let mut style = Style::default();
+                    if stripe {
+                        style = style.bg(Color::Black);
+                    }
+                    stripe = !stripe;
+                    // Movies still to identify, the ones without NFO in italics.
+                    let nfo = catalog.assets(*fs_id, path).map(|a| a.nfo);
+                    if nfo == Some(false) {
+                        style = style.fg(Color::Yellow).add_modifier(Modifier::ITALIC);
+                    } else if m.uniqueid.is_empty() {
+                        style = style.fg(Color::Yellow);
+                    }
+                    Row::new(cells).style(style)
+                }
             })
             .collect();
 
         let table = Table::new(rows)
             .style(Style::default().fg(Color::White))
             .header(
-                Row::new(vec!["Title", "Year", "Source", "Res."])
+                Row::new(header)
                     .style(
                         Style::default()
                             .bg(Color::Blue)
@@ -153,89 +371,547 @@ impl StatefulWidget for MovieTable {
                     )
                     .bottom_margin(1),
             )
-            .widths(&[
-                Constraint::Length(50),
-                Constraint::Length(4),
-                Constraint::Length(10),
-                Constraint::Length(5),
-            ])
+            .widths(&widths)
             .column_spacing(1)
-            .highlight_style(Style::default().bg(Color::LightRed));
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::LightRed)
+                    .remove_modifier(Modifier::ITALIC),
+            );
 
         block.render(movie_chunk, buf);
         StatefulWidget::render(table, inner, buf, &mut state.table_state);
     }
 }
 
+impl MovieTable {
+    fn library_name(&self, fs_id: LibraryId) -> &str {
+        self.libraries
+            .iter()
+            .find(|(id, _)| *id == fs_id)
+            .map_or("", |(_, name)| name.as_str())
+    }
+
+    fn cell(
+        &self,
+        column: TableColumn,
+        m: &Movie,
+        fs_id: LibraryId,
+        path: &Path,
+        assets: Option<&MovieAssets>,
+    ) -> String {
+        let video = m
+            .fileinfo
+            .as_ref()
+            .and_then(|fi| fi.streamdetails.video.first());
+        match column {
+            TableColumn::Title => match assets.map(|a| a.parts) {
+                Some(parts) if parts > 1 => format!("{} ({} parts)", m.title, parts),
+                _ => m.title.clone(),
+            },
+            TableColumn::OriginalTitle => m.original_title.clone().unwrap_or_default(),
+            TableColumn::Year => m.premiered.clone().unwrap_or_default(),
+            TableColumn::Source => m.source.clone().unwrap_or_default(),
+            TableColumn::Resolution => video
+                .and_then(|vt| vt.height)
+                .map(|h| format!("{}p", h))
+                .unwrap_or_default(),
+            TableColumn::Hdr => video
+                .and_then(|vt| vt.hdr_type.as_deref())
+                .map(details::format_hdr)
+                .unwrap_or("")
+                .to_owned(),
+            TableColumn::Codec => video.map(|vt| vt.codec.clone()).unwrap_or_default(),
+            TableColumn::Size => assets
+                .map(|a| a.size)
+                .filter(|size| *size > 0)
+                .map(format_size)
+                .unwrap_or_default(),
+            TableColumn::Library => self.library_name(fs_id).to_owned(),
+            TableColumn::Path => path.display().to_string(),
+            TableColumn::Rating => m
+                .default_rating()
+                .map(|r| format!("{:.1}", r.value))
+                .unwrap_or_default(),
+            TableColumn::Genres => m.genre.join(", "),
+            TableColumn::Artworks => assets.map(MovieAssets::flags).unwrap_or_default(),
+            TableColumn::Edition => m.edition.clone().unwrap_or_default(),
+            TableColumn::Watched => if m.is_watched() { "✓" } else { "" }.to_owned(),
+            TableColumn::Status => assets
+                .map(|a| {
+                    let nfo = if a.nfo { '✔' } else { ' ' };
+                    let art = if a.poster && a.fanart { ' ' } else { '✖' };
+                    format!("{}{}", nfo, art)
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Picker toggling the table columns, it opens again after each pick until cancelled.
+fn column_picker(columns: &[TableColumn], selected: usize) -> Modal {
+    let items = TableColumn::ALL
+        .iter()
+        .map(|column| {
+            let mark = if columns.contains(column) { 'x' } else { ' ' };
+            format!("[{}] {}", mark, column.header())
+        })
+        .collect();
+    Modal::picker(
+        "Table columns",
+        "Enter shows or hides a column, new columns are added at the end.",
+        items,
+        |app_state: &mut AppState, index: usize| {
+            let column = TableColumn::ALL[index];
+            let columns = &mut app_state.config.table.columns;
+            match columns.iter().position(|c| *c == column) {
+                Some(pos) if columns.len() > 1 => {
+                    columns.remove(pos);
+                }
+                Some(_) => {}
+                None => columns.push(column),
+            }
+            let picker = column_picker(columns, index);
+            app_state.modals.push(picker);
+            vec![]
+        },
+    )
+    .select(selected)
+}
+
+/// Picker jumping to a pinned or recently edited movie, None if there is none.
+fn recent_picker(recent: &RecentMovies) -> Option<Modal> {
+    let entries = recent.entries();
+    if entries.is_empty() {
+        return None;
+    }
+    let items = entries
+        .iter()
+        .map(|(pinned, entry)| {
+            let mark = if *pinned { '*' } else { ' ' };
+            format!("{} {} {}", mark, entry.title, entry.path.display())
+        })
+        .collect();
+    let targets: Vec<(LibraryId, PathBuf)> = entries
+        .iter()
+        .map(|(_, entry)| (entry.fs_id, entry.path.clone()))
+        .collect();
+    Some(Modal::picker(
+        "Recent movies",
+        "Pinned movies (*) first, then the last edited ones.",
+        items,
+        move |_, index| {
+            vec![AppEvent::MovieManagerEvent(MovieManagerEvent::SelectMovie(
+                targets[index].clone(),
+            ))]
+        },
+    ))
+}
+
+fn key_tip(
+    keys: &Keybindings,
+    offline: bool,
+    actions: &[Action],
+    label: &'static str,
+) -> Vec<Span<'static>> {
+    let bound: Vec<String> = actions.iter().map(|a| keys.key(*a).to_string()).collect();
+    let (key_style, label_style) = if offline && actions.iter().any(Action::needs_network) {
+        (
+            Style::default().fg(Color::Black).bg(Color::DarkGray),
+            Style::default().fg(Color::DarkGray),
+        )
+    } else {
+        (
+            Style::default().fg(Color::White).bg(Color::Magenta),
+            Style::default(),
+        )
+    };
+    vec![
+        Span::styled(format!(" {} ", bound.join("/")), key_style),
+        Span::styled(format!(" {}", label), label_style),
+        Span::raw("    "),
+    ]
+}
+
 impl MovieTableState {
-    pub fn input(&mut self, app_event: AppEvent) -> bool {
+    pub fn sorted_movies<'a>(&self, catalog: &'a Catalog) -> Vec<&'a (Movie, LibraryId, PathBuf)> {
+        let mut movies: Vec<_> = catalog.iter().collect();
+        match self.sort {
+            SortColumn::Title => {}
+            SortColumn::Year => movies.sort_by(|a, b| a.0.premiered.cmp(&b.0.premiered)),
+            SortColumn::Rating => movies.sort_by(|a, b| {
+                let ra = a.0.default_rating().map(|r| r.value).unwrap_or(-1.0);
+                let rb = b.0.default_rating().map(|r| r.value).unwrap_or(-1.0);
+                rb.total_cmp(&ra)
+            }),
+        }
+        movies
+    }
+
+    /// Lines of the table, in the current sort order and grouping.
+    pub fn rows<'a>(&self, catalog: &'a Catalog) -> Vec<TableRow<'a>> {
+        let movies = self.sorted_movies(catalog);
+        if !self.grouped {
+            return movies.into_iter().map(TableRow::Movie).collect();
+        }
+        let mut libraries: Vec<LibraryId> = movies.iter().map(|(_, fs_id, _)| *fs_id).collect();
+        libraries.sort_unstable_by_key(|fs_id| {
+            let position = self.library_order.iter().position(|id| id == fs_id);
+            (position.unwrap_or(usize::MAX), *fs_id)
+        });
+        libraries.dedup();
+        let mut rows = Vec::with_capacity(movies.len() + libraries.len());
+        for fs_id in libraries {
+            rows.push(TableRow::Library(fs_id));
+            if !self.collapsed.contains(&fs_id) {
+                rows.extend(
+                    movies
+                        .iter()
+                        .filter(|(_, id, _)| *id == fs_id)
+                        .map(|entry| TableRow::Movie(entry)),
+                );
+            }
+        }
+        rows
+    }
+
+    fn selected_row<'a>(&self, catalog: &'a Catalog) -> Option<TableRow<'a>> {
+        self.table_state
+            .selected()
+            .and_then(|s| self.rows(catalog).get(s).copied())
+    }
+
+    pub fn selected_movie<'a>(
+        &self,
+        catalog: &'a Catalog,
+    ) -> Option<&'a (Movie, LibraryId, PathBuf)> {
+        match self.selected_row(catalog) {
+            Some(TableRow::Movie(entry)) => Some(entry),
+            _ => None,
+        }
+    }
+
+    fn selected_key(&self, catalog: &Catalog) -> Option<RowKey> {
+        self.selected_row(catalog).map(|row| match row {
+            TableRow::Library(fs_id) => (fs_id, None),
+            TableRow::Movie((_, fs_id, path)) => (*fs_id, Some(path.clone())),
+        })
+    }
+
+    pub fn input(&mut self, app_event: AppEvent, catalog: &Catalog, keys: &Keybindings) -> bool {
         match app_event {
             AppEvent::KeyEvent(kev) => {
-                if kev.code == KeyCode::Char('r') && (!self.is_loading) {
+                self.restore = None;
+                if keys.is(Action::Reload, &kev) && (!self.is_loading) {
                     self.is_loading = true;
                     let sender = MESSAGE_SENDER.get().unwrap();
                     sender
                         .send(MovieManagerMessage::RefreshMovies.into())
                         .unwrap();
                     true
-                } else if kev.code == KeyCode::Up && self.movies.len() > 0 {
-                    self.table_state.select(
-                        self.table_state
-                            .selected()
-                            .map(|c| (c + self.movies.len() - 1) % self.movies.len()),
-                    );
+                } else if keys.is(Action::ReloadLibrary, &kev) {
+                    let fs_id = match self.selected_row(catalog) {
+                        Some(TableRow::Library(fs_id)) => fs_id,
+                        Some(TableRow::Movie((_, fs_id, _))) => *fs_id,
+                        None => return false,
+                    };
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(MovieManagerMessage::RefreshLibrary(fs_id).into())
+                        .unwrap();
+                    true
+                } else if keys.is(Action::GroupByLibrary, &kev) {
+                    let selected = self.selected_key(catalog);
+                    self.grouped = !self.grouped;
+                    self.collapsed.clear();
+                    self.table_state.select(None);
+                    match selected {
+                        Some((fs_id, Some(path))) => self.select_movie(catalog, fs_id, &path),
+                        Some((fs_id, None)) => self.select_library(catalog, fs_id),
+                        None => {}
+                    }
+                    true
+                } else if matches!(kev.code, KeyCode::Enter | KeyCode::Char(' '))
+                    && matches!(self.selected_row(catalog), Some(TableRow::Library(_)))
+                {
+                    if let Some(TableRow::Library(fs_id)) = self.selected_row(catalog) {
+                        if !self.collapsed.remove(&fs_id) {
+                            self.collapsed.insert(fs_id);
+                        }
+                        self.select_library(catalog, fs_id);
+                    }
+                    true
+                } else if kev.code == KeyCode::Up && !catalog.is_empty() {
+                    let len = self.rows(catalog).len();
+                    self.table_state
+                        .select(self.table_state.selected().map(|c| (c + len - 1) % len));
                     true
-                } else if kev.code == KeyCode::Down && self.movies.len() > 0 {
+                } else if kev.code == KeyCode::Down && !catalog.is_empty() {
+                    let len = self.rows(catalog).len();
                     self.table_state.select(
                         self.table_state
                             .selected()
-                            .map(|c| (c + 1) % self.movies.len())
+                            .map(|c| (c + 1) % len)
                             .or(Some(0)),
                     );
                     true
-                } else if let Some(s) = self.table_state.selected() {
+                } else if keys.is(Action::AutoIdentify, &kev) {
+                    let movies = catalog
+                        .iter()
+                        .filter(|(movie, _, _)| movie.uniqueid.is_empty())
+                        .cloned()
+                        .collect();
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(MovieManagerMessage::AutoIdentify(movies).into())
+                        .unwrap();
+                    true
+                } else if keys.is(Action::Review, &kev) {
+                    while let Some((movie, fs_id, path)) = self.review.pop_front() {
+                        let movie = catalog.get(fs_id, &path).cloned().unwrap_or(movie);
+                        if movie.uniqueid.is_empty() {
+                            let sender = MESSAGE_SENDER.get().unwrap();
+                            sender
+                                .send(AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
+                                    MovieManagerEvent::SearchMovie((movie, fs_id, path)),
+                                )))
+                                .unwrap();
+                            break;
+                        }
+                    }
+                    true
+                } else if keys.is(Action::QuickOpen, &kev) {
+                    let entries: Vec<(LibraryId, PathBuf)> = self
+                        .sorted_movies(catalog)
+                        .iter()
+                        .map(|(_, fs_id, path)| (*fs_id, path.clone()))
+                        .collect();
+                    let items = self
+                        .sorted_movies(catalog)
+                        .iter()
+                        .map(|(movie, _, path)| match movie.original_title.as_deref() {
+                            Some(original) if original != movie.title => {
+                                format!("{} ({}) {}", movie.title, original, path.display())
+                            }
+                            _ => format!("{} {}", movie.title, path.display()),
+                        })
+                        .collect();
+                    modal::open_modal(Modal::fuzzy_picker(
+                        "Quick open",
+                        "Type a title, an original title or a path.",
+                        items,
+                        move |_, index| {
+                            vec![AppEvent::MovieManagerEvent(MovieManagerEvent::SelectMovie(
+                                entries[index].clone(),
+                            ))]
+                        },
+                    ));
+                    true
+                } else if keys.is(Action::Columns, &kev) {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::Closure(Box::new(|app_state: &mut AppState| {
+                            let picker = column_picker(&app_state.config.table.columns, 0);
+                            app_state.modals.push(picker);
+                            vec![]
+                        })))
+                        .unwrap();
+                    true
+                } else if keys.is(Action::Recent, &kev) {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::Closure(Box::new(|app_state: &mut AppState| {
+                            let picker = recent_picker(&app_state.recent);
+                            match picker {
+                                Some(picker) => app_state.modals.push(picker),
+                                None => {
+                                    app_state.notice =
+                                        Some("No recently edited or pinned movie.".into())
+                                }
+                            }
+                            vec![]
+                        })))
+                        .unwrap();
+                    true
+                } else if keys.is(Action::Normalize, &kev) {
+                    let movies: Vec<_> = catalog.iter().cloned().collect();
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    sender
+                        .send(AppMessage::Closure(Box::new(
+                            move |app_state: &mut AppState| {
+                                let ops = normalize_ops(movies, &app_state.config.normalization);
+                                if ops.is_empty() {
+                                    app_state.notice = Some("No NFO to normalize.".into());
+                                } else {
+                                    let sender = MESSAGE_SENDER.get().unwrap();
+                                    sender.send(MovieManagerMessage::Batch(ops).into()).unwrap();
+                                }
+                                vec![]
+                            },
+                        )))
+                        .unwrap();
+                    true
+                } else if keys.is(Action::HealthReport, &kev) {
+                    let movies = catalog.iter().cloned().collect();
                     let sender = MESSAGE_SENDER.get().unwrap();
-                    let msg = match kev.code {
-                        KeyCode::Char('s') => {
+                    sender
+                        .send(MovieManagerMessage::CheckNfos(movies).into())
+                        .unwrap();
+                    true
+                } else if keys.is(Action::Sort, &kev) {
+                    self.sort = self.sort.next();
+                    self.table_state.select(None);
+                    true
+                } else if let Some(TableRow::Movie(entry)) = self.selected_row(catalog) {
+                    let sender = MESSAGE_SENDER.get().unwrap();
+                    let set_source = |source: &str| {
+                        let (mut movie, fs_id, path) = entry.clone();
+                        movie.source = Some(source.into());
+                        MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                    };
+                    let msg = match keys.action(&Action::ALL, &kev) {
+                        Some(Action::Search) => {
                             AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
-                                MovieManagerEvent::SearchMovie(self.movies[s].clone()),
+                                MovieManagerEvent::SearchMovie(entry.clone()),
                             ))
                         }
-                        KeyCode::Char('e') => {
+                        Some(Action::Edit) => {
                             AppMessage::TriggerEvent(AppEvent::MovieManagerEvent(
-                                MovieManagerEvent::EditMovie(self.movies[s].clone()),
+                                MovieManagerEvent::EditMovie(entry.clone()),
                             ))
                         }
-                        KeyCode::Char('t') => {
-                            let (mut movie, fs_id, path) = self.movies[s].clone();
-                            movie.source = Some("TV".into());
-                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        Some(Action::EditSource) => {
+                            MovieManagerMessage::LoadNfoSource(entry.clone()).into()
                         }
-                        KeyCode::Char('b') => {
-                            let (mut movie, fs_id, path) = self.movies[s].clone();
-                            movie.source = Some("Bluray".into());
-                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        Some(Action::SourceTv) => set_source("TV"),
+                        Some(Action::SourceBluray) => set_source("Bluray"),
+                        Some(Action::SourceDvd) => set_source("DVD"),
+                        Some(Action::SourceWeb) => set_source("WEB"),
+                        Some(Action::SourceUhdBluray) => set_source("UHD Bluray"),
+                        Some(
+                            action @ (Action::DownloadArtworks | Action::ForceDownloadArtworks),
+                        ) => {
+                            let (movie, fs_id, path) = entry.clone();
+                            let force = action == Action::ForceDownloadArtworks;
+                            AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                                let is_show = app_state
+                                    .library(fs_id)
+                                    .is_some_and(|lib| lib.flavor == LibraryFlavor::TvShow);
+                                let msg = if is_show {
+                                    MovieManagerMessage::IdentifyShow((fs_id, path, force))
+                                } else {
+                                    MovieManagerMessage::RetrieveArtworks((
+                                        movie, fs_id, path, force,
+                                    ))
+                                };
+                                let sender = MESSAGE_SENDER.get().unwrap();
+                                sender.send(msg.into()).unwrap();
+                                vec![]
+                            }))
                         }
-                        KeyCode::Char('d') => {
-                            let (mut movie, fs_id, path) = self.movies[s].clone();
-                            movie.source = Some("DVD".into());
-                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        Some(Action::Rename) => MovieManagerMessage::Rename(entry.clone()).into(),
+                        Some(Action::DeleteMovie) => {
+                            let title = entry.0.title.clone();
+                            let entry = entry.clone();
+                            modal::open_modal(Modal::confirm(
+                                "Delete movie",
+                                format!("Delete the files of {}? This cannot be undone.", title),
+                                move |_| {
+                                    let sender = MESSAGE_SENDER.get().unwrap();
+                                    sender
+                                        .send(MovieManagerMessage::DeleteMovie(entry).into())
+                                        .unwrap();
+                                    vec![]
+                                },
+                            ));
+                            return true;
                         }
-                        KeyCode::Char('w') => {
-                            let (mut movie, fs_id, path) = self.movies[s].clone();
-                            movie.source = Some("WEB".into());
-                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        Some(Action::MoveMovie) => {
+                            let (movie, fs_id, path) = entry.clone();
+                            AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                                let targets: Vec<(LibraryId, String)> = app_state
+                                    .libraries
+                                    .iter()
+                                    .flatten()
+                                    .filter(|lib| lib.id != fs_id)
+                                    .filter(|lib| lib.flavor == LibraryFlavor::Movie)
+                                    .map(|lib| (lib.id, lib.name.clone()))
+                                    .collect();
+                                if targets.is_empty() {
+                                    app_state.notice =
+                                        Some("No other movie library to move to.".into());
+                                    return vec![];
+                                }
+                                let names = targets.iter().map(|(_, name)| name.clone()).collect();
+                                let message = format!("Move {} to:", movie.title);
+                                app_state.modals.push(Modal::picker(
+                                    "Move movie",
+                                    message,
+                                    names,
+                                    move |_, picked| {
+                                        let dest_id = targets[picked].0;
+                                        let sender = MESSAGE_SENDER.get().unwrap();
+                                        sender
+                                            .send(
+                                                MovieManagerMessage::MoveMovie((
+                                                    movie, fs_id, path, dest_id,
+                                                ))
+                                                .into(),
+                                            )
+                                            .unwrap();
+                                        vec![]
+                                    },
+                                ));
+                                vec![]
+                            }))
                         }
-                        KeyCode::Char('u') => {
-                            let (mut movie, fs_id, path) = self.movies[s].clone();
-                            movie.source = Some("UHD Bluray".into());
-                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        Some(Action::RefreshMetadata) => {
+                            MovieManagerMessage::RefreshFromTmdb(entry.clone()).into()
                         }
-                        KeyCode::Char('a') => {
-                            MovieManagerMessage::RetrieveArtworks(self.movies[s].clone()).into()
+                        Some(Action::ProbeMedia) => {
+                            let entry = entry.clone();
+                            AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                                let (_, fs_id, path) = &entry;
+                                let is_show = app_state
+                                    .library(*fs_id)
+                                    .is_some_and(|lib| lib.flavor == LibraryFlavor::TvShow);
+                                // The episodes of a season are probed together.
+                                let season = path.parent().filter(|_| is_show).map(Path::to_owned);
+                                let msg = match season {
+                                    Some(season_dir) => {
+                                        MovieManagerMessage::ProbeSeason((*fs_id, season_dir))
+                                    }
+                                    None => MovieManagerMessage::ProbeMedia(entry),
+                                };
+                                let sender = MESSAGE_SENDER.get().unwrap();
+                                sender.send(msg.into()).unwrap();
+                                vec![]
+                            }))
                         }
-                        KeyCode::Char('n') => {
-                            MovieManagerMessage::Rename(self.movies[s].clone()).into()
+                        Some(Action::GrabThumb) => {
+                            MovieManagerMessage::GrabThumb(entry.clone()).into()
+                        }
+                        Some(Action::VerifyChecksum) => {
+                            MovieManagerMessage::VerifyChecksum(entry.clone()).into()
+                        }
+                        Some(Action::RestoreNfo) => {
+                            MovieManagerMessage::ListNfoBackups(entry.clone()).into()
+                        }
+                        Some(Action::ToggleWatched) => {
+                            let (mut movie, fs_id, path) = entry.clone();
+                            movie.set_watched(!movie.is_watched());
+                            MovieManagerMessage::SaveNfo((movie, fs_id, path)).into()
+                        }
+                        Some(Action::Pin) => {
+                            let (movie, fs_id, path) = entry.clone();
+                            AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                                let pinned =
+                                    app_state.recent.toggle_pin(fs_id, &path, &movie.title);
+                                let verb = if pinned { "Pinned" } else { "Unpinned" };
+                                app_state.notice = Some(format!("{} {}.", verb, movie.title));
+                                vec![]
+                            }))
                         }
                         _ => return false,
                     };
@@ -245,52 +921,199 @@ impl MovieTableState {
                     false
                 }
             }
-            AppEvent::MovieManagerEvent(MovieManagerEvent::ClearMovieList) => {
+            _ => false,
+        }
+    }
+
+    /// Select the row of a movie, in the current sort order. Its library group gets unfolded.
+    pub fn select_movie(&mut self, catalog: &Catalog, fs_id: LibraryId, path: &Path) {
+        self.restore = None;
+        self.collapsed.remove(&fs_id);
+        if let Some(row) = self
+            .rows(catalog)
+            .iter()
+            .position(|row| matches!(row, TableRow::Movie((_, id, p)) if *id == fs_id && p == path))
+        {
+            self.table_state.select(Some(row));
+        }
+    }
+
+    fn select_library(&mut self, catalog: &Catalog, fs_id: LibraryId) {
+        if let Some(row) = self
+            .rows(catalog)
+            .iter()
+            .position(|row| matches!(row, TableRow::Library(id) if *id == fs_id))
+        {
+            self.table_state.select(Some(row));
+        }
+    }
+
+    pub fn queue_review(&mut self, entries: Vec<(Movie, LibraryId, PathBuf)>) {
+        for entry in entries {
+            if !self
+                .review
+                .iter()
+                .any(|(_, fs_id, path)| *fs_id == entry.1 && *path == entry.2)
+            {
+                self.review.push_back(entry);
+            }
+        }
+    }
+
+    /// Remember the selected row before the catalog gets cleared by `evt`.
+    pub fn catalog_changing(&mut self, evt: &MovieManagerEvent, catalog: &Catalog) {
+        if matches!(
+            evt,
+            MovieManagerEvent::ClearMovieList | MovieManagerEvent::ClearLibrary(_)
+        ) && self.restore.is_none()
+        {
+            self.restore = self
+                .selected_key(catalog)
+                .map(|key| (key, self.table_state.clone()));
+        }
+    }
+
+    pub fn catalog_updated(&mut self, evt: &MovieManagerEvent, catalog: &Catalog) -> bool {
+        match evt {
+            MovieManagerEvent::ClearMovieList | MovieManagerEvent::ClearLibrary(_) => {
                 self.table_state.select(None);
-                self.movies.clear();
                 true
             }
-            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieDiscovered(movie)) => {
+            MovieManagerEvent::MovieDiscovered(_) => {
                 self.is_loading = false;
-                match self
-                    .movies
-                    .binary_search_by_key(&&movie.0.title, |m| &m.0.title)
-                {
-                    Ok(i) => self.movies.insert(i, movie),
-                    Err(i) => self.movies.insert(i, movie),
-                }
+                self.restore_selection(catalog);
                 true
             }
-            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieUpdated((movie, fs_id, path))) => {
+            MovieManagerEvent::MovieUpdated(_) | MovieManagerEvent::ScanCancelled(_) => {
                 self.is_loading = false;
-                if let Some((ind, _)) = self
-                    .movies
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, (_, fi, p))| p == &path && fi == &fs_id)
-                    .next()
-                {
-                    self.movies[ind] = (movie, fs_id, path);
-                } else {
-                    self.movies.push((movie, fs_id, path));
-                }
                 true
             }
-            AppEvent::MovieManagerEvent(MovieManagerEvent::MovieMoved((fs_id, path, new_path))) => {
-                if let Some((ind, _)) = self
-                    .movies
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, (_, fi, p))| p == &path && fi == &fs_id)
-                    .next()
-                {
-                    self.movies[ind].2 = new_path;
-                    true
-                } else {
-                    false
-                }
-            }
+            _ => true,
+        }
+    }
+
+    /// Select the remembered row again once it is back, with the scroll it had.
+    fn restore_selection(&mut self, catalog: &Catalog) {
+        let Some(((fs_id, path), saved)) = &self.restore else {
+            return;
+        };
+        let row = self.rows(catalog).iter().position(|row| match (row, path) {
+            (TableRow::Library(id), None) => id == fs_id,
+            (TableRow::Movie((_, id, p)), Some(path)) => id == fs_id && p == path,
             _ => false,
+        });
+        if let Some(row) = row {
+            if self.table_state.selected().is_none() {
+                self.table_state = saved.clone();
+            }
+            self.table_state.select(Some(row));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfo::UniqueId;
+    use crate::test_util::{library, render_app, state};
+    use crate::views::movie_manager::MovieManagerState;
+    use crate::views::settings::SettingsPage;
+    use crate::views::{App, TabState};
+
+    #[test]
+    fn wide_titles_stay_aligned() {
+        let keys = Keybindings::default();
+        let lib = library();
+        let long = "長い映画のタイトル".repeat(4);
+        let mut mstate = MovieManagerState::default();
+        let movies = [(long.as_str(), "a/a.mkv"), ("Alien", "b/b.mkv")];
+        for (title, path) in movies {
+            let movie = Movie {
+                title: title.into(),
+                premiered: Some("1979-05-25".into()),
+                ..Default::default()
+            };
+            let discovered = MovieManagerEvent::MovieDiscovered((movie, lib.id, path.into()));
+            mstate.input(AppEvent::MovieManagerEvent(discovered), &keys);
+        }
+        let mut state = AppState {
+            tab: TabState::MovieManager(mstate),
+            libraries: vec![Some(lib)],
+            ..state()
+        };
+        let text = render_app(&mut state, 100, 12);
+        let rows: Vec<&str> = text.lines().filter(|line| line.contains("1979")).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows.iter().filter(|row| row.contains('…')).count(), 1);
+        // The years stay aligned after the wide title.
+        let year_column = |row: &str| row[..row.find("1979").unwrap()].width();
+        assert_eq!(year_column(rows[0]), year_column(rows[1]));
+    }
+
+    #[test]
+    fn table_row_styles() {
+        let keys = Keybindings::default();
+        let lib = library();
+        let mut mstate = MovieManagerState::default();
+        let uniqueid = vec![UniqueId {
+            id_type: "tmdb".into(),
+            default: true,
+            value: "348".into(),
+        }];
+        let movies = [
+            ("Alien", uniqueid, true, true),
+            ("Brazil", vec![], true, false),
+            ("Casablanca", vec![], false, false),
+        ];
+        for (title, uniqueid, nfo, artworks) in movies {
+            let path = PathBuf::from(format!("{}/{}.mkv", title, title));
+            let movie = Movie {
+                title: title.into(),
+                uniqueid,
+                ..Default::default()
+            };
+            let assets = MovieAssets {
+                nfo,
+                poster: artworks,
+                fanart: artworks,
+                ..Default::default()
+            };
+            let discovered = MovieManagerEvent::MovieDiscovered((movie, lib.id, path.clone()));
+            let detected = MovieManagerEvent::AssetsDetected((lib.id, path, assets));
+            mstate.input(AppEvent::MovieManagerEvent(discovered), &keys);
+            mstate.input(AppEvent::MovieManagerEvent(detected), &keys);
+        }
+        let mut state = AppState {
+            tab: TabState::MovieManager(mstate),
+            libraries: vec![Some(lib)],
+            ..state()
+        };
+        state.config.table.columns = vec![TableColumn::Title, TableColumn::Status];
+        let text = render_app(&mut state, 60, 12);
+        for row in ["Alien      ✔", "Brazil     ✔✖", "Casablanca  ✖"] {
+            assert!(text.contains(row), "{}", text);
+        }
+
+        let mut terminal = tui::Terminal::new(tui::backend::TestBackend::new(60, 12)).unwrap();
+        let app = App {
+            settings_page: SettingsPage::new(),
+            movie_manager: Default::default(),
+        };
+        terminal
+            .draw(|f| f.render_stateful_widget(app, f.size(), &mut state))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let style = |title: &str| {
+            let y = text.lines().position(|line| line.contains(title)).unwrap();
+            buffer.get(1, y as u16).style()
+        };
+        // Every other movie is striped, the unidentified ones stand out.
+        let brazil = style("Brazil");
+        assert_eq!(brazil.bg, Some(Color::Black));
+        assert_eq!(brazil.fg, Some(Color::Yellow));
+        let casablanca = style("Casablanca");
+        assert_ne!(casablanca.bg, Some(Color::Black));
+        assert_eq!(casablanca.fg, Some(Color::Yellow));
+        assert!(casablanca.add_modifier.contains(Modifier::ITALIC));
+    }
+}
@@ -1,20 +1,29 @@
-use crate::library::{Library, LibraryFlavor, LibraryType};
+use crate::asyncfs::{run_detached, Operation};
+use crate::config::{ConfigLibrary, OrphanedCredential, RenameCollision, Renamer};
+use crate::keys::{Action, KeyContext, Keybindings};
+use crate::library::{
+    default_max_depth, AssetNaming, FsProfile, Library, LibraryFlavor, LibraryId, LibraryType,
+};
+use crate::multifs::ConnectionStatus;
 use crate::util::{OwnedSpan, OwnedSpans};
+use crate::views::modal::{self, Modal};
 use crate::views::widgets::{
     Button, ButtonState, Checkbox, Input, LabelledCheckbox, LabelledCheckboxState, LabelledInput,
     LabelledInputState,
 };
-use crate::{AppEvent, AppMessage, AppState, MultiFs, MESSAGE_SENDER};
-use crossterm::event::{KeyCode, KeyEvent};
-use std::path::PathBuf;
-use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget};
+use crate::{AppEvent, AppMessage, AppState, ConnectionPool, MultiFs, MESSAGE_SENDER};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::path::{Path, PathBuf};
+use tui::widgets::{
+    Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+    Wrap,
+};
 use tui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Span,
+    text::{Span, Spans},
 };
-use url::Url;
 
 #[derive(Clone, Debug)]
 pub struct SettingsPage {
@@ -24,15 +33,19 @@ pub struct SettingsPage {
 #[derive(Clone, Debug)]
 pub enum SettingsState {
     Menu(SettingsMenuState),
-    Edit(SettingsEditState),
+    Edit(Box<SettingsEditState>),
+    Preferences(Box<PreferencesState>),
 }
 
 #[derive(Clone, Debug)]
 pub enum SettingsEvent {
-    OpenMenu(Vec<Library>),
+    OpenMenu(Vec<(Library, ConnectionStatus)>),
     EditNew(LibraryType),
     EditExisting(Library),
     ConnTestResult((bool, bool)),
+    BrowseResult((PathBuf, Result<Vec<String>, String>)), // path, directories or error
+    LibraryStatus((Library, ConnectionStatus)),
+    EditPreferences(Renamer),
 }
 
 #[derive(Clone, Debug)]
@@ -41,6 +54,14 @@ pub enum SettingsMessage {
     EditExisting(Library),
     SaveLibrary(Library),
     TestLibrary(Library),
+    /// List the directories at the path, connecting with the entered library settings.
+    BrowseLibrary((Library, PathBuf)),
+    OpenPreferences,
+    SavePreferences(Renamer),
+    /// Move the clear passwords to a secret store and list the orphaned ones.
+    AuditCredentials,
+    /// Delete the orphaned passwords, once confirmed.
+    DeleteCredentials(Vec<OrphanedCredential>),
 }
 
 impl Default for SettingsState {
@@ -50,21 +71,31 @@ impl Default for SettingsState {
 }
 
 impl SettingsState {
-    pub fn press_key(&mut self, kev: KeyEvent) -> bool {
+    pub fn key_context(&self) -> KeyContext {
+        match self {
+            SettingsState::Menu(_) => KeyContext::Settings,
+            SettingsState::Edit(_) | SettingsState::Preferences(_) => KeyContext::SettingsForm,
+        }
+    }
+
+    pub fn press_key(&mut self, kev: KeyEvent, keys: &Keybindings) -> bool {
         match self {
             SettingsState::Menu(ref mut state) => {
                 return state.press_key(kev);
             }
             SettingsState::Edit(ref mut state) => {
-                return state.press_key(kev);
+                return state.press_key(kev, keys);
+            }
+            SettingsState::Preferences(ref mut state) => {
+                return state.press_key(kev, keys);
             }
         }
         false
     }
 
-    pub fn input(&mut self, evt: AppEvent) -> bool {
+    pub fn input(&mut self, evt: AppEvent, keys: &Keybindings) -> bool {
         match evt {
-            AppEvent::KeyEvent(kev) => self.press_key(kev),
+            AppEvent::KeyEvent(kev) => self.press_key(kev, keys),
             AppEvent::SettingsEvent(SettingsEvent::OpenMenu(libraries)) => {
                 let mut items = standard_actions();
                 for l in libraries {
@@ -78,39 +109,112 @@ impl SettingsState {
                 if fs_type != LibraryType::Local {
                     state.host = Some(LabelledInputState::default());
                     state.username = Some(LabelledInputState::default());
-                    state.password = Some(LabelledInputState::default());
+                    state.password = Some(LabelledInputState::masked());
+                } else {
+                    state.symlinks = Some(LabelledCheckboxState::default());
+                }
+                #[cfg(feature = "ftp")]
+                if fs_type == LibraryType::Ftp {
+                    state.enable_ftp_options(None, false, true);
+                }
+                #[cfg(feature = "smb")]
+                if fs_type == LibraryType::Smb {
+                    state.enable_smb_options(None, None);
+                }
+                #[cfg(feature = "s3")]
+                if fs_type == LibraryType::S3 {
+                    state.enable_s3_options(None, None, true);
                 }
                 state.fs_type = fs_type;
-                *self = SettingsState::Edit(state);
+                *self = SettingsState::Edit(Box::new(state));
                 true
             }
             AppEvent::SettingsEvent(SettingsEvent::EditExisting(lib)) => {
-                let mut state = SettingsEditState::default();
+                let mut state = SettingsEditState {
+                    id: lib.id,
+                    ffmpeg_path_mapping: lib.ffmpeg_path_mapping.clone(),
+                    ..Default::default()
+                };
                 if lib.fs_type != LibraryType::Local {
                     state.host = Some(LabelledInputState::default());
                     state.username = Some(LabelledInputState::default());
-                    state.password = Some(LabelledInputState::default());
-                    if let Some(host) = lib.host {
-                        state.host.as_mut().unwrap().set_value(&host);
+                    state.password = Some(LabelledInputState::masked());
+                    if let (Some(host), Some(input)) = (lib.host, state.host.as_mut()) {
+                        input.set_value(&host);
                     }
-                    if let Some(username) = lib.username {
-                        state.username.as_mut().unwrap().set_value(&username);
+                    if let (Some(username), Some(input)) = (lib.username, state.username.as_mut()) {
+                        input.set_value(&username);
                     }
-                    if let Some(password) = lib.password {
-                        state.password.as_mut().unwrap().set_value(&password);
+                    if let (Some(password), Some(input)) = (lib.password, state.password.as_mut()) {
+                        input.set_value(&password);
                     }
+                } else {
+                    let mut symlinks = LabelledCheckboxState::default();
+                    symlinks.check(lib.follow_symlinks);
+                    state.symlinks = Some(symlinks);
+                }
+                #[cfg(feature = "ftp")]
+                if lib.fs_type == LibraryType::Ftp {
+                    state.enable_ftp_options(lib.port, lib.secure, lib.passive_mode);
+                }
+                #[cfg(feature = "smb")]
+                if lib.fs_type == LibraryType::Smb {
+                    state.enable_smb_options(lib.smb_domain, lib.smb_share);
+                }
+                #[cfg(feature = "s3")]
+                if lib.fs_type == LibraryType::S3 {
+                    state.enable_s3_options(lib.s3_bucket, lib.s3_region, lib.secure);
                 }
                 state.name.set_value(lib.name);
                 state.path.set_value(lib.path.display().to_string());
+                state.lang.set_value(lib.prefered_lang.unwrap_or_default());
+                state
+                    .country
+                    .set_value(lib.prefered_country.unwrap_or_default());
+                state.probe.check(lib.probe_media);
+                state.depth.set_value(lib.max_depth.to_string());
+                state.fs_profile.set_value(lib.fs_profile.to_string());
+                state.keep_unicode.check(lib.keep_unicode);
+                state.read_only.check(lib.read_only);
+                state
+                    .plex_assets
+                    .check(lib.asset_naming == AssetNaming::Plex);
                 if lib.flavor == LibraryFlavor::Movie {
                     state.movie.check(true);
                 } else {
                     state.tv_show.check(true);
                 }
                 state.fs_type = lib.fs_type;
-                *self = SettingsState::Edit(state);
+                *self = SettingsState::Edit(Box::new(state));
                 true
             }
+            AppEvent::SettingsEvent(SettingsEvent::EditPreferences(renamer)) => {
+                *self = SettingsState::Preferences(Box::new(PreferencesState::new(renamer)));
+                true
+            }
+            AppEvent::SettingsEvent(SettingsEvent::LibraryStatus((lib, status))) => {
+                if let SettingsState::Menu(ref mut state) = self {
+                    for item in state.items.iter_mut() {
+                        if matches!(&item.item_type, MenuItemType::ExistingLibrary(l) if **l == lib)
+                        {
+                            *item = MenuItem::from((lib.clone(), status));
+                        }
+                    }
+                    true
+                } else {
+                    false
+                }
+            }
+            AppEvent::SettingsEvent(SettingsEvent::BrowseResult((path, dirs))) => match self {
+                SettingsState::Edit(ref mut state) => match state.browser.as_mut() {
+                    Some(browser) if browser.path == path => {
+                        browser.loaded(dirs);
+                        true
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
             AppEvent::SettingsEvent(SettingsEvent::ConnTestResult(tests)) => {
                 if let SettingsState::Edit(ref mut state) = self {
                     state.test_result = Some(tests);
@@ -122,7 +226,8 @@ impl SettingsState {
             }
             _ => match self {
                 SettingsState::Menu(ref mut state) => state.input(evt),
-                SettingsState::Edit(ref mut state) => state.input(evt),
+                SettingsState::Edit(ref mut state) => state.input(evt, keys),
+                SettingsState::Preferences(ref mut state) => state.input(evt, keys),
             },
         }
     }
@@ -145,7 +250,10 @@ impl StatefulWidget for SettingsPage {
                 StatefulWidget::render(self.menu, area, buf, mstate);
             }
             SettingsState::Edit(ref mut estate) => {
-                StatefulWidget::render(SettingsEdit::default(), area, buf, estate);
+                StatefulWidget::render(SettingsEdit::default(), area, buf, estate.as_mut());
+            }
+            SettingsState::Preferences(ref mut pstate) => {
+                StatefulWidget::render(Preferences::default(), area, buf, pstate.as_mut());
             }
         }
     }
@@ -157,32 +265,48 @@ impl From<SettingsMessage> for AppMessage {
             SettingsMessage::OpenMenu => {
                 AppMessage::Closure(Box::new(|app_state: &mut AppState| {
                     vec![AppEvent::SettingsEvent(SettingsEvent::OpenMenu(
-                        app_state.libraries.iter().flatten().cloned().collect(),
+                        app_state.library_entries(),
                     ))]
                 }))
             }
-            SettingsMessage::EditExisting(_) | SettingsMessage::SaveLibrary(_) => {
-                AppMessage::SettingsMessage(value)
+            SettingsMessage::EditExisting(_)
+            | SettingsMessage::SaveLibrary(_)
+            | SettingsMessage::AuditCredentials
+            | SettingsMessage::DeleteCredentials(_) => AppMessage::SettingsMessage(value),
+            SettingsMessage::OpenPreferences => {
+                AppMessage::Closure(Box::new(|app_state: &mut AppState| {
+                    vec![AppEvent::SettingsEvent(SettingsEvent::EditPreferences(
+                        app_state.config.renamer.clone(),
+                    ))]
+                }))
+            }
+            SettingsMessage::SavePreferences(renamer) => {
+                AppMessage::Closure(Box::new(move |app_state: &mut AppState| {
+                    app_state.config.renamer = renamer;
+                    vec![AppEvent::SettingsEvent(SettingsEvent::OpenMenu(
+                        app_state.library_entries(),
+                    ))]
+                }))
             }
             SettingsMessage::TestLibrary(lib) => AppMessage::Future(Box::new(|_| {
                 Box::pin(async move {
                     let rst = match MultiFs::try_from(&lib) {
                         Ok(mut conn) => {
-                            let _ = conn.as_mut_rfs().connect();
-                            (
-                                conn.as_mut_rfs().is_connected(),
-                                conn.as_mut_rfs()
-                                    .exists(&lib.path.as_path())
-                                    .unwrap_or(false),
-                            )
+                            let path = lib.path.clone();
+                            run_detached(Operation::Connect, move || {
+                                let _ = conn.as_mut_rfs().connect();
+                                (
+                                    conn.as_mut_rfs().is_connected(),
+                                    conn.as_mut_rfs().exists(&path).unwrap_or(false),
+                                )
+                            })
+                            .await
+                            .unwrap_or((false, false))
                         }
                         Err(err) => {
                             log::warn!(
                                 "Connection to library `{}` failed due to:\n{:?}",
-                                Url::try_from(&lib)
-                                    .as_ref()
-                                    .map(Url::as_ref)
-                                    .unwrap_or("N/A"),
+                                lib.redacted_url().as_deref().unwrap_or("N/A"),
                                 err
                             );
                             (false, false)
@@ -191,6 +315,39 @@ impl From<SettingsMessage> for AppMessage {
                     vec![AppEvent::SettingsEvent(SettingsEvent::ConnTestResult(rst))]
                 })
             })),
+            SettingsMessage::BrowseLibrary((lib, path)) => AppMessage::Future(Box::new(|_| {
+                Box::pin(async move {
+                    let listed_path = path.clone();
+                    let listed = match MultiFs::try_from(&lib) {
+                        Ok(mut conn) => run_detached(Operation::List, move || {
+                            conn.as_mut_rfs()
+                                .connect()
+                                .map_err(|err| format!("Connection failed: {}", err))?;
+                            conn.as_mut_rfs()
+                                .list_dir(&listed_path)
+                                .map_err(|err| format!("Listing failed: {}", err))
+                        })
+                        .await
+                        .unwrap_or_else(|| Err("Listing timed out".into())),
+                        Err(err) => Err(format!("{:?}", err)),
+                    };
+                    let dirs = listed.map(|files| {
+                        let mut dirs: Vec<String> = files
+                            .iter()
+                            .filter(|f| f.is_dir())
+                            .map(|f| f.name())
+                            .collect();
+                        dirs.sort();
+                        dirs
+                    });
+                    if let Err(err) = &dirs {
+                        log::warn!("Browsing {} failed due to:\n{}", path.display(), err);
+                    }
+                    vec![AppEvent::SettingsEvent(SettingsEvent::BrowseResult((
+                        path, dirs,
+                    )))]
+                })
+            })),
         }
     }
 }
@@ -289,9 +446,29 @@ impl SettingsMenuState {
                                     )))
                                     .unwrap();
                             }
+                            #[cfg(feature = "s3")]
+                            MenuItemType::NewS3Library => {
+                                sender
+                                    .send(AppMessage::TriggerEvent(AppEvent::SettingsEvent(
+                                        SettingsEvent::EditNew(LibraryType::S3),
+                                    )))
+                                    .unwrap();
+                            }
+                            MenuItemType::Preferences => {
+                                sender.send(SettingsMessage::OpenPreferences.into()).unwrap();
+                            }
+                            MenuItemType::ExportLibraries => {
+                                modal::open_modal(export_modal());
+                            }
+                            MenuItemType::ImportLibraries => {
+                                modal::open_modal(import_modal());
+                            }
+                            MenuItemType::AuditCredentials => {
+                                modal::open_modal(audit_modal());
+                            }
                             MenuItemType::ExistingLibrary(l) => {
                                 sender
-                                    .send(SettingsMessage::EditExisting(l.clone()).into())
+                                    .send(SettingsMessage::EditExisting(l.as_ref().clone()).into())
                                     .unwrap();
                             }
                         }
@@ -334,17 +511,13 @@ impl<'a> From<MenuItem> for ListItem<'a> {
     }
 }
 
-impl From<Library> for MenuItem {
-    fn from(l: Library) -> MenuItem {
-        let url = url::Url::try_from(&l)
-            .map(|mut u| {
-                let _ = u.set_password(None);
-                return u.to_string();
-            })
-            .unwrap_or(l.to_string());
-        MenuItem::new(format!("{} ({})", &l.name, url))
+impl From<(Library, ConnectionStatus)> for MenuItem {
+    fn from((l, status): (Library, ConnectionStatus)) -> MenuItem {
+        let url = l.redacted_url().unwrap_or(l.to_string());
+        let read_only = if l.read_only { ", read-only" } else { "" };
+        MenuItem::new(format!("{} ({}) [{}{}]", &l.name, url, status, read_only))
             .selectable(true)
-            .set_type(MenuItemType::ExistingLibrary(l))
+            .set_type(MenuItemType::ExistingLibrary(Box::new(l)))
     }
 }
 
@@ -384,6 +557,12 @@ pub fn standard_actions() -> Vec<MenuItem> {
     items.push(MenuItem::new("Add a FTP library").set_type(MenuItemType::NewFtpLibrary));
     #[cfg(feature = "smb")]
     items.push(MenuItem::new("Add a SMB library").set_type(MenuItemType::NewSmbLibrary));
+    #[cfg(feature = "s3")]
+    items.push(MenuItem::new("Add a S3 library").set_type(MenuItemType::NewS3Library));
+    items.push(MenuItem::new("Renamer preferences").set_type(MenuItemType::Preferences));
+    items.push(MenuItem::new("Export the libraries").set_type(MenuItemType::ExportLibraries));
+    items.push(MenuItem::new("Import libraries").set_type(MenuItemType::ImportLibraries));
+    items.push(MenuItem::new("Audit the saved passwords").set_type(MenuItemType::AuditCredentials));
     items.push(
         MenuItem::new(" - Existing libraries -")
             .set_type(MenuItemType::None)
@@ -392,6 +571,179 @@ pub fn standard_actions() -> Vec<MenuItem> {
     items
 }
 
+/// Default file of the library exports, in the working directory.
+const EXPORT_FILE: &str = "mkube-libraries.toml";
+
+fn export_modal() -> Modal {
+    Modal::prompt(
+        "Export the libraries",
+        "File to write the libraries to, as JSON for a .json file and TOML otherwise. \
+        The passwords are left out.",
+        EXPORT_FILE,
+        |app_state: &mut AppState, path: String| {
+            let libraries: Vec<ConfigLibrary> = app_state
+                .config
+                .libraries
+                .iter()
+                .flatten()
+                .cloned()
+                .collect();
+            app_state.notice = Some(
+                match crate::config::export_libraries(Path::new(&path), &libraries) {
+                    Ok(()) => format!("{} libraries exported to {}.", libraries.len(), path),
+                    Err(err) => {
+                        log::error!("Library export failed, causes:\n{:?}", err);
+                        format!("Library export failed: {}.", err)
+                    }
+                },
+            );
+            vec![]
+        },
+    )
+}
+
+fn import_modal() -> Modal {
+    Modal::prompt(
+        "Import libraries",
+        "File exported by MKube, in JSON or TOML.",
+        EXPORT_FILE,
+        |app_state: &mut AppState, path: String| {
+            let existing: Vec<LibraryId> =
+                app_state.libraries.iter().flatten().map(|l| l.id).collect();
+            match crate::config::import_libraries(Path::new(&path), &existing) {
+                Ok(libraries) => {
+                    app_state.notice = Some(format!("{} libraries imported.", libraries.len()));
+                    import_next(app_state, libraries);
+                }
+                Err(err) => {
+                    log::error!("Library import failed, causes:\n{:?}", err);
+                    app_state.notice = Some(format!("Library import failed: {}.", err));
+                }
+            }
+            vec![]
+        },
+    )
+}
+
+fn audit_modal() -> Modal {
+    Modal::confirm(
+        "Audit the saved passwords",
+        "Move the passwords written in clear text in the configuration to the secret store, \
+        and look for the saved passwords of the removed libraries?",
+        |_: &mut AppState| {
+            let sender = MESSAGE_SENDER.get().unwrap();
+            sender
+                .send(SettingsMessage::AuditCredentials.into())
+                .unwrap();
+            vec![]
+        },
+    )
+}
+
+/// Confirm the deletion of the saved passwords no library of any profile uses.
+pub fn orphans_modal(orphans: Vec<OrphanedCredential>) -> Modal {
+    let labels: Vec<String> = orphans.iter().map(OrphanedCredential::label).collect();
+    Modal::confirm(
+        "Delete the unused passwords",
+        format!(
+            "No library of any profile uses the saved passwords of {}. Delete them?",
+            labels.join(", ")
+        ),
+        move |_: &mut AppState| {
+            let sender = MESSAGE_SENDER.get().unwrap();
+            sender
+                .send(SettingsMessage::DeleteCredentials(orphans).into())
+                .unwrap();
+            vec![]
+        },
+    )
+}
+
+/// Save the imported libraries, asking the password of the remote ones with a user.
+///
+/// Entered passwords go to the secret store on save, when one is available.
+fn import_next(app_state: &mut AppState, mut pending: Vec<Library>) {
+    let sender = MESSAGE_SENDER.get().unwrap();
+    while !pending.is_empty() {
+        let mut lib = pending.remove(0);
+        if lib.fs_type == LibraryType::Local || lib.username.is_none() {
+            sender
+                .send(SettingsMessage::SaveLibrary(lib).into())
+                .unwrap();
+            continue;
+        }
+        let message = format!(
+            "Password of {} for {}, empty for none. Esc skips this library and the next ones.",
+            lib.username.as_deref().unwrap_or_default(),
+            lib.name
+        );
+        app_state.modals.push(Modal::password(
+            "Import libraries",
+            message,
+            move |app_state: &mut AppState, password: String| {
+                lib.password = Some(password).filter(|p| !p.is_empty());
+                let sender = MESSAGE_SENDER.get().unwrap();
+                sender
+                    .send(SettingsMessage::SaveLibrary(lib).into())
+                    .unwrap();
+                import_next(app_state, pending);
+                vec![]
+            },
+        ));
+        return;
+    }
+}
+
+/// Ask the password of a library with `Credentials::Prompt` and open it, the password is only
+/// kept by its connection. `failed` when the previous password did not connect.
+pub fn password_prompt(lib: Library, failed: bool) -> Modal {
+    let mut message = format!(
+        "Password of {} for {}, it is not stored. Esc leaves the library offline.",
+        lib.username.as_deref().unwrap_or_default(),
+        lib.name
+    );
+    if failed {
+        message.insert_str(0, "Connection failed, check the password. ");
+    }
+    Modal::password("Library password", message, move |_, password: String| {
+        vec![unlock_library(lib, password)]
+    })
+}
+
+/// Open the connection of a library with the password entered, asking it again on failure.
+fn unlock_library(lib: Library, password: String) -> AppEvent {
+    AppEvent::ContinuationIOFuture(Box::new(move |app_state, _, _, conns: &ConnectionPool| {
+        let ttl = app_state.config.listing_cache.for_library(&lib);
+        Box::pin(async move {
+            let unlocked = Library {
+                password: Some(password).filter(|p| !p.is_empty()),
+                ..lib.clone()
+            };
+            let mut conns = conns.lock().await;
+            let status = crate::open_library(&mut conns, &unlocked, ttl).await;
+            let connected = status == Some(ConnectionStatus::Connected);
+            if connected {
+                log::info!("Library {} unlocked.", lib.name);
+            } else {
+                log::warn!("Library {} did not connect with its password.", lib.name);
+                conns.close(lib.id);
+            }
+            vec![AppEvent::ContinuationFuture(Box::new(
+                move |app_state: &mut AppState| {
+                    let events = if connected {
+                        // Back from offline, which scans it.
+                        app_state.set_library_status(lib.id, ConnectionStatus::Connected)
+                    } else {
+                        app_state.modals.push(password_prompt(lib, true));
+                        vec![]
+                    };
+                    Box::pin(async move { events })
+                },
+            ))]
+        })
+    }))
+}
+
 #[derive(Debug, Clone, Default)]
 pub enum MenuItemType {
     #[default]
@@ -401,7 +753,13 @@ pub enum MenuItemType {
     NewSmbLibrary,
     #[cfg(feature = "ftp")]
     NewFtpLibrary,
-    ExistingLibrary(Library),
+    #[cfg(feature = "s3")]
+    NewS3Library,
+    Preferences,
+    ExportLibraries,
+    ImportLibraries,
+    AuditCredentials,
+    ExistingLibrary(Box<Library>),
 }
 
 #[derive(Clone, Debug)]
@@ -410,9 +768,26 @@ pub struct SettingsEdit {
     pub host: LabelledInput,
     pub username: LabelledInput,
     pub password: LabelledInput,
+    pub port: LabelledInput,
+    pub secure: LabelledCheckbox,
+    pub passive: LabelledCheckbox,
+    pub smb_domain: LabelledInput,
+    pub smb_share: LabelledInput,
+    pub s3_bucket: LabelledInput,
+    pub s3_region: LabelledInput,
+    pub https: LabelledCheckbox,
+    pub symlinks: LabelledCheckbox,
     pub path: LabelledInput,
+    pub lang: LabelledInput,
+    pub country: LabelledInput,
     pub movie: LabelledCheckbox,
     pub tv_show: LabelledCheckbox,
+    pub probe: LabelledCheckbox,
+    pub plex_assets: LabelledCheckbox,
+    pub depth: LabelledInput,
+    pub fs_profile: LabelledInput,
+    pub keep_unicode: LabelledCheckbox,
+    pub read_only: LabelledCheckbox,
     pub test: Button,
     pub save: Button,
     pub cancel: Button,
@@ -420,19 +795,68 @@ pub struct SettingsEdit {
 
 #[derive(Clone, Debug)]
 pub struct SettingsEditState {
+    /// Kept when editing a library, so that its movies and tasks still refer to it.
+    pub id: LibraryId,
+    /// Only set in the configuration file, kept as is.
+    pub ffmpeg_path_mapping: Option<PathBuf>,
     pub focused: usize,
     pub fs_type: LibraryType,
     pub name: LabelledInputState,
     pub host: Option<LabelledInputState>,
     pub username: Option<LabelledInputState>,
     pub password: Option<LabelledInputState>,
+    pub port: Option<LabelledInputState>,
+    pub secure: Option<LabelledCheckboxState>,
+    pub passive: Option<LabelledCheckboxState>,
+    pub smb_domain: Option<LabelledInputState>,
+    pub smb_share: Option<LabelledInputState>,
+    pub s3_bucket: Option<LabelledInputState>,
+    pub s3_region: Option<LabelledInputState>,
+    pub symlinks: Option<LabelledCheckboxState>,
     pub path: LabelledInputState,
+    pub lang: LabelledInputState,
+    pub country: LabelledInputState,
     pub movie: LabelledCheckboxState,
     pub tv_show: LabelledCheckboxState,
+    pub probe: LabelledCheckboxState,
+    pub plex_assets: LabelledCheckboxState,
+    pub depth: LabelledInputState,
+    pub fs_profile: LabelledInputState,
+    pub keep_unicode: LabelledCheckboxState,
+    pub read_only: LabelledCheckboxState,
     pub test: ButtonState,
     pub save: ButtonState,
     pub cancel: ButtonState,
     pub test_result: Option<(bool, bool)>,
+    pub browser: Option<DirBrowserState>,
+}
+
+/// Directories of the library being edited, to pick its root path.
+#[derive(Clone, Debug, Default)]
+pub struct DirBrowserState {
+    pub path: PathBuf,
+    /// None while loading.
+    pub dirs: Option<Result<Vec<String>, String>>,
+    pub list_state: ListState,
+}
+
+impl DirBrowserState {
+    fn loaded(&mut self, dirs: Result<Vec<String>, String>) {
+        self.dirs = Some(dirs);
+        self.list_state.select(Some(0));
+    }
+
+    /// The listed entries, `..` first when there is a parent.
+    fn entries(&self) -> Vec<String> {
+        let mut entries = Vec::new();
+        if self.path.parent().is_some() {
+            entries.push("..".to_owned());
+        }
+        if let Some(Ok(dirs)) = &self.dirs {
+            entries.extend(dirs.iter().cloned());
+        }
+        entries
+    }
 }
 
 impl Default for SettingsEdit {
@@ -442,9 +866,32 @@ impl Default for SettingsEdit {
             host: LabelledInput::new("Host: ", Input::default()),
             username: LabelledInput::new("Username: ", Input::default()),
             password: LabelledInput::new("Password: ", Input::default()),
-            path: LabelledInput::new("Path: ", Input::default()),
+            port: LabelledInput::new("Port: ", Input::default()),
+            secure: LabelledCheckbox::new("FTPS", Checkbox::default()),
+            passive: LabelledCheckbox::new("Passive mode", Checkbox::default()),
+            smb_domain: LabelledInput::new("Domain/Workgroup: ", Input::default()),
+            smb_share: LabelledInput::new("Share: ", Input::default()),
+            s3_bucket: LabelledInput::new("Bucket: ", Input::default()),
+            s3_region: LabelledInput::new("Region (optional): ", Input::default()),
+            https: LabelledCheckbox::new("HTTPS", Checkbox::default()),
+            symlinks: LabelledCheckbox::new("Follow symlinks", Checkbox::default()),
+            path: LabelledInput::new("Path (Ctrl+O to browse): ", Input::default()),
+            lang: LabelledInput::new("TMDB language (optional): ", Input::default()),
+            country: LabelledInput::new("TMDB country (optional): ", Input::default()),
             movie: LabelledCheckbox::new("Movie", Checkbox::default()),
             tv_show: LabelledCheckbox::new("TV Show", Checkbox::default()),
+            probe: LabelledCheckbox::new("Probe stream details", Checkbox::default()),
+            plex_assets: LabelledCheckbox::new(
+                "Plex artwork names (poster.jpg, fanart.jpg)",
+                Checkbox::default(),
+            ),
+            depth: LabelledInput::new("Scan depth (-1 for unlimited): ", Input::default()),
+            fs_profile: LabelledInput::new("Filesystem (ntfs, ext4, fat, smb): ", Input::default()),
+            keep_unicode: LabelledCheckbox::new(
+                "Keep Unicode names (no transliteration)",
+                Checkbox::default(),
+            ),
+            read_only: LabelledCheckbox::new("Read-only (audit only)", Checkbox::default()),
             test: Button::default().with_text("Test"),
             save: Button::default().with_text("Save"),
             cancel: Button::default().with_text("Delete"),
@@ -454,20 +901,45 @@ impl Default for SettingsEdit {
 
 impl Default for SettingsEditState {
     fn default() -> SettingsEditState {
+        let mut probe = LabelledCheckboxState::default();
+        probe.check(true);
+        let mut depth = LabelledInputState::default();
+        depth.set_value(default_max_depth().to_string());
+        let mut fs_profile = LabelledInputState::default();
+        fs_profile.set_value(FsProfile::default().to_string());
         SettingsEditState {
+            id: LibraryId::new(),
+            ffmpeg_path_mapping: None,
             focused: 0,
             fs_type: LibraryType::Local,
             name: LabelledInputState::default(),
             host: None,
             username: None,
             password: None,
+            port: None,
+            secure: None,
+            passive: None,
+            smb_domain: None,
+            smb_share: None,
+            s3_bucket: None,
+            s3_region: None,
+            symlinks: None,
             path: LabelledInputState::default(),
+            lang: LabelledInputState::default(),
+            country: LabelledInputState::default(),
             movie: LabelledCheckboxState::default(),
             tv_show: LabelledCheckboxState::default(),
+            probe,
+            plex_assets: LabelledCheckboxState::default(),
+            depth,
+            fs_profile,
+            keep_unicode: LabelledCheckboxState::default(),
+            read_only: LabelledCheckboxState::default(),
             test: ButtonState::default(),
             save: ButtonState::default(),
             cancel: ButtonState::default(),
             test_result: None,
+            browser: None,
         }
     }
 }
@@ -488,11 +960,54 @@ impl StatefulWidget for SettingsEdit {
                     Constraint::Min(1),
                     Constraint::Min(1),
                     Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
                     Constraint::Percentage(100),
                 ]
                 .as_ref(),
             )
             .split(area.clone());
+        let ftp_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Min(16),
+                    Constraint::Min(2),
+                    Constraint::Min(10),
+                    Constraint::Min(2),
+                    Constraint::Min(18),
+                    Constraint::Percentage(100),
+                ]
+                .as_ref(),
+            )
+            .split(rows[4]);
+        let smb_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(50),
+                    Constraint::Min(2),
+                    Constraint::Percentage(50),
+                ]
+                .as_ref(),
+            )
+            .split(rows[4]);
+        let s3_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(40),
+                    Constraint::Min(2),
+                    Constraint::Percentage(40),
+                    Constraint::Min(2),
+                    Constraint::Min(10),
+                ]
+                .as_ref(),
+            )
+            .split(rows[4]);
         let type_selector_cells = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(
@@ -501,11 +1016,13 @@ impl StatefulWidget for SettingsEdit {
                     Constraint::Min(10),
                     Constraint::Min(2),
                     Constraint::Min(12),
+                    Constraint::Min(4),
+                    Constraint::Min(26),
                     Constraint::Percentage(100),
                 ]
                 .as_ref(),
             )
-            .split(rows[5]);
+            .split(rows[8]);
         let buttons_cells = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(
@@ -520,7 +1037,11 @@ impl StatefulWidget for SettingsEdit {
                 ]
                 .as_ref(),
             )
-            .split(rows[7]);
+            .split(rows[12]);
+        let naming_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(46), Constraint::Percentage(100)].as_ref())
+            .split(rows[11]);
 
         StatefulWidget::render(self.name, rows[0], buf, &mut state.name);
         if let Some(ref mut istate) = state.host {
@@ -532,7 +1053,37 @@ impl StatefulWidget for SettingsEdit {
         if let Some(ref mut istate) = state.password {
             StatefulWidget::render(self.password, rows[3], buf, istate);
         }
-        StatefulWidget::render(self.path, rows[4], buf, &mut state.path);
+        if let Some(ref mut istate) = state.port {
+            StatefulWidget::render(self.port, ftp_cells[0], buf, istate);
+        }
+        if let Some(ref mut cstate) = state.secure {
+            if state.s3_bucket.is_some() {
+                StatefulWidget::render(self.https, s3_cells[4], buf, cstate);
+            } else {
+                StatefulWidget::render(self.secure, ftp_cells[2], buf, cstate);
+            }
+        }
+        if let Some(ref mut cstate) = state.passive {
+            StatefulWidget::render(self.passive, ftp_cells[4], buf, cstate);
+        }
+        if let Some(ref mut istate) = state.smb_domain {
+            StatefulWidget::render(self.smb_domain, smb_cells[0], buf, istate);
+        }
+        if let Some(ref mut istate) = state.smb_share {
+            StatefulWidget::render(self.smb_share, smb_cells[2], buf, istate);
+        }
+        if let Some(ref mut istate) = state.s3_bucket {
+            StatefulWidget::render(self.s3_bucket, s3_cells[0], buf, istate);
+        }
+        if let Some(ref mut istate) = state.s3_region {
+            StatefulWidget::render(self.s3_region, s3_cells[2], buf, istate);
+        }
+        if let Some(ref mut cstate) = state.symlinks {
+            StatefulWidget::render(self.symlinks, smb_cells[0], buf, cstate);
+        }
+        StatefulWidget::render(self.path, rows[5], buf, &mut state.path);
+        StatefulWidget::render(self.lang, rows[6], buf, &mut state.lang);
+        StatefulWidget::render(self.country, rows[7], buf, &mut state.country);
 
         let type_label = Paragraph::new(Span::raw("Library type: "));
         Widget::render(type_label, type_selector_cells[0], buf);
@@ -543,6 +1094,17 @@ impl StatefulWidget for SettingsEdit {
             buf,
             &mut state.tv_show,
         );
+        StatefulWidget::render(self.probe, type_selector_cells[5], buf, &mut state.probe);
+        StatefulWidget::render(self.depth, type_selector_cells[6], buf, &mut state.depth);
+        StatefulWidget::render(self.plex_assets, rows[9], buf, &mut state.plex_assets);
+        StatefulWidget::render(self.fs_profile, rows[10], buf, &mut state.fs_profile);
+        StatefulWidget::render(
+            self.keep_unicode,
+            naming_cells[0],
+            buf,
+            &mut state.keep_unicode,
+        );
+        StatefulWidget::render(self.read_only, naming_cells[1], buf, &mut state.read_only);
         StatefulWidget::render(self.test, buttons_cells[0], buf, &mut state.test);
         StatefulWidget::render(self.save, buttons_cells[2], buf, &mut state.save);
         StatefulWidget::render(self.cancel, buttons_cells[4], buf, &mut state.cancel);
@@ -565,21 +1127,134 @@ impl StatefulWidget for SettingsEdit {
             Paragraph::new("Connection: Untested / Path: Untested")
         };
         Widget::render(conn_status, buttons_cells[6], buf);
+        if let Some(browser) = state.browser.as_mut() {
+            render_browser(area, buf, browser);
+        }
+    }
+}
+
+fn render_browser(area: Rect, buf: &mut Buffer, browser: &mut DirBrowserState) {
+    let width = area.width.min(60);
+    let height = area.height.min(20);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .border_type(BorderType::Rounded)
+        .title(format!(" {} ", browser.path.display()));
+    let inner = block.inner(popup);
+    Clear.render(popup, buf);
+    block.render(popup, buf);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+        .split(inner);
+    let items: Vec<ListItem> = browser
+        .entries()
+        .into_iter()
+        .map(|dir| ListItem::new(format!("{}/", dir)))
+        .collect();
+    let listed = items.len() as u16;
+    let list = List::new(items).highlight_style(Style::default().fg(Color::Black).bg(Color::White));
+    StatefulWidget::render(list, chunks[0], buf, &mut browser.list_state);
+    let status_area = Rect {
+        y: chunks[0].y + listed.min(chunks[0].height),
+        height: chunks[0].height.saturating_sub(listed),
+        ..chunks[0]
+    };
+    match &browser.dirs {
+        None => Paragraph::new("Loading...").render(status_area, buf),
+        Some(Err(err)) => Paragraph::new(Span::styled(
+            err.as_str(),
+            Style::default().fg(Color::LightRed),
+        ))
+        .render(status_area, buf),
+        Some(Ok(_)) => {}
     }
+    Paragraph::new("Enter: open   Backspace: up   s: select this directory   Esc: close")
+        .render(chunks[1], buf);
 }
 
-const SETTINGS_EDIT_SELECTABLES: usize = 10;
+const SETTINGS_EDIT_SELECTABLES: usize = 26;
 
 impl SettingsEditState {
-    pub fn press_key(&mut self, kev: KeyEvent) -> bool {
-        if kev.code == KeyCode::Tab {
+    /// List the directories at the path, the browser is opened if needed.
+    fn browse(&mut self, path: PathBuf) {
+        let browser = self.browser.get_or_insert_with(Default::default);
+        browser.path = path.clone();
+        browser.dirs = None;
+        let sender = MESSAGE_SENDER.get().unwrap();
+        sender
+            .send(SettingsMessage::BrowseLibrary((self.library(), path)).into())
+            .unwrap();
+    }
+
+    fn browser_input(&mut self, kev: KeyEvent) {
+        let Some(browser) = self.browser.as_mut() else {
+            return;
+        };
+        let entries = browser.entries();
+        let selected = browser.list_state.selected().unwrap_or(0);
+        match kev.code {
+            KeyCode::Esc => self.browser = None,
+            KeyCode::Up => browser.list_state.select(Some(selected.saturating_sub(1))),
+            KeyCode::Down => browser.list_state.select(Some(Ord::min(
+                selected + 1,
+                entries.len().saturating_sub(1),
+            ))),
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(parent) = browser.path.parent().map(PathBuf::from) {
+                    self.browse(parent);
+                }
+            }
+            KeyCode::Enter | KeyCode::Right => match entries.get(selected).map(String::as_str) {
+                Some("..") => {
+                    if let Some(parent) = browser.path.parent().map(PathBuf::from) {
+                        self.browse(parent);
+                    }
+                }
+                Some(dir) => {
+                    let path = browser.path.join(dir);
+                    self.browse(path);
+                }
+                None => {}
+            },
+            KeyCode::Char('s') => {
+                let path = browser.path.display().to_string();
+                self.path.set_value(path);
+                self.browser = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn press_key(&mut self, kev: KeyEvent, keys: &Keybindings) -> bool {
+        if self.browser.is_some() {
+            self.browser_input(kev);
+            true
+        } else if self.focused == 12
+            && kev.code == KeyCode::Char('o')
+            && kev.modifiers.contains(KeyModifiers::CONTROL)
+        {
+            let path = match self.path.get_value().trim() {
+                "" => PathBuf::from("/"),
+                path => PathBuf::from(path),
+            };
+            self.browse(path);
+            true
+        } else if keys.is(Action::NextField, &kev) {
             self.focus_child(self.focused, false);
             self.focused = (self.focused + 1) % SETTINGS_EDIT_SELECTABLES;
             while !self.focus_child(self.focused, true) {
                 self.focused = (self.focused + 1) % SETTINGS_EDIT_SELECTABLES;
             }
             true
-        } else if kev.code == KeyCode::BackTab {
+        } else if keys.is(Action::PreviousField, &kev) {
             self.focus_child(self.focused, false);
             self.focused =
                 (self.focused + SETTINGS_EDIT_SELECTABLES - 1) % SETTINGS_EDIT_SELECTABLES;
@@ -590,16 +1265,16 @@ impl SettingsEditState {
             true
         } else {
             if self.input_child(self.focused, kev) {
-                if self.focused == 5 {
+                if self.focused == 15 {
                     self.tv_show.check(!self.movie.is_checked());
-                } else if self.focused == 6 {
+                } else if self.focused == 16 {
                     self.movie.check(!self.tv_show.is_checked());
                 } else if self.cancel.is_clicked() {
                     let sender = MESSAGE_SENDER.get().unwrap();
                     sender
                         .send(crate::AppMessage::Future(Box::new(
                             |appstate: &mut AppState| {
-                                let libs = appstate.libraries.iter().flatten().cloned().collect();
+                                let libs = appstate.library_entries();
                                 Box::pin(async move {
                                     vec![AppEvent::SettingsEvent(SettingsEvent::OpenMenu(libs))]
                                 })
@@ -608,37 +1283,13 @@ impl SettingsEditState {
                         .unwrap();
                 } else if self.save.is_clicked() {
                     let sender = MESSAGE_SENDER.get().unwrap();
-                    let library = Library {
-                        name: self.name.get_value().to_owned(),
-                        path: PathBuf::from(self.path.get_value()),
-                        host: self.host.as_ref().map(|c| c.get_value().to_owned()),
-                        username: self.username.as_ref().map(|c| c.get_value().to_owned()),
-                        password: self.password.as_ref().map(|c| c.get_value().to_owned()),
-                        fs_type: self.fs_type.clone(),
-                        flavor: if self.movie.is_checked() {
-                            LibraryFlavor::Movie
-                        } else {
-                            LibraryFlavor::TvShow
-                        },
-                    };
+                    let library = self.library();
                     sender
                         .send(SettingsMessage::SaveLibrary(library).into())
                         .unwrap();
                 } else if self.test.is_clicked() {
                     let sender = MESSAGE_SENDER.get().unwrap();
-                    let library = Library {
-                        name: self.name.get_value().to_owned(),
-                        path: PathBuf::from(self.path.get_value()),
-                        host: self.host.as_ref().map(|c| c.get_value().to_owned()),
-                        username: self.username.as_ref().map(|c| c.get_value().to_owned()),
-                        password: self.password.as_ref().map(|c| c.get_value().to_owned()),
-                        fs_type: self.fs_type.clone(),
-                        flavor: if self.movie.is_checked() {
-                            LibraryFlavor::Movie
-                        } else {
-                            LibraryFlavor::TvShow
-                        },
-                    };
+                    let library = self.library();
                     sender
                         .send(SettingsMessage::TestLibrary(library).into())
                         .unwrap();
@@ -650,13 +1301,100 @@ impl SettingsEditState {
         }
     }
 
-    pub fn input(&mut self, evt: AppEvent) -> bool {
+    pub fn input(&mut self, evt: AppEvent, keys: &Keybindings) -> bool {
         match evt {
-            AppEvent::KeyEvent(kev) => self.press_key(kev),
+            AppEvent::KeyEvent(kev) => self.press_key(kev, keys),
             _ => false,
         }
     }
 
+    #[cfg(feature = "ftp")]
+    fn enable_ftp_options(&mut self, port: Option<u16>, secure: bool, passive: bool) {
+        let mut port_input = LabelledInputState::default();
+        if let Some(port) = port {
+            port_input.set_value(port.to_string());
+        }
+        let mut secure_check = LabelledCheckboxState::default();
+        secure_check.check(secure);
+        let mut passive_check = LabelledCheckboxState::default();
+        passive_check.check(passive);
+        self.port = Some(port_input);
+        self.secure = Some(secure_check);
+        self.passive = Some(passive_check);
+    }
+
+    #[cfg(feature = "smb")]
+    fn enable_smb_options(&mut self, domain: Option<String>, share: Option<String>) {
+        let mut domain_input = LabelledInputState::default();
+        domain_input.set_value(domain.unwrap_or_default());
+        let mut share_input = LabelledInputState::default();
+        share_input.set_value(share.unwrap_or_default());
+        self.smb_domain = Some(domain_input);
+        self.smb_share = Some(share_input);
+    }
+
+    #[cfg(feature = "s3")]
+    fn enable_s3_options(&mut self, bucket: Option<String>, region: Option<String>, https: bool) {
+        let mut bucket_input = LabelledInputState::default();
+        bucket_input.set_value(bucket.unwrap_or_default());
+        let mut region_input = LabelledInputState::default();
+        region_input.set_value(region.unwrap_or_default());
+        let mut https_check = LabelledCheckboxState::default();
+        https_check.check(https);
+        self.s3_bucket = Some(bucket_input);
+        self.s3_region = Some(region_input);
+        self.secure = Some(https_check);
+    }
+
+    fn library(&self) -> Library {
+        let optional = |input: &LabelledInputState| {
+            Some(input.get_value().trim().to_owned()).filter(|v| !v.is_empty())
+        };
+        Library {
+            id: self.id,
+            name: self.name.get_value().to_owned(),
+            path: PathBuf::from(self.path.get_value()),
+            host: self.host.as_ref().map(|c| c.get_value().to_owned()),
+            username: self.username.as_ref().map(|c| c.get_value().to_owned()),
+            password: self.password.as_ref().map(|c| c.get_value().to_owned()),
+            fs_type: self.fs_type.clone(),
+            flavor: if self.movie.is_checked() {
+                LibraryFlavor::Movie
+            } else {
+                LibraryFlavor::TvShow
+            },
+            prefered_lang: optional(&self.lang),
+            prefered_country: optional(&self.country),
+            port: self
+                .port
+                .as_ref()
+                .and_then(|p| p.get_value().trim().parse().ok()),
+            secure: self.secure.as_ref().is_some_and(|c| c.is_checked()),
+            passive_mode: self.passive.as_ref().map_or(true, |c| c.is_checked()),
+            smb_domain: self.smb_domain.as_ref().and_then(optional),
+            smb_share: self.smb_share.as_ref().and_then(optional),
+            s3_bucket: self.s3_bucket.as_ref().and_then(optional),
+            s3_region: self.s3_region.as_ref().and_then(optional),
+            probe_media: self.probe.is_checked(),
+            follow_symlinks: self.symlinks.as_ref().is_some_and(|c| c.is_checked()),
+            max_depth: self
+                .depth
+                .get_value()
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| default_max_depth()),
+            asset_naming: if self.plex_assets.is_checked() {
+                AssetNaming::Plex
+            } else {
+                AssetNaming::Kodi
+            },
+            fs_profile: self.fs_profile.get_value().parse().unwrap_or_default(),
+            keep_unicode: self.keep_unicode.is_checked(),
+            read_only: self.read_only.is_checked(),
+            ffmpeg_path_mapping: self.ffmpeg_path_mapping.clone(),
+        }
+    }
+
     fn focus_child(&mut self, index: usize, state: bool) -> bool {
         match index {
             0 => {
@@ -666,27 +1404,67 @@ impl SettingsEditState {
             1 => self.host.as_mut().map(|u| u.focus(state)).is_some(),
             2 => self.username.as_mut().map(|u| u.focus(state)).is_some(),
             3 => self.password.as_mut().map(|u| u.focus(state)).is_some(),
-            4 => {
+            4 => self.s3_bucket.as_mut().map(|u| u.focus(state)).is_some(),
+            5 => self.s3_region.as_mut().map(|u| u.focus(state)).is_some(),
+            6 => self.port.as_mut().map(|u| u.focus(state)).is_some(),
+            7 => self.secure.as_mut().map(|u| u.focus(state)).is_some(),
+            8 => self.passive.as_mut().map(|u| u.focus(state)).is_some(),
+            9 => self.smb_domain.as_mut().map(|u| u.focus(state)).is_some(),
+            10 => self.smb_share.as_mut().map(|u| u.focus(state)).is_some(),
+            11 => self.symlinks.as_mut().map(|u| u.focus(state)).is_some(),
+            12 => {
                 self.path.focus(state);
                 true
             }
-            5 => {
+            13 => {
+                self.lang.focus(state);
+                true
+            }
+            14 => {
+                self.country.focus(state);
+                true
+            }
+            15 => {
                 self.movie.focus(state);
                 true
             }
-            6 => {
+            16 => {
                 self.tv_show.focus(state);
                 true
             }
-            7 => {
+            17 => {
+                self.probe.focus(state);
+                true
+            }
+            18 => {
+                self.depth.focus(state);
+                true
+            }
+            19 => {
+                self.plex_assets.focus(state);
+                true
+            }
+            20 => {
+                self.fs_profile.focus(state);
+                true
+            }
+            21 => {
+                self.keep_unicode.focus(state);
+                true
+            }
+            22 => {
+                self.read_only.focus(state);
+                true
+            }
+            23 => {
                 self.test.focus(state);
                 true
             }
-            8 => {
+            24 => {
                 self.save.focus(state);
                 true
             }
-            9 => {
+            25 => {
                 self.cancel.focus(state);
                 true
             }
@@ -698,24 +1476,281 @@ impl SettingsEditState {
         match index {
             0 => self.name.input(kev),
             1 => {
-                let r = self.host.as_mut().map(|u| u.input(kev));
-                return r.is_some() && r.unwrap();
+                return self.host.as_mut().is_some_and(|u| u.input(kev));
             }
             2 => {
-                let r = self.username.as_mut().map(|u| u.input(kev));
-                return r.is_some() && r.unwrap();
+                return self.username.as_mut().is_some_and(|u| u.input(kev));
             }
             3 => {
-                let r = self.password.as_mut().map(|u| u.input(kev));
-                return r.is_some() && r.unwrap();
-            }
-            4 => self.path.input(kev),
-            5 => self.movie.input(kev),
-            6 => self.tv_show.input(kev),
-            7 => self.test.input(kev),
-            8 => self.save.input(kev),
-            9 => self.cancel.input(kev),
+                return self.password.as_mut().is_some_and(|u| u.input(kev));
+            }
+            4 => self.s3_bucket.as_mut().is_some_and(|u| u.input(kev)),
+            5 => self.s3_region.as_mut().is_some_and(|u| u.input(kev)),
+            6 => self.port.as_mut().is_some_and(|u| u.input(kev)),
+            7 => self.secure.as_mut().is_some_and(|u| u.input(kev)),
+            8 => self.passive.as_mut().is_some_and(|u| u.input(kev)),
+            9 => self.smb_domain.as_mut().is_some_and(|u| u.input(kev)),
+            10 => self.smb_share.as_mut().is_some_and(|u| u.input(kev)),
+            11 => self.symlinks.as_mut().is_some_and(|u| u.input(kev)),
+            12 => self.path.input(kev),
+            13 => self.lang.input(kev),
+            14 => self.country.input(kev),
+            15 => self.movie.input(kev),
+            16 => self.tv_show.input(kev),
+            17 => self.probe.input(kev),
+            18 => self.depth.input(kev),
+            19 => self.plex_assets.input(kev),
+            20 => self.fs_profile.input(kev),
+            21 => self.keep_unicode.input(kev),
+            22 => self.read_only.input(kev),
+            23 => self.test.input(kev),
+            24 => self.save.input(kev),
+            25 => self.cancel.input(kev),
             _ => false,
         }
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct Preferences {
+    pub dir_format: LabelledInput,
+    pub file_format: LabelledInput,
+    pub dir_separator: LabelledInput,
+    pub file_separator: LabelledInput,
+    pub save: Button,
+    pub cancel: Button,
+}
+
+/// Renamer formats being edited, previewed on [`Renamer::sample_movie`] as they are typed.
+#[derive(Clone, Debug)]
+pub struct PreferencesState {
+    pub focused: usize,
+    /// Not editable here, kept as is on save.
+    pub collision: RenameCollision,
+    pub dir_format: LabelledInputState,
+    pub file_format: LabelledInputState,
+    pub dir_separator: LabelledInputState,
+    pub file_separator: LabelledInputState,
+    pub save: ButtonState,
+    pub cancel: ButtonState,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            dir_format: LabelledInput::new("Folder format: ", Input::default()),
+            file_format: LabelledInput::new("File format: ", Input::default()),
+            dir_separator: LabelledInput::new("Folder separator: ", Input::default()),
+            file_separator: LabelledInput::new("File separator: ", Input::default()),
+            save: Button::default().with_text("Save"),
+            cancel: Button::default().with_text("Cancel"),
+        }
+    }
+}
+
+impl StatefulWidget for Preferences {
+    type State = PreferencesState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Min(1),
+                    Constraint::Percentage(100),
+                ]
+                .as_ref(),
+            )
+            .split(area.clone());
+        let separator_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(50),
+                    Constraint::Min(2),
+                    Constraint::Percentage(50),
+                ]
+                .as_ref(),
+            )
+            .split(rows[2]);
+        let buttons_cells = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Min(6),
+                    Constraint::Min(2),
+                    Constraint::Min(8),
+                    Constraint::Percentage(100),
+                ]
+                .as_ref(),
+            )
+            .split(rows[4]);
+
+        StatefulWidget::render(self.dir_format, rows[0], buf, &mut state.dir_format);
+        StatefulWidget::render(self.file_format, rows[1], buf, &mut state.file_format);
+        StatefulWidget::render(
+            self.dir_separator,
+            separator_cells[0],
+            buf,
+            &mut state.dir_separator,
+        );
+        StatefulWidget::render(
+            self.file_separator,
+            separator_cells[2],
+            buf,
+            &mut state.file_separator,
+        );
+        let fields: Vec<String> = Renamer::FIELDS
+            .iter()
+            .map(|field| format!("{{{}}}", field))
+            .collect();
+        Widget::render(
+            Paragraph::new(format!(
+                "Fields: {}, {{field|fallback}} when empty",
+                fields.join(" ")
+            )),
+            rows[3],
+            buf,
+        );
+        StatefulWidget::render(self.save, buttons_cells[0], buf, &mut state.save);
+        StatefulWidget::render(self.cancel, buttons_cells[2], buf, &mut state.cancel);
+        let lines = match state.renamer().preview() {
+            Ok(path) => vec![Spans::from(vec![
+                Span::raw("Preview: "),
+                Span::styled(path, Style::default().fg(Color::Green)),
+            ])],
+            Err(issues) => issues
+                .into_iter()
+                .map(|issue| {
+                    Spans::from(Span::styled(issue, Style::default().fg(Color::LightRed)))
+                })
+                .collect(),
+        };
+        Widget::render(
+            Paragraph::new(lines).wrap(Wrap { trim: false }),
+            rows[6],
+            buf,
+        );
+    }
+}
+
+const PREFERENCES_SELECTABLES: usize = 6;
+
+impl PreferencesState {
+    pub fn new(renamer: Renamer) -> PreferencesState {
+        let input = |value: String| {
+            let mut input = LabelledInputState::default();
+            input.set_value(value);
+            input
+        };
+        PreferencesState {
+            focused: 0,
+            collision: renamer.collision,
+            dir_format: input(renamer.dir_format),
+            file_format: input(renamer.file_format),
+            dir_separator: input(renamer.dir_separator),
+            file_separator: input(renamer.file_separator),
+            save: ButtonState::default(),
+            cancel: ButtonState::default(),
+        }
+    }
+
+    pub fn renamer(&self) -> Renamer {
+        Renamer {
+            dir_format: self.dir_format.get_value(),
+            file_format: self.file_format.get_value(),
+            dir_separator: self.dir_separator.get_value(),
+            file_separator: self.file_separator.get_value(),
+            collision: self.collision,
+        }
+    }
+
+    pub fn press_key(&mut self, kev: KeyEvent, keys: &Keybindings) -> bool {
+        if keys.is(Action::NextField, &kev) {
+            self.focus_child(self.focused, false);
+            self.focused = (self.focused + 1) % PREFERENCES_SELECTABLES;
+            self.focus_child(self.focused, true);
+            true
+        } else if keys.is(Action::PreviousField, &kev) {
+            self.focus_child(self.focused, false);
+            self.focused = (self.focused + PREFERENCES_SELECTABLES - 1) % PREFERENCES_SELECTABLES;
+            self.focus_child(self.focused, true);
+            true
+        } else if self.input_child(self.focused, kev) {
+            let sender = MESSAGE_SENDER.get().unwrap();
+            if self.cancel.is_clicked() {
+                sender.send(SettingsMessage::OpenMenu.into()).unwrap();
+            } else if self.save.is_clicked() {
+                // The issues are already listed under the form.
+                if self.renamer().preview().is_ok() {
+                    sender
+                        .send(SettingsMessage::SavePreferences(self.renamer()).into())
+                        .unwrap();
+                } else {
+                    self.save.click(false);
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn input(&mut self, evt: AppEvent, keys: &Keybindings) -> bool {
+        match evt {
+            AppEvent::KeyEvent(kev) => self.press_key(kev, keys),
+            _ => false,
+        }
+    }
+
+    fn focus_child(&mut self, index: usize, state: bool) {
+        match index {
+            0 => self.dir_format.focus(state),
+            1 => self.file_format.focus(state),
+            2 => self.dir_separator.focus(state),
+            3 => self.file_separator.focus(state),
+            4 => self.save.focus(state),
+            5 => self.cancel.focus(state),
+            _ => {}
+        }
+    }
+
+    fn input_child(&mut self, index: usize, kev: KeyEvent) -> bool {
+        match index {
+            0 => self.dir_format.input(kev),
+            1 => self.file_format.input(kev),
+            2 => self.dir_separator.input(kev),
+            3 => self.file_separator.input(kev),
+            4 => self.save.input(kev),
+            5 => self.cancel.input(kev),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{message_sender, state};
+    use crate::views::TabState;
+
+    #[test]
+    fn esc_closes_the_browser_only() {
+        message_sender();
+        let mut state = state();
+        state.tab = TabState::Settings(SettingsState::Edit(Box::new(SettingsEditState {
+            browser: Some(Default::default()),
+            ..Default::default()
+        })));
+        assert!(!state.press_key(KeyCode::Esc.into()));
+        assert!(matches!(
+            &state.tab,
+            TabState::Settings(SettingsState::Edit(edit)) if edit.browser.is_none()
+        ));
+    }
+}
@@ -0,0 +1,225 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use std::fmt;
+use std::str::FromStr;
+use tui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::Text,
+};
+
+use crate::views::widgets::input::{Input, InputState};
+
+/// A calendar date, written `YYYY-MM-DD` like the NFO `premiered` field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl Date {
+    pub fn next_day(self) -> Self {
+        if self.day < days_in_month(self.year, self.month) {
+            Self {
+                day: self.day + 1,
+                ..self
+            }
+        } else if self.month < 12 {
+            Self {
+                month: self.month + 1,
+                day: 1,
+                ..self
+            }
+        } else {
+            Self {
+                year: self.year.saturating_add(1).min(9999),
+                month: 1,
+                day: 1,
+            }
+        }
+    }
+
+    pub fn previous_day(self) -> Self {
+        if self.day > 1 {
+            Self {
+                day: self.day - 1,
+                ..self
+            }
+        } else if self.month > 1 {
+            Self {
+                month: self.month - 1,
+                day: days_in_month(self.year, self.month - 1),
+                ..self
+            }
+        } else {
+            Self {
+                year: self.year.saturating_sub(1),
+                month: 12,
+                day: 31,
+            }
+        }
+    }
+
+    /// Move by `delta` months, the day is clamped to the length of the new month.
+    pub fn add_months(self, delta: i32) -> Self {
+        let months =
+            (self.year as i32 * 12 + self.month as i32 - 1 + delta).clamp(0, 9999 * 12 + 11);
+        let year = (months / 12) as u16;
+        let month = (months % 12) as u8 + 1;
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{}` is not a valid date (YYYY-MM-DD)", s);
+        let mut parts = s.trim().split('-');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(y), Some(m), Some(d), None) if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+                (y, m, d)
+            }
+            _ => return Err(invalid()),
+        };
+        let digits = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+        if !digits(year) || !digits(month) || !digits(day) {
+            return Err(invalid());
+        }
+        let date = Date {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.parse().map_err(|_| invalid())?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+        if date.month == 0
+            || date.month > 12
+            || date.day == 0
+            || date.day > days_in_month(date.year, date.month)
+        {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DateInput {
+    pub input: Input,
+    pub invalid_style: Style,
+}
+
+impl Default for DateInput {
+    fn default() -> DateInput {
+        DateInput {
+            input: Input {
+                placeholder: Some("YYYY-MM-DD".into()),
+                ..Default::default()
+            },
+            invalid_style: Style::default().fg(Color::LightRed),
+        }
+    }
+}
+
+/// A date input, Up/Down (or +/-) change the year, month or day under the cursor.
+#[derive(Clone, Debug, Default)]
+pub struct DateInputState {
+    input: InputState,
+}
+
+impl DateInputState {
+    pub fn input(&mut self, kev: KeyEvent) -> bool {
+        match kev.code {
+            KeyCode::Up | KeyCode::Char('+') => self.step(1),
+            KeyCode::Down => self.step(-1),
+            // Typed as is while the date is incomplete.
+            KeyCode::Char('-') if matches!(self.date(), Ok(Some(_))) => self.step(-1),
+            KeyCode::Char(c) if !c.is_ascii_digit() && c != '-' => false,
+            _ => self.input.input(kev),
+        }
+    }
+
+    fn step(&mut self, delta: i32) -> bool {
+        let date = match self.date() {
+            Ok(Some(date)) => date,
+            _ => return false,
+        };
+        let date = match self.input.cursor() {
+            0..=4 => date.add_months(12 * delta),
+            5..=7 => date.add_months(delta),
+            _ if delta > 0 => date.next_day(),
+            _ => date.previous_day(),
+        };
+        self.input.edit_value(date.to_string());
+        true
+    }
+
+    pub fn set_focus(&mut self, f: bool) {
+        self.input.set_focus(f);
+    }
+
+    pub fn set_value<T: Into<String>>(&mut self, val: T) {
+        self.input.set_value(val);
+    }
+
+    pub fn get_value(&self) -> String {
+        self.input.get_value()
+    }
+
+    /// The date, None if empty, or the reason why it is malformed.
+    pub fn date(&self) -> Result<Option<Date>, String> {
+        if self.input.is_empty() {
+            Ok(None)
+        } else {
+            self.input.get_value().parse().map(Some)
+        }
+    }
+}
+
+impl DateInput {
+    pub fn render_text<'a>(self, area: Rect, state: &'a mut DateInputState) -> (Text<'a>, Style) {
+        let invalid = state.date().is_err();
+        let (text, style) = self.input.render_text(area, &mut state.input);
+        if invalid {
+            (text, style.patch(self.invalid_style))
+        } else {
+            (text, style)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_dates() {
+        let date: Date = "2024-02-28".parse().unwrap();
+        assert_eq!(date.next_day().to_string(), "2024-02-29");
+        assert_eq!(date.next_day().next_day().to_string(), "2024-03-01");
+        assert_eq!(date.add_months(-2).to_string(), "2023-12-28");
+        let leap: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(leap.add_months(12).to_string(), "2025-02-28");
+        for bad in ["2023-02-29", "2024-13-01", "2024-1-01", "20", "2024-01-01-"] {
+            assert!(bad.parse::<Date>().is_err(), "{} should be rejected", bad);
+        }
+    }
+}
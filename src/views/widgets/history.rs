@@ -0,0 +1,46 @@
+const MAX_HISTORY: usize = 100;
+
+/// Undo/redo stacks of an input value.
+///
+/// Consecutive typed characters are recorded as a single edit, like in most editors.
+#[derive(Clone, Debug, Default)]
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    typing: bool,
+}
+
+impl<T> History<T> {
+    /// Record the value before an edit, `typing` edits following each other are merged.
+    pub fn record(&mut self, before: T, typing: bool) {
+        if !(typing && self.typing) {
+            self.undo.push(before);
+            if self.undo.len() > MAX_HISTORY {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.typing = typing;
+    }
+
+    /// The value before the last edit, `current` becomes redoable.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current);
+        self.typing = false;
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        self.typing = false;
+        Some(next)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.typing = false;
+    }
+}
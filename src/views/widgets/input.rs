@@ -1,12 +1,17 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::ops::Range;
+use std::sync::Mutex;
 use tui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans, Text},
-    widgets::{Paragraph, StatefulWidget, Widget, Wrap},
+    widgets::{Paragraph, StatefulWidget, Widget},
 };
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::views::widgets::history::History;
 
 #[derive(Clone, Debug)]
 pub struct Input {
@@ -15,6 +20,7 @@ pub struct Input {
     pub disable_style: Style,
     pub placeholder: Option<String>,
     pub placeholder_style: Style,
+    pub selection_style: Style,
     pub horiz_constraint: Constraint,
 }
 
@@ -28,74 +34,132 @@ impl Default for Input {
                 .add_modifier(Modifier::UNDERLINED),
             placeholder: None,
             placeholder_style: Style::default().add_modifier(Modifier::ITALIC),
+            selection_style: Style::default().fg(Color::Black).bg(Color::Yellow),
             horiz_constraint: Constraint::Percentage(100),
         }
     }
 }
 
+/// Clipboard shared by every input, the terminal one is not reachable.
+static CLIPBOARD: Mutex<String> = Mutex::new(String::new());
+
+/// Shown for each grapheme of a masked value.
+const MASK: &str = "•";
+
+/// Single-line text input.
+///
+/// Besides the usual keys, Ctrl+Left/Right jump by words, Ctrl+Backspace/Delete delete
+/// words, Shift with a movement key selects, Ctrl+X or Shift+Delete cut the selection,
+/// Ctrl+Insert copies it and Ctrl+V or Shift+Insert paste. Ctrl+Z undoes and Ctrl+Y redoes.
+///
+/// A masked input, for the passwords, shows bullets until Ctrl+R reveals its value. It is
+/// hidden again when the input loses the focus, and its value is never copied.
 #[derive(Clone, Debug, Default)]
 pub struct InputState {
     value: Vec<String>,
     focused: bool,
     disabled: bool,
     cursor: usize,
+    anchor: Option<usize>,
+    offset: usize,
+    history: History<(Vec<String>, usize)>,
+    masked: bool,
+    revealed: bool,
+}
+
+fn is_blank(grapheme: &str) -> bool {
+    grapheme.trim().is_empty()
 }
 
 impl InputState {
     pub fn input(&mut self, kev: KeyEvent) -> bool {
-        match kev.code {
-            KeyCode::Char(c) => {
-                // Store the graphemes len of the composants
-                let mut old_len = 0;
-
-                // Prepare and format the new input using the surrounding graphemes (as they might combine
-                // due to Combining character).
-                let prev = if self.cursor > 0 {
-                    old_len += 1;
-                    self.value[self.cursor - 1].as_str()
-                } else {
-                    ""
-                };
-                let next = if self.cursor < self.value.len() {
-                    old_len += 1;
-                    self.value[self.cursor].as_str()
-                } else {
-                    ""
-                };
-                let tmp = format!("{}{}{}", prev, c, next);
-                let new_len = tmp.graphemes(false).count();
-
-                // Replace efficiently the inner value
-                self.value.splice(
-                    self.cursor.saturating_sub(1)..Ord::min(self.cursor + 1, self.value.len()),
-                    tmp.graphemes(false).into_iter().map(|s| s.to_string()),
-                );
-
-                // If the input create a new grapheme, increment the cursor.
-                if old_len < new_len {
-                    self.cursor += 1;
+        let ctrl = kev.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = kev.modifiers.contains(KeyModifiers::SHIFT);
+        if self.masked && ctrl && kev.code == KeyCode::Char('r') {
+            self.revealed = !self.revealed;
+            return true;
+        }
+        // Only the edits need a snapshot, not the cursor moves.
+        if !matches!(
+            kev.code,
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete | KeyCode::Insert
+        ) {
+            return self.edit(kev);
+        }
+        let current = (self.value.clone(), self.cursor);
+        let restored = match kev.code {
+            KeyCode::Char('z') if ctrl && !shift => self.history.undo(current),
+            KeyCode::Char('y') | KeyCode::Char('Z') if ctrl => self.history.redo(current),
+            _ => {
+                let typing = matches!(kev.code, KeyCode::Char(c) if !ctrl && !c.is_whitespace());
+                let handled = self.edit(kev);
+                if self.value != current.0 {
+                    self.history.record(current, typing);
                 }
+                return handled;
             }
-            KeyCode::Backspace => {
-                if self.cursor > 0 {
-                    self.value.remove(self.cursor - 1);
-                    self.cursor -= 1;
+        };
+        if let Some((value, cursor)) = restored {
+            self.value = value;
+            self.cursor = cursor;
+            self.anchor = None;
+        }
+        true
+    }
+
+    fn edit(&mut self, kev: KeyEvent) -> bool {
+        let ctrl = kev.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = kev.modifiers.contains(KeyModifiers::SHIFT);
+        match kev.code {
+            KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Up
+            | KeyCode::Down
+            | KeyCode::Home
+            | KeyCode::End => {
+                if !shift {
+                    self.anchor = None;
+                } else if self.anchor.is_none() {
+                    self.anchor = Some(self.cursor);
                 }
+                self.cursor = match kev.code {
+                    KeyCode::Left if ctrl => self.word_start(self.cursor),
+                    KeyCode::Left => self.cursor.saturating_sub(1),
+                    KeyCode::Right if ctrl => self.word_end(self.cursor),
+                    KeyCode::Right => Ord::min(self.cursor + 1, self.value.len()),
+                    KeyCode::Up | KeyCode::Home => 0,
+                    _ => self.value.len(),
+                };
             }
-            KeyCode::Delete => {
-                if self.cursor < self.value.len() {
-                    self.value.remove(self.cursor);
-                }
+            KeyCode::Char('x') if ctrl => self.cut(),
+            KeyCode::Char('v') if ctrl => self.paste(),
+            KeyCode::Char(_) if ctrl || kev.modifiers.contains(KeyModifiers::ALT) => return false,
+            KeyCode::Char(c) => {
+                self.delete_selection();
+                self.insert_char(c);
             }
-            KeyCode::Left => {
-                self.cursor = self.cursor.saturating_sub(1);
+            KeyCode::Insert if ctrl => self.copy(),
+            KeyCode::Insert if shift => self.paste(),
+            KeyCode::Delete if shift => self.cut(),
+            KeyCode::Backspace | KeyCode::Delete if self.selection().is_some() => {
+                self.delete_selection();
             }
-            KeyCode::Right => self.cursor = Ord::min(self.cursor + 1, self.value.len()),
-            KeyCode::Up | KeyCode::Home => {
-                self.cursor = 0;
+            KeyCode::Backspace => {
+                let start = if ctrl {
+                    self.word_start(self.cursor)
+                } else {
+                    self.cursor.saturating_sub(1)
+                };
+                self.value.drain(start..self.cursor);
+                self.cursor = start;
             }
-            KeyCode::Down | KeyCode::End => {
-                self.cursor = self.value.len();
+            KeyCode::Delete => {
+                let end = if ctrl {
+                    self.word_end(self.cursor)
+                } else {
+                    Ord::min(self.cursor + 1, self.value.len())
+                };
+                self.value.drain(self.cursor..end);
             }
             _ => {
                 return false;
@@ -104,8 +168,137 @@ impl InputState {
         true
     }
 
+    fn insert_char(&mut self, c: char) {
+        // Store the graphemes len of the composants
+        let mut old_len = 0;
+
+        // Prepare and format the new input using the surrounding graphemes (as they might combine
+        // due to Combining character).
+        let prev = if self.cursor > 0 {
+            old_len += 1;
+            self.value[self.cursor - 1].as_str()
+        } else {
+            ""
+        };
+        let next = if self.cursor < self.value.len() {
+            old_len += 1;
+            self.value[self.cursor].as_str()
+        } else {
+            ""
+        };
+        let tmp = format!("{}{}{}", prev, c, next);
+        let new_len = tmp.graphemes(false).count();
+
+        // Replace efficiently the inner value
+        self.value.splice(
+            self.cursor.saturating_sub(1)..Ord::min(self.cursor + 1, self.value.len()),
+            tmp.graphemes(false).into_iter().map(|s| s.to_string()),
+        );
+
+        // If the input create a new grapheme, increment the cursor.
+        if old_len < new_len {
+            self.cursor += 1;
+        }
+    }
+
+    /// Start of the word before `pos`, skipping the blanks first.
+    fn word_start(&self, pos: usize) -> usize {
+        // The words would tell where the spaces of a password are.
+        if self.masked {
+            return 0;
+        }
+        let mut pos = pos;
+        while pos > 0 && is_blank(&self.value[pos - 1]) {
+            pos -= 1;
+        }
+        while pos > 0 && !is_blank(&self.value[pos - 1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// End of the word after `pos`, skipping the blanks first.
+    fn word_end(&self, pos: usize) -> usize {
+        if self.masked {
+            return self.value.len();
+        }
+        let mut pos = pos;
+        while pos < self.value.len() && is_blank(&self.value[pos]) {
+            pos += 1;
+        }
+        while pos < self.value.len() && !is_blank(&self.value[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Selected graphemes, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor.filter(|a| *a != self.cursor)?;
+        let range = Ord::min(anchor, self.cursor)..Ord::max(anchor, self.cursor);
+        Some(range.start..Ord::min(range.end, self.value.len()))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection().map(|range| self.value[range].concat())
+    }
+
+    /// Remove the selected text, if any, and end the selection.
+    fn delete_selection(&mut self) {
+        if let Some(range) = self.selection() {
+            self.cursor = range.start;
+            self.value.drain(range);
+        }
+        self.anchor = None;
+    }
+
+    fn copy(&mut self) {
+        if self.masked {
+            return;
+        }
+        if let Some(text) = self.selected_text() {
+            *CLIPBOARD.lock().unwrap() = text;
+        }
+    }
+
+    fn cut(&mut self) {
+        self.copy();
+        self.delete_selection();
+    }
+
+    fn paste(&mut self) {
+        let text = CLIPBOARD.lock().unwrap().clone();
+        self.delete_selection();
+        let graphemes: Vec<String> = text
+            .graphemes(false)
+            .filter(|g| !g.contains(['\n', '\r']))
+            .map(|s| s.to_owned())
+            .collect();
+        let len = graphemes.len();
+        self.value.splice(self.cursor..self.cursor, graphemes);
+        self.cursor += len;
+    }
+
     pub fn set_focus(&mut self, f: bool) {
         self.focused = f;
+        if !f {
+            self.revealed = false;
+        }
+    }
+
+    /// Show bullets instead of the value, see [`InputState`].
+    pub fn set_masked(&mut self, masked: bool) {
+        self.masked = masked;
+        self.revealed = false;
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.masked
+    }
+
+    /// Whether the value of a masked input is shown, with Ctrl+R.
+    pub fn is_revealed(&self) -> bool {
+        self.revealed
     }
 
     pub fn toggle(&mut self, t: bool) {
@@ -120,6 +313,11 @@ impl InputState {
         self.disabled
     }
 
+    /// Cursor position, in graphemes.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
     pub fn is_empty(&self) -> bool {
         self.value.len() == 0
     }
@@ -131,6 +329,20 @@ impl InputState {
             .into_iter()
             .map(|s| s.to_owned())
             .collect();
+        self.cursor = Ord::min(self.cursor, self.value.len());
+        self.anchor = None;
+        self.history.clear();
+    }
+
+    /// Replace the value like set_value, as an edit that can be undone.
+    pub fn edit_value<T: Into<String>>(&mut self, val: T) {
+        let before = (std::mem::take(&mut self.value), self.cursor);
+        let history = std::mem::take(&mut self.history);
+        self.set_value(val);
+        self.history = history;
+        if self.value != before.0 {
+            self.history.record(before, false);
+        }
     }
 
     pub fn get_value<'a>(&'a self) -> String {
@@ -152,9 +364,8 @@ impl StatefulWidget for Input {
             .split(rows[0]);
 
         let (content, style) = self.render_text(chunks[0].clone(), state);
-        let par = Paragraph::new(content)
-            .wrap(Wrap { trim: true })
-            .style(style);
+        // The content is already cut to the width, wrapping would trim its leading spaces.
+        let par = Paragraph::new(content).style(style);
         par.render(chunks[0], buf);
     }
 }
@@ -168,67 +379,168 @@ impl Input {
         } else {
             self.style
         };
-        if area.width < 10 {
-            let error_style = Style::default()
-                .bg(Color::Red)
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD)
-                .add_modifier(Modifier::SLOW_BLINK);
-            (Text::raw("TOO SMALL"), error_style)
+        if state.value.is_empty() && !state.focused {
+            return match self.placeholder.clone() {
+                Some(placeholder) => (Text::raw(placeholder), style.patch(self.placeholder_style)),
+                None => (Text::raw(""), style),
+            };
+        }
+        let bullets: Vec<String>;
+        let value = if state.masked && !state.revealed {
+            bullets = vec![MASK.to_owned(); state.value.len()];
+            &bullets
+        } else {
+            &state.value
+        };
+        let width = area.width as usize;
+        let widths: Vec<usize> = value.iter().map(|g| g.width()).collect();
+        let (start, end) = if state.focused {
+            state.offset = scroll_offset(&widths, state.cursor, state.offset, width);
+            (state.offset, visible_end(&widths, state.offset, width))
         } else {
-            if state.value.len() == 0 {
-                if let Some(placeholder) = self.placeholder.clone() {
-                    (
-                        Text::raw(placeholder),
-                        style.patch(self.placeholder_style.clone()),
-                    )
-                } else {
-                    (Text::raw(state.get_value()), style.clone())
-                }
+            (0, visible_end(&widths, 0, width))
+        };
+        let content = &value[start..end];
+        if !state.focused {
+            return (Text::raw(content.concat()), style);
+        }
+        let selection = state.selection().unwrap_or_default();
+        let cursor_style = Style::default().fg(Color::Black).bg(Color::White);
+        let mut spans: Vec<(String, Style)> = Vec::new();
+        for (i, grapheme) in content.iter().enumerate() {
+            let pos = start + i;
+            let grapheme_style = if pos == state.cursor {
+                cursor_style
+            } else if selection.contains(&pos) {
+                self.selection_style
             } else {
-                let width = area.width as usize;
-                let text_col = state.cursor / width;
-                let text_start = (text_col * width).saturating_sub(10);
-                let cursor_pos = state.cursor - text_start;
-                let text_end = Ord::min(text_start + (width as usize), state.value.len());
-                let content: Vec<_> = state
-                    .value
-                    .iter()
-                    .skip(text_start)
-                    .take(text_end.saturating_sub(text_start))
-                    .map(|s| s.as_str())
-                    .collect();
-                if state.focused {
-                    if state.value.len() <= state.cursor {
-                        (
-                            Text::from(Spans::from(vec![
-                                Span::raw(String::from_iter(content)),
-                                Span::styled(
-                                    tui::symbols::block::FULL,
-                                    Style::default().bg(Color::Red),
-                                ),
-                            ])),
-                            style,
-                        )
-                    } else {
-                        (
-                            Text::from(Spans::from(vec![
-                                Span::raw(String::from_iter(content[..(cursor_pos)].to_owned())),
-                                Span::styled(
-                                    content[cursor_pos],
-                                    Style::default().fg(Color::Black).bg(Color::White),
-                                ),
-                                Span::raw(String::from_iter(
-                                    content[(cursor_pos + 1)..].to_owned(),
-                                )),
-                            ])),
-                            style,
-                        )
+                Style::default()
+            };
+            match spans.last_mut() {
+                Some((text, last_style)) if *last_style == grapheme_style => {
+                    text.push_str(grapheme)
+                }
+                _ => spans.push((grapheme.to_string(), grapheme_style)),
+            }
+        }
+        let mut spans: Vec<Span> = spans
+            .into_iter()
+            .map(|(text, style)| Span::styled(text, style))
+            .collect();
+        if state.cursor >= value.len() && width > 0 {
+            spans.push(Span::styled(
+                tui::symbols::block::FULL,
+                Style::default().bg(Color::Red),
+            ));
+        }
+        (Text::from(Spans::from(spans)), style)
+    }
+}
+
+/// First visible grapheme, moved as little as possible to keep the cursor in `width` columns.
+///
+/// The cursor takes one column when it is after the last grapheme.
+fn scroll_offset(widths: &[usize], cursor: usize, offset: usize, width: usize) -> usize {
+    let cursor = Ord::min(cursor, widths.len());
+    let mut offset = Ord::min(offset, cursor);
+    let cursor_width = widths.get(cursor).copied().unwrap_or(1);
+    let mut used: usize = widths[offset..cursor].iter().sum::<usize>() + cursor_width;
+    while used > width && offset < cursor {
+        used -= widths[offset];
+        offset += 1;
+    }
+    offset
+}
+
+/// End of the graphemes that fit in `width` columns from `offset`.
+fn visible_end(widths: &[usize], offset: usize, width: usize) -> usize {
+    let mut used = 0;
+    let mut end = offset;
+    while end < widths.len() && used + widths[end] <= width {
+        used += widths[end];
+        end += 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_word_editing() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let mut input = InputState::default();
+        input.set_value("The quick brown fox");
+        input.input(key(KeyCode::End, KeyModifiers::NONE));
+        input.input(key(
+            KeyCode::Left,
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        ));
+        assert_eq!(input.selected_text().as_deref(), Some("fox"));
+        input.input(key(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        input.input(key(KeyCode::Backspace, KeyModifiers::CONTROL));
+        assert_eq!(input.get_value(), "The quick ");
+        input.input(key(KeyCode::Home, KeyModifiers::NONE));
+        input.input(key(KeyCode::Char('v'), KeyModifiers::CONTROL));
+        assert_eq!(input.get_value(), "foxThe quick ");
+        input.input(key(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        input.input(key(KeyCode::Char('z'), KeyModifiers::CONTROL));
+        assert_eq!(input.get_value(), "The quick brown ");
+        input.input(key(KeyCode::Char('y'), KeyModifiers::CONTROL));
+        assert_eq!(input.get_value(), "The quick ");
+    }
+
+    #[test]
+    fn input_window_fits_every_width() {
+        for value in [
+            "",
+            "a",
+            "The quick brown fox",
+            "映画のタイトル",
+            "e\u{301}te\u{301} mix 映画",
+        ] {
+            let len = value.chars().count();
+            for width in 1..24u16 {
+                let mut input = InputState::default();
+                input.set_value(value);
+                input.set_focus(true);
+                input.input(KeyEvent::new(KeyCode::Home, KeyModifiers::NONE));
+                for _ in 0..=len {
+                    let area = Rect::new(0, 0, width, 1);
+                    let (text, _) = Input::default().render_text(area, &mut input);
+                    // Wide graphemes can not fit in a single column.
+                    if width > 1 {
+                        assert!(text.width() <= width as usize, "{:?} at {}", value, width);
+                        let cursor_shown = text.lines[0].0.iter().any(|span| {
+                            span.style.bg == Some(Color::White) || span.style.bg == Some(Color::Red)
+                        });
+                        assert!(cursor_shown, "{:?} at {}", value, width);
                     }
-                } else {
-                    (Text::raw(String::from_iter(content)), style)
+                    input.input(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
                 }
             }
         }
     }
+
+    #[test]
+    fn masked_input() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let mut input = InputState::default();
+        input.set_masked(true);
+        input.set_focus(true);
+        input.set_value("open sesame");
+        input.input(key(KeyCode::End, KeyModifiers::NONE));
+        input.input(key(
+            KeyCode::Left,
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        ));
+        // The spaces are not given away by the word moves.
+        assert_eq!(input.selected_text().as_deref(), Some("open sesame"));
+        assert!(input.input(key(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+        assert!(input.is_revealed());
+        assert_eq!(input.get_value(), "open sesame");
+        input.set_focus(false);
+        assert!(!input.is_revealed());
+    }
 }
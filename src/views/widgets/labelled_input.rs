@@ -51,6 +51,13 @@ pub struct LabelledInputState {
 }
 
 impl LabelledInputState {
+    /// An input showing bullets instead of its value, for a password.
+    pub fn masked() -> Self {
+        let mut input_state = InputState::default();
+        input_state.set_masked(true);
+        Self { input_state }
+    }
+
     pub fn input(&mut self, kev: KeyEvent) -> bool {
         self.input_state.input(kev)
     }
@@ -1,11 +1,20 @@
 mod button;
 mod checkbox;
+mod date_input;
+mod history;
 mod input;
 mod labelled_checkbox;
 mod labelled_input;
+mod progress;
+mod tag_picker;
+mod textarea;
 
 pub use button::{Button, ButtonState};
 pub use checkbox::{Checkbox, CheckboxState};
+pub use date_input::{Date, DateInput, DateInputState};
 pub use input::{Input, InputState};
 pub use labelled_checkbox::{LabelledCheckbox, LabelledCheckboxState};
 pub use labelled_input::{LabelledInput, LabelledInputState};
+pub use progress::{format_size, Progress};
+pub use tag_picker::{TagPicker, TagPickerState};
+pub use textarea::{TextArea, TextAreaState};
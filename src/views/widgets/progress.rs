@@ -0,0 +1,109 @@
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    symbols,
+    widgets::Widget,
+};
+
+/// One line gauge, the label and the amounts then a bar filling the rest of the line.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub label: String,
+    pub done: u64,
+    pub total: u64,
+    /// Show the amounts as sizes (e.g. `1.2 GB`), otherwise as counts.
+    pub bytes: bool,
+    pub style: Style,
+    pub bar_style: Style,
+}
+
+impl Default for Progress {
+    fn default() -> Progress {
+        Progress {
+            label: String::new(),
+            done: 0,
+            total: 0,
+            bytes: true,
+            style: Style::default().fg(Color::White),
+            bar_style: Style::default().fg(Color::LightCyan),
+        }
+    }
+}
+
+/// Size with a unit, e.g. `512 B`, `1.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000f64 && unit + 1 < UNITS.len() {
+        size /= 1000f64;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl Progress {
+    pub fn new(label: impl Into<String>, done: u64, total: u64) -> Self {
+        Self {
+            label: label.into(),
+            done,
+            total,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_count(mut self) -> Self {
+        self.bytes = false;
+        self
+    }
+
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            0f64
+        } else {
+            (self.done as f64 / self.total as f64).min(1f64)
+        }
+    }
+
+    fn text(&self) -> String {
+        let (done, total) = if self.bytes {
+            (format_size(self.done), format_size(self.total))
+        } else {
+            (self.done.to_string(), self.total.to_string())
+        };
+        format!(
+            "{} {:>3}% ({}/{}) ",
+            self.label,
+            (self.ratio() * 100f64) as u16,
+            done,
+            total
+        )
+    }
+}
+
+impl Widget for Progress {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+        let (bar_x, _) =
+            buf.set_stringn(area.x, area.y, self.text(), area.width as usize, self.style);
+        let bar_width = area.right().saturating_sub(bar_x);
+        let filled = (bar_width as f64 * self.ratio()).round() as u16;
+        for x in 0..bar_width {
+            let symbol = if x < filled {
+                symbols::block::FULL
+            } else {
+                symbols::line::HORIZONTAL
+            };
+            buf.get_mut(bar_x + x, area.y)
+                .set_symbol(symbol)
+                .set_style(self.bar_style);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget,
+        Widget,
+    },
+};
+
+use crate::views::widgets::input::{Input, InputState};
+
+#[derive(Clone, Debug)]
+pub struct TagPicker {
+    pub title: String,
+    pub style: Style,
+    pub highlight_style: Style,
+}
+
+impl Default for TagPicker {
+    fn default() -> TagPicker {
+        TagPicker {
+            title: String::new(),
+            style: Style::default().fg(Color::White),
+            highlight_style: Style::default().fg(Color::Black).bg(Color::White),
+        }
+    }
+}
+
+/// Multi-select list of known tags, filtered by typing.
+///
+/// Enter toggles the highlighted tag, a filter matching no tag can be added as a custom one.
+#[derive(Clone, Debug, Default)]
+pub struct TagPickerState {
+    options: Vec<String>,
+    selected: Vec<String>,
+    filter: InputState,
+    list_state: ListState,
+}
+
+/// A listed tag, as (value, custom entry).
+type Entry = (String, bool);
+
+fn same_tag(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+impl TagPickerState {
+    pub fn set_options(&mut self, options: Vec<String>) {
+        self.options = options;
+    }
+
+    pub fn has_options(&self) -> bool {
+        !self.options.is_empty()
+    }
+
+    pub fn set_selected(&mut self, tags: Vec<String>) {
+        self.selected = tags.into_iter().filter(|s| !s.trim().is_empty()).collect();
+    }
+
+    pub fn get_value(&self) -> &[String] {
+        &self.selected
+    }
+
+    pub fn is_selected(&self, tag: &str) -> bool {
+        self.selected.iter().any(|s| same_tag(s, tag))
+    }
+
+    /// Reset the filter, for a new opening of the picker.
+    pub fn reset(&mut self) {
+        self.filter.set_value("");
+        self.list_state.select(Some(0));
+    }
+
+    /// The selected tags then the others, matching the filter.
+    fn entries(&self) -> Vec<Entry> {
+        let filter = self.filter.get_value().trim().to_lowercase();
+        let matches = |tag: &&String| tag.to_lowercase().contains(&filter);
+        let mut entries: Vec<Entry> = Vec::new();
+        let known = self.selected.iter().chain(self.options.iter());
+        if !filter.is_empty() && !known.clone().any(|tag| same_tag(tag, &filter)) {
+            entries.push((self.filter.get_value().trim().to_owned(), true));
+        }
+        entries.extend(
+            self.selected
+                .iter()
+                .filter(matches)
+                .map(|tag| (tag.clone(), false)),
+        );
+        entries.extend(
+            self.options
+                .iter()
+                .filter(matches)
+                .filter(|tag| !self.is_selected(tag))
+                .map(|tag| (tag.clone(), false)),
+        );
+        entries
+    }
+
+    pub fn toggle(&mut self, tag: &str) {
+        if self.is_selected(tag) {
+            self.selected.retain(|s| !same_tag(s, tag));
+        } else {
+            self.selected.push(tag.to_owned());
+        }
+    }
+
+    pub fn input(&mut self, kev: KeyEvent) -> bool {
+        let len = self.entries().len();
+        let highlighted = self.list_state.selected().unwrap_or(0);
+        match kev.code {
+            KeyCode::Up => self.list_state.select(Some(highlighted.saturating_sub(1))),
+            KeyCode::Down => self
+                .list_state
+                .select(Some(Ord::min(highlighted + 1, len.saturating_sub(1)))),
+            KeyCode::Enter => {
+                if let Some((tag, custom)) = self.entries().get(highlighted).cloned() {
+                    self.toggle(&tag);
+                    if custom {
+                        self.filter.set_value("");
+                    }
+                }
+            }
+            _ => {
+                if !self.filter.input(kev) {
+                    return false;
+                }
+                self.list_state.select(Some(0));
+            }
+        }
+        true
+    }
+}
+
+impl StatefulWidget for TagPicker {
+    type State = TagPickerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::default()
+            .title(format!(" {} (Enter to toggle, Esc to close) ", self.title))
+            .borders(Borders::ALL)
+            .border_style(self.style)
+            .border_type(BorderType::Rounded);
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(inner);
+        state.filter.set_focus(true);
+        let filter = Input {
+            placeholder: Some("Type to filter or add a tag".into()),
+            ..Default::default()
+        };
+        let (text, style) = filter.render_text(chunks[0], &mut state.filter);
+        Paragraph::new(text).style(style).render(chunks[0], buf);
+        let items: Vec<ListItem> = state
+            .entries()
+            .into_iter()
+            .map(|(tag, custom)| {
+                let mark = if custom {
+                    "[+]"
+                } else if state.is_selected(&tag) {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
+                ListItem::new(format!("{} {}", mark, tag))
+            })
+            .collect();
+        let list = List::new(items)
+            .style(self.style)
+            .highlight_style(self.highlight_style);
+        StatefulWidget::render(list, chunks[1], buf, &mut state.list_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn tag_picker_custom_entries() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut picker = TagPickerState::default();
+        picker.set_options(vec![
+            "Action".into(),
+            "Drama".into(),
+            "Science Fiction".into(),
+        ]);
+        picker.set_selected(vec!["Drama".into()]);
+        for c in "action".chars() {
+            picker.input(key(KeyCode::Char(c)));
+        }
+        picker.input(key(KeyCode::Enter));
+        assert_eq!(picker.get_value(), ["Drama", "Action"]);
+        picker.reset();
+        for c in "Noir".chars() {
+            picker.input(key(KeyCode::Char(c)));
+        }
+        picker.input(key(KeyCode::Enter));
+        picker.input(key(KeyCode::Enter));
+        assert_eq!(picker.get_value(), ["Action", "Noir"]);
+    }
+}
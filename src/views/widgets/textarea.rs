@@ -0,0 +1,319 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Span, Spans, Text},
+    widgets::{Paragraph, StatefulWidget, Widget},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::views::widgets::history::History;
+
+#[derive(Clone, Debug)]
+pub struct TextArea {
+    pub style: Style,
+    pub focus_style: Style,
+}
+
+impl Default for TextArea {
+    fn default() -> TextArea {
+        TextArea {
+            style: Style::default().fg(Color::Gray).bg(Color::Black),
+            focus_style: Style::default().fg(Color::White).bg(Color::Black),
+        }
+    }
+}
+
+/// Multi-line text, each line is stored as graphemes like in the InputState.
+///
+/// Lines are wrapped at the width of the last render, the cursor moves through the wrapped rows.
+/// Ctrl+Z undoes and Ctrl+Y redoes.
+#[derive(Clone, Debug)]
+pub struct TextAreaState {
+    lines: Vec<Vec<String>>,
+    focused: bool,
+    row: usize,
+    col: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+    history: History<(Vec<Vec<String>>, usize, usize)>,
+}
+
+impl Default for TextAreaState {
+    fn default() -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            focused: false,
+            row: 0,
+            col: 0,
+            top: 0,
+            width: 0,
+            height: 1,
+            history: History::default(),
+        }
+    }
+}
+
+/// Split a line in rows of at most `width` graphemes, after a whitespace when possible.
+fn wrap_line(line: &[String], width: usize) -> Vec<(usize, usize)> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while width > 0 && line.len() - start > width {
+        let end = start + width;
+        let brk = (start + 1..=end)
+            .rev()
+            .find(|i| line[i - 1].trim().is_empty())
+            .unwrap_or(end);
+        rows.push((start, brk));
+        start = brk;
+    }
+    rows.push((start, line.len()));
+    rows
+}
+
+impl TextAreaState {
+    pub fn input(&mut self, kev: KeyEvent) -> bool {
+        let ctrl = kev.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = kev.modifiers.contains(KeyModifiers::SHIFT);
+        // Only the edits need a snapshot, not the cursor moves.
+        if !matches!(
+            kev.code,
+            KeyCode::Char(_) | KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete
+        ) {
+            return self.edit(kev);
+        }
+        let current = (self.lines.clone(), self.row, self.col);
+        let restored = match kev.code {
+            KeyCode::Char('z') if ctrl && !shift => self.history.undo(current),
+            KeyCode::Char('y') | KeyCode::Char('Z') if ctrl => self.history.redo(current),
+            _ => {
+                let typing = matches!(kev.code, KeyCode::Char(c) if !ctrl && !c.is_whitespace());
+                let handled = self.edit(kev);
+                if self.lines != current.0 {
+                    self.history.record(current, typing);
+                }
+                return handled;
+            }
+        };
+        if let Some((lines, row, col)) = restored {
+            self.lines = lines;
+            self.row = row;
+            self.col = col;
+        }
+        true
+    }
+
+    fn edit(&mut self, kev: KeyEvent) -> bool {
+        match kev.code {
+            KeyCode::Char(c)
+                if !kev
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                let line = &mut self.lines[self.row];
+                // Re-segment the surrounding graphemes, the char might combine with them.
+                let start = self.col.saturating_sub(1);
+                let end = Ord::min(self.col + 1, line.len());
+                let old_len = end - start;
+                let mut tmp = line[start..end].concat();
+                tmp.insert(line[start..self.col].concat().len(), c);
+                let graphemes: Vec<String> = tmp.graphemes(false).map(|s| s.to_owned()).collect();
+                let new_len = graphemes.len();
+                line.splice(start..end, graphemes);
+                if old_len < new_len {
+                    self.col += 1;
+                }
+            }
+            KeyCode::Enter => {
+                let rest = self.lines[self.row].split_off(self.col);
+                self.row += 1;
+                self.col = 0;
+                self.lines.insert(self.row, rest);
+            }
+            KeyCode::Backspace => {
+                if self.col > 0 {
+                    self.col -= 1;
+                    self.lines[self.row].remove(self.col);
+                } else if self.row > 0 {
+                    let line = self.lines.remove(self.row);
+                    self.row -= 1;
+                    self.col = self.lines[self.row].len();
+                    self.lines[self.row].extend(line);
+                }
+            }
+            KeyCode::Delete => {
+                if self.col < self.lines[self.row].len() {
+                    self.lines[self.row].remove(self.col);
+                } else if self.row + 1 < self.lines.len() {
+                    let line = self.lines.remove(self.row + 1);
+                    self.lines[self.row].extend(line);
+                }
+            }
+            KeyCode::Left => {
+                if self.col > 0 {
+                    self.col -= 1;
+                } else if self.row > 0 {
+                    self.row -= 1;
+                    self.col = self.lines[self.row].len();
+                }
+            }
+            KeyCode::Right => {
+                if self.col < self.lines[self.row].len() {
+                    self.col += 1;
+                } else if self.row + 1 < self.lines.len() {
+                    self.row += 1;
+                    self.col = 0;
+                }
+            }
+            KeyCode::Up => self.move_rows(-1),
+            KeyCode::Down => self.move_rows(1),
+            KeyCode::PageUp => self.move_rows(-(self.height as isize)),
+            KeyCode::PageDown => self.move_rows(self.height as isize),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = self.lines[self.row].len(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Every wrapped row, as (line, start, end).
+    fn visual_rows(&self) -> Vec<(usize, usize, usize)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                wrap_line(line, self.width)
+                    .into_iter()
+                    .map(move |(start, end)| (i, start, end))
+            })
+            .collect()
+    }
+
+    /// Index of the wrapped row holding the cursor.
+    fn cursor_row(&self, rows: &[(usize, usize, usize)]) -> usize {
+        rows.iter()
+            .enumerate()
+            .filter(|(_, (line, _, _))| *line == self.row)
+            .find(|(i, (_, _, end))| {
+                self.col < *end || rows.get(i + 1).map_or(true, |r| r.0 != self.row)
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Move the cursor by `delta` wrapped rows, keeping its column when possible.
+    fn move_rows(&mut self, delta: isize) {
+        let rows = self.visual_rows();
+        let current = self.cursor_row(&rows);
+        let x = self.col - rows[current].1;
+        let target = current.saturating_add_signed(delta).min(rows.len() - 1);
+        let (line, start, end) = rows[target];
+        let last_of_line = rows.get(target + 1).map_or(true, |r| r.0 != line);
+        self.row = line;
+        // The end of a wrapped row is the start of the next one.
+        self.col = if last_of_line {
+            Ord::min(start + x, end)
+        } else {
+            Ord::min(start + x, end.saturating_sub(1))
+        };
+    }
+
+    pub fn set_focus(&mut self, f: bool) {
+        self.focused = f;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|line| line.is_empty())
+    }
+
+    /// Cursor position, as (line, grapheme).
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn set_value<T: AsRef<str>>(&mut self, val: T) {
+        self.lines = val
+            .as_ref()
+            .split('\n')
+            .map(|line| {
+                line.trim_end_matches('\r')
+                    .graphemes(false)
+                    .map(|s| s.to_owned())
+                    .collect()
+            })
+            .collect();
+        self.row = 0;
+        self.col = 0;
+        self.top = 0;
+        self.history.clear();
+    }
+
+    pub fn get_value(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.concat())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl StatefulWidget for TextArea {
+    type State = TextAreaState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let (content, style) = self.render_text(area, state);
+        Paragraph::new(content).style(style).render(area, buf);
+    }
+}
+
+impl TextArea {
+    /// The visible wrapped rows, scrolled to the cursor, for an area of that size.
+    pub fn render_text<'a>(self, area: Rect, state: &'a mut TextAreaState) -> (Text<'a>, Style) {
+        state.width = area.width as usize;
+        state.height = Ord::max(area.height as usize, 1);
+        let rows = state.visual_rows();
+        let cursor_row = state.cursor_row(&rows);
+        if cursor_row < state.top {
+            state.top = cursor_row;
+        } else if cursor_row >= state.top + state.height {
+            state.top = cursor_row + 1 - state.height;
+        }
+        let cursor_style = Style::default().fg(Color::Black).bg(Color::White);
+        let content: Vec<Spans> = rows
+            .iter()
+            .enumerate()
+            .skip(state.top)
+            .take(state.height)
+            .map(|(i, (line, start, end))| {
+                let visible = &state.lines[*line][*start..*end];
+                if !state.focused || i != cursor_row {
+                    return Spans::from(visible.concat());
+                }
+                let cursor = state.col - start;
+                match visible.get(cursor) {
+                    Some(grapheme) => Spans::from(vec![
+                        Span::raw(visible[..cursor].concat()),
+                        Span::styled(grapheme.clone(), cursor_style),
+                        Span::raw(visible[(cursor + 1)..].concat()),
+                    ]),
+                    None => Spans::from(vec![
+                        Span::raw(visible.concat()),
+                        Span::styled(" ", cursor_style),
+                    ]),
+                }
+            })
+            .collect();
+        let style = if state.focused {
+            self.focus_style
+        } else {
+            self.style
+        };
+        (Text::from(content), style)
+    }
+}